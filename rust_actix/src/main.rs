@@ -90,20 +90,28 @@ async fn main() {
 use actix_web::Responder;
 use tokio::time::{Duration, interval};
 
-async fn sse_handler() -> impl Responder {
+async fn sse_handler(req: HttpRequest) -> impl Responder {
+    // 客户端重连时带上最后收到的事件ID，从下一个事件继续发送；超过流长度的ID按流已结束处理，避免 counter 溢出
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .unwrap_or(0)
+        .min(10);
     let stream = async_stream::stream! {
         let mut interval = interval(Duration::from_secs(1));
-        let mut counter = 0_u32;
+        let mut counter = last_event_id;
 
         loop {
             interval.tick().await;
             counter += 1;
-            if counter == 10 {
+            if counter >= 10 {
                 break;
             }
 
-            // 构造 SSE 消息
-            let message = format!("data: {{\"count\": {}}}\n", counter);
+            // 构造 SSE 消息，每个事件必须以空行结束，id 用于客户端重连时通过 Last-Event-ID 续传
+            let message = format!("id: {}\ndata: {{\"count\": {}}}\n\n", counter, counter);
 
             // ✅ 转换为 Bytes
             yield Ok::<_, actix_web::Error>(web::Bytes::from(message));
@@ -0,0 +1,509 @@
+/**
+ * Builder 派生宏
+ *
+ * #[derive(Builder)] 为结构体 User 生成 UserBuilder 和 UserBuilderError，通过 User::builder() 或者 UserBuilder::new() 创建。
+ *
+ * 必填字段使用类型状态（typestate）在编译期检查：
+ *  每个必填字段对应 UserBuilder 的一个类型参数，未设置时为 ()，设置之后为字段的类型（同时保存字段的值），
+ *  只有所有必填字段都设置之后才有 build 方法，漏掉必填字段时编译报错：
+ *  no method named `build` found for struct `UserBuilder<u64>`（第二个必填字段未设置，类型参数为默认的 ()，没有显示）。
+ *
+ * 以下字段是可选的：
+ *  1、Option<T>：默认为 None，setter 的参数为 T。
+ *  2、Vec、VecDeque、LinkedList、HashSet、BTreeSet、BinaryHeap、HashMap、BTreeMap：默认为空，另外生成 push_字段名 方法逐个添加元素。
+ *  3、#[builder(default)] 或 #[builder(default = 表达式)]：未设置时使用 Default::default() 或者表达式的值。
+ *
+ * 其他字段属性：
+ *  1、#[builder(into)]：setter 的参数为 impl Into<T>，写在结构体上时对所有字段生效。
+ *  2、#[builder(push = "add_tag")]：集合字段逐个添加元素的方法名称。
+ *  3、#[builder(validate = path)]：校验字段，path 的类型为 fn(&T) -> Result<(), E>，E 实现了 Display，
+ *     Option<T> 字段的 T 为内部类型，未设置（None）时不校验。
+ *
+ * 结构体上的 #[builder(validate = path)] 在字段校验之后校验整个结构体，
+ * path 的类型为 fn(&User) -> Result<(), Vec<(&'static str, String)>>，返回无效的字段和原因。
+ * build 返回所有校验失败的字段，而不是遇到第一个错误就返回。
+ */
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    DeriveInput, Expr, Field, GenericArgument, GenericParam, Ident, LitStr, Path, PathArguments,
+    Type, parse_quote,
+};
+
+use crate::getter::named_fields;
+
+/// 生成 push 方法的集合类型，值为元素的类型参数个数（Map 的元素为键值对）
+const COLLECTIONS: &[(&str, usize)] = &[
+    ("Vec", 1),
+    ("VecDeque", 1),
+    ("LinkedList", 1),
+    ("HashSet", 1),
+    ("BTreeSet", 1),
+    ("BinaryHeap", 1),
+    ("HashMap", 2),
+    ("BTreeMap", 2),
+];
+
+enum Kind {
+    /// 必填字段，在类型状态参数中的序号
+    Required(usize),
+    /// Option<T> 字段，保存 T 的类型
+    Optional(Type),
+    /// 有默认值的字段，None 表示 Default::default()
+    Default(Option<Expr>),
+}
+
+struct FieldSpec<'a> {
+    ident: &'a Ident,
+    name: String,
+    ty: &'a Type,
+    kind: Kind,
+    into: bool,
+    /// push 方法的名称和元素类型
+    push: Option<(Ident, Type)>,
+    validate: Option<Path>,
+}
+
+#[derive(Default)]
+struct StructOptions {
+    into: bool,
+    validate: Option<Path>,
+}
+
+impl StructOptions {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut options = StructOptions::default();
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("builder"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("into") {
+                    options.into = true;
+                } else if meta.path.is_ident("validate") {
+                    options.validate = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("结构体上的 #[builder] 只支持 into、validate"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+impl<'a> FieldSpec<'a> {
+    fn parse(field: &'a Field, options: &StructOptions, required: &mut usize) -> syn::Result<Self> {
+        let ident = field.ident.as_ref().expect("具名字段");
+        let name = ident.to_string().trim_start_matches("r#").to_string();
+        let mut default: Option<Option<Expr>> = None;
+        let mut into = options.into;
+        let mut push_name: Option<(Ident, proc_macro2::Span)> = None;
+        let mut validate = None;
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("builder"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    default = Some(if meta.input.peek(syn::Token![=]) {
+                        Some(meta.value()?.parse()?)
+                    } else {
+                        None
+                    });
+                } else if meta.path.is_ident("into") {
+                    into = true;
+                } else if meta.path.is_ident("push") {
+                    let value: LitStr = meta.value()?.parse()?;
+                    push_name = Some((value.parse()?, value.span()));
+                } else if meta.path.is_ident("validate") {
+                    validate = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error(
+                        "#[builder] 不支持该选项，可用的选项: default、into、push、validate",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        let item = collection_item(&field.ty);
+        let push = match (push_name, &item) {
+            (Some((name, _)), Some(item)) => Some((name, item.clone())),
+            (Some((_, span)), None) => {
+                return Err(syn::Error::new(span, "push 只支持标准库的集合类型"));
+            }
+            (None, Some(item)) => Some((format_ident!("push_{}", name), item.clone())),
+            (None, None) => None,
+        };
+
+        let kind = match (option_inner(&field.ty), default) {
+            (Some(_), Some(_)) => {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "Option 字段默认为 None，不需要 default",
+                ));
+            }
+            (Some(inner), None) => Kind::Optional(inner.clone()),
+            (None, Some(default)) => Kind::Default(default),
+            (None, None) if item.is_some() => Kind::Default(None),
+            (None, None) => {
+                *required += 1;
+                Kind::Required(*required - 1)
+            }
+        };
+
+        Ok(Self {
+            ident,
+            name,
+            ty: &field.ty,
+            kind,
+            into,
+            push,
+            validate,
+        })
+    }
+
+    /// 字段在 UserBuilder 中的类型
+    fn storage(&self, states: &[Ident]) -> TokenStream {
+        let ty = self.ty;
+        match &self.kind {
+            Kind::Required(index) => {
+                let state = &states[*index];
+                quote!(#state)
+            }
+            Kind::Optional(_) => quote!(#ty),
+            Kind::Default(_) => quote!(::core::option::Option<#ty>),
+        }
+    }
+
+    /// UserBuilder::new() 中字段的初始值
+    fn initial(&self) -> TokenStream {
+        match self.kind {
+            Kind::Required(_) => quote!(()),
+            Kind::Optional(_) | Kind::Default(_) => quote!(::core::option::Option::None),
+        }
+    }
+
+    /// build 中从 UserBuilder 取出字段的值
+    fn finish(&self) -> TokenStream {
+        let ident = self.ident;
+        match &self.kind {
+            Kind::Required(_) | Kind::Optional(_) => quote!(self.#ident),
+            Kind::Default(None) => quote!(self.#ident.unwrap_or_default()),
+            Kind::Default(Some(expr)) => quote!(self.#ident.unwrap_or_else(|| #expr)),
+        }
+    }
+}
+
+fn last_segment(ty: &Type) -> Option<(&Ident, Vec<&Type>)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+    let segment = path.path.segments.last()?;
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    Some((&segment.ident, args))
+}
+
+pub(crate) fn option_inner(ty: &Type) -> Option<&Type> {
+    match last_segment(ty)? {
+        (ident, args) if ident == "Option" && args.len() == 1 => Some(args[0]),
+        _ => None,
+    }
+}
+
+fn collection_item(ty: &Type) -> Option<Type> {
+    let (ident, args) = last_segment(ty)?;
+    let (_, count) = COLLECTIONS.iter().find(|(name, _)| ident == name)?;
+    match (count, args.as_slice()) {
+        (1, [item, ..]) => Some((*item).clone()),
+        (2, [key, value, ..]) => Some(parse_quote!((#key, #value))),
+        _ => None,
+    }
+}
+
+pub(crate) fn builder(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input, "Builder")?;
+    let options = StructOptions::parse(input)?;
+
+    let mut required = 0;
+    let mut specs = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for field in fields {
+        match FieldSpec::parse(field, &options, &mut required) {
+            Ok(spec) => specs.push(spec),
+            Err(e) => match errors.as_mut() {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let builder = format_ident!("{}Builder", name);
+    let error = format_ident!("{}BuilderError", name);
+    let states: Vec<Ident> = (0..required).map(|i| format_ident!("__F{}", i)).collect();
+
+    // 结构体自身的泛型参数：声明（带约束和默认值）、impl 中的参数（去掉默认值）、使用时的参数
+    let generics = &input.generics;
+    let where_clause = &generics.where_clause;
+    let declared: Vec<&GenericParam> = generics.params.iter().collect();
+    let impl_params: Vec<GenericParam> = generics
+        .params
+        .iter()
+        .cloned()
+        .map(|mut param| {
+            match &mut param {
+                GenericParam::Type(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                GenericParam::Const(param) => {
+                    param.eq_token = None;
+                    param.default = None;
+                }
+                GenericParam::Lifetime(_) => {}
+            }
+            param
+        })
+        .collect();
+    let args: Vec<TokenStream> = generics
+        .params
+        .iter()
+        .map(|param| match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote!(#lifetime)
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+            GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote!(#ident)
+            }
+        })
+        .collect();
+    let phantoms: Vec<TokenStream> = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                Some(quote!(&#lifetime ()))
+            }
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                Some(quote!(#ident))
+            }
+            GenericParam::Const(_) => None,
+        })
+        .collect();
+
+    let idents: Vec<&Ident> = specs.iter().map(|spec| spec.ident).collect();
+    let storages: Vec<TokenStream> = specs.iter().map(|spec| spec.storage(&states)).collect();
+    let initials: Vec<TokenStream> = specs.iter().map(FieldSpec::initial).collect();
+    let finishes: Vec<TokenStream> = specs.iter().map(FieldSpec::finish).collect();
+    let unset: Vec<TokenStream> = states.iter().map(|_| quote!(())).collect();
+    let complete: Vec<&Type> = specs
+        .iter()
+        .filter(|spec| matches!(spec.kind, Kind::Required(_)))
+        .map(|spec| spec.ty)
+        .collect();
+
+    let mut setters = Vec::new();
+    for spec in &specs {
+        let ident = spec.ident;
+        let value_ty = match &spec.kind {
+            Kind::Optional(inner) => inner,
+            _ => spec.ty,
+        };
+        let (param, value) = if spec.into {
+            (
+                quote!(#ident: impl ::core::convert::Into<#value_ty>),
+                quote!(::core::convert::Into::into(#ident)),
+            )
+        } else {
+            (quote!(#ident: #value_ty), quote!(#ident))
+        };
+        setters.push(match &spec.kind {
+            Kind::Required(index) => {
+                // 设置必填字段会改变 UserBuilder 的类型，需要把所有字段移动到新的值中
+                let ty = spec.ty;
+                let next_states = states.iter().enumerate().map(|(i, state)| {
+                    if i == *index {
+                        quote!(#ty)
+                    } else {
+                        quote!(#state)
+                    }
+                });
+                let others = idents.iter().filter(|other| **other != ident);
+                quote! {
+                    #vis fn #ident(self, #param) -> #builder<#(#args,)* #(#next_states,)*> {
+                        #builder {
+                            #ident: #value,
+                            #(#others: self.#others,)*
+                            __marker: ::core::marker::PhantomData,
+                        }
+                    }
+                }
+            }
+            Kind::Optional(_) | Kind::Default(_) => quote! {
+                #vis fn #ident(mut self, #param) -> Self {
+                    self.#ident = ::core::option::Option::Some(#value);
+                    self
+                }
+            },
+        });
+        if let Some((push, item)) = &spec.push {
+            let (param, value) = if spec.into {
+                (
+                    quote!(item: impl ::core::convert::Into<#item>),
+                    quote!(::core::convert::Into::into(item)),
+                )
+            } else {
+                (quote!(item: #item), quote!(item))
+            };
+            let init = match &spec.kind {
+                Kind::Default(Some(expr)) => quote!(|| #expr),
+                _ => quote!(::core::default::Default::default),
+            };
+            setters.push(quote! {
+                #vis fn #push(mut self, #param) -> Self {
+                    ::core::iter::Extend::extend(
+                        self.#ident.get_or_insert_with(#init),
+                        ::core::iter::once(#value),
+                    );
+                    self
+                }
+            });
+        }
+    }
+
+    let field_checks = specs.iter().filter_map(|spec| {
+        let path = spec.validate.as_ref()?;
+        let ident = spec.ident;
+        let field = &spec.name;
+        // Option<T> 字段未设置时不校验，设置之后以 &T 调用
+        let check = |value: TokenStream| {
+            quote! {
+                if let ::core::result::Result::Err(e) = #path(#value) {
+                    errors.push((#field, ::std::string::ToString::to_string(&e)));
+                }
+            }
+        };
+        Some(match spec.kind {
+            Kind::Optional(_) => {
+                let check = check(quote!(inner));
+                quote! {
+                    if let ::core::option::Option::Some(inner) = &value.#ident {
+                        #check
+                    }
+                }
+            }
+            _ => check(quote!(&value.#ident)),
+        })
+    });
+    let struct_check = options.validate.as_ref().map(|path| {
+        quote! {
+            if let ::core::result::Result::Err(invalid) = #path(&value) {
+                errors.extend(invalid);
+            }
+        }
+    });
+    let builder_doc = format!("{name} 的构建器，所有必填字段设置之后才能调用 build");
+    let error_doc = format!("构建 {name} 时校验失败的字段和原因");
+    let error_prefix = format!("构建{name}失败: ");
+
+    Ok(quote! {
+        #[doc = #builder_doc]
+        #[must_use]
+        #vis struct #builder<#(#declared,)* #(#states = (),)*> #where_clause {
+            #(#idents: #storages,)*
+            __marker: ::core::marker::PhantomData<fn() -> (#(#phantoms,)*)>,
+        }
+
+        #[doc = #error_doc]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis struct #error {
+            pub errors: ::std::vec::Vec<(&'static str, ::std::string::String)>,
+        }
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(#error_prefix)?;
+                for (i, (field, reason)) in self.errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    ::core::write!(f, "{}: {}", field, reason)?;
+                }
+                ::core::result::Result::Ok(())
+            }
+        }
+
+        impl ::std::error::Error for #error {}
+
+        impl<#(#impl_params,)*> #name<#(#args,)*> #where_clause {
+            #vis fn builder() -> #builder<#(#args,)* #(#unset,)*> {
+                #builder::new()
+            }
+        }
+
+        impl<#(#impl_params,)*> #builder<#(#args,)* #(#unset,)*> #where_clause {
+            #vis fn new() -> Self {
+                #builder {
+                    #(#idents: #initials,)*
+                    __marker: ::core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl<#(#impl_params,)*> ::core::default::Default for #builder<#(#args,)* #(#unset,)*> #where_clause {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<#(#impl_params,)* #(#states,)*> #builder<#(#args,)* #(#states,)*> #where_clause {
+            #(#setters)*
+        }
+
+        impl<#(#impl_params,)*> #builder<#(#args,)* #(#complete,)*> #where_clause {
+            #vis fn build(self) -> ::core::result::Result<#name<#(#args,)*>, #error> {
+                let value = #name {
+                    #(#idents: #finishes,)*
+                };
+                let mut errors: ::std::vec::Vec<(&'static str, ::std::string::String)> = ::std::vec::Vec::new();
+                #(#field_checks)*
+                #struct_check
+                if errors.is_empty() {
+                    ::core::result::Result::Ok(value)
+                } else {
+                    ::core::result::Result::Err(#error { errors })
+                }
+            }
+        }
+    })
+}
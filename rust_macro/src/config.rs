@@ -0,0 +1,391 @@
+/**
+ * Config 派生宏：使用 config crate 分层加载配置，再逐个字段取值
+ *
+ * 优先级从低到高：字段的默认值、配置文件（按传入的顺序，后面的覆盖前面的）、环境变量。
+ * 文件格式由扩展名决定，支持 config crate 支持的 TOML、YAML、JSON、INI 等格式。
+ *
+ * 结构体属性：#[config(prefix = "APP")]：环境变量的前缀。
+ * 字段属性：
+ *  1、#[config(default = 表达式)]：配置中没有该项时使用的值，表达式通过 Into 转换为字段类型，例如 default = "127.0.0.1"；
+ *     #[config(default)] 使用 Default::default()。Option 字段没有配置时为 None。
+ *  2、#[config(env = "DATABASE_URL")]：环境变量的名称，默认为 前缀_路径，路径中的 . 替换为 __ 并转为大写，例如 APP_DATABASE__URL。
+ *  3、#[config(secret)]：describe 中不显示默认值，取值失败时错误信息中不包含配置的值。
+ *  4、#[config(nested)]：字段的类型同样派生了 Config，作为配置中的一个表，键为 字段名.子字段名。
+ *
+ * 生成的方法：
+ *  1、load()：只使用默认值和环境变量；load_from(&[文件路径])：加载文件和环境变量；from_config(&config::Config)：从已经构建好的配置中取值。
+ *  2、所有字段的错误一起返回 结构体名ConfigError，每个错误包含完整的键路径。
+ *  3、describe()：列出所有配置项的键、类型、默认值和环境变量。
+ */
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use syn::{DeriveInput, Expr, Field, Ident, LitStr, Type};
+
+use crate::{builder::option_inner, getter::named_fields};
+
+enum DefaultValue {
+    None,
+    Trait,
+    Expr(Expr),
+}
+
+struct FieldSpec<'a> {
+    ident: &'a Ident,
+    key: String,
+    ty: &'a Type,
+    default: DefaultValue,
+    env: Option<LitStr>,
+    secret: bool,
+    nested: bool,
+}
+
+impl<'a> FieldSpec<'a> {
+    fn parse(field: &'a Field) -> syn::Result<Self> {
+        let ident = field.ident.as_ref().expect("具名字段");
+        let mut spec = FieldSpec {
+            ident,
+            key: ident.to_string().trim_start_matches("r#").to_string(),
+            ty: &field.ty,
+            default: DefaultValue::None,
+            env: None,
+            secret: false,
+            nested: false,
+        };
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("config"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    spec.default = if meta.input.peek(syn::Token![=]) {
+                        DefaultValue::Expr(meta.value()?.parse()?)
+                    } else {
+                        DefaultValue::Trait
+                    };
+                } else if meta.path.is_ident("env") {
+                    spec.env = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("secret") {
+                    spec.secret = true;
+                } else if meta.path.is_ident("nested") {
+                    spec.nested = true;
+                } else {
+                    return Err(meta.error(
+                        "#[config] 不支持该选项，可用的选项: default、env、secret、nested",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+        if spec.nested && (spec.env.is_some() || !matches!(spec.default, DefaultValue::None)) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "nested 字段不能使用 env 和 default，请在子结构体的字段上设置",
+            ));
+        }
+        if option_inner(spec.ty).is_some() && !matches!(spec.default, DefaultValue::None) {
+            return Err(syn::Error::new_spanned(
+                spec.ty,
+                "Option 字段没有配置时为 None，不需要 default",
+            ));
+        }
+        Ok(spec)
+    }
+
+    /// describe 中显示的类型，去掉 to_string 产生的多余空格
+    fn type_name(&self) -> String {
+        self.ty
+            .to_token_stream()
+            .to_string()
+            .replace(" < ", "<")
+            .replace("< ", "<")
+            .replace(" >", ">")
+            .replace(" :: ", "::")
+            .replace(" ,", ",")
+            .replace("& ", "&")
+    }
+
+    /// describe 中显示的默认值
+    fn default_text(&self) -> TokenStream {
+        if self.secret && !matches!(self.default, DefaultValue::None) {
+            return quote!(::core::option::Option::Some(::std::string::String::from(
+                "***"
+            )));
+        }
+        match &self.default {
+            DefaultValue::None if option_inner(self.ty).is_some() => {
+                quote!(::core::option::Option::Some(::std::string::String::from(
+                    "None"
+                )))
+            }
+            DefaultValue::None => quote!(::core::option::Option::None),
+            DefaultValue::Trait => {
+                let ty = self.ty;
+                quote!(::core::option::Option::Some(
+                    ::std::format!("{:?}", <#ty as ::core::default::Default>::default())
+                ))
+            }
+            DefaultValue::Expr(expr) => {
+                let text = expr.to_token_stream().to_string();
+                quote!(::core::option::Option::Some(::std::string::String::from(#text)))
+            }
+        }
+    }
+}
+
+pub(crate) fn config(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = named_fields(input, "Config")?;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "Config 不支持泛型结构体",
+        ));
+    }
+    let mut prefix: Option<LitStr> = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("config"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("prefix") {
+                prefix = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("结构体上的 #[config] 只支持 prefix"))
+            }
+        })?;
+    }
+    let prefix = prefix.map(|prefix| prefix.value()).unwrap_or_default();
+
+    let mut specs = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for field in fields {
+        match FieldSpec::parse(field) {
+            Ok(spec) => specs.push(spec),
+            Err(e) => match errors.as_mut() {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let error = format_ident!("{}ConfigError", name);
+    let error_prefix = format!("加载{name}配置失败:");
+
+    // 每个字段的完整键、环境变量、类型、默认值和是否为 secret；nested 字段展开为子结构体的字段
+    let describe = specs.iter().map(|spec| {
+        let key = &spec.key;
+        let ty = spec.ty;
+        if spec.nested {
+            return quote! {
+                <#ty>::__config_fields(&__join(path, #key), prefix, out);
+            };
+        }
+        let env = match &spec.env {
+            Some(env) => quote!(::std::string::String::from(#env)),
+            None => quote!(__env_name(prefix, &__join(path, #key))),
+        };
+        let type_name = spec.type_name();
+        let default = spec.default_text();
+        let secret = spec.secret;
+        quote! {
+            out.push((__join(path, #key), #env, #type_name, #default, #secret));
+        }
+    });
+
+    let extract = specs.iter().map(|spec| {
+        let ident = spec.ident;
+        let key = &spec.key;
+        let ty = spec.ty;
+        if spec.nested {
+            return quote! {
+                let #ident = <#ty>::__config_extract(config, &__join(path, #key), errors);
+            };
+        }
+        let missing = match &spec.default {
+            DefaultValue::Expr(expr) => {
+                quote!(::core::option::Option::Some(::core::convert::Into::<#ty>::into(#expr)))
+            }
+            DefaultValue::Trait => {
+                quote!(::core::option::Option::Some(<#ty as ::core::default::Default>::default()))
+            }
+            DefaultValue::None if option_inner(ty).is_some() => {
+                quote!(::core::option::Option::Some(::core::option::Option::None))
+            }
+            DefaultValue::None => quote! {{
+                errors.push((key, ::std::string::String::from("缺少配置项")));
+                ::core::option::Option::None
+            }},
+        };
+        let invalid = if spec.secret {
+            quote!(::std::string::String::from(
+                "值无效（secret 配置项不显示具体的值）"
+            ))
+        } else {
+            quote!(::std::string::ToString::to_string(&e))
+        };
+        quote! {
+            let #ident: ::core::option::Option<#ty> = {
+                let key = __join(path, #key);
+                match config.get::<#ty>(&key) {
+                    ::core::result::Result::Ok(value) => ::core::option::Option::Some(value),
+                    ::core::result::Result::Err(::config::ConfigError::NotFound(_)) => #missing,
+                    ::core::result::Result::Err(e) => {
+                        errors.push((key, #invalid));
+                        ::core::option::Option::None
+                    }
+                }
+            };
+        }
+    });
+    let idents: Vec<&Ident> = specs.iter().map(|spec| spec.ident).collect();
+
+    Ok(quote! {
+        #[doc = "加载配置时所有出错的配置项和原因"]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis struct #error {
+            /// 完整的键路径和错误信息，文件读取失败时键为空
+            pub errors: ::std::vec::Vec<(::std::string::String, ::std::string::String)>,
+        }
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(#error_prefix)?;
+                for (key, message) in &self.errors {
+                    if key.is_empty() {
+                        ::core::write!(f, "\n  {}", message)?;
+                    } else {
+                        ::core::write!(f, "\n  {}: {}", key, message)?;
+                    }
+                }
+                ::core::result::Result::Ok(())
+            }
+        }
+
+        impl ::std::error::Error for #error {}
+
+        impl #name {
+            /// 只使用默认值和环境变量加载配置
+            #vis fn load() -> ::core::result::Result<Self, #error> {
+                Self::load_from::<&str>(&[])
+            }
+
+            /// 依次加载配置文件，再使用环境变量覆盖
+            #vis fn load_from<P: ::core::convert::AsRef<::std::path::Path>>(
+                files: &[P],
+            ) -> ::core::result::Result<Self, #error> {
+                let mut builder = ::config::Config::builder();
+                for file in files {
+                    builder = builder.add_source(::config::File::from(file.as_ref()));
+                }
+                let config = builder.build().map_err(|e| #error {
+                    errors: ::std::vec![(::std::string::String::new(), ::std::string::ToString::to_string(&e))],
+                })?;
+                Self::from_config(&config)
+            }
+
+            /// 从已经构建好的配置中取值，环境变量的优先级高于 config 中的值
+            #vis fn from_config(config: &::config::Config) -> ::core::result::Result<Self, #error> {
+                let mut builder = ::config::Config::builder().add_source(::core::clone::Clone::clone(config));
+                let mut fields = ::std::vec::Vec::new();
+                Self::__config_fields("", #prefix, &mut fields);
+                for (key, env, ..) in fields {
+                    if let ::core::result::Result::Ok(value) = ::std::env::var(&env) {
+                        builder = builder.set_override(key, value).map_err(|e| #error {
+                            errors: ::std::vec![(::std::string::String::new(), ::std::string::ToString::to_string(&e))],
+                        })?;
+                    }
+                }
+                let config = builder.build().map_err(|e| #error {
+                    errors: ::std::vec![(::std::string::String::new(), ::std::string::ToString::to_string(&e))],
+                })?;
+                let mut errors = ::std::vec::Vec::new();
+                match Self::__config_extract(&config, "", &mut errors) {
+                    ::core::option::Option::Some(value) if errors.is_empty() => ::core::result::Result::Ok(value),
+                    _ => ::core::result::Result::Err(#error { errors }),
+                }
+            }
+
+            /// 所有配置项的键、类型、默认值和环境变量
+            #vis fn describe() -> ::std::string::String {
+                let mut fields = ::std::vec::Vec::new();
+                Self::__config_fields("", #prefix, &mut fields);
+                let mut rows = ::std::vec![[
+                    ::std::string::String::from("KEY"),
+                    ::std::string::String::from("TYPE"),
+                    ::std::string::String::from("DEFAULT"),
+                    ::std::string::String::from("ENV"),
+                ]];
+                for (key, env, ty, default, secret) in fields {
+                    let default = default.unwrap_or_else(|| ::std::string::String::from("（必填）"));
+                    let key = if secret { ::std::format!("{} [secret]", key) } else { key };
+                    rows.push([key, ::std::string::String::from(ty), default, env]);
+                }
+                let mut widths = [0usize; 4];
+                for row in &rows {
+                    for (width, cell) in widths.iter_mut().zip(row) {
+                        *width = (*width).max(cell.chars().count());
+                    }
+                }
+                let mut text = ::std::string::String::new();
+                for row in &rows {
+                    let mut line = ::std::string::String::new();
+                    for (i, cell) in row.iter().enumerate() {
+                        line.push_str(cell);
+                        if i + 1 < row.len() {
+                            let padding = widths[i] - cell.chars().count() + 2;
+                            line.extend(::std::iter::repeat_n(' ', padding));
+                        }
+                    }
+                    text.push_str(line.trim_end());
+                    text.push('\n');
+                }
+                text
+            }
+
+            #[doc(hidden)]
+            #[allow(clippy::type_complexity)]
+            pub fn __config_fields(
+                path: &str,
+                prefix: &str,
+                out: &mut ::std::vec::Vec<(
+                    ::std::string::String,
+                    ::std::string::String,
+                    &'static str,
+                    ::core::option::Option<::std::string::String>,
+                    bool,
+                )>,
+            ) {
+                fn __join(path: &str, key: &str) -> ::std::string::String {
+                    if path.is_empty() { ::std::string::String::from(key) } else { ::std::format!("{}.{}", path, key) }
+                }
+                fn __env_name(prefix: &str, key: &str) -> ::std::string::String {
+                    let name = key.replace('.', "__").to_uppercase();
+                    if prefix.is_empty() { name } else { ::std::format!("{}_{}", prefix, name) }
+                }
+                #(#describe)*
+            }
+
+            #[doc(hidden)]
+            pub fn __config_extract(
+                config: &::config::Config,
+                path: &str,
+                errors: &mut ::std::vec::Vec<(::std::string::String, ::std::string::String)>,
+            ) -> ::core::option::Option<Self> {
+                fn __join(path: &str, key: &str) -> ::std::string::String {
+                    if path.is_empty() { ::std::string::String::from(key) } else { ::std::format!("{}.{}", path, key) }
+                }
+                #(#extract)*
+                ::core::option::Option::Some(Self {
+                    #(#idents: #idents?,)*
+                })
+            }
+        }
+    })
+}
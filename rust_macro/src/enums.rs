@@ -0,0 +1,596 @@
+/**
+ * 枚举工具派生宏，生成的都是固有方法和常量，不需要额外的 trait
+ *
+ * 1、EnumIter：iter() 按声明顺序返回所有变体，带字段的变体使用字段的 Default::default()。
+ * 2、EnumCount：COUNT 常量，变体的个数。
+ * 3、FromStr、Display：变体和字符串互相转换，Display 另外生成 as_str()，FromStr 的错误类型为 枚举名ParseError。
+ *    枚举上的 #[enum_str(rename_all = "snake_case", ascii_case_insensitive)]：
+ *      rename_all 支持 lowercase、UPPERCASE、PascalCase、camelCase、snake_case、SCREAMING_SNAKE_CASE、kebab-case、SCREAMING-KEBAB-CASE；
+ *      ascii_case_insensitive 解析时忽略 ASCII 大小写。
+ *    变体上的 #[enum_str(rename = "已成交", alias = "filled", alias = "done")]：rename 优先于 rename_all，alias 只用于解析。
+ *    两个变体对应同一个字符串时编译报错。
+ * 4、EnumDiscriminants：生成只有变体名称的 枚举名Discriminants（Debug、Clone、Copy、PartialEq、Eq、Hash），
+ *    以及 discriminant() 和 From<&枚举>；#[discriminants(name = "OrderKind", derive(PartialOrd, EnumIter))] 修改名称、增加 derive。
+ * 5、VariantTable：每个变体对应一组常量，用于标签、颜色、处理函数等查表的场景。
+ *    枚举上的 #[table(label: &'static str, color: u32 = 0)] 声明列的名称、类型和可选的默认值，
+ *    变体上的 #[table(label = "已成交", color = 0x52c41a)] 给出每一列的值。
+ *    生成 枚举名Meta 结构体（Debug、Clone、Copy），TABLE 常量（变体名称和 Meta），meta() 以及每一列的同名方法。
+ */
+use std::collections::HashMap;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    Attribute, Data, DeriveInput, Expr, Fields, Ident, LitStr, Path, Token, Type, Variant,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    token::Comma,
+};
+
+/// 枚举的所有变体，其他类型返回指向类型名称的错误
+fn enum_variants<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<Variant, Comma>> {
+    match &input.data {
+        Data::Enum(data) => Ok(&data.variants),
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{derive} 只支持枚举"),
+        )),
+    }
+}
+
+/// 构造变体，字段使用 Default::default()
+fn construct(variant: &Variant) -> TokenStream {
+    let ident = &variant.ident;
+    match &variant.fields {
+        Fields::Unit => quote!(Self::#ident),
+        Fields::Unnamed(fields) => {
+            let defaults = fields
+                .unnamed
+                .iter()
+                .map(|_| quote!(::core::default::Default::default()));
+            quote!(Self::#ident(#(#defaults),*))
+        }
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(Self::#ident { #(#names: ::core::default::Default::default()),* })
+        }
+    }
+}
+
+/// 合并多个错误，所有错误一起报告
+fn push_error(errors: &mut Option<syn::Error>, error: syn::Error) {
+    match errors.as_mut() {
+        Some(errors) => errors.combine(error),
+        None => *errors = Some(error),
+    }
+}
+
+pub(crate) fn enum_iter(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let variants = enum_variants(input, "EnumIter")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let count = variants.len();
+    let constructs = variants.iter().map(construct);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// 按声明顺序返回所有变体
+            #vis fn iter() -> impl ::core::iter::DoubleEndedIterator<Item = Self> + ::core::iter::ExactSizeIterator {
+                let variants: [Self; #count] = [#(#constructs),*];
+                ::core::iter::IntoIterator::into_iter(variants)
+            }
+        }
+    })
+}
+
+pub(crate) fn enum_count(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let variants = enum_variants(input, "EnumCount")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let count = variants.len();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// 变体的个数
+            #vis const COUNT: usize = #count;
+        }
+    })
+}
+
+/// rename_all 支持的命名规则
+const RENAME_RULES: &[&str] = &[
+    "lowercase",
+    "UPPERCASE",
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "kebab-case",
+    "SCREAMING-KEBAB-CASE",
+];
+
+/// 把变体名称拆分为单词：HttpServer、HTTPServer、Http_Server 都拆分为 Http/HTTP 和 Server
+fn split_words(ident: &str) -> Vec<String> {
+    let chars: Vec<char> = ident.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn apply_rename(ident: &str, rule: &str) -> String {
+    let words = split_words(ident);
+    let join = |separator: &str, upper: bool| {
+        words
+            .iter()
+            .map(|word| {
+                if upper {
+                    word.to_uppercase()
+                } else {
+                    word.to_lowercase()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+    match rule {
+        "lowercase" => join("", false),
+        "UPPERCASE" => join("", true),
+        "PascalCase" => words.iter().map(|word| capitalize(word)).collect(),
+        "camelCase" => words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                if i == 0 {
+                    word.to_lowercase()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect(),
+        "snake_case" => join("_", false),
+        "SCREAMING_SNAKE_CASE" => join("_", true),
+        "kebab-case" => join("-", false),
+        "SCREAMING-KEBAB-CASE" => join("-", true),
+        _ => unreachable!("rename_all 已经校验"),
+    }
+}
+
+/// 变体对应的字符串和别名
+struct StrSpec<'a> {
+    variant: &'a Variant,
+    name: String,
+    aliases: Vec<LitStr>,
+}
+
+struct StrOptions<'a> {
+    case_insensitive: bool,
+    specs: Vec<StrSpec<'a>>,
+}
+
+impl<'a> StrOptions<'a> {
+    /// FromStr 和 Display 共用 #[enum_str] 属性，检查重复的字符串
+    fn parse(input: &'a DeriveInput, derive: &str) -> syn::Result<Self> {
+        let variants = enum_variants(input, derive)?;
+        let mut rename_all: Option<String> = None;
+        let mut case_insensitive = false;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("enum_str"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    let rule: LitStr = meta.value()?.parse()?;
+                    if !RENAME_RULES.contains(&rule.value().as_str()) {
+                        return Err(syn::Error::new(
+                            rule.span(),
+                            format!("未知的命名规则，可用的规则: {}", RENAME_RULES.join("、")),
+                        ));
+                    }
+                    rename_all = Some(rule.value());
+                } else if meta.path.is_ident("ascii_case_insensitive") {
+                    case_insensitive = true;
+                } else {
+                    return Err(meta
+                        .error("枚举上的 #[enum_str] 只支持 rename_all、ascii_case_insensitive"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let mut specs = Vec::new();
+        let mut errors: Option<syn::Error> = None;
+        // 已经使用的字符串，忽略大小写时按小写比较
+        let mut used: HashMap<String, &Ident> = HashMap::new();
+        for variant in variants {
+            let ident_text = variant.ident.to_string();
+            let mut spec = StrSpec {
+                variant,
+                name: match &rename_all {
+                    Some(rule) => apply_rename(&ident_text, rule),
+                    None => ident_text,
+                },
+                aliases: Vec::new(),
+            };
+            let mut rename_span = variant.ident.span();
+            for attr in variant
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("enum_str"))
+            {
+                let result = attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        let rename: LitStr = meta.value()?.parse()?;
+                        spec.name = rename.value();
+                        rename_span = rename.span();
+                    } else if meta.path.is_ident("alias") {
+                        spec.aliases.push(meta.value()?.parse()?);
+                    } else {
+                        return Err(meta.error("变体上的 #[enum_str] 只支持 rename、alias"));
+                    }
+                    Ok(())
+                });
+                if let Err(e) = result {
+                    push_error(&mut errors, e);
+                }
+            }
+
+            let names = std::iter::once((spec.name.clone(), rename_span)).chain(
+                spec.aliases
+                    .iter()
+                    .map(|alias| (alias.value(), alias.span())),
+            );
+            for (text, span) in names {
+                let key = if case_insensitive {
+                    text.to_ascii_lowercase()
+                } else {
+                    text.clone()
+                };
+                if let Some(other) = used.insert(key, &variant.ident)
+                    && other != &variant.ident
+                {
+                    push_error(
+                        &mut errors,
+                        syn::Error::new(span, format!("字符串 \"{text}\" 已经被变体 {other} 使用")),
+                    );
+                }
+            }
+            specs.push(spec);
+        }
+        if let Some(errors) = errors {
+            return Err(errors);
+        }
+        Ok(StrOptions {
+            case_insensitive,
+            specs,
+        })
+    }
+}
+
+pub(crate) fn from_str(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let options = StrOptions::parse(input, "FromStr")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let error = format_ident!("{}ParseError", name);
+    let expected = options
+        .specs
+        .iter()
+        .map(|spec| spec.name.as_str())
+        .collect::<Vec<_>>()
+        .join("、");
+    let error_message = format!("无效的 {name}: '{{}}'，可选值: {expected}");
+    let case_insensitive = options.case_insensitive;
+    let branches = options.specs.iter().map(|spec| {
+        let names = std::iter::once(spec.name.clone())
+            .chain(spec.aliases.iter().map(LitStr::value))
+            .map(|text| {
+                if case_insensitive {
+                    quote!(s.eq_ignore_ascii_case(#text))
+                } else {
+                    quote!(s == #text)
+                }
+            });
+        let construct = construct(spec.variant);
+        quote! {
+            if #(#names)||* {
+                return ::core::result::Result::Ok(#construct);
+            }
+        }
+    });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[doc = "字符串不对应任何变体"]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis struct #error {
+            /// 解析失败的字符串
+            pub input: ::std::string::String,
+        }
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                ::core::write!(f, #error_message, self.input)
+            }
+        }
+
+        impl ::std::error::Error for #error {}
+
+        impl #impl_generics ::core::str::FromStr for #name #ty_generics #where_clause {
+            type Err = #error;
+
+            fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                #(#branches)*
+                ::core::result::Result::Err(#error {
+                    input: ::std::string::ToString::to_string(s),
+                })
+            }
+        }
+    })
+}
+
+pub(crate) fn display(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let options = StrOptions::parse(input, "Display")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let idents = options.specs.iter().map(|spec| &spec.variant.ident);
+    let names = options.specs.iter().map(|spec| &spec.name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// 变体对应的字符串，和 Display 相同
+            #vis fn as_str(&self) -> &'static str {
+                match *self {
+                    #(Self::#idents { .. } => #names,)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.pad(self.as_str())
+            }
+        }
+    })
+}
+
+pub(crate) fn discriminants(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let variants = enum_variants(input, "EnumDiscriminants")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+    let mut kind = format_ident!("{}Discriminants", name);
+    let mut derives: Vec<Path> = Vec::new();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("discriminants"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let lit: LitStr = meta.value()?.parse()?;
+                kind = lit.parse()?;
+            } else if meta.path.is_ident("derive") {
+                meta.parse_nested_meta(|derive| {
+                    derives.push(derive.path);
+                    Ok(())
+                })?;
+            } else {
+                return Err(meta.error("#[discriminants] 只支持 name、derive"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let idents: Vec<&Ident> = variants.iter().map(|variant| &variant.ident).collect();
+    // 保留显式的判别值，只有 #[doc] 和 #[enum_str] 等属性不复制
+    let declarations = variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        match &variant.discriminant {
+            Some((_, value)) => quote!(#ident = #value),
+            None => quote!(#ident),
+        }
+    });
+    let doc = format!("{name} 的变体，不包含字段");
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash #(, #derives)*)]
+        #vis enum #kind {
+            #(#declarations,)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// 不包含字段的变体
+            #vis fn discriminant(&self) -> #kind {
+                match *self {
+                    #(Self::#idents { .. } => #kind::#idents,)*
+                }
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<&#name #ty_generics> for #kind #where_clause {
+            fn from(value: &#name #ty_generics) -> Self {
+                value.discriminant()
+            }
+        }
+    })
+}
+
+/// 枚举上声明的一列：名称: 类型 = 默认值
+struct Column {
+    ident: Ident,
+    ty: Type,
+    default: Option<Expr>,
+}
+
+impl Parse for Column {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+        let default = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        Ok(Column { ident, ty, default })
+    }
+}
+
+fn table_attr(attrs: &[Attribute]) -> Option<&Attribute> {
+    attrs.iter().find(|attr| attr.path().is_ident("table"))
+}
+
+pub(crate) fn variant_table(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let variants = enum_variants(input, "VariantTable")?;
+    let Some(schema) = table_attr(&input.attrs) else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "VariantTable 需要在枚举上使用 #[table(名称: 类型, ...)] 声明列",
+        ));
+    };
+    let columns: Vec<Column> = schema
+        .parse_args_with(Punctuated::<Column, Token![,]>::parse_terminated)?
+        .into_iter()
+        .collect();
+    let mut seen: HashMap<String, Span> = HashMap::new();
+    for column in &columns {
+        if seen
+            .insert(column.ident.to_string(), column.ident.span())
+            .is_some()
+        {
+            return Err(syn::Error::new(column.ident.span(), "重复的列"));
+        }
+    }
+
+    // 每个变体每一列的值，缺少的列使用默认值
+    let mut rows: Vec<Vec<TokenStream>> = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    for variant in variants {
+        let Some(attr) = table_attr(&variant.attrs) else {
+            push_error(
+                &mut errors,
+                syn::Error::new_spanned(&variant.ident, "缺少 #[table(...)]"),
+            );
+            continue;
+        };
+        let mut values: HashMap<String, Expr> = HashMap::new();
+        let result = attr.parse_nested_meta(|meta| {
+            let Some(ident) = meta.path.get_ident() else {
+                return Err(meta.error("列名必须是标识符"));
+            };
+            let key = ident.to_string();
+            if !seen.contains_key(&key) {
+                let names: Vec<String> = columns.iter().map(|c| c.ident.to_string()).collect();
+                return Err(meta.error(format!(
+                    "未声明的列 {key}，已声明的列: {}",
+                    names.join("、")
+                )));
+            }
+            let value: Expr = meta.value()?.parse()?;
+            if values.insert(key, value).is_some() {
+                return Err(meta.error("重复的列"));
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            push_error(&mut errors, e);
+            continue;
+        }
+        let mut row = Vec::new();
+        let mut missing = Vec::new();
+        for column in &columns {
+            match values.remove(&column.ident.to_string()) {
+                Some(value) => row.push(value.to_token_stream()),
+                None => match &column.default {
+                    Some(default) => row.push(default.to_token_stream()),
+                    None => missing.push(column.ident.to_string()),
+                },
+            }
+        }
+        if !missing.is_empty() {
+            push_error(
+                &mut errors,
+                syn::Error::new_spanned(attr, format!("缺少列: {}", missing.join("、"))),
+            );
+            continue;
+        }
+        rows.push(row);
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let meta = format_ident!("{}Meta", name);
+    let doc = format!("{name} 每个变体对应的常量");
+    let column_idents: Vec<&Ident> = columns.iter().map(|column| &column.ident).collect();
+    let column_types: Vec<&Type> = columns.iter().map(|column| &column.ty).collect();
+    let idents: Vec<&Ident> = variants.iter().map(|variant| &variant.ident).collect();
+    let variant_names = idents.iter().map(|ident| ident.to_string());
+    let entries = rows
+        .iter()
+        .map(|row| quote!(#meta { #(#column_idents: #row),* }));
+    let indexes = 0..idents.len();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #meta {
+            #(pub #column_idents: #column_types,)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// 按声明顺序排列的变体名称和常量
+            #vis const TABLE: &'static [(&'static str, #meta)] = &[
+                #((#variant_names, #entries),)*
+            ];
+
+            /// 变体对应的常量
+            #vis fn meta(&self) -> &'static #meta {
+                match *self {
+                    #(Self::#idents { .. } => &Self::TABLE[#indexes].1,)*
+                }
+            }
+
+            #(
+                #vis fn #column_idents(&self) -> #column_types {
+                    self.meta().#column_idents
+                }
+            )*
+        }
+    })
+}
@@ -0,0 +1,508 @@
+/**
+ * Error 派生宏，和 thiserror 类似，额外生成错误码和 HTTP 状态码
+ *
+ * 只支持枚举，每个变体使用以下属性：
+ *  1、#[msg("...")]：Display 的模板，{name}、{name:?} 引用具名字段，{0}、{1:?} 引用元组字段；
+ *     #[msg(transparent)] 把 Display 和 source 转发给唯一的字段。
+ *  2、#[code(1001)]：错误码。没有标注的变体和枚举的判别值规则相同，为上一个变体的错误码加 1，
+ *     第一个变体默认为枚举上 #[code(base = 1000)] 的值（默认为 1）。新增变体请加在最后，已有的错误码就不会变化。
+ *  3、#[status(404)]：HTTP 状态码，默认为枚举上 #[status(500)] 的值（默认为 500）。
+ *  4、字段上的 #[from] 生成 From 实现并作为 source，#[source] 或者名称为 source 的字段作为 source，
+ *     字段类型可以是实现了 Error 的类型，也可以是 Box<dyn Error + Send + Sync>。
+ *
+ * 生成的方法：code()、status()、name()（变体名称）、to_json()。
+ * to_json 返回 {"code":1001,"status":404,"error":"NotFound","message":"..."}，不依赖 serde。
+ *
+ * 枚举上的 #[error(serialize, actix)] 生成可选的实现，使用的 crate 需要有对应的依赖：
+ *  1、serialize：serde::Serialize，字段和 to_json 相同，可以作为 Tauri 命令的错误类型。
+ *  2、actix：actix_web::ResponseError，响应的状态码为 status()，响应体为 to_json()。
+ */
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Data, DeriveInput, Fields, Ident, LitInt, LitStr, Member, Variant, spanned::Spanned,
+};
+
+const DEFAULT_STATUS: u16 = 500;
+const DEFAULT_BASE: u32 = 1;
+
+enum Message {
+    Template(LitStr),
+    Transparent,
+}
+
+struct VariantSpec<'a> {
+    variant: &'a Variant,
+    message: Message,
+    code: u32,
+    status: u16,
+    /// 作为 source 的字段，是否为 #[from]
+    source: Option<(Member, bool)>,
+}
+
+#[derive(Default)]
+struct EnumOptions {
+    base: Option<u32>,
+    status: Option<u16>,
+    serialize: bool,
+    actix: bool,
+}
+
+fn parse_status(lit: &LitInt) -> syn::Result<u16> {
+    let status: u16 = lit.base10_parse()?;
+    if !(100..=599).contains(&status) {
+        return Err(syn::Error::new(
+            lit.span(),
+            "HTTP 状态码必须在 100 到 599 之间",
+        ));
+    }
+    Ok(status)
+}
+
+impl EnumOptions {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = EnumOptions::default();
+        for attr in attrs {
+            if attr.path().is_ident("code") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("base") {
+                        options.base = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                        Ok(())
+                    } else {
+                        Err(meta.error("枚举上的 #[code] 只支持 base"))
+                    }
+                })?;
+            } else if attr.path().is_ident("status") {
+                options.status = Some(parse_status(&attr.parse_args()?)?);
+            } else if attr.path().is_ident("error") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("serialize") {
+                        options.serialize = true;
+                    } else if meta.path.is_ident("actix") {
+                        options.actix = true;
+                    } else {
+                        return Err(meta.error("#[error] 只支持 serialize、actix"));
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// 把模板中的字段引用改写为 match 中绑定的变量名称：{name} 不变，{0} 改为 {_0}
+fn rewrite_template(template: &LitStr, fields: &Fields) -> syn::Result<LitStr> {
+    let text = template.value();
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push_str("{{");
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push_str("}}");
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+                let (argument, spec) = match placeholder.split_once(':') {
+                    Some((argument, spec)) => (argument, Some(spec)),
+                    None => (placeholder.as_str(), None),
+                };
+                let argument = argument.trim();
+                let binding = match fields {
+                    Fields::Unnamed(unnamed) => match argument.parse::<usize>() {
+                        Ok(index) if index < unnamed.unnamed.len() => format!("_{index}"),
+                        _ => {
+                            return Err(syn::Error::new(
+                                template.span(),
+                                format!("模板中的 {{{argument}}} 不是该变体的字段序号"),
+                            ));
+                        }
+                    },
+                    Fields::Named(named)
+                        if named.named.iter().any(|field| {
+                            field.ident.as_ref().is_some_and(|ident| ident == argument)
+                        }) =>
+                    {
+                        argument.to_string()
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            template.span(),
+                            format!("模板中的 {{{argument}}} 不是该变体的字段"),
+                        ));
+                    }
+                };
+                output.push('{');
+                output.push_str(&binding);
+                if let Some(spec) = spec {
+                    output.push(':');
+                    output.push_str(spec);
+                }
+                output.push('}');
+            }
+            c => output.push(c),
+        }
+    }
+    Ok(LitStr::new(&output, template.span()))
+}
+
+impl<'a> VariantSpec<'a> {
+    /// next_code 为 None 表示上一个变体的错误码已经是 u32::MAX，必须显式标注 #[code]
+    fn parse(
+        variant: &'a Variant,
+        options: &EnumOptions,
+        next_code: Option<u32>,
+    ) -> syn::Result<Self> {
+        let mut message = None;
+        let mut code = None;
+        let mut status = None;
+        for attr in &variant.attrs {
+            if attr.path().is_ident("msg") {
+                message = Some(match attr.parse_args::<LitStr>() {
+                    Ok(template) => {
+                        Message::Template(rewrite_template(&template, &variant.fields)?)
+                    }
+                    Err(_) => {
+                        let ident: Ident = attr.parse_args().map_err(|_| {
+                            syn::Error::new_spanned(
+                                attr,
+                                "#[msg] 的参数为模板字符串或者 transparent",
+                            )
+                        })?;
+                        if ident != "transparent" {
+                            return Err(syn::Error::new_spanned(
+                                ident,
+                                "#[msg] 的参数为模板字符串或者 transparent",
+                            ));
+                        }
+                        if variant.fields.len() != 1 {
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                "#[msg(transparent)] 的变体只能有一个字段",
+                            ));
+                        }
+                        Message::Transparent
+                    }
+                });
+            } else if attr.path().is_ident("code") {
+                code = Some(attr.parse_args::<LitInt>()?.base10_parse()?);
+            } else if attr.path().is_ident("status") {
+                status = Some(parse_status(&attr.parse_args()?)?);
+            }
+        }
+        let message = message.ok_or_else(|| {
+            syn::Error::new_spanned(
+                &variant.ident,
+                "缺少 #[msg(\"...\")] 或者 #[msg(transparent)]",
+            )
+        })?;
+
+        let code = code.or(next_code).ok_or_else(|| {
+            syn::Error::new_spanned(
+                &variant.ident,
+                "上一个变体的错误码为 u32::MAX，无法加 1，请用 #[code(...)] 指定错误码",
+            )
+        })?;
+
+        let mut source = None;
+        for (index, field) in variant.fields.iter().enumerate() {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(index.into()),
+            };
+            let from = field.attrs.iter().any(|attr| attr.path().is_ident("from"));
+            let marked = from
+                || field
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("source"))
+                || field.ident.as_ref().is_some_and(|ident| ident == "source");
+            if from && variant.fields.len() != 1 {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "#[from] 的变体只能有一个字段",
+                ));
+            }
+            if marked {
+                if source.is_some() {
+                    return Err(syn::Error::new_spanned(field, "只能有一个 source 字段"));
+                }
+                source = Some((member, from));
+            }
+        }
+        if source.is_none() && matches!(message, Message::Transparent) {
+            source = Some((variant.fields.members().next().expect("唯一的字段"), false));
+        }
+
+        Ok(Self {
+            variant,
+            message,
+            code,
+            status: status.or(options.status).unwrap_or(DEFAULT_STATUS),
+            source,
+        })
+    }
+
+    /// match 分支的模式，字段绑定为名称或者 _0、_1
+    fn pattern(&self) -> TokenStream {
+        let ident = &self.variant.ident;
+        match &self.variant.fields {
+            Fields::Named(named) => {
+                let names = named.named.iter().map(|field| &field.ident);
+                quote!(Self::#ident { #(#names),* })
+            }
+            Fields::Unnamed(unnamed) => {
+                let names = (0..unnamed.unnamed.len()).map(|i| format_ident!("_{}", i));
+                quote!(Self::#ident(#(#names),*))
+            }
+            Fields::Unit => quote!(Self::#ident),
+        }
+    }
+
+    /// 字段在 match 中绑定的变量
+    fn binding(member: &Member) -> Ident {
+        match member {
+            Member::Named(ident) => ident.clone(),
+            Member::Unnamed(index) => format_ident!("_{}", index.index),
+        }
+    }
+}
+
+pub(crate) fn error(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "Error 只支持枚举"));
+    };
+    let options = EnumOptions::parse(&input.attrs)?;
+
+    let mut specs: Vec<VariantSpec> = Vec::new();
+    let mut errors: Option<syn::Error> = None;
+    let mut next_code = Some(options.base.unwrap_or(DEFAULT_BASE));
+    let mut codes: HashMap<u32, &Ident> = HashMap::new();
+    for variant in &data.variants {
+        let result = VariantSpec::parse(variant, &options, next_code).and_then(|spec| {
+            if let Some(other) = codes.insert(spec.code, &variant.ident) {
+                return Err(syn::Error::new(
+                    variant.span(),
+                    format!("错误码{}和变体{other}重复", spec.code),
+                ));
+            }
+            Ok(spec)
+        });
+        match result {
+            Ok(spec) => {
+                next_code = spec.code.checked_add(1);
+                specs.push(spec);
+            }
+            Err(e) => match errors.as_mut() {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+
+    let name = &input.ident;
+    let name_text = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let patterns: Vec<TokenStream> = specs.iter().map(VariantSpec::pattern).collect();
+    let variant_idents: Vec<&Ident> = specs.iter().map(|spec| &spec.variant.ident).collect();
+    let variants: Vec<String> = variant_idents
+        .iter()
+        .map(|ident| ident.to_string())
+        .collect();
+    let codes: Vec<u32> = specs.iter().map(|spec| spec.code).collect();
+    let statuses: Vec<u16> = specs.iter().map(|spec| spec.status).collect();
+
+    let displays = specs.iter().map(|spec| match &spec.message {
+        Message::Template(template) => quote!(::core::write!(__formatter, #template)),
+        Message::Transparent => {
+            let binding = VariantSpec::binding(&spec.source.as_ref().expect("唯一的字段").0);
+            quote!(::core::fmt::Display::fmt(#binding, __formatter))
+        }
+    });
+    let sources = specs
+        .iter()
+        .map(|spec| match (&spec.source, &spec.message) {
+            (Some((member, _)), Message::Transparent) => {
+                let binding = VariantSpec::binding(member);
+                quote!(::std::error::Error::source((*#binding).as_dyn_error()))
+            }
+            (Some((member, _)), _) => {
+                let binding = VariantSpec::binding(member);
+                quote!(::core::option::Option::Some((*#binding).as_dyn_error()))
+            }
+            (None, _) => quote!(::core::option::Option::None),
+        });
+    // source 字段可能是 Box<dyn Error + Send + Sync>，它没有实现 Error，不能直接转换为 &dyn Error，
+    // 和 thiserror 一样用辅助 trait 转换：字段类型实现了 Error 时使用第一个实现，Box 解引用后使用 dyn Error 的实现
+    let as_dyn_error = specs.iter().any(|spec| spec.source.is_some()).then(|| {
+        quote! {
+            trait AsDynError {
+                fn as_dyn_error(&self) -> &(dyn ::std::error::Error + 'static);
+            }
+
+            impl<T: ::std::error::Error + 'static> AsDynError for T {
+                fn as_dyn_error(&self) -> &(dyn ::std::error::Error + 'static) {
+                    self
+                }
+            }
+
+            impl AsDynError for dyn ::std::error::Error + 'static {
+                fn as_dyn_error(&self) -> &(dyn ::std::error::Error + 'static) {
+                    self
+                }
+            }
+
+            impl AsDynError for dyn ::std::error::Error + ::core::marker::Send + 'static {
+                fn as_dyn_error(&self) -> &(dyn ::std::error::Error + 'static) {
+                    self
+                }
+            }
+
+            impl AsDynError for dyn ::std::error::Error + ::core::marker::Send + ::core::marker::Sync + 'static {
+                fn as_dyn_error(&self) -> &(dyn ::std::error::Error + 'static) {
+                    self
+                }
+            }
+        }
+    });
+    let froms = specs.iter().filter_map(|spec| {
+        let (member, true) = spec.source.as_ref()? else {
+            return None;
+        };
+        let field = spec.variant.fields.iter().next()?;
+        let ty = &field.ty;
+        let ident = &spec.variant.ident;
+        let construct = match member {
+            Member::Named(field) => quote!(Self::#ident { #field: source }),
+            Member::Unnamed(_) => quote!(Self::#ident(source)),
+        };
+        Some(quote! {
+            impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+                fn from(source: #ty) -> Self {
+                    #construct
+                }
+            }
+        })
+    });
+
+    let serialize = options.serialize.then(|| {
+        quote! {
+            impl #impl_generics ::serde::Serialize for #name #ty_generics #where_clause {
+                fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> ::core::result::Result<S::Ok, S::Error> {
+                    use ::serde::ser::SerializeStruct;
+                    let mut state = serializer.serialize_struct(#name_text, 4)?;
+                    state.serialize_field("code", &self.code())?;
+                    state.serialize_field("status", &self.status())?;
+                    state.serialize_field("error", self.name())?;
+                    state.serialize_field("message", &::std::string::ToString::to_string(self))?;
+                    state.end()
+                }
+            }
+        }
+    });
+    let actix = options.actix.then(|| {
+        quote! {
+            impl #impl_generics ::actix_web::ResponseError for #name #ty_generics #where_clause {
+                fn status_code(&self) -> ::actix_web::http::StatusCode {
+                    ::actix_web::http::StatusCode::from_u16(self.status())
+                        .unwrap_or(::actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+                }
+
+                fn error_response(&self) -> ::actix_web::HttpResponse {
+                    ::actix_web::HttpResponse::build(self.status_code())
+                        .content_type("application/json")
+                        .body(self.to_json())
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::core::fmt::Display for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn fmt(&self, __formatter: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    #(#patterns => #displays,)*
+                }
+            }
+        }
+
+        impl #impl_generics ::std::error::Error for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                #as_dyn_error
+                match self {
+                    #(#patterns => #sources,)*
+                }
+            }
+        }
+
+        #(#froms)*
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// 错误码
+            pub fn code(&self) -> u32 {
+                match self {
+                    #(Self::#variant_idents { .. } => #codes,)*
+                }
+            }
+
+            /// HTTP 状态码
+            pub fn status(&self) -> u16 {
+                match self {
+                    #(Self::#variant_idents { .. } => #statuses,)*
+                }
+            }
+
+            /// 变体名称
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#variant_idents { .. } => #variants,)*
+                }
+            }
+
+            /// {"code":1001,"status":404,"error":"NotFound","message":"..."}
+            pub fn to_json(&self) -> ::std::string::String {
+                let message = ::std::string::ToString::to_string(self);
+                let mut escaped = ::std::string::String::with_capacity(message.len() + 2);
+                for c in message.chars() {
+                    match c {
+                        '"' => escaped.push_str("\\\""),
+                        '\\' => escaped.push_str("\\\\"),
+                        '\n' => escaped.push_str("\\n"),
+                        '\r' => escaped.push_str("\\r"),
+                        '\t' => escaped.push_str("\\t"),
+                        c if (c as u32) < 0x20 => escaped.push_str(&::std::format!("\\u{:04x}", c as u32)),
+                        c => escaped.push(c),
+                    }
+                }
+                ::std::format!(
+                    "{{\"code\":{},\"status\":{},\"error\":\"{}\",\"message\":\"{}\"}}",
+                    self.code(),
+                    self.status(),
+                    self.name(),
+                    escaped
+                )
+            }
+        }
+
+        #serialize
+        #actix
+    })
+}
@@ -0,0 +1,377 @@
+/**
+ * 过程宏展开结果的快照测试，快照保存在 src/snapshots 目录，随代码一起提交
+ *
+ * 生成的代码有变化时测试失败，使用 cargo insta review 逐个检查并接受新的快照，
+ * 或者 INSTA_UPDATE=always cargo test -p rust_macro 全部接受，评审时查看 .snap 文件的 diff。
+ * 展开结果使用 prettyplease 格式化，不依赖本机是否安装了 rustfmt，快照在不同环境下保持一致。
+ *
+ * make_greeting、HelloMacro、hello_macro_attr 直接使用 proc_macro::TokenStream，只能在编译器中运行，不在这里测试。
+ */
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, ItemFn, parse_quote};
+
+fn format(tokens: TokenStream) -> String {
+    let file = syn::parse2(tokens).expect("展开结果不是合法的条目");
+    prettyplease::unparse(&file)
+}
+
+fn derive(expand: fn(&DeriveInput) -> syn::Result<TokenStream>, input: DeriveInput) -> String {
+    format(expand(&input).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn attribute(
+    expand: fn(TokenStream, ItemFn) -> syn::Result<TokenStream>,
+    args: TokenStream,
+    item: ItemFn,
+) -> String {
+    format(expand(args, item).unwrap_or_else(syn::Error::into_compile_error))
+}
+
+fn user() -> DeriveInput {
+    parse_quote! {
+        pub struct User<'a> {
+            name: String,
+            #[getter(copy)]
+            age: u32,
+            #[getter(rename = "nick", vis = "pub(crate)")]
+            #[setter(skip)]
+            nickname: &'a str,
+            #[getter(skip)]
+            #[with(skip)]
+            password: String,
+        }
+    }
+}
+
+#[test]
+fn getters() {
+    insta::assert_snapshot!(derive(crate::getter::getters, user()));
+}
+
+#[test]
+fn setters() {
+    insta::assert_snapshot!(derive(crate::getter::setters, user()));
+}
+
+#[test]
+fn withs() {
+    insta::assert_snapshot!(derive(crate::getter::withs, user()));
+}
+
+#[test]
+fn builder() {
+    insta::assert_snapshot!(derive(
+        crate::builder::builder,
+        parse_quote! {
+            #[builder(validate = check_server)]
+            pub struct Server {
+                #[builder(into)]
+                host: String,
+                #[builder(validate = check_port)]
+                port: u16,
+                #[builder(default = 30)]
+                timeout: u64,
+                #[builder(validate = check_tls)]
+                tls: Option<bool>,
+                #[builder(push = "tag")]
+                tags: Vec<String>,
+            }
+        }
+    ));
+}
+
+#[test]
+fn error() {
+    insta::assert_snapshot!(derive(
+        crate::error::error,
+        parse_quote! {
+            #[code(base = 1000)]
+            #[status(500)]
+            pub enum ApiError {
+                #[msg("用户 {id} 不存在")]
+                #[status(404)]
+                NotFound { id: u64 },
+                #[msg("读取文件失败: {0}")]
+                Io(#[from] std::io::Error),
+                #[msg(transparent)]
+                #[code(2000)]
+                Other(Box<dyn std::error::Error + Send + Sync>),
+                #[msg("文件 {f} 格式错误")]
+                Format { f: String },
+                #[msg("调用远程服务失败")]
+                Remote { source: Box<dyn std::error::Error + Send + Sync> },
+            }
+        }
+    ));
+}
+
+#[test]
+fn timed() {
+    insta::assert_snapshot!(attribute(
+        crate::instrument::timed,
+        quote!(name = "load"),
+        parse_quote! {
+            pub async fn load_user(id: u64) -> Option<String> {
+                Some(id.to_string())
+            }
+        }
+    ));
+}
+
+#[test]
+fn traced() {
+    insta::assert_snapshot!(attribute(
+        crate::instrument::traced,
+        quote!(level = "info", redact(password)),
+        parse_quote! {
+            fn login(user: &str, password: &str) -> Result<u64, String> {
+                Ok(user.len() as u64)
+            }
+        }
+    ));
+}
+
+#[test]
+fn retry() {
+    insta::assert_snapshot!(attribute(
+        crate::instrument::retry,
+        quote!(times = 5, backoff = "100ms", factor = 2, max_backoff = "1s"),
+        parse_quote! {
+            async fn fetch(url: &str) -> std::io::Result<String> {
+                Ok(url.to_string())
+            }
+        }
+    ));
+}
+
+#[test]
+fn memoize() {
+    insta::assert_snapshot!(attribute(
+        crate::memoize::memoize,
+        quote!(capacity = 100, ttl = "30s"),
+        parse_quote! {
+            fn lookup(name: &str, id: u32) -> Result<String, String> {
+                Ok(format!("{name}-{id}"))
+            }
+        }
+    ));
+}
+
+#[test]
+fn memoize_async() {
+    insta::assert_snapshot!(attribute(
+        crate::memoize::memoize,
+        TokenStream::new(),
+        parse_quote! {
+            async fn fetch(url: String) -> String {
+                url
+            }
+        }
+    ));
+}
+
+#[test]
+fn config() {
+    insta::assert_snapshot!(derive(
+        crate::config::config,
+        parse_quote! {
+            #[config(prefix = "APP")]
+            pub struct AppConfig {
+                #[config(default = "127.0.0.1")]
+                host: String,
+                #[config(env = "HTTP_PORT", default = 8080u16)]
+                port: u16,
+                #[config(default)]
+                debug: bool,
+                name: Option<String>,
+                #[config(secret)]
+                token: String,
+                #[config(nested)]
+                database: DatabaseConfig,
+            }
+        }
+    ));
+}
+
+fn order_status() -> DeriveInput {
+    parse_quote! {
+        #[enum_str(rename_all = "snake_case", ascii_case_insensitive)]
+        #[discriminants(name = "StatusKind", derive(PartialOrd))]
+        #[table(label: &'static str, color: u32 = 0x8c8c8c)]
+        pub enum OrderStatus {
+            #[enum_str(rename = "已成交", alias = "filled")]
+            #[table(label = "已成交", color = 0x52c41a)]
+            Filled,
+            #[table(label = "部分成交")]
+            PartFilled { amount: u64 },
+            #[table(label = "已撤单")]
+            Cancelled(String),
+        }
+    }
+}
+
+#[test]
+fn enum_iter() {
+    insta::assert_snapshot!(derive(crate::enums::enum_iter, order_status()));
+}
+
+#[test]
+fn enum_count() {
+    insta::assert_snapshot!(derive(crate::enums::enum_count, order_status()));
+}
+
+#[test]
+fn from_str() {
+    insta::assert_snapshot!(derive(crate::enums::from_str, order_status()));
+}
+
+#[test]
+fn display() {
+    insta::assert_snapshot!(derive(crate::enums::display, order_status()));
+}
+
+#[test]
+fn discriminants() {
+    insta::assert_snapshot!(derive(crate::enums::discriminants, order_status()));
+}
+
+#[test]
+fn variant_table() {
+    insta::assert_snapshot!(derive(crate::enums::variant_table, order_status()));
+}
+
+/**
+ * sql! 的表结构使用 tests/sql_schema.txt，快照中的绝对路径替换为 $CARGO_MANIFEST_DIR
+ * 展开结果是表达式，放在函数体中才能用 rustfmt 格式化
+ */
+fn sql(input: TokenStream) -> String {
+    let input = syn::parse2(input).expect("sql! 参数");
+    let expanded = match crate::sql::sql(input) {
+        Ok(statement) => format(quote!(fn statement() -> ::sea_orm::Statement { #statement })),
+        Err(err) => format(err.into_compile_error()),
+    };
+    expanded.replace(env!("CARGO_MANIFEST_DIR"), "$CARGO_MANIFEST_DIR")
+}
+
+#[test]
+fn sql_select() {
+    insta::assert_snapshot!(sql(quote! {
+        schema = "tests/sql_schema.txt",
+        Postgres,
+        "SELECT s.id, c.city_name FROM driving_school s JOIN city c ON s.city_id = c.id \
+         WHERE c.city_code = ? AND c.longitude > ? ORDER BY s.id LIMIT ?",
+        code, 116.0, 10
+    }));
+}
+
+#[test]
+fn sql_insert() {
+    insta::assert_snapshot!(sql(quote! {
+        schema = "tests/sql_schema.txt",
+        MySql,
+        "INSERT INTO city (city_name, city_code, longitude) VALUES (?, ?, ?)",
+        name, "110100", None::<f64>
+    }));
+}
+
+#[test]
+fn sql_errors() {
+    let cases = [
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "SELECT id, city_nme FROM city"
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "SELECT * FROM cities"
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "SELECT s.id FROM driving_school s JOIN city c ON s.city_id = c.id WHERE city_name = ?",
+            name
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            Sqlite,
+            "UPDATE city SET city_name = 'Beijing' WHERE city_code = 110100"
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "DELETE FROM city WHERE id = ? OR city_code = ?",
+            1
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "INSERT INTO city (city_name, city_code) VALUES (?, ?, ?)",
+            a,
+            b,
+            c
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "SELECT id FROM city WHERE ? = ?",
+            1,
+            1
+        ),
+        quote!(
+            schema = "tests/sql_schema.txt",
+            MySql,
+            "SELECT id FORM city"
+        ),
+        quote!(schema = "tests/missing.txt", MySql, "SELECT id FROM city"),
+    ];
+    let errors: Vec<String> = cases.into_iter().map(sql).collect();
+    insta::assert_snapshot!(errors.join("\n"));
+}
+
+#[test]
+fn mcp_tools() {
+    let item = parse_quote! {
+        #[tool_router]
+        impl EmailServer {
+            /// 发送邮件
+            #[tool]
+            async fn send_email(
+                &self,
+                /// 收件人邮箱地址
+                #[arg(validate = check_email)]
+                to: String,
+                /// 邮件主题
+                #[arg(length(min = 1, max = 100))]
+                subject: String,
+                body: Option<String>,
+            ) -> Result<CallToolResult, ErrorData> {
+                Ok(CallToolResult::success(vec![Content::text(to)]))
+            }
+
+            #[tool(description = "获取联系人列表")]
+            async fn contact_list(
+                &self,
+                #[arg(extract)] context: RequestContext<RoleServer>,
+                /// 联系人分组名称
+                group: Option<String>,
+                /// 返回的联系人数量
+                #[arg(default = 10, range(min = 1, max = 100))]
+                limit: i32,
+            ) -> Result<CallToolResult, ErrorData> {
+                Ok(CallToolResult::success(vec![]))
+            }
+
+            #[tool(description = "获取所有工具列表")]
+            async fn list_tool(&self) -> Result<CallToolResult, ErrorData> {
+                Ok(CallToolResult::success(vec![]))
+            }
+        }
+    };
+    insta::assert_snapshot!(format(
+        crate::mcp::mcp_tools(TokenStream::new(), item)
+            .unwrap_or_else(syn::Error::into_compile_error)
+    ));
+}
@@ -1,183 +1,224 @@
-extern crate proc_macro;
-
-use std::convert::From;
-use std::iter::Extend;
-
-use proc_macro2::{TokenTree, TokenStream, Delimiter};
-use quote::quote;
+/**
+ * 访问器派生宏：Getters、Setters、With
+ *
+ * 只支持具名字段的结构体，字段上可以使用同名属性调整生成的方法：
+ *  1、#[getter(skip)]：不生成方法。
+ *  2、#[getter(rename = "name")]：方法名称，默认 getter 为字段名，setter 为 set_字段名，with 为 with_字段名。
+ *  3、#[getter(vis = "pub(crate)")]：方法的可见性，默认为 pub；也可以写在结构体上作为所有字段的默认值。
+ *  4、#[getter(copy)]：按值返回字段。过程宏看不到类型是否实现了 Copy，
+ *     基本类型（整数、浮点数、bool、char）和共享引用会自动按值返回，其他 Copy 类型需要手动标注。
+ *
+ * Setters 和 With 使用 #[setter(...)]、#[with(...)]，支持 skip、rename、vis。
+ */
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
 use syn::{
-    Data,
-    Fields,
-    DeriveInput,
-    FieldsNamed,
-    Type,
-    AttrStyle,
-    Ident,
-    Lit,
-    parse_macro_input,
-    parse_str,
+    Attribute, Data, DeriveInput, Field, Fields, Ident, LitStr, Type, Visibility,
+    punctuated::Punctuated, token::Comma,
 };
 
-static INVALID_STRUCT: &str = "Struct must be a named struct. Not unnamed or unit.";
-static INVALID_VARIANT: &str = "Variant must be a struct. Not an enum or union.";
-static VALID_ATTR: &str = "Either #[getter(skip)] or #[getter(rename=\"name\")].";
-
-enum FieldAttribute {
-    Skip,
-    Rename(Ident),
+/// 基本类型中实现了 Copy 的类型
+const COPY_TYPES: &[&str] = &[
+    "bool", "char", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128",
+    "usize", "f32", "f64",
+];
+
+#[derive(Default)]
+pub(crate) struct Options {
+    pub skip: bool,
+    pub rename: Option<Ident>,
+    pub vis: Option<Visibility>,
+    pub copy: bool,
 }
 
-fn parse_attribute_tokens(token_stream: TokenStream) -> FieldAttribute {
-    // There must be tokens
-    let first_token_tree = token_stream
-        .into_iter()
-        .next()
-        .unwrap_or_else(|| panic!("The getter attribute has no tokens. {}", VALID_ATTR));
-
-    // First token tree needs to be a parentheses grouping
-    let mut inner_token_iter = match first_token_tree {
-        TokenTree::Group(group) => match group.delimiter() {
-            Delimiter::Parenthesis => group
-                .stream()
-                .into_iter(),
-            _ => panic!("The getter attribute grouping must be parentheses. {}",
-                        VALID_ATTR),
-        },
-        _ => panic!("The getter attribute must have a grouping. {}", VALID_ATTR),
-    };
-
-    let second_token_tree = inner_token_iter
-        .next()
-        .unwrap_or_else(|| panic!("No getter option has been specified. {}", VALID_ATTR));
-
-    let third_token_tree = inner_token_iter.next();
-    let fourth_token_tree = inner_token_iter.next();
-    let fifth_token_tree = inner_token_iter.next();
-
-    // Second token needs to be either skip or rename
-    match second_token_tree {
-        TokenTree::Ident(ident) => if ident == "skip" {
-            // Check if more tokens follow.
-            if third_token_tree.is_some() {
-                panic!("No further tokens must follow skip. {}", VALID_ATTR);
+impl Options {
+    /// 解析名称为 name 的属性，allow_copy 为 false 时不接受 copy
+    pub fn parse(attrs: &[Attribute], name: &str, allow_copy: bool) -> syn::Result<Self> {
+        let mut options = Options::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident(name)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    options.skip = true;
+                } else if meta.path.is_ident("rename") {
+                    options.rename = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                } else if meta.path.is_ident("vis") {
+                    options.vis = Some(meta.value()?.parse::<LitStr>()?.parse()?);
+                } else if allow_copy && meta.path.is_ident("copy") {
+                    options.copy = true;
+                } else {
+                    let expected = if allow_copy {
+                        "skip、rename、vis、copy"
+                    } else {
+                        "skip、rename、vis"
+                    };
+                    return Err(
+                        meta.error(format!("#[{name}] 不支持该选项，可用的选项: {expected}"))
+                    );
+                }
+                Ok(())
+            })?;
+            if options.skip && (options.rename.is_some() || options.vis.is_some()) {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "skip 不能和 rename、vis 同时使用",
+                ));
             }
-            return FieldAttribute::Skip;
-        } else if ident != "rename" {
-            panic!("Invalid attribute {}. {}", &ident, VALID_ATTR);
-        },
-        _ => panic!("No identifier found. {}", VALID_ATTR),
-    }
-    
-    match third_token_tree {
-        Some(TokenTree::Punct(p)) => if p.as_char() != '=' {
-            panic!("Punctuation must be '='. {}", VALID_ATTR);
-        },
-        _ => panic!("rename must be followed by '=' punctuation. {}", VALID_ATTR),
+        }
+        Ok(options)
     }
+}
 
-    let name = match fourth_token_tree {
-        Some(TokenTree::Literal(l)) => match Lit::new(l) {
-            Lit::Str(lstr) => lstr.value(),
-            _ => panic!("Name litera must be a string. {}", VALID_ATTR),
+/// 结构体的具名字段，其他类型返回指向类型名称的错误
+pub(crate) fn named_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<Field, Comma>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                format!("{derive} 只支持具名字段的结构体"),
+            )),
         },
-        _ => panic!("Name must be a literal. {}", VALID_ATTR),
-    };
-    
-    if fifth_token_tree.is_some() {
-        panic!("No futher tokens must follow the literal in rename. {}", VALID_ATTR);
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{derive} 只支持结构体"),
+        )),
     }
-   
-    let new_name = match parse_str::<Ident>(&name) {
-        Ok(nn) => nn,
-        Err(e) => panic!("{}", e),
-    };
-    
-    FieldAttribute::Rename(new_name)
 }
 
-fn isolate_named_fields<'a>(ast: &'a DeriveInput) -> Result<&'a FieldsNamed, &'static str> {
-    match ast.data {
-        Data::Struct(ref structure) => {
-            match structure.fields {
-                Fields::Named(ref fields) => Ok(fields),
-                Fields::Unnamed(_) | Fields::Unit => Err(INVALID_STRUCT),
+/// 为每个字段调用 generate，收集所有字段的错误后一起返回，生成的方法放在同一个 impl 块中
+pub(crate) fn expand(
+    input: &DeriveInput,
+    derive: &str,
+    attr: &str,
+    allow_copy: bool,
+    generate: impl Fn(&Field, &Ident, Options, Visibility) -> TokenStream,
+) -> syn::Result<TokenStream> {
+    let fields = named_fields(input, derive)?;
+    let defaults = Options::parse(&input.attrs, attr, false)?;
+    if defaults.skip || defaults.rename.is_some() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("结构体上的 #[{attr}] 只支持 vis"),
+        ));
+    }
+    let default_vis = defaults.vis.unwrap_or_else(|| syn::parse_quote!(pub));
+
+    let mut errors: Option<syn::Error> = None;
+    let mut methods = Vec::new();
+    for field in fields {
+        match Options::parse(&field.attrs, attr, allow_copy) {
+            Ok(options) if options.skip => {}
+            Ok(options) => {
+                let ident = field.ident.as_ref().expect("具名字段");
+                let vis = options.vis.clone().unwrap_or_else(|| default_vis.clone());
+                methods.push(generate(field, ident, options, vis));
             }
-        },
-        Data::Enum(_) | Data::Union(_) => Err(INVALID_VARIANT),
+            Err(e) => match errors.as_mut() {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
     }
-}
-
-fn getters_from_fields(fields: &FieldsNamed) -> Vec<proc_macro2::TokenStream> {
-    fields.named
-        .iter()
-        .map(|field| {
-            let field_name = field.ident.as_ref().unwrap(); // Must never fail.
-            let returns = &field.ty;
-            let maybie_lifetime = match &field.ty {
-                Type::Reference(type_reference) => type_reference.lifetime.as_ref(),
-                _ => None,
-            };
-
-            // Check for skip or rename field attributes. We deal with the last attribute.
-            let mf_attribute: Option<FieldAttribute> = field.attrs
-                .iter()
-                .fold(None, |m_last, attr| match (attr.path.is_ident("getter"), m_last) {
-                    (true, _) => {
-                        match attr.style {
-                            AttrStyle::Outer => (),
-                            AttrStyle::Inner(_) => panic!(
-                                "The getter attribute is an outer not inner attribute."
-                            ),
-                        }
 
-                        Some(parse_attribute_tokens(attr.tokens.to_owned()))
-                    },
-                    (false, Some(last)) => Some(last),
-                    (false, None) => None,
-                });            
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    })
+}
 
-            let maybie_getter_name: Option<&Ident> = match mf_attribute {
-                Some(FieldAttribute::Rename(ref name)) => Some(name),
-                Some(FieldAttribute::Skip) => None,
-                None => Some(field_name)
-            };
+fn is_copy(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) => reference.mutability.is_none(),
+        Type::Path(path) => {
+            path.qself.is_none()
+                && path
+                    .path
+                    .get_ident()
+                    .is_some_and(|ident| COPY_TYPES.iter().any(|copy| ident == copy))
+        }
+        Type::Paren(paren) => is_copy(&paren.elem),
+        Type::Group(group) => is_copy(&group.elem),
+        _ => false,
+    }
+}
 
-            match (maybie_lifetime, maybie_getter_name) {
-                (Some(lifetime), Some(getter_name)) => quote!(
-                    pub fn #getter_name(&#lifetime self) -> #returns {
-                        self.#field_name
+pub(crate) fn getters(input: &DeriveInput) -> syn::Result<TokenStream> {
+    expand(
+        input,
+        "Getters",
+        "getter",
+        true,
+        |field, ident, options, vis| {
+            let method = options.rename.unwrap_or_else(|| ident.clone());
+            let ty = &field.ty;
+            if options.copy || is_copy(ty) {
+                quote! {
+                    #[inline]
+                    #vis fn #method(&self) -> #ty {
+                        self.#ident
                     }
-                ),
-                (None, Some(getter_name)) => quote!(
-                    pub fn #getter_name(&self) -> &#returns {
-                        &self.#field_name
+                }
+            } else {
+                quote! {
+                    #[inline]
+                    #vis fn #method(&self) -> &#ty {
+                        &self.#ident
                     }
-                ),
-                (_, None) => quote!(),
+                }
+            }
+        },
+    )
+}
+
+pub(crate) fn setters(input: &DeriveInput) -> syn::Result<TokenStream> {
+    expand(
+        input,
+        "Setters",
+        "setter",
+        false,
+        |field, ident, options, vis| {
+            let method = options.rename.unwrap_or_else(|| {
+                format_ident!("set_{}", ident.to_string().trim_start_matches("r#"))
+            });
+            let ty = &field.ty;
+            quote! {
+                #[inline]
+                #vis fn #method(&mut self, #ident: #ty) -> &mut Self {
+                    self.#ident = #ident;
+                    self
+                }
             }
-        })
-        .collect()
+        },
+    )
 }
 
-/// # Derive getters
-/// Generate getter methods for all named struct fields in a seperate struct `impl` block.
-/// Getter methods share the name of the field they're 'getting'. Methods return an
-/// immutable reference to the field.
-#[proc_macro_derive(Getters, attributes(getter))]
-pub fn getters(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let ast = parse_macro_input!(input as DeriveInput);
- 
-    let struct_name = &ast.ident;
-    let (impl_generics, struct_generics, where_clause) = ast.generics.split_for_impl();
-    
-    let fields = isolate_named_fields(&ast).unwrap();
-    let methods = getters_from_fields(fields);
-    
-    quote!(
-        impl #impl_generics #struct_name #struct_generics
-            #where_clause
-        {
-            #(#methods)*
-        }
-    ).into()
-}
\ No newline at end of file
+pub(crate) fn withs(input: &DeriveInput) -> syn::Result<TokenStream> {
+    expand(
+        input,
+        "With",
+        "with",
+        false,
+        |field, ident, options, vis| {
+            let method = options.rename.unwrap_or_else(|| {
+                format_ident!("with_{}", ident.to_string().trim_start_matches("r#"))
+            });
+            let ty = &field.ty;
+            quote! {
+                #[inline]
+                #vis fn #method(mut self, #ident: #ty) -> Self {
+                    self.#ident = #ident;
+                    self
+                }
+            }
+        },
+    )
+}
@@ -0,0 +1,364 @@
+/**
+ * 函数属性宏：#[timed]、#[traced]、#[retry]
+ *
+ * 生成的函数保留原函数完整的签名（属性、可见性、async、泛型、参数和返回值），只改写函数体。
+ * 函数名称为 module_path!()::函数名，日志使用 log crate，异步函数的 retry 使用 tokio::time::sleep 等待。
+ *
+ *  1、#[timed]：记录函数的执行时间，函数返回（包括提前 return、? 和 panic）或者 Future 被丢弃时记录。
+ *     #[timed(hook = path)]：path 的类型为 fn(&'static str, Duration)，默认使用 log::debug! 输出；name = "..." 修改函数名称。
+ *  2、#[traced]：函数开始时输出参数，结束时输出返回值和耗时，参数和返回值需要实现 Debug。
+ *     level = "info"：日志级别，默认为 debug，返回 Err 时固定为 error；skip(a, b)：不输出的参数；
+ *     redact(password)：输出为 ***；redact_result：返回值（Ok 中的值）输出为 ***。
+ *  3、#[retry(times = 3, backoff = "100ms")]：返回 Err 时重试，times 为最多重试的次数（默认 3），backoff 为重试前等待的时间（默认不等待）。
+ *     factor = 2：每次重试后等待时间乘以 factor（默认 1）；max_backoff = "5s"：等待时间的上限，factor 为 1 时也限制 backoff；
+ *     when = path：path 的类型为 fn(&E) -> bool，返回 false 的错误不重试。
+ *     每次重试都重新执行函数体，函数体不能获取参数的所有权（需要时先 clone）。
+ */
+use std::time::Duration;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{ToTokens, quote};
+use syn::{
+    FnArg, Ident, ItemFn, LitInt, LitStr, Pat, Path, ReturnType, Type, meta::ParseNestedMeta,
+    parse::Parser,
+};
+
+/// 解析 "500us"、"100ms"、"30s"、"5m"、"1h" 格式的时间
+pub(crate) fn parse_duration(lit: &LitStr) -> syn::Result<Duration> {
+    let text = lit.value();
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| syn::Error::new(lit.span(), "时间格式为数字加单位，例如 100ms、30s"))?;
+    let seconds = |multiplier: u64| {
+        number
+            .checked_mul(multiplier)
+            .map(Duration::from_secs)
+            .ok_or_else(|| syn::Error::new(lit.span(), "时间太长，超出了 Duration 的范围"))
+    };
+    let duration = match unit.trim() {
+        "ns" => Duration::from_nanos(number),
+        "us" => Duration::from_micros(number),
+        "ms" => Duration::from_millis(number),
+        "s" => Duration::from_secs(number),
+        "m" => seconds(60)?,
+        "h" => seconds(3600)?,
+        _ => {
+            return Err(syn::Error::new(
+                lit.span(),
+                "时间单位只支持 ns、us、ms、s、m、h",
+            ));
+        }
+    };
+    Ok(duration)
+}
+
+/// 按秒和纳秒生成 Duration，超过 u64 纳秒（约 584 年）的时间也不会被截断
+pub(crate) fn duration_tokens(duration: Duration) -> TokenStream {
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    quote!(::std::time::Duration::new(#secs, #nanos))
+}
+
+/// 解析属性宏的参数，使用和 #[derive] 的字段属性相同的 parse_nested_meta 格式
+pub(crate) fn parse_args(
+    args: TokenStream,
+    parser: impl FnMut(ParseNestedMeta) -> syn::Result<()>,
+) -> syn::Result<()> {
+    syn::meta::parser(parser).parse2(args)
+}
+
+/// 函数的完整名称，在生成的函数体中为常量 __NAME
+fn name_const(item: &ItemFn, name: Option<LitStr>) -> TokenStream {
+    match name {
+        Some(name) => quote!(const __NAME: &str = #name;),
+        None => {
+            let ident = item.sig.ident.to_string();
+            quote!(
+                const __NAME: &str = ::core::concat!(::core::module_path!(), "::", #ident);
+            )
+        }
+    }
+}
+
+fn contains_impl_trait(ty: &Type) -> bool {
+    fn walk(tokens: TokenStream) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => ident == "impl",
+            TokenTree::Group(group) => walk(group.stream()),
+            _ => false,
+        })
+    }
+    walk(ty.to_token_stream())
+}
+
+/// __result 的类型标注，没有返回值时为 ()；返回值包含 impl Trait 时不能标注，由编译器推断
+fn result_type(item: &ItemFn) -> TokenStream {
+    match &item.sig.output {
+        ReturnType::Default => quote!(: ()),
+        ReturnType::Type(_, ty) if contains_impl_trait(ty) => quote!(),
+        ReturnType::Type(_, ty) => quote!(: #ty),
+    }
+}
+
+/// 执行原函数体的表达式：放在闭包（异步函数为异步闭包）中立即调用，函数体中的 return 和 ? 只作用于闭包。
+/// 闭包标注了返回值的类型，? 的错误类型可以正常推断；返回值包含 impl Trait 时无法标注，由编译器推断。
+fn call_body(item: &ItemFn) -> TokenStream {
+    let block = &item.block;
+    let output = match &item.sig.output {
+        ReturnType::Type(_, ty) if contains_impl_trait(ty) => quote!(),
+        ReturnType::Type(_, ty) => quote!(-> #ty),
+        ReturnType::Default => quote!(-> ()),
+    };
+    if item.sig.asyncness.is_some() {
+        quote!((async || #output #block)().await)
+    } else {
+        quote!((|| #output #block)())
+    }
+}
+
+/// 返回值是否为 Result（包括 io::Result 这类别名）
+fn is_result(item: &ItemFn) -> bool {
+    match &item.sig.output {
+        ReturnType::Type(_, ty) => match ty.as_ref() {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Result"),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    }
+}
+
+/// 用新的函数体替换原函数体，保留签名
+fn with_body(item: &ItemFn, body: TokenStream) -> TokenStream {
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let sig = &item.sig;
+    quote! {
+        #(#attrs)*
+        #vis #sig {
+            #body
+        }
+    }
+}
+
+pub(crate) fn timed(args: TokenStream, item: ItemFn) -> syn::Result<TokenStream> {
+    let mut hook: Option<Path> = None;
+    let mut name: Option<LitStr> = None;
+    parse_args(args, |meta| {
+        if meta.path.is_ident("hook") {
+            hook = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("#[timed] 只支持 hook、name"));
+        }
+        Ok(())
+    })?;
+
+    let name = name_const(&item, name);
+    let record = match hook {
+        Some(hook) => quote!(#hook(__NAME, self.0.elapsed())),
+        None => quote!(::log::debug!("{} 耗时 {:?}", __NAME, self.0.elapsed())),
+    };
+    let stmts = &item.block.stmts;
+    // 计时器在函数体的所有局部变量之前创建，最后被丢弃
+    Ok(with_body(
+        &item,
+        quote! {
+            #name
+            let __timed = {
+                struct __Timed(::std::time::Instant);
+                impl ::core::ops::Drop for __Timed {
+                    fn drop(&mut self) {
+                        #record;
+                    }
+                }
+                __Timed(::std::time::Instant::now())
+            };
+            #(#stmts)*
+        },
+    ))
+}
+
+fn parse_idents(meta: &ParseNestedMeta, idents: &mut Vec<Ident>) -> syn::Result<()> {
+    meta.parse_nested_meta(|inner| {
+        idents.push(inner.path.require_ident()?.clone());
+        Ok(())
+    })
+}
+
+pub(crate) fn traced(args: TokenStream, item: ItemFn) -> syn::Result<TokenStream> {
+    let mut level = quote!(::log::Level::Debug);
+    let mut skip: Vec<Ident> = Vec::new();
+    let mut redact: Vec<Ident> = Vec::new();
+    let mut redact_result = false;
+    parse_args(args, |meta| {
+        if meta.path.is_ident("level") {
+            let value: LitStr = meta.value()?.parse()?;
+            level = match value.value().to_lowercase().as_str() {
+                "trace" => quote!(::log::Level::Trace),
+                "debug" => quote!(::log::Level::Debug),
+                "info" => quote!(::log::Level::Info),
+                "warn" => quote!(::log::Level::Warn),
+                "error" => quote!(::log::Level::Error),
+                _ => {
+                    return Err(syn::Error::new(
+                        value.span(),
+                        "日志级别只支持 trace、debug、info、warn、error",
+                    ));
+                }
+            };
+        } else if meta.path.is_ident("skip") {
+            parse_idents(&meta, &mut skip)?;
+        } else if meta.path.is_ident("redact") {
+            parse_idents(&meta, &mut redact)?;
+        } else if meta.path.is_ident("redact_result") {
+            redact_result = true;
+        } else {
+            return Err(meta.error("#[traced] 只支持 level、skip、redact、redact_result"));
+        }
+        Ok(())
+    })?;
+
+    // 只输出 name: T 形式的参数，self 和解构的参数不输出
+    let params: Vec<&Ident> = item
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(typed) => match typed.pat.as_ref() {
+                Pat::Ident(pat) => Some(&pat.ident),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    for ident in skip.iter().chain(&redact) {
+        if !params.contains(&ident) {
+            return Err(syn::Error::new_spanned(ident, "函数没有这个参数"));
+        }
+    }
+
+    let mut format = Vec::new();
+    let mut values = Vec::new();
+    for ident in params.iter().filter(|ident| !skip.contains(ident)) {
+        let name = ident.to_string();
+        if redact.contains(ident) {
+            format.push(format!("{name} = ***"));
+        } else {
+            format.push(format!("{name} = {{:?}}"));
+            values.push(quote!(&#ident));
+        }
+    }
+    let enter = format!("-> {{}}({})", format.join(", "));
+
+    let name = name_const(&item, None);
+    let call = call_body(&item);
+    let ret = result_type(&item);
+    let exit = if is_result(&item) {
+        let ok = if redact_result {
+            quote!(::log::log!(#level, "<- {} = Ok(***) [{:?}]", __NAME, __elapsed))
+        } else {
+            quote!(::log::log!(#level, "<- {} = Ok({:?}) [{:?}]", __NAME, value, __elapsed))
+        };
+        quote! {
+            match &__result {
+                ::core::result::Result::Ok(value) => #ok,
+                ::core::result::Result::Err(error) => {
+                    ::log::log!(::log::Level::Error, "<- {} = Err({:?}) [{:?}]", __NAME, error, __elapsed)
+                }
+            }
+        }
+    } else if redact_result {
+        quote!(::log::log!(#level, "<- {} = *** [{:?}]", __NAME, __elapsed))
+    } else {
+        quote!(::log::log!(#level, "<- {} = {:?} [{:?}]", __NAME, &__result, __elapsed))
+    };
+
+    Ok(with_body(
+        &item,
+        quote! {
+            #name
+            ::log::log!(#level, #enter, __NAME #(, #values)*);
+            let __start = ::std::time::Instant::now();
+            let __result #ret = #call;
+            let __elapsed = __start.elapsed();
+            #exit;
+            __result
+        },
+    ))
+}
+
+pub(crate) fn retry(args: TokenStream, item: ItemFn) -> syn::Result<TokenStream> {
+    let mut times: u32 = 3;
+    let mut backoff = Duration::ZERO;
+    let mut factor: u32 = 1;
+    let mut max_backoff: Option<Duration> = None;
+    let mut when: Option<Path> = None;
+    parse_args(args, |meta| {
+        if meta.path.is_ident("times") {
+            times = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+        } else if meta.path.is_ident("backoff") {
+            backoff = parse_duration(&meta.value()?.parse()?)?;
+        } else if meta.path.is_ident("factor") {
+            factor = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+        } else if meta.path.is_ident("max_backoff") {
+            max_backoff = Some(parse_duration(&meta.value()?.parse()?)?);
+        } else if meta.path.is_ident("when") {
+            when = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("#[retry] 只支持 times、backoff、factor、max_backoff、when"));
+        }
+        Ok(())
+    })?;
+    if !is_result(&item) {
+        return Err(syn::Error::new_spanned(
+            &item.sig,
+            "#[retry] 只能用于返回 Result 的函数",
+        ));
+    }
+
+    let call = call_body(&item);
+    let ret = result_type(&item);
+    let initial = duration_tokens(max_backoff.map_or(backoff, |max| backoff.min(max)));
+    let sleep = if item.sig.asyncness.is_some() {
+        quote!(::tokio::time::sleep(__delay).await)
+    } else {
+        quote!(::std::thread::sleep(__delay))
+    };
+    let when = when.map(|when| quote!(&& #when(__error)));
+    let grow = (factor != 1).then(|| {
+        let limit = max_backoff.map(|max| {
+            let max = duration_tokens(max);
+            quote!(.min(#max))
+        });
+        quote!(__delay = __delay.saturating_mul(#factor) #limit;)
+    });
+
+    Ok(with_body(
+        &item,
+        quote! {
+            let mut __attempt: u32 = 0;
+            let mut __delay = #initial;
+            loop {
+                let __result #ret = #call;
+                match &__result {
+                    ::core::result::Result::Err(__error) if __attempt < #times #when => {
+                        __attempt += 1;
+                        #sleep;
+                        #grow
+                    }
+                    _ => return __result,
+                }
+            }
+        },
+    ))
+}
@@ -1,8 +1,19 @@
+mod builder;
+mod config;
 pub(crate) mod declaration_macro_demo;
+mod enums;
+mod error;
+#[cfg(test)]
+mod expand_test;
+mod getter;
+mod instrument;
+mod mcp;
+mod memoize;
+mod sql;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{DeriveInput, ItemFn, LitStr, parse_macro_input};
+use syn::{DeriveInput, ItemFn, ItemImpl, LitStr, parse_macro_input};
 
 /**
  * 过程宏允许你编写自定义的宏，这些宏可以在编译时生成或修改代码。过程宏分为三种类型：函数宏、派生宏和属性宏。
@@ -19,7 +30,6 @@ use syn::{DeriveInput, ItemFn, LitStr, parse_macro_input};
  * 过程宏只能定义在lib.rs文件中; 过程宏的输入和输出都是TokenStream类型
  * 不能在定义过程宏的同一个 crate 中使用该过程宏，建议在 tests 目录下创建集成测试来使用此宏
  */
-
 //  函数宏类似于函数调用，使用#[proc_macro]属性定义。
 #[proc_macro]
 pub fn make_greeting(input: TokenStream) -> TokenStream {
@@ -70,15 +80,210 @@ pub fn hello_macro_attr(_: TokenStream, item: TokenStream) -> TokenStream {
        block: Block,           // 函数体的代码块
     */
     let input = parse_macro_input!(item as ItemFn); // ItemFn 类型（函数）
-    let name = &input.sig.ident; // 函数名称
+    let sig = &input.sig; // 函数签名，保留参数、返回值、async 和泛型
     let block = &input.block; // 函数体的代码块
-    let _attrs = &input.attrs; // 函数的属性列表
+    let attrs = &input.attrs; // 函数的属性列表
+    let vis = &input.vis;
 
     let result = quote! {
-        fn #name() {
+        #(#attrs)*
+        #vis #sig {
             println!("hello macro");
             #block
         }
     };
     result.into()
 }
+
+/**
+ * 访问器派生宏，字段属性见 getter.rs
+ *
+ * #[derive(Getters)] 为每个字段生成 fn x(&self) -> &T，基本类型和共享引用按值返回
+ */
+#[proc_macro_derive(Getters, attributes(getter))]
+pub fn getters_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    getter::getters(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// #[derive(Setters)] 为每个字段生成 fn set_x(&mut self, x: T) -> &mut Self
+#[proc_macro_derive(Setters, attributes(setter))]
+pub fn setters_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    getter::setters(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// #[derive(With)] 为每个字段生成 fn with_x(mut self, x: T) -> Self，用于链式构造
+#[proc_macro_derive(With, attributes(with))]
+pub fn with_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    getter::withs(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * Builder 派生宏，字段属性见 builder.rs
+ *
+ * #[derive(Builder)] 生成 UserBuilder，必填字段在编译期检查，build 返回所有校验失败的字段
+ */
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn builder_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    builder::builder(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * Error 派生宏，变体属性见 error.rs
+ *
+ * 根据 #[msg("...")] 模板生成 Display、Error、From，另外生成 code()、status()、to_json()
+ */
+#[proc_macro_derive(Error, attributes(msg, code, status, from, source, error))]
+pub fn error_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    error::error(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * 函数属性宏，参数见 instrument.rs
+ *
+ * #[timed] 记录执行时间，#[traced] 输出参数和返回值，#[retry] 在返回 Err 时重试，生成的函数保留原函数的签名
+ */
+#[proc_macro_attribute]
+pub fn timed(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    instrument::timed(args.into(), item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn traced(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    instrument::traced(args.into(), item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn retry(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    instrument::retry(args.into(), item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * 按参数缓存函数的返回值，参数见 memoize.rs
+ *
+ * #[memoize(capacity = 100, ttl = "30s")]，异步函数合并并发的相同调用，另外生成 函数名_clear_cache 和 函数名_cache_stats
+ */
+#[proc_macro_attribute]
+pub fn memoize(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    memoize::memoize(args.into(), item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * 分层加载配置的派生宏，属性见 config.rs
+ *
+ * 默认值 < 配置文件（TOML、YAML、JSON、INI） < 带前缀的环境变量，错误汇总所有字段并带有完整的键路径，describe() 列出所有配置项
+ */
+#[proc_macro_derive(Config, attributes(config))]
+pub fn config_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    config::config(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * 枚举工具派生宏，属性见 enums.rs
+ *
+ * EnumIter 生成 iter()，EnumCount 生成 COUNT，FromStr/Display 按 #[enum_str] 的规则和字符串互相转换，
+ * EnumDiscriminants 生成不带字段的 枚举名Discriminants，VariantTable 按 #[table] 为每个变体生成一组常量
+ */
+#[proc_macro_derive(EnumIter)]
+pub fn enum_iter_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::enum_iter(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(EnumCount)]
+pub fn enum_count_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::enum_count(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromStr, attributes(enum_str))]
+pub fn from_str_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::from_str(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Display, attributes(enum_str))]
+pub fn display_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::display(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(EnumDiscriminants, attributes(discriminants))]
+pub fn enum_discriminants_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::discriminants(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(VariantTable, attributes(table))]
+pub fn variant_table_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    enums::variant_table(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * 编译期检查的 SQL，语法和检查的内容见 sql.rs
+ *
+ * sql!(MySql, "SELECT id FROM city WHERE city_code = ?", code)，根据 sql_schema.txt 检查表和列，展开为绑定参数的 sea_orm::Statement
+ */
+#[proc_macro]
+pub fn sql(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as sql::SqlInput);
+    sql::sql(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/**
+ * 把 rmcp 工具方法的参数转换为参数结构体，属性见 mcp.rs
+ *
+ * 放在 #[tool_router] 的上面，#[tool] 方法直接写 async fn send_email(&self, to: String, ...)，
+ * 生成 SendEmailArgs（文档注释作为 schemars 的描述）、参数校验和 Parameters 的解构
+ */
+#[proc_macro_attribute]
+pub fn mcp_tools(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemImpl);
+    mcp::mcp_tools(args.into(), item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,424 @@
+/**
+ * #[mcp_tools]：把 rmcp 工具方法的普通参数转换为参数结构体，工具代码只需要写函数体
+ *
+ * 放在 #[tool_router] 的上面（属性宏从上到下展开，要在 rmcp 的宏之前改写方法），处理 impl 中所有带 #[tool] 的方法：
+ *  1、除 self 之外的参数收集为 方法名（驼峰）Args 结构体，派生 Debug、Deserialize、JsonSchema，
+ *     参数改为 Parameters(方法名Args { 参数, ... }): Parameters<方法名Args>，函数体中按原来的参数名使用。
+ *  2、方法的文档注释作为结构体的文档（schemars 的描述），#[tool] 没有 description 时同时作为工具的描述；
+ *     参数上的文档注释作为字段的描述。Option 类型的参数为可选参数。
+ *  3、参数上的 #[arg(...)]：
+ *      default = 表达式：可选参数，没有传入时使用默认值，函数体中为原来的类型；
+ *      range(min = 1, max = 100)：数值的范围，min、max 可以只写一个；
+ *      length(min = 1, max = 64)：字符串的字符数或者 Vec 等集合的长度；
+ *      validate = 函数：fn(&T) -> Result<(), E>，E 实现 Display；
+ *      extract：不是工具参数，原样保留，例如 rmcp 的 RequestContext、Peer 等提取器。
+ *     range 和 length 同时写入 JSON Schema，校验在函数体之前执行，失败时返回 ErrorData::invalid_params。
+ *  4、没有工具参数的方法不生成结构体，rmcp 为它生成空的 object 输入模式。
+ *
+ * 使用的 crate 需要依赖 rmcp（启用 schemars 功能）和 serde。
+ */
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{ToTokens, format_ident, quote, quote_spanned};
+use syn::{
+    Attribute, Expr, FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, LitInt, LitStr, Meta, Pat, Path,
+    Type, parse_quote, spanned::Spanned,
+};
+
+use crate::{builder::option_inner, instrument::parse_args, memoize::to_camel_case};
+
+/// 参数上的 #[arg(...)]
+#[derive(Default)]
+struct ArgOptions {
+    default: Option<Expr>,
+    range: (Option<Expr>, Option<Expr>),
+    length: (Option<LitInt>, Option<LitInt>),
+    validate: Option<Path>,
+    extract: bool,
+}
+
+impl ArgOptions {
+    fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("arg")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    options.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("range") {
+                    meta.parse_nested_meta(|bound| {
+                        if bound.path.is_ident("min") {
+                            options.range.0 = Some(bound.value()?.parse()?);
+                        } else if bound.path.is_ident("max") {
+                            options.range.1 = Some(bound.value()?.parse()?);
+                        } else {
+                            return Err(bound.error("range 只支持 min、max"));
+                        }
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("length") {
+                    meta.parse_nested_meta(|bound| {
+                        if bound.path.is_ident("min") {
+                            options.length.0 = Some(bound.value()?.parse()?);
+                        } else if bound.path.is_ident("max") {
+                            options.length.1 = Some(bound.value()?.parse()?);
+                        } else {
+                            return Err(bound.error("length 只支持 min、max"));
+                        }
+                        Ok(())
+                    })?;
+                } else if meta.path.is_ident("validate") {
+                    options.validate = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("extract") {
+                    options.extract = true;
+                } else {
+                    return Err(
+                        meta.error("#[arg] 只支持 default、range、length、validate、extract")
+                    );
+                }
+                Ok(())
+            })?;
+            if options.extract
+                && (options.default.is_some()
+                    || options.range.0.is_some()
+                    || options.range.1.is_some()
+                    || options.length.0.is_some()
+                    || options.length.1.is_some()
+                    || options.validate.is_some())
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "extract 不能和其他参数同时使用",
+                ));
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// 一个工具参数
+struct ToolArg {
+    name: Ident,
+    mutable: bool,
+    ty: Type,
+    docs: Vec<Attribute>,
+    options: ArgOptions,
+}
+
+/// 文档注释的文本：去掉每行首尾的空白和块注释的 *，去掉首尾的空行
+fn doc_text(attrs: &[Attribute]) -> Option<String> {
+    let mut lines = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+        if let Meta::NameValue(meta) = &attr.meta
+            && let Expr::Lit(expr) = &meta.value
+            && let syn::Lit::Str(doc) = &expr.lit
+        {
+            for line in doc.value().lines() {
+                let line = line.trim();
+                lines.push(line.strip_prefix('*').unwrap_or(line).trim().to_string());
+            }
+        }
+    }
+    let start = lines.iter().position(|line| !line.is_empty())?;
+    let end = lines.iter().rposition(|line| !line.is_empty())?;
+    Some(lines[start..=end].join("\n"))
+}
+
+fn is_tool_attr(attr: &Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "tool")
+}
+
+/// #[tool] 没有 description 时使用方法的文档注释
+fn add_description(attr: &mut Attribute, docs: Option<&String>) {
+    let Some(docs) = docs else {
+        return;
+    };
+    let path = attr.path().clone();
+    match &attr.meta {
+        Meta::Path(_) => *attr = parse_quote!(#[#path(description = #docs)]),
+        Meta::List(list) => {
+            let has_description =
+                list.tokens.clone().into_iter().any(
+                    |token| matches!(token, TokenTree::Ident(ident) if ident == "description"),
+                );
+            if !has_description {
+                let tokens = &list.tokens;
+                *attr = parse_quote!(#[#path(#tokens, description = #docs)]);
+            }
+        }
+        Meta::NameValue(_) => {}
+    }
+}
+
+/// 参数的类型是否按字符计算长度
+fn is_string(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) => is_string(&reference.elem),
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "String" || segment.ident == "str"),
+        _ => false,
+    }
+}
+
+fn invalid_params(span: Span, message: TokenStream) -> TokenStream {
+    quote_spanned! {span=>
+        return ::core::result::Result::Err(::core::convert::Into::into(
+            ::rmcp::ErrorData::invalid_params(#message, ::core::option::Option::None),
+        ));
+    }
+}
+
+/// 参数的校验语句，value 为参数值的引用
+fn checks(arg: &ToolArg, ty: &Type) -> TokenStream {
+    let name = arg.name.to_string();
+    let span = arg.name.span();
+    let mut checks = Vec::new();
+    let (min, max) = &arg.options.range;
+    if let Some(min) = min {
+        let fail = invalid_params(
+            span,
+            quote!(::std::format!("参数 {} 不能小于 {}，传入的是 {:?}", #name, #min, value)),
+        );
+        checks.push(quote!(if *value < #min { #fail }));
+    }
+    if let Some(max) = max {
+        let fail = invalid_params(
+            span,
+            quote!(::std::format!("参数 {} 不能大于 {}，传入的是 {:?}", #name, #max, value)),
+        );
+        checks.push(quote!(if *value > #max { #fail }));
+    }
+    let (min, max) = &arg.options.length;
+    if min.is_some() || max.is_some() {
+        let len = if is_string(ty) {
+            quote!(value.chars().count())
+        } else {
+            quote!(value.len())
+        };
+        checks.push(quote!(let len: usize = #len;));
+        if let Some(min) = min {
+            let fail = invalid_params(
+                span,
+                quote!(::std::format!("参数 {} 的长度不能小于 {}，实际为 {}", #name, #min, len)),
+            );
+            checks.push(quote!(if len < #min { #fail }));
+        }
+        if let Some(max) = max {
+            let fail = invalid_params(
+                span,
+                quote!(::std::format!("参数 {} 的长度不能大于 {}，实际为 {}", #name, #max, len)),
+            );
+            checks.push(quote!(if len > #max { #fail }));
+        }
+    }
+    if let Some(validate) = &arg.options.validate {
+        let fail = invalid_params(
+            span,
+            quote!(::std::format!("参数 {} 无效: {}", #name, message)),
+        );
+        checks.push(quote! {
+            if let ::core::result::Result::Err(message) = #validate(value) { #fail }
+        });
+    }
+    quote!(#(#checks)*)
+}
+
+/// 函数体之前的语句：使用默认值、按原来的可变性重新绑定、校验参数
+fn prelude(arg: &ToolArg) -> TokenStream {
+    let name = &arg.name;
+    let ty = &arg.ty;
+    let mutability = arg.mutable.then(|| quote!(mut));
+    let rebind = match &arg.options.default {
+        Some(default) => quote! {
+            let #mutability #name: #ty = #name.unwrap_or_else(|| #default);
+        },
+        None if arg.mutable => quote!(let mut #name = #name;),
+        None => quote!(),
+    };
+    let check = match (arg.options.default.is_none(), option_inner(ty)) {
+        (true, Some(inner)) => {
+            let checks = checks(arg, inner);
+            (!checks.is_empty())
+                .then(|| quote!(if let ::core::option::Option::Some(value) = &#name { #checks }))
+        }
+        _ => {
+            let checks = checks(arg, ty);
+            (!checks.is_empty()).then(|| quote!({ let value = &#name; #checks }))
+        }
+    };
+    quote!(#rebind #check)
+}
+
+/// schemars 的 range、length 中的 min = .., max = ..，都没有时为 None
+fn bounds(min: &Option<impl ToTokens>, max: &Option<impl ToTokens>) -> Option<TokenStream> {
+    let min = min.as_ref().map(|min| quote!(min = #min));
+    let max = max.as_ref().map(|max| quote!(max = #max));
+    let bounds: Vec<_> = min.into_iter().chain(max).collect();
+    (!bounds.is_empty()).then(|| quote!(#(#bounds),*))
+}
+
+/// 改写一个工具方法，有工具参数时返回生成的参数结构体
+fn tool(method: &mut ImplItemFn) -> syn::Result<Option<TokenStream>> {
+    let docs = doc_text(&method.attrs);
+    if let Some(attr) = method.attrs.iter_mut().find(|attr| is_tool_attr(attr)) {
+        add_description(attr, docs.as_ref());
+    }
+
+    let mut errors: Option<syn::Error> = None;
+    let mut push = |err: syn::Error| match &mut errors {
+        Some(errors) => errors.combine(err),
+        None => errors = Some(err),
+    };
+    let mut args = Vec::new();
+    // 工具参数放在第一个工具参数原来的位置，其他参数保持原来的顺序
+    let mut position = None;
+    let mut inputs = Vec::new();
+    for input in std::mem::take(&mut method.sig.inputs) {
+        let FnArg::Typed(mut typed) = input else {
+            inputs.push(input);
+            continue;
+        };
+        let options = match ArgOptions::from_attrs(&typed.attrs) {
+            Ok(options) => options,
+            Err(err) => {
+                push(err);
+                ArgOptions::default()
+            }
+        };
+        let (docs, attrs): (Vec<_>, Vec<_>) = std::mem::take(&mut typed.attrs)
+            .into_iter()
+            .filter(|attr| !attr.path().is_ident("arg"))
+            .partition(|attr| attr.path().is_ident("doc"));
+        typed.attrs = attrs;
+        let pat = match typed.pat.as_ref() {
+            Pat::Ident(pat) if !options.extract => pat,
+            _ => {
+                // 提取器和已经写成 Parameters(..) 的参数原样保留
+                typed.attrs.extend(docs);
+                inputs.push(FnArg::Typed(typed));
+                continue;
+            }
+        };
+        if pat.by_ref.is_some() || pat.subpat.is_some() {
+            push(syn::Error::new_spanned(
+                pat,
+                "工具参数只支持 名称: 类型 的形式",
+            ));
+            continue;
+        }
+        if options.default.is_some() && option_inner(&typed.ty).is_some() {
+            push(syn::Error::new_spanned(
+                &typed.ty,
+                "Option 参数没有传入时为 None，不需要 default",
+            ));
+            continue;
+        }
+        position.get_or_insert(inputs.len());
+        args.push(ToolArg {
+            name: pat.ident.clone(),
+            mutable: pat.mutability.is_some(),
+            ty: (*typed.ty).clone(),
+            docs,
+            options,
+        });
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+    let Some(position) = position else {
+        method.sig.inputs = inputs.into_iter().collect();
+        return Ok(None);
+    };
+
+    let method_name = &method.sig.ident;
+    let args_ident = format_ident!(
+        "{}Args",
+        to_camel_case(&method_name.to_string()),
+        span = method_name.span()
+    );
+    let names: Vec<_> = args.iter().map(|arg| &arg.name).collect();
+    let parameters = quote!(::rmcp::handler::server::wrapper::Parameters);
+    inputs.insert(
+        position,
+        parse_quote!(#parameters(#args_ident { #(#names),* }): #parameters<#args_ident>),
+    );
+    method.sig.inputs = inputs.into_iter().collect();
+
+    let preludes: Vec<_> = args.iter().map(prelude).collect();
+    let block = &method.block;
+    method.block = parse_quote!({
+        #(#preludes)*
+        #block
+    });
+
+    let fields = args.iter().map(|arg| {
+        let name = &arg.name;
+        let ty = &arg.ty;
+        let ty = match arg.options.default {
+            Some(_) => quote!(::core::option::Option<#ty>),
+            None => quote!(#ty),
+        };
+        let docs = doc_text(&arg.docs).map(|docs| quote!(#[doc = #docs]));
+        let range = bounds(&arg.options.range.0, &arg.options.range.1)
+            .map(|bounds| quote!(#[schemars(range(#bounds))]));
+        let length = bounds(&arg.options.length.0, &arg.options.length.1)
+            .map(|bounds| quote!(#[schemars(length(#bounds))]));
+        quote! {
+            #docs
+            #range
+            #length
+            pub #name: #ty,
+        }
+    });
+    let vis = &method.vis;
+    let description = docs.unwrap_or_else(|| format!("工具 {method_name} 的参数"));
+    let crate_path = LitStr::new("::rmcp::schemars", Span::call_site());
+    Ok(Some(quote! {
+        #[doc = #description]
+        #[derive(::core::fmt::Debug, ::serde::Deserialize, ::rmcp::schemars::JsonSchema)]
+        #[schemars(crate = #crate_path)]
+        #vis struct #args_ident {
+            #(#fields)*
+        }
+    }))
+}
+
+pub(crate) fn mcp_tools(args: TokenStream, mut item: ItemImpl) -> syn::Result<TokenStream> {
+    parse_args(args, |meta| Err(meta.error("#[mcp_tools] 没有参数")))?;
+    let mut errors: Option<syn::Error> = None;
+    let mut structs = Vec::new();
+    let mut tools = 0;
+    for impl_item in &mut item.items {
+        let ImplItem::Fn(method) = impl_item else {
+            continue;
+        };
+        if !method.attrs.iter().any(is_tool_attr) {
+            continue;
+        }
+        tools += 1;
+        match tool(method) {
+            Ok(generated) => structs.extend(generated),
+            Err(err) => match &mut errors {
+                Some(errors) => errors.combine(err),
+                None => errors = Some(err),
+            },
+        }
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+    if tools == 0 {
+        return Err(syn::Error::new(
+            item.impl_token.span(),
+            "#[mcp_tools] 的 impl 中没有带 #[tool] 的方法",
+        ));
+    }
+    Ok(quote! {
+        #(#structs)*
+        #item
+    })
+}
@@ -0,0 +1,355 @@
+/**
+ * #[memoize(capacity = 1000, ttl = "30s")]：按参数缓存函数的返回值
+ *
+ *  1、缓存为线程安全的 LRU（lru::LruCache 加 Mutex），超过 capacity（默认 1024）时淘汰最久未使用的结果；
+ *     ttl 为结果的有效期（格式同 #[retry] 的 backoff），不设置时一直有效。
+ *  2、缓存的键为所有参数组成的元组，参数需要实现 Clone + Hash + Eq；引用参数使用 ToOwned 转换，例如 &str 的键为 String。
+ *     返回值需要实现 Clone；返回 Result 时只缓存 Ok 中的值，错误不缓存。
+ *  3、异步函数合并并发的相同调用（single-flight）：同一个键只有一个调用执行函数体，其他调用等待它的结果；
+ *     执行失败（返回 Err）时等待的调用中的一个会重新执行。需要使用的 crate 依赖 tokio。
+ *  4、同时生成 函数名_clear_cache() 清空缓存，函数名_cache_stats() 返回 函数名（驼峰）CacheStats，包含命中次数、未命中次数、缓存数量和容量。
+ *
+ * 缓存保存在静态变量中，只支持没有泛型参数和 self 的自由函数，使用的 crate 需要依赖 lru。
+ * 缓存和统计结构体生成在函数旁边，不能放在 impl 中；属性宏看不到外层的 impl，签名或函数体中出现 Self 时报告错误，
+ * 其他的关联函数由编译器报告 struct is not supported in `impl`s。
+ */
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{ToTokens, format_ident, quote};
+use syn::{FnArg, GenericArgument, ItemFn, LitInt, Pat, PathArguments, ReturnType, Type};
+
+use crate::instrument::{duration_tokens, parse_args, parse_duration};
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Result<T, E> 中 T 的类型，包括 io::Result<T> 这类别名
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+/// tokens 中第一个 Self，出现 Self 说明函数在 impl 或 trait 中
+fn find_self_type(tokens: TokenStream) -> Option<Ident> {
+    tokens.into_iter().find_map(|token| match token {
+        TokenTree::Ident(ident) if ident == "Self" => Some(ident),
+        TokenTree::Group(group) => find_self_type(group.stream()),
+        _ => None,
+    })
+}
+
+pub(crate) fn to_camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+pub(crate) fn memoize(args: TokenStream, item: ItemFn) -> syn::Result<TokenStream> {
+    let mut capacity = DEFAULT_CAPACITY;
+    let mut ttl = None;
+    parse_args(args, |meta| {
+        if meta.path.is_ident("capacity") {
+            let value: LitInt = meta.value()?.parse()?;
+            capacity = value.base10_parse()?;
+            if capacity == 0 {
+                return Err(syn::Error::new(value.span(), "capacity 必须大于 0"));
+            }
+        } else if meta.path.is_ident("ttl") {
+            ttl = Some(parse_duration(&meta.value()?.parse()?)?);
+        } else {
+            return Err(meta.error("#[memoize] 只支持 capacity、ttl"));
+        }
+        Ok(())
+    })?;
+
+    let sig = &item.sig;
+    if let Some(param) = sig
+        .generics
+        .type_params()
+        .next()
+        .map(|param| &param.ident)
+        .or_else(|| sig.generics.const_params().next().map(|param| &param.ident))
+    {
+        return Err(syn::Error::new_spanned(
+            param,
+            "#[memoize] 不支持泛型函数，缓存保存在静态变量中",
+        ));
+    }
+
+    if let Some(self_type) = find_self_type(sig.to_token_stream())
+        .or_else(|| find_self_type(item.block.to_token_stream()))
+    {
+        return Err(syn::Error::new_spanned(
+            self_type,
+            "#[memoize] 只支持自由函数，不能用在 impl 中：缓存和统计结构体需要生成在模块中",
+        ));
+    }
+
+    // 参数 -> 键的类型和值
+    let mut key_types = Vec::new();
+    let mut key_values = Vec::new();
+    for input in &sig.inputs {
+        let FnArg::Typed(typed) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "#[memoize] 不支持 self 参数，缓存由所有实例共享",
+            ));
+        };
+        let Pat::Ident(pat) = typed.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(
+                &typed.pat,
+                "#[memoize] 的参数需要是 name: T 的形式",
+            ));
+        };
+        let ident = &pat.ident;
+        match typed.ty.as_ref() {
+            Type::Reference(reference) if reference.mutability.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    &typed.ty,
+                    "#[memoize] 不支持 &mut 参数",
+                ));
+            }
+            Type::Reference(reference) => {
+                let elem = &reference.elem;
+                key_types.push(quote!(<#elem as ::std::borrow::ToOwned>::Owned));
+                key_values.push(quote!(::std::borrow::ToOwned::to_owned(#ident)));
+            }
+            Type::ImplTrait(_) => {
+                return Err(syn::Error::new_spanned(
+                    &typed.ty,
+                    "#[memoize] 不支持 impl Trait 参数",
+                ));
+            }
+            ty => {
+                key_types.push(quote!(#ty));
+                key_values.push(quote!(::core::clone::Clone::clone(&#ident)));
+            }
+        }
+    }
+
+    let (ret, ok_type) = match &sig.output {
+        ReturnType::Default => (quote!(()), None),
+        ReturnType::Type(_, ty) => (quote!(#ty), result_ok_type(ty)),
+    };
+    // 缓存中保存的值：返回 Result 时为 Ok 中的值
+    let value_type = match ok_type {
+        Some(ty) => quote!(#ty),
+        None => ret.clone(),
+    };
+    let hit = match ok_type {
+        Some(_) => quote!(::core::result::Result::Ok(__value)),
+        None => quote!(__value),
+    };
+
+    let name = &sig.ident;
+    let state = format_ident!("__memoize_{}", name);
+    let clear = format_ident!("{}_clear_cache", name);
+    let stats_fn = format_ident!("{}_cache_stats", name);
+    let stats = format_ident!("{}CacheStats", to_camel_case(&name.to_string()));
+    let vis = &item.vis;
+    let attrs = &item.attrs;
+    let is_async = sig.asyncness.is_some();
+    let ttl = match ttl {
+        Some(ttl) => {
+            let ttl = duration_tokens(ttl);
+            quote!(::core::option::Option::Some(#ttl))
+        }
+        None => quote!(::core::option::Option::None),
+    };
+    let key_type = quote!((#(#key_types,)*));
+    let block = &item.block;
+
+    let in_flight_field = is_async.then(|| {
+        quote! {
+            in_flight: ::std::collections::HashMap<#key_type, ::std::sync::Arc<::tokio::sync::OnceCell<#value_type>>>,
+        }
+    });
+    let in_flight_init = is_async.then(|| quote!(in_flight: ::std::collections::HashMap::new(),));
+
+    let body = if is_async {
+        // 同一个键的并发调用共用一个 OnceCell，只有一个调用执行函数体
+        let compute = match ok_type {
+            Some(_) => quote! {
+                __cell.get_or_try_init(|| async { (async || -> #ret #block)().await }).await
+            },
+            None => quote! {
+                ::core::result::Result::<_, ::core::convert::Infallible>::Ok(
+                    __cell.get_or_init(|| async { (async || -> #ret #block)().await }).await
+                )
+            },
+        };
+        let finish = match ok_type {
+            Some(_) => quote! {
+                match __outcome {
+                    ::core::result::Result::Ok(__value) => ::core::result::Result::Ok(::core::clone::Clone::clone(__value)),
+                    ::core::result::Result::Err(__error) => ::core::result::Result::Err(__error),
+                }
+            },
+            None => quote! {
+                match __outcome {
+                    ::core::result::Result::Ok(__value) => ::core::clone::Clone::clone(__value),
+                    ::core::result::Result::Err(__never) => match __never {},
+                }
+            },
+        };
+        quote! {
+            let __key: #key_type = (#(#key_values,)*);
+            let __cached = #state::lock().lookup(&__key);
+            if let ::core::option::Option::Some(__value) = __cached {
+                return #hit;
+            }
+            let __cell = ::core::clone::Clone::clone(
+                #state::lock()
+                    .in_flight
+                    .entry(::core::clone::Clone::clone(&__key))
+                    .or_default(),
+            );
+            let __outcome = #compute;
+            {
+                // 失败时如果还有调用在等待这个 OnceCell 就保留它：等待的调用接着执行函数体，
+                // 新的调用也加入这个 OnceCell，同一个键仍然只有一个调用在执行；在锁内判断引用计数，不会和加入的调用竞争
+                let mut __state = #state::lock();
+                if __state
+                    .in_flight
+                    .get(&__key)
+                    .is_some_and(|__other| ::std::sync::Arc::ptr_eq(__other, &__cell))
+                    && (__outcome.is_ok() || ::std::sync::Arc::strong_count(&__cell) == 2)
+                {
+                    __state.in_flight.remove(&__key);
+                }
+                if let ::core::result::Result::Ok(__value) = &__outcome {
+                    __state.store(__key, ::core::clone::Clone::clone(*__value));
+                }
+            }
+            #finish
+        }
+    } else {
+        let store = match ok_type {
+            Some(_) => quote! {
+                if let ::core::result::Result::Ok(__value) = &__result {
+                    #state::lock().store(__key, ::core::clone::Clone::clone(__value));
+                }
+            },
+            None => quote! {
+                #state::lock().store(__key, ::core::clone::Clone::clone(&__result));
+            },
+        };
+        quote! {
+            let __key: #key_type = (#(#key_values,)*);
+            let __cached = #state::lock().lookup(&__key);
+            if let ::core::option::Option::Some(__value) = __cached {
+                return #hit;
+            }
+            // 执行函数体时不持有锁，递归调用自身不会死锁
+            let __result: #ret = (|| -> #ret #block)();
+            #store
+            __result
+        }
+    };
+
+    let stats_doc = format!("{name} 的缓存统计");
+    Ok(quote! {
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        struct #state {
+            cache: ::lru::LruCache<#key_type, (#value_type, ::std::time::Instant)>,
+            hits: u64,
+            misses: u64,
+            #in_flight_field
+        }
+
+        impl #state {
+            fn lock() -> ::std::sync::MutexGuard<'static, #state> {
+                static STATE: ::std::sync::OnceLock<::std::sync::Mutex<#state>> = ::std::sync::OnceLock::new();
+                STATE
+                    .get_or_init(|| {
+                        ::std::sync::Mutex::new(#state {
+                            cache: ::lru::LruCache::new(::core::num::NonZeroUsize::new(#capacity).unwrap()),
+                            hits: 0,
+                            misses: 0,
+                            #in_flight_init
+                        })
+                    })
+                    .lock()
+                    .unwrap_or_else(::std::sync::PoisonError::into_inner)
+            }
+
+            fn lookup(&mut self, key: &#key_type) -> ::core::option::Option<#value_type> {
+                let ttl: ::core::option::Option<::std::time::Duration> = #ttl;
+                let cached = self.cache.get(key).map(|(value, at)| {
+                    (
+                        ::core::clone::Clone::clone(value),
+                        ttl.is_none_or(|ttl| at.elapsed() < ttl),
+                    )
+                });
+                match cached {
+                    ::core::option::Option::Some((value, true)) => {
+                        self.hits += 1;
+                        ::core::option::Option::Some(value)
+                    }
+                    ::core::option::Option::Some((_, false)) => {
+                        self.cache.pop(key);
+                        self.misses += 1;
+                        ::core::option::Option::None
+                    }
+                    ::core::option::Option::None => {
+                        self.misses += 1;
+                        ::core::option::Option::None
+                    }
+                }
+            }
+
+            fn store(&mut self, key: #key_type, value: #value_type) {
+                self.cache.put(key, (value, ::std::time::Instant::now()));
+            }
+        }
+
+        #[doc = #stats_doc]
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #vis struct #stats {
+            pub hits: u64,
+            pub misses: u64,
+            pub size: usize,
+            pub capacity: usize,
+        }
+
+        #[allow(dead_code)]
+        #vis fn #clear() {
+            #state::lock().cache.clear();
+        }
+
+        #[allow(dead_code)]
+        #vis fn #stats_fn() -> #stats {
+            let state = #state::lock();
+            #stats {
+                hits: state.hits,
+                misses: state.misses,
+                size: state.cache.len(),
+                capacity: state.cache.cap().get(),
+            }
+        }
+
+        #(#attrs)*
+        #vis #sig {
+            #body
+        }
+    })
+}
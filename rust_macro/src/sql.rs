@@ -0,0 +1,1336 @@
+/**
+ * sql!(后端, "SQL", 参数...)：编译期检查 SQL，展开为绑定了参数的 sea_orm::Statement
+ *
+ * 后端为 MySql、Postgres、Sqlite，Postgres 的 ? 替换为 $1、$2...；参数按 ? 的顺序传入，个数必须一致。
+ * 表结构从 CARGO_MANIFEST_DIR 下的 sql_schema.txt 读取（由 sea-orm 实体生成，每行为 表名 列名 Rust类型），
+ * 第一个参数可以是 schema = "路径" 指定其他文件；文件修改之后宏会重新展开。
+ *
+ * 支持的 SQL：
+ *  1、SELECT [DISTINCT] 列 [AS 别名], ... FROM 表 [别名] [[INNER | LEFT | RIGHT] JOIN 表 [别名] ON 条件]
+ *     [WHERE 条件] [GROUP BY 列] [HAVING 条件] [ORDER BY 列 [ASC | DESC]] [LIMIT n [OFFSET n]]
+ *  2、INSERT INTO 表 (列, ...) VALUES (值, ...), ...
+ *  3、UPDATE 表 SET 列 = 值, ... [WHERE 条件]
+ *  4、DELETE FROM 表 [WHERE 条件]
+ *  条件支持 = != <> < <= > >=、+ - * / %、AND OR NOT、LIKE、IN (...)、BETWEEN、IS [NOT] NULL 和函数调用。
+ *
+ * 检查的内容：表和列是否存在（拼写错误时给出相近的名称）、列是否有歧义、字面量和列的类型是否匹配、INSERT 的列数和值的个数。
+ * 每个 ? 的类型由和它比较或赋值的列推断，参数通过 Into<列的类型> 转换后绑定，类型不匹配时在参数上报错；
+ * LIKE 的参数为 String，LIMIT 和 OFFSET 的参数为 u64，无法推断类型的 ? 编译报错。
+ */
+use std::{collections::HashMap, path::PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{
+    Expr as RustExpr, Ident, LitStr, Token,
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+};
+
+const DEFAULT_SCHEMA: &str = "sql_schema.txt";
+const BACKENDS: &[&str] = &["MySql", "Postgres", "Sqlite"];
+const KEYWORDS: &[&str] = &[
+    "SELECT", "DISTINCT", "FROM", "WHERE", "JOIN", "INNER", "LEFT", "RIGHT", "OUTER", "ON",
+    "GROUP", "ORDER", "BY", "HAVING", "LIMIT", "OFFSET", "AS", "ASC", "DESC", "INSERT", "INTO",
+    "VALUES", "UPDATE", "SET", "DELETE", "AND", "OR", "NOT", "IN", "IS", "NULL", "LIKE", "BETWEEN",
+    "TRUE", "FALSE", "UNION",
+];
+const NUMERIC_TYPES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64", "Decimal",
+];
+
+pub(crate) struct SqlInput {
+    schema: Option<LitStr>,
+    backend: Ident,
+    sql: LitStr,
+    args: Vec<RustExpr>,
+}
+
+impl Parse for SqlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut schema = None;
+        if input.peek(Ident) && input.peek2(Token![=]) {
+            let key: Ident = input.parse()?;
+            if key != "schema" {
+                return Err(syn::Error::new(key.span(), "只支持 schema = \"路径\""));
+            }
+            input.parse::<Token![=]>()?;
+            schema = Some(input.parse()?);
+            input.parse::<Token![,]>()?;
+        }
+        let backend: Ident = input.parse()?;
+        if !BACKENDS.iter().any(|name| backend == name) {
+            return Err(syn::Error::new(
+                backend.span(),
+                format!("未知的数据库后端，可用的后端: {}", BACKENDS.join("、")),
+            ));
+        }
+        input.parse::<Token![,]>()?;
+        let sql = input.parse()?;
+        let mut args = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            args.push(input.parse()?);
+        }
+        Ok(SqlInput {
+            schema,
+            backend,
+            sql,
+            args,
+        })
+    }
+}
+
+/// 表名 -> 列名和 Rust 类型，保留文件中的顺序
+struct Schema {
+    tables: HashMap<String, Vec<(String, String)>>,
+}
+
+impl Schema {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut tables: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(table), Some(column), Some(ty), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(format!(
+                    "第 {} 行格式错误，每行为 表名 列名 Rust类型",
+                    number + 1
+                ));
+            };
+            tables
+                .entry(table.to_string())
+                .or_default()
+                .push((column.to_string(), ty.to_string()));
+        }
+        Ok(Schema { tables })
+    }
+
+    fn columns(&self, table: &str) -> Option<&[(String, String)]> {
+        self.tables.get(table).map(Vec::as_slice)
+    }
+}
+
+/// 编辑距离，用于拼写错误时提示相近的名称
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                previous.min(current).min(row[j]) + 1
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// “，是不是 xxx？”或者列出所有可用的名称
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    let mut candidates: Vec<&str> = candidates.collect();
+    candidates.sort();
+    let closest = candidates
+        .iter()
+        .map(|candidate| (distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+    match closest {
+        Some((_, candidate)) => format!("，是不是 {candidate}？"),
+        None => format!("，可用的名称: {}", candidates.join("、")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    /// 标识符，quoted 为 `name` 或 "name"，不会被当作关键字
+    Ident {
+        name: String,
+        quoted: bool,
+    },
+    Number,
+    Str,
+    /// ? 占位符，值为参数的序号
+    Param(usize),
+    Punct(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    /// 在 SQL 中的字节偏移
+    pos: usize,
+}
+
+struct SqlError {
+    pos: usize,
+    message: String,
+}
+
+type SqlResult<T> = Result<T, SqlError>;
+
+fn error<T>(pos: usize, message: impl Into<String>) -> SqlResult<T> {
+    Err(SqlError {
+        pos,
+        message: message.into(),
+    })
+}
+
+const PUNCTS: &[&str] = &[
+    "<=", ">=", "<>", "!=", "=", "<", ">", ",", "(", ")", ".", "*", "+", "-", "/", "%", ";",
+];
+
+fn tokenize(sql: &str) -> SqlResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut params = 0;
+    let mut chars = sql.char_indices().peekable();
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token {
+                tok: Tok::Ident {
+                    name,
+                    quoted: false,
+                },
+                pos,
+            });
+        } else if c.is_ascii_digit() {
+            while let Some(&(_, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                chars.next();
+            }
+            tokens.push(Token {
+                tok: Tok::Number,
+                pos,
+            });
+        } else if c == '\'' || c == '`' || c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    // 两个引号表示引号本身
+                    Some((_, q)) if q == c => {
+                        if chars.peek().is_some_and(|&(_, next)| next == c) {
+                            chars.next();
+                            text.push(c);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some((_, other)) => text.push(other),
+                    None => return error(pos, "引号没有闭合"),
+                }
+            }
+            let tok = if c == '\'' {
+                Tok::Str
+            } else {
+                Tok::Ident {
+                    name: text,
+                    quoted: true,
+                }
+            };
+            tokens.push(Token { tok, pos });
+        } else if c == '?' {
+            chars.next();
+            tokens.push(Token {
+                tok: Tok::Param(params),
+                pos,
+            });
+            params += 1;
+        } else {
+            let rest = &sql[pos..];
+            let Some(punct) = PUNCTS.iter().find(|punct| rest.starts_with(**punct)) else {
+                return error(pos, format!("不支持的字符 {c}"));
+            };
+            for _ in 0..punct.chars().count() {
+                chars.next();
+            }
+            tokens.push(Token {
+                tok: Tok::Punct(punct),
+                pos,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Logic,
+    Compare,
+    Like,
+    Arith,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Column {
+        table: Option<String>,
+        name: String,
+        pos: usize,
+    },
+    Param {
+        index: usize,
+        pos: usize,
+    },
+    Number(usize),
+    Str(usize),
+    Null(usize),
+    Bool,
+    /// COUNT(*) 中的 *
+    Star,
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary {
+        op: Op,
+        /// = 或者 != 等，用于检查和 NULL 的比较
+        equality: bool,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        pos: usize,
+    },
+    IsNull(Box<Expr>),
+    In {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+    },
+    Between {
+        expr: Box<Expr>,
+        low: Box<Expr>,
+        high: Box<Expr>,
+    },
+    Func {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+struct TableRef {
+    name: String,
+    alias: Option<String>,
+    pos: usize,
+}
+
+enum SelectItem {
+    /// * 或者 表.*
+    Star {
+        table: Option<String>,
+        pos: usize,
+    },
+    Expr {
+        expr: Expr,
+        alias: Option<String>,
+    },
+}
+
+enum Statement {
+    Select {
+        items: Vec<SelectItem>,
+        tables: Vec<TableRef>,
+        conditions: Vec<Expr>,
+        group_by: Vec<Expr>,
+        order_by: Vec<Expr>,
+        limits: Vec<Expr>,
+    },
+    Insert {
+        table: TableRef,
+        columns: Vec<(String, usize)>,
+        rows: Vec<(Vec<Expr>, usize)>,
+    },
+    Update {
+        table: TableRef,
+        sets: Vec<(String, usize, Expr)>,
+        condition: Option<Expr>,
+    },
+    Delete {
+        table: TableRef,
+        condition: Option<Expr>,
+    },
+}
+
+struct Parser<'a> {
+    sql: &'a str,
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.index).map(|token| &token.tok)
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map(|token| token.pos)
+            .unwrap_or(self.sql.len())
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident { name, quoted: false }) if name.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> SqlResult<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            error(self.pos(), format!("这里应该是 {keyword}"))
+        }
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Punct(p)) if *p == punct)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = self.is_punct(punct);
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> SqlResult<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            error(self.pos(), format!("这里应该是 {punct}"))
+        }
+    }
+
+    /// 表名、列名或别名，不能是关键字
+    fn ident(&mut self, what: &str) -> SqlResult<(String, usize)> {
+        let pos = self.pos();
+        match self.peek() {
+            Some(Tok::Ident { name, quoted })
+                if *quoted || !KEYWORDS.iter().any(|k| name.eq_ignore_ascii_case(k)) =>
+            {
+                let name = name.clone();
+                self.index += 1;
+                Ok((name, pos))
+            }
+            _ => error(pos, format!("这里应该是{what}")),
+        }
+    }
+
+    /// 下一个是不是可以作为别名的标识符
+    fn at_alias(&self) -> bool {
+        matches!(self.peek(), Some(Tok::Ident { name, quoted })
+            if *quoted || !KEYWORDS.iter().any(|k| name.eq_ignore_ascii_case(k)))
+    }
+
+    fn alias(&mut self) -> SqlResult<Option<String>> {
+        if self.eat_keyword("AS") {
+            return Ok(Some(self.ident("别名")?.0));
+        }
+        if self.at_alias() {
+            return Ok(Some(self.ident("别名")?.0));
+        }
+        Ok(None)
+    }
+
+    fn comma_list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> SqlResult<T>,
+    ) -> SqlResult<Vec<T>> {
+        let mut items = vec![item(self)?];
+        while self.eat_punct(",") {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn table_ref(&mut self) -> SqlResult<TableRef> {
+        let (name, pos) = self.ident("表名")?;
+        let alias = self.alias()?;
+        Ok(TableRef { name, alias, pos })
+    }
+
+    fn statement(&mut self) -> SqlResult<Statement> {
+        let statement = if self.eat_keyword("SELECT") {
+            self.select()?
+        } else if self.eat_keyword("INSERT") {
+            self.insert()?
+        } else if self.eat_keyword("UPDATE") {
+            self.update()?
+        } else if self.eat_keyword("DELETE") {
+            self.expect_keyword("FROM")?;
+            let table = self.table_ref()?;
+            let condition = self.where_clause()?;
+            Statement::Delete { table, condition }
+        } else {
+            return error(self.pos(), "只支持 SELECT、INSERT、UPDATE、DELETE");
+        };
+        self.eat_punct(";");
+        if self.index < self.tokens.len() {
+            return error(self.pos(), "无法解析的内容");
+        }
+        Ok(statement)
+    }
+
+    fn where_clause(&mut self) -> SqlResult<Option<Expr>> {
+        if self.eat_keyword("WHERE") {
+            Ok(Some(self.expr()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn select(&mut self) -> SqlResult<Statement> {
+        self.eat_keyword("DISTINCT");
+        let items = self.comma_list(|parser| {
+            let pos = parser.pos();
+            if parser.eat_punct("*") {
+                return Ok(SelectItem::Star { table: None, pos });
+            }
+            // 表.*
+            if let (Some(Tok::Ident { name, .. }), Some(Tok::Punct(".")), Some(Tok::Punct("*"))) = (
+                parser.peek().cloned(),
+                parser.tokens.get(parser.index + 1).map(|t| t.tok.clone()),
+                parser.tokens.get(parser.index + 2).map(|t| t.tok.clone()),
+            ) {
+                parser.index += 3;
+                return Ok(SelectItem::Star {
+                    table: Some(name),
+                    pos,
+                });
+            }
+            let expr = parser.expr()?;
+            let alias = parser.alias()?;
+            Ok(SelectItem::Expr { expr, alias })
+        })?;
+        self.expect_keyword("FROM")?;
+        let mut tables = self.comma_list(Self::table_ref)?;
+        let mut conditions = Vec::new();
+        loop {
+            if self.eat_keyword("INNER") {
+                self.expect_keyword("JOIN")?;
+            } else if self.eat_keyword("LEFT") || self.eat_keyword("RIGHT") {
+                self.eat_keyword("OUTER");
+                self.expect_keyword("JOIN")?;
+            } else if !self.eat_keyword("JOIN") {
+                break;
+            }
+            tables.push(self.table_ref()?);
+            self.expect_keyword("ON")?;
+            conditions.push(self.expr()?);
+        }
+        conditions.extend(self.where_clause()?);
+        let mut group_by = Vec::new();
+        if self.eat_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by = self.comma_list(Self::expr)?;
+        }
+        if self.eat_keyword("HAVING") {
+            conditions.push(self.expr()?);
+        }
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by = self.comma_list(|parser| {
+                let expr = parser.expr()?;
+                if !parser.eat_keyword("ASC") {
+                    parser.eat_keyword("DESC");
+                }
+                Ok(expr)
+            })?;
+        }
+        let mut limits = Vec::new();
+        if self.eat_keyword("LIMIT") {
+            limits.push(self.primary()?);
+            if self.eat_keyword("OFFSET") || self.eat_punct(",") {
+                limits.push(self.primary()?);
+            }
+        }
+        Ok(Statement::Select {
+            items,
+            tables,
+            conditions,
+            group_by,
+            order_by,
+            limits,
+        })
+    }
+
+    fn insert(&mut self) -> SqlResult<Statement> {
+        self.expect_keyword("INTO")?;
+        let (name, pos) = self.ident("表名")?;
+        let table = TableRef {
+            name,
+            alias: None,
+            pos,
+        };
+        self.expect_punct("(")?;
+        let columns = self.comma_list(|parser| parser.ident("列名"))?;
+        self.expect_punct(")")?;
+        self.expect_keyword("VALUES")?;
+        let rows = self.comma_list(|parser| {
+            let pos = parser.pos();
+            parser.expect_punct("(")?;
+            let values = parser.comma_list(Self::expr)?;
+            parser.expect_punct(")")?;
+            Ok((values, pos))
+        })?;
+        Ok(Statement::Insert {
+            table,
+            columns,
+            rows,
+        })
+    }
+
+    fn update(&mut self) -> SqlResult<Statement> {
+        let table = self.table_ref()?;
+        self.expect_keyword("SET")?;
+        let sets = self.comma_list(|parser| {
+            let (column, pos) = parser.ident("列名")?;
+            parser.expect_punct("=")?;
+            Ok((column, pos, parser.expr()?))
+        })?;
+        let condition = self.where_clause()?;
+        Ok(Statement::Update {
+            table,
+            sets,
+            condition,
+        })
+    }
+
+    fn expr(&mut self) -> SqlResult<Expr> {
+        self.or()
+    }
+
+    fn or(&mut self) -> SqlResult<Expr> {
+        let mut left = self.and()?;
+        while self.is_keyword("OR") {
+            let pos = self.pos();
+            self.index += 1;
+            let right = self.and()?;
+            left = binary(Op::Logic, false, left, right, pos);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> SqlResult<Expr> {
+        let mut left = self.not()?;
+        while self.is_keyword("AND") {
+            let pos = self.pos();
+            self.index += 1;
+            let right = self.not()?;
+            left = binary(Op::Logic, false, left, right, pos);
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> SqlResult<Expr> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> SqlResult<Expr> {
+        let left = self.additive()?;
+        let pos = self.pos();
+        for punct in ["=", "!=", "<>", "<=", ">=", "<", ">"] {
+            if self.eat_punct(punct) {
+                let right = self.additive()?;
+                let equality = matches!(punct, "=" | "!=" | "<>");
+                return Ok(binary(Op::Compare, equality, left, right, pos));
+            }
+        }
+        if self.eat_keyword("IS") {
+            self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull(Box::new(left)));
+        }
+        let negated = self.eat_keyword("NOT");
+        let expr = if self.eat_keyword("LIKE") {
+            let right = self.additive()?;
+            binary(Op::Like, false, left, right, pos)
+        } else if self.eat_keyword("IN") {
+            self.expect_punct("(")?;
+            let list = self.comma_list(Self::expr)?;
+            self.expect_punct(")")?;
+            Expr::In {
+                expr: Box::new(left),
+                list,
+            }
+        } else if self.eat_keyword("BETWEEN") {
+            let low = self.additive()?;
+            self.expect_keyword("AND")?;
+            let high = self.additive()?;
+            Expr::Between {
+                expr: Box::new(left),
+                low: Box::new(low),
+                high: Box::new(high),
+            }
+        } else if negated {
+            return error(self.pos(), "NOT 之后应该是 LIKE、IN 或 BETWEEN");
+        } else {
+            return Ok(left);
+        };
+        Ok(expr)
+    }
+
+    fn additive(&mut self) -> SqlResult<Expr> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let pos = self.pos();
+            if self.eat_punct("+") || self.eat_punct("-") {
+                let right = self.multiplicative()?;
+                left = binary(Op::Arith, false, left, right, pos);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn multiplicative(&mut self) -> SqlResult<Expr> {
+        let mut left = self.primary()?;
+        loop {
+            let pos = self.pos();
+            if self.eat_punct("*") || self.eat_punct("/") || self.eat_punct("%") {
+                let right = self.primary()?;
+                left = binary(Op::Arith, false, left, right, pos);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> SqlResult<Expr> {
+        let pos = self.pos();
+        let Some(token) = self.peek().cloned() else {
+            return error(pos, "SQL 不完整");
+        };
+        match token {
+            Tok::Param(index) => {
+                self.index += 1;
+                Ok(Expr::Param { index, pos })
+            }
+            Tok::Number => {
+                self.index += 1;
+                Ok(Expr::Number(pos))
+            }
+            Tok::Str => {
+                self.index += 1;
+                Ok(Expr::Str(pos))
+            }
+            Tok::Punct("(") => {
+                self.index += 1;
+                let expr = self.expr()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Tok::Punct("-") => {
+                self.index += 1;
+                Ok(Expr::Neg(Box::new(self.primary()?)))
+            }
+            Tok::Ident { name, quoted } => {
+                if !quoted {
+                    if name.eq_ignore_ascii_case("NULL") {
+                        self.index += 1;
+                        return Ok(Expr::Null(pos));
+                    }
+                    if name.eq_ignore_ascii_case("TRUE") || name.eq_ignore_ascii_case("FALSE") {
+                        self.index += 1;
+                        return Ok(Expr::Bool);
+                    }
+                }
+                let (name, pos) = self.ident("列名")?;
+                if self.eat_punct("(") {
+                    let args = if self.eat_punct(")") {
+                        Vec::new()
+                    } else {
+                        let args = if self.eat_punct("*") {
+                            vec![Expr::Star]
+                        } else {
+                            self.eat_keyword("DISTINCT");
+                            self.comma_list(Self::expr)?
+                        };
+                        self.expect_punct(")")?;
+                        args
+                    };
+                    return Ok(Expr::Func {
+                        name: name.to_ascii_uppercase(),
+                        args,
+                    });
+                }
+                if self.eat_punct(".") {
+                    let (column, column_pos) = self.ident("列名")?;
+                    return Ok(Expr::Column {
+                        table: Some(name),
+                        name: column,
+                        pos: column_pos,
+                    });
+                }
+                Ok(Expr::Column {
+                    table: None,
+                    name,
+                    pos,
+                })
+            }
+            _ => error(pos, "这里应该是列名、参数或者值"),
+        }
+    }
+}
+
+fn binary(op: Op, equality: bool, left: Expr, right: Expr, pos: usize) -> Expr {
+    Expr::Binary {
+        op,
+        equality,
+        left: Box::new(left),
+        right: Box::new(right),
+        pos,
+    }
+}
+
+/// 表达式的类型，列的类型为 Rust 类型的名称
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Column(String),
+    Number,
+    Text,
+    Null,
+    Bool,
+    Param(usize, usize),
+    /// 无法确定类型，例如未知函数的返回值
+    Any,
+}
+
+/// Option<T> 中的 T
+fn inner(ty: &str) -> &str {
+    ty.strip_prefix("Option<")
+        .and_then(|ty| ty.strip_suffix('>'))
+        .unwrap_or(ty)
+}
+
+struct Checker<'a> {
+    schema: &'a Schema,
+    /// 别名（没有别名时为表名）和表名
+    scope: Vec<(String, String)>,
+    /// SELECT 中的别名，可以在 ORDER BY 和 HAVING 中使用
+    aliases: Vec<String>,
+    /// 每个参数的类型
+    params: Vec<Option<String>>,
+}
+
+impl Checker<'_> {
+    fn table(&mut self, table: &TableRef) -> SqlResult<()> {
+        if self.schema.columns(&table.name).is_none() {
+            return error(
+                table.pos,
+                format!(
+                    "表 {} 不存在{}",
+                    table.name,
+                    suggest(&table.name, self.schema.tables.keys().map(String::as_str))
+                ),
+            );
+        }
+        let alias = table.alias.clone().unwrap_or_else(|| table.name.clone());
+        if self.scope.iter().any(|(name, _)| *name == alias) {
+            return error(table.pos, format!("表名或别名 {alias} 重复"));
+        }
+        self.scope.push((alias, table.name.clone()));
+        Ok(())
+    }
+
+    fn column_type(&self, table: &str, column: &str, pos: usize) -> SqlResult<String> {
+        let columns = self.schema.columns(table).expect("表已经检查");
+        match columns.iter().find(|(name, _)| name == column) {
+            Some((_, ty)) => Ok(ty.clone()),
+            None => error(
+                pos,
+                format!(
+                    "表 {table} 没有列 {column}{}",
+                    suggest(column, columns.iter().map(|(name, _)| name.as_str()))
+                ),
+            ),
+        }
+    }
+
+    fn resolve(&self, table: Option<&str>, column: &str, pos: usize) -> SqlResult<Ty> {
+        if let Some(qualifier) = table {
+            let Some((_, table)) = self.scope.iter().find(|(alias, _)| alias == qualifier) else {
+                return error(pos, format!("未知的表或别名 {qualifier}"));
+            };
+            return self.column_type(table, column, pos).map(Ty::Column);
+        }
+        let found: Vec<(&str, &str)> = self
+            .scope
+            .iter()
+            .filter_map(|(alias, table)| {
+                let columns = self.schema.columns(table)?;
+                columns
+                    .iter()
+                    .find(|(name, _)| name == column)
+                    .map(|(_, ty)| (alias.as_str(), ty.as_str()))
+            })
+            .collect();
+        match found.as_slice() {
+            [(_, ty)] => Ok(Ty::Column(ty.to_string())),
+            [] if self.aliases.iter().any(|alias| alias == column) => Ok(Ty::Any),
+            [] => {
+                let candidates = self
+                    .scope
+                    .iter()
+                    .filter_map(|(_, table)| self.schema.columns(table))
+                    .flatten()
+                    .map(|(name, _)| name.as_str());
+                error(
+                    pos,
+                    format!("列 {column} 不存在{}", suggest(column, candidates)),
+                )
+            }
+            _ => {
+                let tables: Vec<&str> = found.iter().map(|(alias, _)| *alias).collect();
+                error(
+                    pos,
+                    format!(
+                        "列 {column} 有歧义，存在于 {}，请加上表名",
+                        tables.join("、")
+                    ),
+                )
+            }
+        }
+    }
+
+    fn bind(&mut self, index: usize, ty: String) {
+        self.params[index] = Some(ty);
+    }
+
+    /// 不能推断类型的参数报错
+    fn concrete(&self, ty: Ty) -> SqlResult<Ty> {
+        match ty {
+            Ty::Param(_, pos) => error(pos, "无法推断参数的类型，参数需要和列比较或者赋值给列"),
+            ty => Ok(ty),
+        }
+    }
+
+    fn check_literal(column: &str, other: &Ty, pos: usize) -> SqlResult<()> {
+        let ty = inner(column);
+        let numeric = NUMERIC_TYPES.contains(&ty) || ty == "bool";
+        match other {
+            Ty::Number if ty == "String" => error(
+                pos,
+                format!("类型为 {column} 的列不能和数字比较，字符串需要加引号"),
+            ),
+            Ty::Text if numeric => error(pos, format!("类型为 {column} 的列不能和字符串比较")),
+            _ => Ok(()),
+        }
+    }
+
+    /// 两边的类型需要兼容，参数使用另一边的类型
+    fn unify(&mut self, left: Ty, right: Ty, pos: usize) -> SqlResult<Ty> {
+        match (left, right) {
+            (Ty::Param(_, pos), Ty::Param(..)) => {
+                error(pos, "无法推断参数的类型，参数需要和列比较或者赋值给列")
+            }
+            (Ty::Param(index, _), Ty::Column(ty)) | (Ty::Column(ty), Ty::Param(index, _)) => {
+                self.bind(index, inner(&ty).to_string());
+                Ok(Ty::Column(ty))
+            }
+            (Ty::Param(index, _), Ty::Text) | (Ty::Text, Ty::Param(index, _)) => {
+                self.bind(index, "String".to_string());
+                Ok(Ty::Text)
+            }
+            (Ty::Param(index, _), Ty::Bool) | (Ty::Bool, Ty::Param(index, _)) => {
+                self.bind(index, "bool".to_string());
+                Ok(Ty::Bool)
+            }
+            (Ty::Param(index, _), Ty::Number) | (Ty::Number, Ty::Param(index, _)) => {
+                self.bind(index, "f64".to_string());
+                Ok(Ty::Number)
+            }
+            (Ty::Param(_, pos), _) | (_, Ty::Param(_, pos)) => {
+                error(pos, "无法推断参数的类型，参数需要和列比较或者赋值给列")
+            }
+            (Ty::Column(ty), other) | (other, Ty::Column(ty)) => {
+                Self::check_literal(&ty, &other, pos)?;
+                Ok(Ty::Column(ty))
+            }
+            (left, _) => Ok(left),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> SqlResult<Ty> {
+        match expr {
+            Expr::Column { table, name, pos } => self.resolve(table.as_deref(), name, *pos),
+            Expr::Param { index, pos } => Ok(Ty::Param(*index, *pos)),
+            Expr::Number(_) => Ok(Ty::Number),
+            Expr::Str(_) => Ok(Ty::Text),
+            Expr::Null(_) => Ok(Ty::Null),
+            Expr::Bool => Ok(Ty::Bool),
+            Expr::Star => Ok(Ty::Any),
+            Expr::Not(expr) => {
+                let ty = self.expr(expr)?;
+                self.concrete(ty)?;
+                Ok(Ty::Bool)
+            }
+            Expr::Neg(expr) => self.expr(expr),
+            Expr::Binary {
+                op,
+                equality,
+                left,
+                right,
+                pos,
+            } => {
+                let left = self.expr(left)?;
+                let right = self.expr(right)?;
+                match op {
+                    Op::Logic => {
+                        self.concrete(left)?;
+                        self.concrete(right)?;
+                        Ok(Ty::Bool)
+                    }
+                    Op::Like => {
+                        match right {
+                            Ty::Param(index, _) => self.bind(index, "String".to_string()),
+                            right => {
+                                self.unify(left, right, *pos)?;
+                            }
+                        }
+                        Ok(Ty::Bool)
+                    }
+                    Op::Compare => {
+                        if *equality && (left == Ty::Null || right == Ty::Null) {
+                            return error(*pos, "和 NULL 比较请使用 IS NULL 或 IS NOT NULL");
+                        }
+                        self.unify(left, right, *pos)?;
+                        Ok(Ty::Bool)
+                    }
+                    Op::Arith => self.unify(left, right, *pos),
+                }
+            }
+            Expr::IsNull(expr) => {
+                let ty = self.expr(expr)?;
+                self.concrete(ty)?;
+                Ok(Ty::Bool)
+            }
+            Expr::In { expr, list } => {
+                let mut ty = self.expr(expr)?;
+                for item in list {
+                    let item_ty = self.expr(item)?;
+                    ty = self.unify(ty, item_ty, expr_pos(item))?;
+                }
+                Ok(Ty::Bool)
+            }
+            Expr::Between { expr, low, high } => {
+                let ty = self.expr(expr)?;
+                let low_ty = self.expr(low)?;
+                let ty = self.unify(ty, low_ty, expr_pos(low))?;
+                let high_ty = self.expr(high)?;
+                self.unify(ty, high_ty, expr_pos(high))?;
+                Ok(Ty::Bool)
+            }
+            Expr::Func { name, args } => {
+                let mut known = None;
+                let mut params = Vec::new();
+                for arg in args {
+                    match self.expr(arg)? {
+                        Ty::Param(index, pos) => params.push((index, pos)),
+                        Ty::Column(ty) if known.is_none() => known = Some(inner(&ty).to_string()),
+                        _ => {}
+                    }
+                }
+                // COALESCE(列, ?) 这类函数的参数使用列的类型
+                for (index, pos) in params {
+                    match &known {
+                        Some(ty) => self.bind(index, ty.clone()),
+                        None => {
+                            return error(pos, "无法推断参数的类型，参数需要和列比较或者赋值给列");
+                        }
+                    }
+                }
+                Ok(match name.as_str() {
+                    "COUNT" | "LENGTH" | "CHAR_LENGTH" => Ty::Column("i64".to_string()),
+                    "UPPER" | "LOWER" | "TRIM" | "CONCAT" | "SUBSTR" | "SUBSTRING" => {
+                        Ty::Column("String".to_string())
+                    }
+                    "MIN" | "MAX" | "ABS" | "COALESCE" | "IFNULL" | "ROUND" => {
+                        known.map(Ty::Column).unwrap_or(Ty::Any)
+                    }
+                    _ => Ty::Any,
+                })
+            }
+        }
+    }
+
+    /// INSERT 和 UPDATE 中赋值给列的值，参数使用列的完整类型（可为空的列为 Option<T>）
+    fn assign(&mut self, column_ty: &str, column: &str, value: &Expr) -> SqlResult<()> {
+        match value {
+            Expr::Param { index, .. } => {
+                self.bind(*index, column_ty.to_string());
+                Ok(())
+            }
+            Expr::Null(pos) if !column_ty.starts_with("Option<") => {
+                error(*pos, format!("列 {column} 不能为 NULL"))
+            }
+            value => {
+                let ty = self.expr(value)?;
+                self.unify(Ty::Column(column_ty.to_string()), ty, expr_pos(value))?;
+                Ok(())
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) -> SqlResult<()> {
+        match statement {
+            Statement::Select {
+                items,
+                tables,
+                conditions,
+                group_by,
+                order_by,
+                limits,
+            } => {
+                for table in tables {
+                    self.table(table)?;
+                }
+                for item in items {
+                    match item {
+                        SelectItem::Star {
+                            table: Some(table),
+                            pos,
+                        } if !self.scope.iter().any(|(alias, _)| alias == table) => {
+                            return error(*pos, format!("未知的表或别名 {table}"));
+                        }
+                        SelectItem::Star { .. } => {}
+                        SelectItem::Expr { expr, alias } => {
+                            let ty = self.expr(expr)?;
+                            self.concrete(ty)?;
+                            self.aliases.extend(alias.clone());
+                        }
+                    }
+                }
+                for expr in conditions.iter().chain(group_by).chain(order_by) {
+                    let ty = self.expr(expr)?;
+                    self.concrete(ty)?;
+                }
+                for limit in limits {
+                    match limit {
+                        Expr::Param { index, .. } => self.bind(*index, "u64".to_string()),
+                        Expr::Number(_) => {}
+                        other => return error(expr_pos(other), "LIMIT 和 OFFSET 只能是数字或者 ?"),
+                    }
+                }
+            }
+            Statement::Insert {
+                table,
+                columns,
+                rows,
+            } => {
+                self.table(table)?;
+                let mut types = Vec::new();
+                for (index, (column, pos)) in columns.iter().enumerate() {
+                    if columns[..index].iter().any(|(other, _)| other == column) {
+                        return error(*pos, format!("列 {column} 重复"));
+                    }
+                    types.push(self.column_type(&table.name, column, *pos)?);
+                }
+                for (values, pos) in rows {
+                    if values.len() != columns.len() {
+                        return error(
+                            *pos,
+                            format!("有 {} 个列，但是有 {} 个值", columns.len(), values.len()),
+                        );
+                    }
+                    for (((column, _), ty), value) in columns.iter().zip(&types).zip(values) {
+                        self.assign(ty, column, value)?;
+                    }
+                }
+            }
+            Statement::Update {
+                table,
+                sets,
+                condition,
+            } => {
+                self.table(table)?;
+                for (column, pos, value) in sets {
+                    let ty = self.column_type(&table.name, column, *pos)?;
+                    self.assign(&ty, column, value)?;
+                }
+                if let Some(condition) = condition {
+                    let ty = self.expr(condition)?;
+                    self.concrete(ty)?;
+                }
+            }
+            Statement::Delete { table, condition } => {
+                self.table(table)?;
+                if let Some(condition) = condition {
+                    let ty = self.expr(condition)?;
+                    self.concrete(ty)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 表达式在 SQL 中的位置，用于报错
+fn expr_pos(expr: &Expr) -> usize {
+    match expr {
+        Expr::Column { pos, .. }
+        | Expr::Param { pos, .. }
+        | Expr::Number(pos)
+        | Expr::Str(pos)
+        | Expr::Null(pos)
+        | Expr::Binary { pos, .. } => *pos,
+        Expr::Not(expr) | Expr::Neg(expr) | Expr::IsNull(expr) => expr_pos(expr),
+        Expr::In { expr, .. } | Expr::Between { expr, .. } => expr_pos(expr),
+        Expr::Func { args, .. } => args.first().map(expr_pos).unwrap_or(0),
+        Expr::Bool | Expr::Star => 0,
+    }
+}
+
+/// 检查 SQL，返回每个参数的类型和 Postgres 使用的 SQL
+fn check(schema: &Schema, sql: &str) -> SqlResult<(Vec<String>, String)> {
+    let tokens = tokenize(sql)?;
+    let placeholders: Vec<usize> = tokens
+        .iter()
+        .filter(|token| matches!(token.tok, Tok::Param(_)))
+        .map(|token| token.pos)
+        .collect();
+    let mut parser = Parser {
+        sql,
+        tokens,
+        index: 0,
+    };
+    let statement = parser.statement()?;
+    let mut checker = Checker {
+        schema,
+        scope: Vec::new(),
+        aliases: Vec::new(),
+        params: vec![None; placeholders.len()],
+    };
+    checker.statement(&statement)?;
+    let mut types = Vec::new();
+    for (ty, pos) in checker.params.into_iter().zip(&placeholders) {
+        match ty {
+            Some(ty) => types.push(ty),
+            None => return error(*pos, "无法推断参数的类型，参数需要和列比较或者赋值给列"),
+        }
+    }
+
+    let mut numbered = String::with_capacity(sql.len() + placeholders.len());
+    let mut last = 0;
+    for (index, pos) in placeholders.iter().enumerate() {
+        numbered.push_str(&sql[last..*pos]);
+        numbered.push_str(&format!("${}", index + 1));
+        last = pos + 1;
+    }
+    numbered.push_str(&sql[last..]);
+    Ok((types, numbered))
+}
+
+/// 参数的类型：基本类型和 String 直接使用，其他类型使用 sea_orm::prelude 中的定义
+fn type_tokens(ty: &str) -> TokenStream {
+    if let Some(ty) = ty
+        .strip_prefix("Option<")
+        .and_then(|ty| ty.strip_suffix('>'))
+    {
+        let inner = type_tokens(ty);
+        return quote!(::core::option::Option<#inner>);
+    }
+    match ty {
+        "Json" | "Date" | "Time" | "DateTime" | "DateTimeUtc" | "Decimal" | "Uuid" => {
+            let ident = Ident::new(ty, proc_macro2::Span::call_site());
+            quote!(::sea_orm::prelude::#ident)
+        }
+        "Value" => quote!(::sea_orm::Value),
+        ty => syn::parse_str::<syn::Type>(ty)
+            .map(|ty| quote!(#ty))
+            .unwrap_or_else(|_| quote!(::sea_orm::Value)),
+    }
+}
+
+pub(crate) fn sql(input: SqlInput) -> syn::Result<TokenStream> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let (relative, span) = match &input.schema {
+        Some(schema) => (schema.value(), schema.span()),
+        None => (DEFAULT_SCHEMA.to_string(), input.sql.span()),
+    };
+    let path = PathBuf::from(manifest_dir).join(&relative);
+    let text = std::fs::read_to_string(&path).map_err(|e| {
+        syn::Error::new(
+            span,
+            format!(
+                "读取表结构文件 {} 失败: {e}，请先根据 sea-orm 实体生成",
+                path.display()
+            ),
+        )
+    })?;
+    let schema = Schema::parse(&text)
+        .map_err(|e| syn::Error::new(span, format!("表结构文件 {relative} {e}")))?;
+
+    let sql = input.sql.value();
+    let (types, numbered) = check(&schema, &sql).map_err(|e| {
+        let column = sql[..e.pos.min(sql.len())].chars().count() + 1;
+        let near: String = sql[e.pos.min(sql.len())..].chars().take(20).collect();
+        let message = if near.is_empty() {
+            format!("{}（SQL 的末尾）", e.message)
+        } else {
+            format!("{}（第 {column} 个字符，{near}）", e.message)
+        };
+        syn::Error::new(input.sql.span(), message)
+    })?;
+    if types.len() != input.args.len() {
+        return Err(syn::Error::new(
+            input.sql.span(),
+            format!(
+                "SQL 有 {} 个参数，传入了 {} 个",
+                types.len(),
+                input.args.len()
+            ),
+        ));
+    }
+
+    let backend = &input.backend;
+    let sql = if backend == "Postgres" { numbered } else { sql };
+    let values = types.iter().zip(&input.args).map(|(ty, arg)| {
+        let ty = type_tokens(ty);
+        quote_spanned! {arg.span()=>
+            ::sea_orm::Value::from(::core::convert::Into::<#ty>::into(#arg))
+        }
+    });
+    let path = path.to_string_lossy().into_owned();
+    Ok(quote! {
+        {
+            // 表结构文件变化时重新展开
+            const _: &[u8] = ::core::include_bytes!(#path);
+            let values: ::std::vec::Vec<::sea_orm::Value> = ::std::vec![#(#values),*];
+            ::sea_orm::Statement::from_sql_and_values(::sea_orm::DbBackend::#backend, #sql, values)
+        }
+    })
+}
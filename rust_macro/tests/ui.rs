@@ -0,0 +1,12 @@
+/**
+ * 过程宏的编译失败测试，tests/ui 目录下每个 .rs 文件的编译错误需要和同名的 .stderr 一致
+ * tests/ui/pass 目录下的文件必须编译通过
+ *
+ * 修改错误信息之后使用 TRYBUILD=overwrite cargo test -p rust_macro --test ui 更新 .stderr
+ */
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+    t.pass("tests/ui/pass/*.rs");
+}
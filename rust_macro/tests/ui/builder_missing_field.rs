@@ -0,0 +1,12 @@
+use rust_macro::Builder;
+
+#[derive(Builder)]
+struct Server {
+    host: String,
+    port: u16,
+}
+
+fn main() {
+    // port 是必填字段，没有设置时没有 build 方法
+    let _ = Server::builder().host("localhost".to_string()).build();
+}
@@ -0,0 +1,9 @@
+use rust_macro::Builder;
+
+#[derive(Builder)]
+struct Server {
+    #[builder(default = Some(8080))]
+    port: Option<u16>,
+}
+
+fn main() {}
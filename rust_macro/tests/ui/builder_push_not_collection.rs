@@ -0,0 +1,9 @@
+use rust_macro::Builder;
+
+#[derive(Builder)]
+struct Server {
+    #[builder(push = "add_host")]
+    host: String,
+}
+
+fn main() {}
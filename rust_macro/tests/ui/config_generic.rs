@@ -0,0 +1,8 @@
+use rust_macro::Config;
+
+#[derive(Config)]
+struct AppConfig<T> {
+    value: T,
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use rust_macro::Config;
+
+#[derive(Config)]
+struct Database {
+    url: String,
+}
+
+#[derive(Config)]
+struct AppConfig {
+    #[config(default = Some(8080))]
+    port: Option<u16>,
+    #[config(nested, env = "DATABASE")]
+    database: Database,
+    #[config(required)]
+    name: String,
+}
+
+fn main() {}
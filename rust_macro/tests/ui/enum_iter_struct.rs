@@ -0,0 +1,9 @@
+use rust_macro::EnumIter;
+
+#[derive(EnumIter)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use rust_macro::FromStr;
+
+#[derive(FromStr)]
+enum Status {
+    #[enum_str(alias = "done")]
+    Filled,
+    #[enum_str(rename = "Filled")]
+    Pending,
+    #[enum_str(alias = "done")]
+    Cancelled,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use rust_macro::Display;
+
+#[derive(Display)]
+#[enum_str(rename_all = "snake")]
+enum Command {
+    Test,
+    RunAll,
+}
+
+fn main() {}
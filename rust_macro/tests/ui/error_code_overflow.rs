@@ -0,0 +1,12 @@
+use rust_macro::Error;
+
+#[derive(Debug, Error)]
+enum ApiError {
+    #[msg("last")]
+    #[code(4294967295)]
+    Last,
+    #[msg("overflow")]
+    Overflow,
+}
+
+fn main() {}
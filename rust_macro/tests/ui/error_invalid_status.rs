@@ -0,0 +1,10 @@
+use rust_macro::Error;
+
+#[derive(Debug, Error)]
+enum ApiError {
+    #[msg("not found")]
+    #[status(1000)]
+    NotFound,
+}
+
+fn main() {}
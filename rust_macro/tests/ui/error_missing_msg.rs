@@ -0,0 +1,10 @@
+use rust_macro::Error;
+
+#[derive(Debug, Error)]
+enum ApiError {
+    #[msg("not found")]
+    NotFound,
+    Unauthorized,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use rust_macro::Error;
+
+#[derive(Debug, Error)]
+struct ApiError {
+    message: String,
+}
+
+fn main() {}
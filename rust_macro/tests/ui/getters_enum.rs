@@ -0,0 +1,8 @@
+use rust_macro::Getters;
+
+#[derive(Getters)]
+enum Shape {
+    Circle(f64),
+}
+
+fn main() {}
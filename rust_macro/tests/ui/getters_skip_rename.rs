@@ -0,0 +1,9 @@
+use rust_macro::Getters;
+
+#[derive(Getters)]
+struct User {
+    #[getter(skip, rename = "user_name")]
+    name: String,
+}
+
+fn main() {}
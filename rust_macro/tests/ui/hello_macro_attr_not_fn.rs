@@ -0,0 +1,6 @@
+use rust_macro::hello_macro_attr;
+
+#[hello_macro_attr]
+struct A;
+
+fn main() {}
@@ -0,0 +1,7 @@
+use rust_macro::HelloMacro;
+
+// 派生宏只生成 impl，HelloMacro trait 需要由使用者定义
+#[derive(HelloMacro)]
+struct A;
+
+fn main() {}
@@ -0,0 +1,5 @@
+use rust_macro::make_greeting;
+
+make_greeting!(42);
+
+fn main() {}
@@ -0,0 +1,18 @@
+use rust_macro::mcp_tools;
+
+struct Server;
+
+#[mcp_tools]
+impl Server {
+    #[tool]
+    async fn contact_list(
+        &self,
+        #[arg(default = 10)] group: Option<String>,
+        #[arg(range(min = 1, step = 2))] limit: i32,
+        #[arg(extract, default = 1)] page: u32,
+    ) -> Result<String, String> {
+        Ok(format!("{group:?} {limit} {page}"))
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use rust_macro::mcp_tools;
+
+struct Server;
+
+#[mcp_tools]
+impl Server {
+    async fn send_email(&self, to: String) -> String {
+        to
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use rust_macro::memoize;
+
+#[derive(Clone)]
+struct Client;
+
+impl Client {
+    #[memoize]
+    fn connect(url: String) -> Self {
+        Client
+    }
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use rust_macro::memoize;
+
+#[memoize]
+fn double<T: Clone + std::ops::Add<Output = T>>(value: T) -> T {
+    value.clone() + value
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use rust_macro::memoize;
+
+struct Client;
+
+impl Client {
+    #[memoize]
+    fn get(&self, url: String) -> String {
+        url
+    }
+}
+
+fn main() {}
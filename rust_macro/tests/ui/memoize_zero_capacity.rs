@@ -0,0 +1,8 @@
+use rust_macro::memoize;
+
+#[memoize(capacity = 0)]
+fn square(n: u64) -> u64 {
+    n * n
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use std::fmt::Debug;
+
+use rust_macro::{retry, traced};
+
+#[traced]
+fn a() -> impl Debug {
+    1
+}
+
+#[traced]
+async fn b() -> impl Debug {
+    "b"
+}
+
+#[retry(times = 2)]
+fn c() -> Result<impl Debug, String> {
+    Ok(1)
+}
+
+fn main() {
+    let _ = (a(), b(), c());
+}
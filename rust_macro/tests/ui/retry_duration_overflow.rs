@@ -0,0 +1,8 @@
+use rust_macro::retry;
+
+#[retry(backoff = "99999999999999999h")]
+fn fetch() -> Result<String, String> {
+    Ok(String::new())
+}
+
+fn main() {}
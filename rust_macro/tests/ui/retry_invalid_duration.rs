@@ -0,0 +1,8 @@
+use rust_macro::retry;
+
+#[retry(backoff = "100 years")]
+fn fetch() -> Result<String, String> {
+    Ok(String::new())
+}
+
+fn main() {}
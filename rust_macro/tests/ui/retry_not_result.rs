@@ -0,0 +1,8 @@
+use rust_macro::retry;
+
+#[retry(times = 3)]
+fn fetch() -> String {
+    String::new()
+}
+
+fn main() {}
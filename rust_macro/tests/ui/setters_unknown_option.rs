@@ -0,0 +1,9 @@
+use rust_macro::Setters;
+
+#[derive(Setters)]
+struct User {
+    #[setter(copy)]
+    age: u32,
+}
+
+fn main() {}
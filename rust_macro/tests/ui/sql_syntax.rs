@@ -0,0 +1,5 @@
+use rust_macro::sql;
+
+fn main() {
+    let _ = sql!(Oracle, "SELECT id FROM city");
+}
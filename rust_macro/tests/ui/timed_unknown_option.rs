@@ -0,0 +1,6 @@
+use rust_macro::timed;
+
+#[timed(level = "info")]
+fn work() {}
+
+fn main() {}
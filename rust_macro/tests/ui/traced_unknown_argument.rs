@@ -0,0 +1,8 @@
+use rust_macro::traced;
+
+#[traced(skip(token))]
+fn login(user: &str, password: &str) -> bool {
+    user == password
+}
+
+fn main() {}
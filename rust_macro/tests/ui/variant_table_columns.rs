@@ -0,0 +1,13 @@
+use rust_macro::VariantTable;
+
+#[derive(VariantTable)]
+#[table(label: &'static str, color: u32)]
+enum Status {
+    #[table(label = "已成交")]
+    Filled,
+    #[table(label = "待成交", colour = 0xfaad14)]
+    Pending,
+    Cancelled,
+}
+
+fn main() {}
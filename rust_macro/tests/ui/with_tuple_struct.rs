@@ -0,0 +1,6 @@
+use rust_macro::With;
+
+#[derive(With)]
+struct Point(i32, i32);
+
+fn main() {}
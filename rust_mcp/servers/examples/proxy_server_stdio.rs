@@ -0,0 +1,36 @@
+use rmcp::ServiceExt;
+use rmcp::transport::stdio;
+use servers::proxy::{Hub, ProxyConfig, ProxyServer};
+
+/**
+ * 把多个 MCP 服务器合并为一个的代理，参数为配置文件，默认为 rust_mcp/servers/proxy.json，
+ * 其中的子进程在代理的工作目录中启动，默认配置需要在项目根目录下运行：
+ *      cargo run --example proxy_server_stdio -- rust_mcp/servers/proxy.json
+ * email 后端需要先启动 server_sse，没有启动时在后台重试。
+ *
+ * 客户端只需要配置代理：
+ * ```json
+ * {
+ *     "mcpServers": {
+ *         "proxy": {
+ *             "command": "cargo",
+ *             "args": ["run", "--quiet", "--example", "proxy_server_stdio"]
+ *         }
+ *     }
+ * }
+ * ```
+ */
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // stdout 用于 MCP 通信，后端断开、重启等日志输出到 stderr
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "rust_mcp/servers/proxy.json".to_string());
+    let hub = Hub::start(ProxyConfig::load(path)?).await;
+    let service = ProxyServer::new(hub).serve(stdio()).await?;
+    service.waiting().await?;
+    Ok(())
+}
@@ -0,0 +1,506 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use base64::Engine;
+use notify::{EventKind, RecursiveMode, Watcher};
+use rmcp::{
+    ErrorData as McpError, RoleServer, ServerHandler, ServiceExt,
+    model::*,
+    service::{Peer, RequestContext},
+    transport::stdio,
+};
+use sea_orm::{
+    ConnectionTrait, Database, DatabaseConnection, DbBackend, FromQueryResult, JsonValue, Statement,
+};
+use serde_json::json;
+use tokio::sync::{Mutex, mpsc};
+use url::Url;
+use walkdir::WalkDir;
+
+const PAGE_SIZE: usize = 20;
+const ROW_LIMIT: u32 = 100;
+
+/**
+ * 提供 MCP 资源（resources）的服务器：目录树中的文件和 SQLite 数据库中的表
+ *
+ * 启动参数：resource_server_stdio [目录，默认当前目录] [SQLite 文件，默认 src/database/sqlite.db]
+ * ```json
+ * {
+ *   "mcpServers": {
+ *     "resource-server": {
+ *       "command": "cargo",
+ *       "args": ["run", "--example", "resource_server_stdio", "--", "docs", "src/database/sqlite.db"]
+ *     }
+ *   }
+ * }
+ * ```
+ *
+ * 1、resources/list：目录下的文件（file:///绝对路径，空格、# 和中文等字符经过百分号编码）和每张表（db://表名），
+ *    按 PAGE_SIZE 分页，cursor 为下一页的起始位置。隐藏文件、隐藏目录和 target 不列出，也不能读取。
+ * 2、resources/templates/list：db://{table}/{id} 按 id 读取一行，file:///{path} 读取目录下列出的文件。
+ * 3、resources/read：文件的 MIME 类型由 mime_guess 根据扩展名判断，UTF-8 文本返回 text，其他返回 base64 的 blob；
+ *    db://表名 返回前 ROW_LIMIT 行，db://表名/id 返回一行，都为 JSON。
+ * 4、resources/subscribe：文件监听器发现文件修改时发送 notifications/resources/updated，
+ *    数据库文件修改时通知所有订阅的 db:// 资源；文件新增或删除时发送 notifications/resources/list_changed。
+ */
+#[derive(Clone)]
+struct ResourceServer {
+    root: PathBuf,
+    db_path: PathBuf,
+    db: DatabaseConnection,
+    // 已订阅的资源 URI
+    subscriptions: Arc<Mutex<HashSet<String>>>,
+    // 订阅时保存客户端，文件监听任务通过它发送通知
+    peer: Arc<Mutex<Option<Peer<RoleServer>>>>,
+}
+
+impl ResourceServer {
+    async fn new(root: PathBuf, db_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        let root = root.canonicalize()?;
+        let db_path = db_path.canonicalize()?;
+        let db_url = format!("sqlite:{}?mode=ro", db_path.to_str().unwrap());
+        let db = Database::connect(&db_url).await?;
+        Ok(Self {
+            root,
+            db_path,
+            db,
+            subscriptions: Arc::new(Mutex::new(HashSet::new())),
+            peer: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    fn file_uri(path: &Path) -> String {
+        Url::from_file_path(path)
+            .map(String::from)
+            .unwrap_or_else(|_| format!("file://{}", path.display()))
+    }
+
+    /// 目录下的文件，跳过隐藏文件、隐藏目录和 target
+    fn files(&self) -> Vec<Resource> {
+        WalkDir::new(&self.root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0 || listed(&entry.file_name().to_string_lossy())
+            })
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let path = entry.path();
+                let name = path.strip_prefix(&self.root).unwrap_or(path);
+                let mut resource =
+                    RawResource::new(Self::file_uri(path), name.display().to_string());
+                resource.mime_type = Some(
+                    mime_guess::from_path(path)
+                        .first_or_octet_stream()
+                        .to_string(),
+                );
+                resource.size = entry.metadata().ok().map(|meta| meta.len() as u32);
+                resource.no_annotation()
+            })
+            .collect()
+    }
+
+    async fn tables(&self) -> Result<Vec<String>, McpError> {
+        let rows = self
+            .db
+            .query_all(Statement::from_string(
+                DbBackend::Sqlite,
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            ))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        rows.iter()
+            .map(|row| row.try_get::<String>("", "name"))
+            .collect::<Result<_, _>>()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))
+    }
+
+    /// 读取 db://表名 或 db://表名/id，表名必须是数据库中存在的表
+    async fn read_table(&self, uri: &str) -> Result<JsonValue, McpError> {
+        let path = uri.strip_prefix("db://").unwrap_or_default();
+        let (table, id) = match path.split_once('/') {
+            Some((table, id)) => (table, Some(id)),
+            None => (path, None),
+        };
+        if !self.tables().await?.iter().any(|name| name == table) {
+            return Err(McpError::resource_not_found(
+                format!("表 {table} 不存在"),
+                Some(json!({ "uri": uri })),
+            ));
+        }
+        let result = match id {
+            Some(id) => JsonValue::find_by_statement(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                format!("SELECT * FROM \"{table}\" WHERE id = ?"),
+                [id.into()],
+            ))
+            .one(&self.db)
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+            .ok_or_else(|| {
+                McpError::resource_not_found(
+                    format!("表 {table} 中没有 id 为 {id} 的数据"),
+                    Some(json!({ "uri": uri })),
+                )
+            })?,
+            None => JsonValue::Array(
+                JsonValue::find_by_statement(Statement::from_string(
+                    DbBackend::Sqlite,
+                    format!("SELECT * FROM \"{table}\" LIMIT {ROW_LIMIT}"),
+                ))
+                .all(&self.db)
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
+            ),
+        };
+        Ok(result)
+    }
+
+    fn read_file(&self, uri: &str) -> Result<ResourceContents, McpError> {
+        let path = file_path(&self.root, uri)?;
+        let bytes =
+            std::fs::read(&path).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let mime = mime_guess::from_path(&path).first();
+        Ok(match String::from_utf8(bytes) {
+            Ok(text) => ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(mime.map_or("text/plain".to_string(), |mime| mime.to_string())),
+                text,
+                meta: None,
+            },
+            Err(err) => ResourceContents::BlobResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(mime.map_or("application/octet-stream".to_string(), |mime| {
+                    mime.to_string()
+                })),
+                blob: base64::engine::general_purpose::STANDARD.encode(err.into_bytes()),
+                meta: None,
+            },
+        })
+    }
+
+    /**
+     * 监听目录和数据库文件，把文件的变化转换为资源通知
+     * notify 的回调在它自己的线程中执行，通过 channel 把事件交给 tokio 任务，监听器在任务结束之前一直有效
+     */
+    fn watch(&self) -> notify::Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let _ = tx.send(event);
+            })?;
+        watcher.watch(&self.root, RecursiveMode::Recursive)?;
+        if !self.db_path.starts_with(&self.root) {
+            watcher.watch(&self.db_path, RecursiveMode::NonRecursive)?;
+        }
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            let _watcher = watcher;
+            while let Some(event) = rx.recv().await {
+                let Ok(event) = event else {
+                    continue;
+                };
+                let Some(peer) = server.peer.lock().await.clone() else {
+                    continue;
+                };
+                let subscriptions = server.subscriptions.lock().await.clone();
+                let mut updated: Vec<String> = Vec::new();
+                for path in &event.paths {
+                    // SQLite 的 -wal、-journal 文件变化也是数据库的修改
+                    if path
+                        .to_string_lossy()
+                        .starts_with(&*server.db_path.to_string_lossy())
+                    {
+                        updated.extend(
+                            subscriptions
+                                .iter()
+                                .filter(|uri| uri.starts_with("db://"))
+                                .cloned(),
+                        );
+                    } else if subscriptions.contains(&Self::file_uri(path)) {
+                        updated.push(Self::file_uri(path));
+                    }
+                }
+                updated.sort();
+                updated.dedup();
+                for uri in updated {
+                    let _ = peer
+                        .notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                        .await;
+                }
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_)) {
+                    let _ = peer.notify_resource_list_changed().await;
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// files 是否列出这个名称的文件或目录
+fn listed(name: &str) -> bool {
+    !(name.starts_with('.') || name == "target")
+}
+
+/// file:// URI 对应的文件，只允许访问 root 下 files 会列出的文件
+fn file_path(root: &Path, uri: &str) -> Result<PathBuf, McpError> {
+    let not_found =
+        |message: &'static str| McpError::resource_not_found(message, Some(json!({ "uri": uri })));
+    let path = Url::parse(uri)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| not_found("无效的 file:// URI"))?;
+    let path = path.canonicalize().map_err(|_| not_found("文件不存在"))?;
+    let listed = path.strip_prefix(root).is_ok_and(|relative| {
+        relative
+            .components()
+            .all(|component| listed(&component.as_os_str().to_string_lossy()))
+    });
+    if !listed || !path.is_file() {
+        return Err(not_found("只能读取服务目录下的文件"));
+    }
+    Ok(path)
+}
+
+/// cursor 为下一页的起始位置，返回当前页和下一页的 cursor
+fn paginate<T>(
+    items: Vec<T>,
+    request: Option<PaginatedRequestParam>,
+) -> Result<(Vec<T>, Option<String>), McpError> {
+    let start = match request.and_then(|request| request.cursor) {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .ok()
+            .filter(|start| *start <= items.len())
+            .ok_or_else(|| McpError::invalid_params(format!("无效的 cursor: {cursor}"), None))?,
+        None => 0,
+    };
+    let end = (start + PAGE_SIZE).min(items.len());
+    let next_cursor = (end < items.len()).then(|| end.to_string());
+    Ok((
+        items.into_iter().skip(start).take(PAGE_SIZE).collect(),
+        next_cursor,
+    ))
+}
+
+impl ServerHandler for ResourceServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_resources_list_changed()
+                .build(),
+            server_info: Implementation::from_build_env(),
+            instructions: Some(format!(
+                "此服务器以资源的形式提供目录 {} 下的文件和 SQLite 数据库中的表，表中的一行使用 db://表名/id 读取。",
+                self.root.display()
+            )),
+            ..Default::default()
+        }
+    }
+
+    async fn list_resources(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let mut resources = self.files();
+        for table in self.tables().await? {
+            let mut resource = RawResource::new(format!("db://{table}"), format!("表 {table}"));
+            resource.description = Some(format!("表 {table} 的前 {ROW_LIMIT} 行"));
+            resource.mime_type = Some("application/json".to_string());
+            resources.push(resource.no_annotation());
+        }
+        let (resources, next_cursor) = paginate(resources, request)?;
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor,
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let templates = vec![
+            RawResourceTemplate {
+                uri_template: "db://{table}/{id}".to_string(),
+                name: "表中的一行".to_string(),
+                title: None,
+                description: Some("按 id 列读取表中的一行".to_string()),
+                mime_type: Some("application/json".to_string()),
+            }
+            .no_annotation(),
+            RawResourceTemplate {
+                uri_template: format!("{}/{{path}}", Self::file_uri(&self.root)),
+                name: "目录下的文件".to_string(),
+                title: None,
+                description: Some("path 为相对于服务目录的路径".to_string()),
+                mime_type: None,
+            }
+            .no_annotation(),
+        ];
+        let (resource_templates, next_cursor) = paginate(templates, request)?;
+        Ok(ListResourceTemplatesResult {
+            resource_templates,
+            next_cursor,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let contents = if uri.starts_with("db://") {
+            let value = self.read_table(&uri).await?;
+            ResourceContents::TextResourceContents {
+                uri: uri.clone(),
+                mime_type: Some("application/json".to_string()),
+                text: serde_json::to_string_pretty(&value).unwrap(),
+                meta: None,
+            }
+        } else if uri.starts_with("file://") {
+            self.read_file(&uri)?
+        } else {
+            return Err(McpError::resource_not_found(
+                "只支持 file:// 和 db:// 资源",
+                Some(json!({ "uri": uri })),
+            ));
+        };
+        Ok(ReadResourceResult {
+            contents: vec![contents],
+        })
+    }
+
+    async fn subscribe(
+        &self,
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        // 先检查资源是否存在
+        if uri.starts_with("db://") {
+            self.read_table(&uri).await?;
+        } else {
+            file_path(&self.root, &uri)?;
+        }
+        *self.peer.lock().await = Some(context.peer);
+        self.subscriptions.lock().await.insert(uri);
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.subscriptions.lock().await.remove(&uri);
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let root = args.next().unwrap_or_else(|| ".".to_string());
+    let db_path = args
+        .next()
+        .unwrap_or_else(|| "src/database/sqlite.db".to_string());
+
+    let server = ResourceServer::new(root.into(), db_path.into()).await?;
+    server.watch()?;
+    let service = server.serve(stdio()).await?;
+    service.waiting().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod resource_server_test {
+    use rmcp::model::PaginatedRequestParam;
+
+    use super::{PAGE_SIZE, ResourceServer, file_path, paginate};
+
+    fn cursor(cursor: &str) -> Option<PaginatedRequestParam> {
+        Some(PaginatedRequestParam {
+            cursor: Some(cursor.to_string()),
+        })
+    }
+
+    #[test]
+    fn paginate_pages() {
+        let items: Vec<usize> = (0..PAGE_SIZE + 5).collect();
+        let (first, next) = paginate(items.clone(), None).unwrap();
+        assert_eq!(first.len(), PAGE_SIZE);
+        assert_eq!(next.as_deref(), Some("20"));
+
+        // 最后一页没有下一页的 cursor
+        let (last, next) = paginate(items.clone(), cursor("20")).unwrap();
+        assert_eq!(last, [20, 21, 22, 23, 24]);
+        assert_eq!(next, None);
+        let (empty, next) = paginate(items.clone(), cursor("25")).unwrap();
+        assert!(empty.is_empty() && next.is_none());
+
+        for bad in ["26", "-1", "abc", ""] {
+            assert!(paginate(items.clone(), cursor(bad)).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn file_path_inside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let uri = format!("file://{}/a.txt", root.display());
+        assert_eq!(file_path(&root, &uri).unwrap(), root.join("a.txt"));
+        assert!(file_path(&root, "file:///etc/passwd").is_err());
+        let uri = format!("file://{}/../secret.txt", root.display());
+        assert!(file_path(&root, &uri).is_err());
+        // 目录和不存在的文件
+        assert!(file_path(&root, &format!("file://{}", root.display())).is_err());
+        assert!(file_path(&root, &format!("file://{}/b.txt", root.display())).is_err());
+    }
+
+    // 文件名中的空格、#、% 和中文经过编码，生成的 URI 可以读回同一个文件
+    #[test]
+    fn file_uri_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let path = root.join("周报 #1 100%.md");
+        std::fs::write(&path, "周报").unwrap();
+
+        let uri = ResourceServer::file_uri(&path);
+        assert!(uri.starts_with("file:///"));
+        assert!(!uri.contains([' ', '#']));
+        assert_eq!(file_path(&root, &uri).unwrap(), path);
+    }
+
+    // 不列出的隐藏文件和 target 目录也不能通过 URI 读取
+    #[test]
+    fn hidden_files_not_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::write(root.join(".env"), "TOKEN=1").unwrap();
+        std::fs::write(root.join(".git/config"), "").unwrap();
+        std::fs::write(root.join("target/debug/app"), "").unwrap();
+        std::fs::write(root.join("README.md"), "").unwrap();
+
+        for name in [".env", ".git/config", "target/debug/app"] {
+            let uri = ResourceServer::file_uri(&root.join(name));
+            assert!(file_path(&root, &uri).is_err(), "{name}");
+        }
+        let uri = ResourceServer::file_uri(&root.join("README.md"));
+        assert!(file_path(&root, &uri).is_ok());
+    }
+}
@@ -0,0 +1,42 @@
+use servers::email::EmailState;
+use servers::http::{HttpConfig, router};
+
+/**
+ * Streamable HTTP 服务器，计数器和邮件工具都在 http://127.0.0.1:8000/mcp 上
+{
+    "mcpServers": {
+        "streamable-server": {
+        "type": "streamableHttp",
+        "url": "http://127.0.0.1:8000/mcp"
+        }
+    }
+}
+ *
+ * MCP_ALLOWED_ORIGINS：允许的浏览器 Origin，多个用逗号分隔；SMTP 和联系人的环境变量见 EmailState::from_env
+ */
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = HttpConfig {
+        allowed_origins: std::env::var("MCP_ALLOWED_ORIGINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(str::to_string)
+            .collect(),
+        ..Default::default()
+    };
+    let app = router(EmailState::from_env()?, config);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:8000").await?;
+    println!(
+        "MCP Streamable HTTP 服务器: http://{}/mcp",
+        listener.local_addr()?
+    );
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.unwrap();
+        })
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use rmcp::ServiceExt;
+use rmcp::transport::stdio;
+use servers::sandbox::{Sandbox, SandboxPolicy};
+use servers::workspace::WorkspaceServer;
+
+/**
+ * 在沙箱中访问工作区的 MCP 服务器，参数为工作区目录，默认为当前目录
+ *
+ * 环境变量：
+ *  WORKSPACE_COMMANDS：允许执行的命令，逗号分隔，默认 ls,cat
+ *  WORKSPACE_MAX_BYTES：单个文件的大小限制，默认 1048576
+ *  WORKSPACE_TIMEOUT：每次工具调用的超时秒数，默认 30
+ *  WORKSPACE_AUDIT_LOG：审计日志文件，默认写到 stderr
+ *
+ * MCP 配置如下：
+{
+    "mcpServers": {
+        "workspace": {
+            "command": "cargo",
+            "args": ["run", "--example", "workspace_server_stdio", "--", "/path/to/project"],
+            "env": { "WORKSPACE_COMMANDS": "ls,cat,rg" }
+        }
+    }
+}
+ */
+#[tokio::main]
+async fn main() {
+    let root = std::env::args().nth(1).unwrap_or_else(|| ".".to_string());
+    let mut policy = SandboxPolicy::new(root).unwrap();
+    if let Ok(commands) = std::env::var("WORKSPACE_COMMANDS") {
+        policy.allowed_commands = commands
+            .split(',')
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+    if let Ok(bytes) = std::env::var("WORKSPACE_MAX_BYTES") {
+        policy.max_file_bytes = bytes.parse().unwrap();
+    }
+    if let Ok(seconds) = std::env::var("WORKSPACE_TIMEOUT") {
+        policy.timeout = Duration::from_secs(seconds.parse().unwrap());
+    }
+    policy.audit_log = std::env::var_os("WORKSPACE_AUDIT_LOG").map(Into::into);
+
+    let sandbox = Sandbox::new(policy).unwrap();
+    let server = WorkspaceServer::new(sandbox).serve(stdio()).await.unwrap();
+    server.waiting().await.unwrap();
+}
@@ -0,0 +1,462 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use lettre::AsyncTransport;
+use rmcp::handler::server::tool::ToolRouter;
+use rmcp::model::{Implementation, ProtocolVersion, ServerCapabilities, ServerInfo};
+use rmcp::tool;
+use rmcp::{
+    ErrorData, ServerHandler,
+    model::{CallToolResult, Content},
+    tool_handler, tool_router,
+};
+use rust_macro::mcp_tools;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::smtp::{Email, EmailAttachment, SmtpConfig, build_message, smtp_transport};
+
+/// 联系人，可以属于多个分组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Contact {
+    name: String,
+    email: String,
+    groups: Vec<String>,
+}
+
+/// 已发送的邮件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SentEmail {
+    id: u64,
+    to: Vec<String>,
+    subject: String,
+    attachments: Vec<String>,
+    // 发送时间，UNIX 时间戳（秒）
+    sent_at: u64,
+}
+
+/// 联系人和发送记录，每次修改后写回 JSON 文件
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct EmailStore {
+    contacts: Vec<Contact>,
+    sent: Vec<SentEmail>,
+}
+
+impl EmailStore {
+    fn load(path: &Path) -> std::io::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(std::io::Error::other)
+    }
+
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, text)
+    }
+
+    /// 按分组过滤，query 匹配名称或邮箱（不区分大小写）
+    fn search(&self, group: Option<&str>, query: Option<&str>) -> Vec<&Contact> {
+        let query = query.map(str::to_lowercase);
+        self.contacts
+            .iter()
+            .filter(|contact| group.is_none_or(|group| contact.groups.iter().any(|g| g == group)))
+            .filter(|contact| {
+                query.as_ref().is_none_or(|query| {
+                    contact.name.to_lowercase().contains(query)
+                        || contact.email.to_lowercase().contains(query)
+                })
+            })
+            .collect()
+    }
+
+    /// 收件人可以是邮箱、联系人名称或者分组名称
+    fn resolve(&self, recipient: &str) -> Vec<String> {
+        if recipient.contains('@') {
+            return vec![recipient.to_string()];
+        }
+        let by_name: Vec<_> = self
+            .contacts
+            .iter()
+            .filter(|contact| contact.name == recipient)
+            .map(|contact| contact.email.clone())
+            .collect();
+        if !by_name.is_empty() {
+            return by_name;
+        }
+        self.search(Some(recipient), None)
+            .into_iter()
+            .map(|contact| contact.email.clone())
+            .collect()
+    }
+}
+
+/// 单个附件的大小限制
+const MAX_ATTACHMENT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 所有会话共享的 SMTP 配置、发件人和联系人存储
+pub struct EmailState {
+    smtp: SmtpConfig,
+    from: String,
+    store: Mutex<EmailStore>,
+    store_path: PathBuf,
+    attachment_dir: Option<PathBuf>,
+}
+
+impl EmailState {
+    /**
+     * store_path 为保存联系人和已发送邮件的 JSON 文件，不存在时为空
+     * attachment_dir 为附件目录，只能发送这个目录下的文件，为 None 时不能发送附件
+     */
+    pub fn new(
+        smtp: SmtpConfig,
+        from: &str,
+        store_path: PathBuf,
+        attachment_dir: Option<PathBuf>,
+    ) -> std::io::Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            smtp,
+            from: from.to_string(),
+            store: Mutex::new(EmailStore::load(&store_path)?),
+            store_path,
+            attachment_dir: attachment_dir.map(|dir| dir.canonicalize()).transpose()?,
+        }))
+    }
+
+    /**
+     * 从环境变量创建：
+     *      SMTP_HOST、SMTP_PORT、SMTP_USERNAME、SMTP_PASSWORD、SMTP_TLS：SMTP 服务器，见 SmtpConfig::from_env，
+     *          不设置 SMTP_HOST 时发送到本地 1025 端口，可以用 MailHog、Mailpit 之类的测试服务器查看邮件；
+     *      SMTP_FROM：发件人，默认为 SMTP_USERNAME；
+     *      EMAIL_STORE：保存联系人和已发送邮件的 JSON 文件，默认为 email_store.json；
+     *      EMAIL_ATTACHMENT_DIR：附件目录，附件路径相对于这个目录，不设置时不能发送附件。
+     */
+    pub fn from_env() -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let smtp = SmtpConfig::from_env()?;
+        let from = std::env::var("SMTP_FROM")
+            .ok()
+            .or_else(|| smtp.username.clone())
+            .unwrap_or_else(|| "mcp@localhost".to_string());
+        let store_path =
+            std::env::var("EMAIL_STORE").unwrap_or_else(|_| "email_store.json".to_string());
+        let attachment_dir = std::env::var_os("EMAIL_ATTACHMENT_DIR").map(PathBuf::from);
+        Ok(Self::new(smtp, &from, store_path.into(), attachment_dir)?)
+    }
+
+    /**
+     * 读取附件，路径相对于附件目录
+     *
+     * 和 SandboxPolicy::resolve 一样检查真实路径（解析 .. 和符号链接之后）必须在附件目录下，
+     * 文件不能超过 MAX_ATTACHMENT_BYTES。
+     */
+    async fn read_attachment(&self, path: &str) -> Result<EmailAttachment, ErrorData> {
+        let dir = self.attachment_dir.as_ref().ok_or_else(|| {
+            ErrorData::invalid_params(
+                "没有配置附件目录（EMAIL_ATTACHMENT_DIR），不能发送附件",
+                None,
+            )
+        })?;
+        let real = dir
+            .join(path)
+            .canonicalize()
+            .map_err(|e| ErrorData::invalid_params(format!("读取附件 {path} 失败: {e}"), None))?;
+        if !real.starts_with(dir) {
+            return Err(ErrorData::invalid_params(
+                format!("附件 {path} 不在附件目录 {} 中", dir.display()),
+                None,
+            ));
+        }
+        let metadata = tokio::fs::metadata(&real)
+            .await
+            .map_err(|e| ErrorData::invalid_params(format!("读取附件 {path} 失败: {e}"), None))?;
+        if !metadata.is_file() {
+            return Err(ErrorData::invalid_params(
+                format!("附件 {path} 不是文件"),
+                None,
+            ));
+        }
+        if metadata.len() > MAX_ATTACHMENT_BYTES {
+            return Err(ErrorData::invalid_params(
+                format!(
+                    "附件 {path} 的大小 {} 字节超过限制 {MAX_ATTACHMENT_BYTES} 字节",
+                    metadata.len()
+                ),
+                None,
+            ));
+        }
+        let content = tokio::fs::read(&real)
+            .await
+            .map_err(|e| ErrorData::invalid_params(format!("读取附件 {path} 失败: {e}"), None))?;
+        Ok(EmailAttachment {
+            filename: real.file_name().map_or_else(
+                || path.to_string(),
+                |name| name.to_string_lossy().to_string(),
+            ),
+            content_type: mime_guess::from_path(&real)
+                .first_or_octet_stream()
+                .to_string(),
+            content,
+        })
+    }
+
+    fn save(&self, store: &EmailStore) -> Result<(), ErrorData> {
+        store.save(&self.store_path).map_err(|e| {
+            ErrorData::internal_error(
+                format!("保存 {} 失败: {e}", self.store_path.display()),
+                None,
+            )
+        })
+    }
+}
+
+/// 邮件工具，每个会话一个，共享同一个 EmailState
+pub struct EmailServer {
+    state: Arc<EmailState>,
+    // tool_router 字段是必须的，不然 tool_handler 会报错
+    pub(crate) tool_router: ToolRouter<EmailServer>,
+}
+
+impl EmailServer {
+    pub fn new(state: Arc<EmailState>) -> Self {
+        Self {
+            state,
+            tool_router: Self::tool_router(),
+        }
+    }
+}
+
+/**
+ * #[mcp_tools] 要放在 #[tool_router] 的上面，它把工具方法的参数收集为 SendEmailArgs、ContactListArgs 这样的参数结构体：
+ * 1、协议规范要求 ：MCP 协议要求每个工具都必须有一个输入模式（inputSchema），且类型必须是 "object"，参数结构体生成这个模式。
+ * 2、参数上的文档注释是字段的描述，#[arg(...)] 设置默认值和校验规则，校验失败时返回 invalid_params 错误。
+ * 3、没有参数的工具不需要空的参数结构体，rmcp 会生成空的 object 模式；工具列表由 tools/list 提供，不需要单独的工具。
+ */
+#[mcp_tools]
+#[tool_router]
+impl EmailServer {
+    #[tool(description = "发送邮件，dry_run 为 true 时只返回生成的 MIME 邮件，不发送")]
+    async fn send_email(
+        &self,
+        /// 收件人，多个用逗号分隔，可以是邮箱地址、联系人名称或者分组名称
+        #[arg(length(min = 1))]
+        to: String,
+        /// 邮件主题
+        #[arg(length(min = 1, max = 200))]
+        subject: String,
+        /// 邮件内容
+        body: String,
+        /// 邮件内容是否为 HTML，默认为纯文本
+        #[arg(default = false)]
+        html: bool,
+        /// 附件的文件路径，相对于服务器配置的附件目录
+        #[arg(default = Vec::new())]
+        attachments: Vec<String>,
+        /// 只生成邮件，不发送
+        #[arg(default = false)]
+        dry_run: bool,
+    ) -> Result<CallToolResult, ErrorData> {
+        // 有一个收件人解析不到就不发送，避免只发给了其中一部分人
+        let mut recipients = Vec::new();
+        let mut unresolved = Vec::new();
+        {
+            let store = self.state.store.lock().await;
+            for recipient in to.split(',').map(str::trim) {
+                if recipient.is_empty() {
+                    continue;
+                }
+                let emails = store.resolve(recipient);
+                if emails.is_empty() {
+                    unresolved.push(recipient);
+                }
+                recipients.extend(emails);
+            }
+        }
+        if !unresolved.is_empty() || recipients.is_empty() {
+            return Err(ErrorData::invalid_params(
+                format!("找不到收件人: {}", unresolved.join(", ")),
+                None,
+            ));
+        }
+
+        let mut files = Vec::new();
+        for path in &attachments {
+            files.push(self.state.read_attachment(path).await?);
+        }
+        let email = Email {
+            from: self.state.from.clone(),
+            to: recipients.clone(),
+            subject: subject.clone(),
+            body,
+            html,
+            attachments: files,
+        };
+        let message =
+            build_message(&email).map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
+        if dry_run {
+            return Ok(CallToolResult::success(vec![Content::text(
+                String::from_utf8_lossy(&message.formatted()).to_string(),
+            )]));
+        }
+
+        smtp_transport(&self.state.smtp)
+            .map_err(|e| ErrorData::internal_error(e.to_string(), None))?
+            .send(message)
+            .await
+            .map_err(|e| ErrorData::internal_error(format!("SMTP 发送失败: {e}"), None))?;
+
+        let mut store = self.state.store.lock().await;
+        let id = store.sent.last().map_or(1, |sent| sent.id + 1);
+        store.sent.push(SentEmail {
+            id,
+            to: recipients.clone(),
+            subject: subject.clone(),
+            attachments: email
+                .attachments
+                .iter()
+                .map(|a| a.filename.clone())
+                .collect(),
+            sent_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        });
+        self.state.save(&store)?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "邮件 {} 已发送至: {}, 主题: {}",
+            id,
+            recipients.join(", "),
+            subject
+        ))]))
+    }
+
+    #[tool(description = "获取联系人列表，可以按分组过滤和搜索")]
+    async fn contact_list(
+        &self,
+        /// 联系人分组名称，可选
+        group: Option<String>,
+        /// 搜索名称或邮箱，可选
+        query: Option<String>,
+        /// 返回的联系人数量限制，默认为10
+        #[arg(default = 10, range(min = 1, max = 100))]
+        limit: usize,
+    ) -> Result<CallToolResult, ErrorData> {
+        let store = self.state.store.lock().await;
+        let list = store
+            .search(group.as_deref(), query.as_deref())
+            .into_iter()
+            .take(limit)
+            .map(|contact| {
+                Content::text(format!(
+                    "{} <{}> [{}]",
+                    contact.name,
+                    contact.email,
+                    contact.groups.join(", ")
+                ))
+            })
+            .collect::<Vec<_>>();
+        if list.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "没有符合条件的联系人",
+            )]));
+        }
+        Ok(CallToolResult::success(list))
+    }
+
+    #[tool(description = "添加联系人，邮箱已存在时更新名称和分组")]
+    async fn add_contact(
+        &self,
+        /// 联系人名称
+        #[arg(length(min = 1, max = 50))]
+        name: String,
+        /// 联系人邮箱地址
+        #[arg(validate = check_email)]
+        email: String,
+        /// 联系人所属的分组
+        #[arg(default = Vec::new())]
+        groups: Vec<String>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let mut store = self.state.store.lock().await;
+        let contact = Contact {
+            name,
+            email: email.clone(),
+            groups,
+        };
+        match store.contacts.iter_mut().find(|c| c.email == email) {
+            Some(existing) => *existing = contact,
+            None => store.contacts.push(contact),
+        }
+        self.state.save(&store)?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "已保存联系人 {email}"
+        ))]))
+    }
+
+    #[tool(description = "删除联系人")]
+    async fn remove_contact(
+        &self,
+        /// 联系人邮箱地址
+        email: String,
+    ) -> Result<CallToolResult, ErrorData> {
+        let mut store = self.state.store.lock().await;
+        let count = store.contacts.len();
+        store.contacts.retain(|contact| contact.email != email);
+        if store.contacts.len() == count {
+            return Err(ErrorData::invalid_params(
+                format!("联系人 {email} 不存在"),
+                None,
+            ));
+        }
+        self.state.save(&store)?;
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "已删除联系人 {email}"
+        ))]))
+    }
+
+    #[tool(description = "获取已发送的邮件，最近发送的在前")]
+    async fn list_sent(
+        &self,
+        /// 返回的邮件数量限制，默认为10
+        #[arg(default = 10, range(min = 1, max = 100))]
+        limit: usize,
+    ) -> Result<CallToolResult, ErrorData> {
+        let store = self.state.store.lock().await;
+        if store.sent.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                "没有已发送的邮件",
+            )]));
+        }
+        let list = store
+            .sent
+            .iter()
+            .rev()
+            .take(limit)
+            .map(Content::json)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CallToolResult::success(list))
+    }
+}
+
+/// 邮箱地址的简单校验
+fn check_email(email: &str) -> Result<(), String> {
+    match email.split_once('@') {
+        Some((user, domain)) if !user.is_empty() && domain.contains('.') => Ok(()),
+        _ => Err(format!("{email} 不是有效的邮箱地址")),
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for EmailServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::default(),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            server_info: Implementation::default(),
+            instructions: Some(
+                "此服务器提供发送邮件（支持附件和只生成不发送）、管理联系人和查看已发送邮件的工具。"
+                    .to_string(),
+            ),
+        }
+    }
+}
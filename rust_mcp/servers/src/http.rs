@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use rmcp::{
+    ErrorData as McpError, RoleServer, ServerHandler,
+    handler::server::tool::ToolCallContext,
+    model::*,
+    service::RequestContext,
+    transport::streamable_http_server::{
+        StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+    },
+};
+
+use crate::counter::Counter;
+use crate::email::{EmailServer, EmailState};
+
+/**
+ * Streamable HTTP 传输（MCP 2025-03-26 规范）：客户端通过同一个端点 POST 请求、GET 订阅服务器推送
+ *
+ *  1、会话：initialize 的响应头中返回 Mcp-Session-Id，之后的请求都要带上这个请求头，
+ *     没有或者不存在的会话返回错误，DELETE 请求结束会话；每个会话创建一个 McpServer，计数器是会话自己的。
+ *  2、Origin 检查：浏览器发起的请求带有 Origin 请求头，不在允许列表中的返回 403，防止 DNS 重绑定攻击；
+ *     localhost、127.0.0.1、[::1] 总是允许，没有 Origin 的请求（非浏览器客户端）不检查。
+ */
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// 允许的 Origin，例如 https://example.com
+    pub allowed_origins: Vec<String>,
+    /// SSE 流的心跳间隔
+    pub keep_alive: Option<Duration>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            keep_alive: Some(Duration::from_secs(15)),
+        }
+    }
+}
+
+/// 一个会话的服务器，合并 Counter 和 EmailServer 的工具
+pub struct McpServer {
+    counter: Counter,
+    email: EmailServer,
+}
+
+impl McpServer {
+    pub fn new(email: Arc<EmailState>) -> Self {
+        Self {
+            counter: Counter::new(),
+            email: EmailServer::new(email),
+        }
+    }
+}
+
+impl ServerHandler for McpServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            server_info: Implementation::from_build_env(),
+            instructions: Some(
+                "此服务器提供计数器（每个会话独立计数）和发送邮件、管理联系人的工具。".to_string(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let mut tools = self.counter.tool_router.list_all();
+        tools.extend(self.email.tool_router.list_all());
+        Ok(ListToolsResult {
+            tools,
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if self.counter.tool_router.has_route(&request.name) {
+            let context = ToolCallContext::new(&self.counter, request, context);
+            self.counter.tool_router.call(context).await
+        } else {
+            let context = ToolCallContext::new(&self.email, request, context);
+            self.email.tool_router.call(context).await
+        }
+    }
+}
+
+/// Origin 的主机是否为本机
+fn is_loopback(origin: &str) -> bool {
+    let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+async fn check_origin(
+    State(allowed): State<Arc<Vec<String>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(origin) = request.headers().get(header::ORIGIN) {
+        let allowed = origin.to_str().is_ok_and(|origin| {
+            is_loopback(origin) || allowed.iter().any(|allowed| allowed == origin)
+        });
+        if !allowed {
+            return (StatusCode::FORBIDDEN, "Origin 不允许访问").into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// 在 /mcp 上提供 Streamable HTTP 服务的路由
+pub fn router(email: Arc<EmailState>, config: HttpConfig) -> Router {
+    let service = StreamableHttpService::new(
+        move || Ok(McpServer::new(email.clone())),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig {
+            sse_keep_alive: config.keep_alive,
+            stateful_mode: true,
+        },
+    );
+    Router::new()
+        .nest_service("/mcp", service)
+        .layer(middleware::from_fn_with_state(
+            Arc::new(config.allowed_origins),
+            check_origin,
+        ))
+}
+
+#[cfg(test)]
+mod http_test {
+    use super::is_loopback;
+
+    #[test]
+    fn loopback_origin() {
+        assert!(is_loopback("http://localhost:5173"));
+        assert!(is_loopback("http://127.0.0.1"));
+        assert!(is_loopback("http://[::1]:8080"));
+        assert!(!is_loopback("http://localhost.evil.com"));
+        assert!(!is_loopback("https://example.com"));
+    }
+}
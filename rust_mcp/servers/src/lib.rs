@@ -0,0 +1,15 @@
+/**
+ * MCP 服务端的公共代码，examples 中的服务器和 tests 中的集成测试共用
+ *
+ * counter：计数器工具；email：发送邮件和管理联系人的工具；smtp：SMTP 配置和邮件构建，供 email 使用；
+ * http：在同一个 Streamable HTTP 端点上提供 counter 和 email 的工具，每个会话有自己的状态；
+ * sandbox：工作区的目录限制、大小限制、超时、命令白名单和审计日志；workspace：在沙箱中读写、搜索、打包文件和执行命令的工具；
+ * proxy：把多个 MCP 服务器（子进程或者 SSE）合并为一个的代理。
+ */
+pub mod counter;
+pub mod email;
+pub mod http;
+pub mod proxy;
+pub mod sandbox;
+pub mod smtp;
+pub mod workspace;
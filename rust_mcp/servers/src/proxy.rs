@@ -0,0 +1,1028 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rmcp::model::*;
+use rmcp::service::{
+    NotificationContext, Peer, PeerRequestOptions, RequestContext, RunningService, ServiceError,
+};
+use rmcp::transport::common::client_side_sse::FixedInterval;
+use rmcp::transport::sse_client::SseClientConfig;
+use rmcp::transport::{ConfigureCommandExt, SseClientTransport, TokioChildProcess};
+use rmcp::{
+    ClientHandler, ErrorData as McpError, RoleClient, RoleServer, ServerHandler, ServiceExt,
+};
+use serde::Deserialize;
+use tokio::process::Command;
+use tokio::sync::{Mutex, RwLock, oneshot};
+use tokio::task::JoinHandle;
+
+/// 后端名称和工具名、提示名之间的分隔符
+const SEPARATOR: &str = "__";
+/// 后端断开后第一次重启前的等待时间，之后每次加倍
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+/// 重启等待时间的上限，后端稳定运行超过这个时间后等待时间重置
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/**
+ * 代理的配置，格式和客户端的 mcpServers 配置相同，有 command 的是子进程，有 url 的是 SSE 服务器：
+ * ```json
+ * {
+ *     "mcpServers": {
+ *         "counter": { "command": "cargo", "args": ["run", "--example", "count_server_stdio"] },
+ *         "email": { "type": "sse", "url": "http://127.0.0.1:8080/sse" }
+ *     }
+ * }
+ * ```
+ * 后端名称用作前缀，只能包含字母、数字和 -，并且以字母开头。
+ */
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(rename = "mcpServers")]
+    pub servers: BTreeMap<String, BackendConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum BackendConfig {
+    /// 启动子进程，通过 stdin/stdout 通信，cwd 默认为代理的工作目录
+    Stdio {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+        cwd: Option<PathBuf>,
+    },
+    /// 连接到 SSE 服务器
+    Sse { url: String },
+}
+
+impl ProxyConfig {
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let config: Self = serde_json::from_str(json).map_err(io::Error::other)?;
+        for name in config.servers.keys() {
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !valid {
+                return Err(io::Error::other(format!(
+                    "后端名称 {name} 只能包含字母、数字和 -，并且以字母开头"
+                )));
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// 工具名、提示名加上后端名称前缀：counter__increment
+pub fn namespaced(backend: &str, name: &str) -> String {
+    format!("{backend}{SEPARATOR}{name}")
+}
+
+/// 拆分出后端名称和后端中的名称
+pub fn split_name(name: &str) -> Option<(&str, &str)> {
+    name.split_once(SEPARATOR)
+}
+
+/// 资源 URI 和 URI 模板在 scheme 前加上后端名称：file:///a.txt -> files+file:///a.txt，结果仍然是合法的 URI
+pub fn namespaced_uri(backend: &str, uri: &str) -> String {
+    format!("{backend}+{uri}")
+}
+
+/// 拆分出后端名称和后端中的 URI
+pub fn split_uri(uri: &str) -> Option<(&str, &str)> {
+    uri.split_once('+')
+}
+
+fn service_error(err: ServiceError) -> McpError {
+    match err {
+        ServiceError::McpError(err) => err,
+        err => McpError::internal_error(err.to_string(), None),
+    }
+}
+
+fn unexpected_response() -> McpError {
+    McpError::internal_error("后端返回的响应和请求不匹配", None)
+}
+
+/// 日志级别从低到高的顺序，LoggingLevel 没有实现 Ord
+fn level_rank(level: LoggingLevel) -> u8 {
+    level as u8
+}
+
+/**
+ * 连接到代理的客户端（会话），后端的通知转发给它们
+ * 列表变化的通知发给所有会话；资源更新只发给订阅了这个资源的会话，日志只发给设置了日志级别并且级别足够的会话。
+ * 会话结束时由 Session 移除它的订阅和日志级别。
+ */
+#[derive(Default)]
+struct Upstream {
+    next_id: AtomicUsize,
+    peers: Mutex<Vec<(usize, Peer<RoleServer>)>>,
+    /// 加上命名空间的资源 URI -> 订阅了它的会话
+    subscriptions: Mutex<HashMap<String, HashSet<usize>>>,
+    /// 设置了日志级别的会话和它的级别
+    levels: Mutex<HashMap<usize, LoggingLevel>>,
+    /// 发给后端的请求的 progress token -> 客户端和客户端请求中的 progress token
+    progress: Mutex<HashMap<ProgressToken, (Peer<RoleServer>, ProgressToken)>>,
+    next_progress: AtomicUsize,
+}
+
+impl Upstream {
+    fn session_id(&self) -> usize {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    async fn add(&self, session: usize, peer: Peer<RoleServer>) {
+        self.peers.lock().await.push((session, peer));
+    }
+
+    /// 把通知发给 filter 选中的会话，发送失败的会话已经断开，不再给它发送；它的订阅和日志级别在会话结束时移除
+    async fn notify<F, Fut>(&self, filter: impl Fn(usize) -> bool, notify: F)
+    where
+        F: Fn(Peer<RoleServer>) -> Fut,
+        Fut: Future<Output = Result<(), ServiceError>>,
+    {
+        let peers = self.peers.lock().await.clone();
+        let mut closed = Vec::new();
+        for (id, peer) in peers {
+            if filter(id) && notify(peer).await.is_err() {
+                closed.push(id);
+            }
+        }
+        if closed.is_empty() {
+            return;
+        }
+        self.peers
+            .lock()
+            .await
+            .retain(|(id, _)| !closed.contains(id));
+    }
+
+    async fn broadcast<F, Fut>(&self, notify: F)
+    where
+        F: Fn(Peer<RoleServer>) -> Fut,
+        Fut: Future<Output = Result<(), ServiceError>>,
+    {
+        self.notify(|_| true, notify).await;
+    }
+
+    async fn subscribe(&self, uri: &str, session: usize) {
+        self.subscriptions
+            .lock()
+            .await
+            .entry(uri.to_string())
+            .or_default()
+            .insert(session);
+    }
+
+    /// 取消会话的订阅，返回是否还有其他会话订阅了这个资源
+    async fn unsubscribe(&self, uri: &str, session: usize) -> bool {
+        let mut subscriptions = self.subscriptions.lock().await;
+        let Some(sessions) = subscriptions.get_mut(uri) else {
+            return false;
+        };
+        sessions.remove(&session);
+        if sessions.is_empty() {
+            subscriptions.remove(uri);
+            return false;
+        }
+        true
+    }
+
+    /// 设置会话的日志级别，返回所有会话中最低的级别，后端按这个级别发送日志
+    async fn set_level(&self, session: usize, level: LoggingLevel) -> LoggingLevel {
+        let mut levels = self.levels.lock().await;
+        levels.insert(session, level);
+        levels
+            .values()
+            .copied()
+            .min_by_key(|level| level_rank(*level))
+            .unwrap_or(level)
+    }
+
+    /**
+     * 移除结束的会话和它的订阅、日志级别，返回不再有会话订阅的资源；
+     * 会话设置过日志级别并且还有其他会话设置了日志级别时，同时返回剩下的会话中最低的级别
+     */
+    async fn remove(&self, session: usize) -> (Vec<String>, Option<LoggingLevel>) {
+        self.peers.lock().await.retain(|(id, _)| *id != session);
+        let mut unsubscribed = Vec::new();
+        self.subscriptions.lock().await.retain(|uri, sessions| {
+            if sessions.remove(&session) && sessions.is_empty() {
+                unsubscribed.push(uri.clone());
+                return false;
+            }
+            true
+        });
+        let mut levels = self.levels.lock().await;
+        let level = levels.remove(&session).and_then(|_| {
+            levels
+                .values()
+                .copied()
+                .min_by_key(|level| level_rank(*level))
+        });
+        (unsubscribed, level)
+    }
+
+    /**
+     * 后端重新连接后是新的会话，恢复客户端对它的资源的订阅和日志级别
+     */
+    async fn restore(&self, backend: &str, peer: &Peer<RoleClient>) {
+        let uris: Vec<String> = self
+            .subscriptions
+            .lock()
+            .await
+            .keys()
+            .filter_map(|uri| match split_uri(uri) {
+                Some((name, uri)) if name == backend => Some(uri.to_string()),
+                _ => None,
+            })
+            .collect();
+        for uri in uris {
+            if let Err(err) = peer
+                .subscribe(SubscribeRequestParam { uri: uri.clone() })
+                .await
+            {
+                tracing::warn!("恢复后端 {backend} 的资源订阅 {uri} 失败: {err}");
+            }
+        }
+        let level = self
+            .levels
+            .lock()
+            .await
+            .values()
+            .copied()
+            .min_by_key(|level| level_rank(*level));
+        if let Some(level) = level
+            && let Err(err) = peer.set_level(SetLevelRequestParam { level }).await
+        {
+            tracing::warn!("恢复后端 {backend} 的日志级别失败: {err}");
+        }
+    }
+
+    /// 后端断开或者重新连接后，它的工具、提示和资源都可能变化
+    async fn list_changed(&self) {
+        self.broadcast(|peer| async move { peer.notify_tool_list_changed().await })
+            .await;
+        self.broadcast(|peer| async move { peer.notify_prompt_list_changed().await })
+            .await;
+        self.broadcast(|peer| async move { peer.notify_resource_list_changed().await })
+            .await;
+    }
+}
+
+/// 后端连接的客户端处理程序，把后端的通知加上命名空间后转发给客户端
+struct BackendHandler {
+    name: String,
+    upstream: Arc<Upstream>,
+}
+
+impl ClientHandler for BackendHandler {
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _: NotificationContext<RoleClient>,
+    ) {
+        let target = self
+            .upstream
+            .progress
+            .lock()
+            .await
+            .get(&params.progress_token)
+            .cloned();
+        if let Some((peer, progress_token)) = target {
+            let _ = peer
+                .notify_progress(ProgressNotificationParam {
+                    progress_token,
+                    ..params
+                })
+                .await;
+        }
+    }
+
+    async fn on_logging_message(
+        &self,
+        mut params: LoggingMessageNotificationParam,
+        _: NotificationContext<RoleClient>,
+    ) {
+        params.logger = Some(match params.logger {
+            Some(logger) => namespaced(&self.name, &logger),
+            None => self.name.clone(),
+        });
+        let levels = self.upstream.levels.lock().await.clone();
+        let rank = level_rank(params.level);
+        self.upstream
+            .notify(
+                |session| {
+                    levels
+                        .get(&session)
+                        .is_some_and(|level| level_rank(*level) <= rank)
+                },
+                |peer| {
+                    let params = params.clone();
+                    async move { peer.notify_logging_message(params).await }
+                },
+            )
+            .await;
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _: NotificationContext<RoleClient>,
+    ) {
+        let uri = namespaced_uri(&self.name, &params.uri);
+        let Some(sessions) = self.upstream.subscriptions.lock().await.get(&uri).cloned() else {
+            return;
+        };
+        self.upstream
+            .notify(
+                |session| sessions.contains(&session),
+                |peer| {
+                    let uri = uri.clone();
+                    async move {
+                        peer.notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                            .await
+                    }
+                },
+            )
+            .await;
+    }
+
+    async fn on_resource_list_changed(&self, _: NotificationContext<RoleClient>) {
+        self.upstream
+            .broadcast(|peer| async move { peer.notify_resource_list_changed().await })
+            .await;
+    }
+
+    async fn on_tool_list_changed(&self, _: NotificationContext<RoleClient>) {
+        self.upstream
+            .broadcast(|peer| async move { peer.notify_tool_list_changed().await })
+            .await;
+    }
+
+    async fn on_prompt_list_changed(&self, _: NotificationContext<RoleClient>) {
+        self.upstream
+            .broadcast(|peer| async move { peer.notify_prompt_list_changed().await })
+            .await;
+    }
+}
+
+/// 一个后端，peer 为 None 时后端没有连接或者正在重启；capabilities 为最近一次连接时后端声明的功能
+struct Backend {
+    name: String,
+    config: BackendConfig,
+    peer: RwLock<Option<Peer<RoleClient>>>,
+    capabilities: std::sync::Mutex<Option<ServerCapabilities>>,
+}
+
+impl Backend {
+    async fn connect(
+        &self,
+        upstream: Arc<Upstream>,
+    ) -> io::Result<RunningService<RoleClient, BackendHandler>> {
+        let handler = BackendHandler {
+            name: self.name.clone(),
+            upstream,
+        };
+        let service = match &self.config {
+            BackendConfig::Stdio {
+                command,
+                args,
+                env,
+                cwd,
+            } => {
+                let transport = TokioChildProcess::new(Command::new(command).configure(|cmd| {
+                    cmd.args(args).envs(env);
+                    if let Some(cwd) = cwd {
+                        cmd.current_dir(cwd);
+                    }
+                }))?;
+                handler.serve(transport).await
+            }
+            BackendConfig::Sse { url } => {
+                // 不让 SSE 传输自己重连：重连后服务器端是新的会话，需要重新初始化，由 supervise 负责
+                let config = SseClientConfig {
+                    sse_endpoint: url.as_str().into(),
+                    retry_policy: Arc::new(FixedInterval {
+                        max_times: Some(0),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                let transport =
+                    SseClientTransport::start_with_client(reqwest::Client::new(), config)
+                        .await
+                        .map_err(|err| io::Error::other(err.to_string()))?;
+                handler.serve(transport).await
+            }
+        };
+        service.map_err(|err| io::Error::other(err.to_string()))
+    }
+
+    /**
+     * 连接后端，断开后重新连接：子进程崩溃或者 SSE 连接断开后等待 RESTART_DELAY 重启，每次失败等待时间加倍，
+     * 最长 MAX_RESTART_DELAY。断开和重新连接时通知客户端列表已经变化，重新连接后恢复资源订阅和日志级别。
+     * 第一次连接完成（成功或者失败）后通过 ready 通知 Hub::start。
+     */
+    async fn supervise(self: Arc<Self>, upstream: Arc<Upstream>, ready: oneshot::Sender<()>) {
+        let mut ready = Some(ready);
+        let mut delay = RESTART_DELAY;
+        loop {
+            match self.connect(upstream.clone()).await {
+                Ok(service) => {
+                    let peer = service.peer().clone();
+                    *self.capabilities.lock().unwrap() =
+                        peer.peer_info().map(|info| info.capabilities.clone());
+                    upstream.restore(&self.name, &peer).await;
+                    *self.peer.write().await = Some(peer);
+                    match ready.take() {
+                        Some(ready) => {
+                            let _ = ready.send(());
+                        }
+                        None => upstream.list_changed().await,
+                    }
+                    let started = Instant::now();
+                    let reason = service.waiting().await;
+                    *self.peer.write().await = None;
+                    tracing::warn!("后端 {} 已断开: {reason:?}", self.name);
+                    upstream.list_changed().await;
+                    if started.elapsed() >= MAX_RESTART_DELAY {
+                        delay = RESTART_DELAY;
+                    }
+                }
+                Err(err) => tracing::error!("连接后端 {} 失败: {err}", self.name),
+            }
+            if let Some(ready) = ready.take() {
+                let _ = ready.send(());
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RESTART_DELAY);
+        }
+    }
+}
+
+/**
+ * 所有后端和连接到代理的客户端，多个客户端会话共用
+ * Hub 被释放时停止所有后端，子进程随之退出
+ */
+pub struct Hub {
+    backends: Vec<Arc<Backend>>,
+    upstream: Arc<Upstream>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Hub {
+    /// 启动所有后端，每个后端第一次连接完成（成功或者失败）后返回，失败的后端在后台重试
+    pub async fn start(config: ProxyConfig) -> Arc<Self> {
+        let upstream = Arc::new(Upstream::default());
+        let mut backends = Vec::new();
+        let mut tasks = Vec::new();
+        let mut ready = Vec::new();
+        for (name, config) in config.servers {
+            let backend = Arc::new(Backend {
+                name,
+                config,
+                peer: RwLock::new(None),
+                capabilities: std::sync::Mutex::new(None),
+            });
+            let (tx, rx) = oneshot::channel();
+            tasks.push(tokio::spawn(
+                backend.clone().supervise(upstream.clone(), tx),
+            ));
+            backends.push(backend);
+            ready.push(rx);
+        }
+        for rx in ready {
+            let _ = rx.await;
+        }
+        Arc::new(Self {
+            backends,
+            upstream,
+            tasks,
+        })
+    }
+
+    async fn peer(&self, name: &str) -> Result<Peer<RoleClient>, McpError> {
+        let backend = self
+            .backends
+            .iter()
+            .find(|backend| backend.name == name)
+            .ok_or_else(|| McpError::invalid_params(format!("没有名为 {name} 的后端"), None))?;
+        backend.peer.read().await.clone().ok_or_else(|| {
+            McpError::internal_error(format!("后端 {name} 没有连接，正在重启"), None)
+        })
+    }
+
+    /// 根据拆分出的后端名称找到后端，返回后端和后端中的名称
+    async fn route(
+        &self,
+        parts: Option<(&str, &str)>,
+        full: &str,
+    ) -> Result<(Peer<RoleClient>, String), McpError> {
+        let (backend, name) = parts
+            .ok_or_else(|| McpError::invalid_params(format!("{full} 没有后端名称前缀"), None))?;
+        Ok((self.peer(backend).await?, name.to_string()))
+    }
+
+    /// 是否有后端（按最近一次连接时的声明）支持某项功能
+    fn supports(&self, supports: fn(&ServerCapabilities) -> bool) -> bool {
+        self.backends.iter().any(|backend| {
+            backend
+                .capabilities
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(supports)
+        })
+    }
+
+    /// 已经连接并且支持某项功能的后端
+    async fn connected(
+        &self,
+        supports: fn(&ServerCapabilities) -> bool,
+    ) -> Vec<(String, Peer<RoleClient>)> {
+        let mut peers = Vec::new();
+        for backend in &self.backends {
+            let Some(peer) = backend.peer.read().await.clone() else {
+                continue;
+            };
+            if peer
+                .peer_info()
+                .is_some_and(|info| supports(&info.capabilities))
+            {
+                peers.push((backend.name.clone(), peer));
+            }
+        }
+        peers
+    }
+
+    /// 从每个后端取出完整的列表并加上命名空间，获取失败的后端跳过
+    async fn collect<T, F, Fut>(
+        &self,
+        supports: fn(&ServerCapabilities) -> bool,
+        list: F,
+        rename: impl Fn(&str, &mut T),
+    ) -> Vec<T>
+    where
+        F: Fn(Peer<RoleClient>) -> Fut,
+        Fut: Future<Output = Result<Vec<T>, ServiceError>>,
+    {
+        let mut items = Vec::new();
+        for (name, peer) in self.connected(supports).await {
+            match list(peer).await {
+                Ok(list) => items.extend(list.into_iter().map(|mut item| {
+                    rename(&name, &mut item);
+                    item
+                })),
+                Err(err) => tracing::warn!("获取后端 {name} 的列表失败: {err}"),
+            }
+        }
+        items
+    }
+
+    /**
+     * 把请求转发给后端并等待响应
+     * 客户端取消请求时向后端发送取消通知；客户端请求中有 progress token 时，后端的进度通知转发给客户端。
+     */
+    async fn forward(
+        &self,
+        peer: &Peer<RoleClient>,
+        request: ClientRequest,
+        context: &RequestContext<RoleServer>,
+    ) -> Result<ServerResult, McpError> {
+        // progress token 由代理选择，在请求发出之前记录对应关系，后端很快发来的进度通知也能转发
+        let mut options = PeerRequestOptions::no_options();
+        let progress_token = match context.meta.get_progress_token() {
+            Some(token) => {
+                let id = self.upstream.next_progress.fetch_add(1, Ordering::Relaxed);
+                let progress_token =
+                    ProgressToken(NumberOrString::String(format!("proxy-{id}").into()));
+                self.upstream
+                    .progress
+                    .lock()
+                    .await
+                    .insert(progress_token.clone(), (context.peer.clone(), token));
+                let mut meta = Meta::new();
+                meta.set_progress_token(progress_token.clone());
+                options.meta = Some(meta);
+                Some(progress_token)
+            }
+            None => None,
+        };
+        let handle = peer.send_cancellable_request(request, options).await;
+        let handle = match handle {
+            Ok(handle) => handle,
+            Err(err) => {
+                self.forget_progress(progress_token).await;
+                return Err(service_error(err));
+            }
+        };
+        let request_id = handle.id.clone();
+        let result = tokio::select! {
+            result = handle.await_response() => result.map_err(service_error),
+            _ = context.ct.cancelled() => {
+                let _ = peer
+                    .notify_cancelled(CancelledNotificationParam {
+                        request_id,
+                        reason: Some("客户端取消了请求".to_string()),
+                    })
+                    .await;
+                Err(McpError::internal_error("请求已取消", None))
+            }
+        };
+        self.forget_progress(progress_token).await;
+        result
+    }
+
+    async fn forget_progress(&self, progress_token: Option<ProgressToken>) {
+        if let Some(progress_token) = progress_token {
+            self.upstream.progress.lock().await.remove(&progress_token);
+        }
+    }
+
+    /// 把日志级别设置到已经连接并且支持日志的后端
+    async fn set_level(&self, level: LoggingLevel) {
+        let request = SetLevelRequestParam { level };
+        for (name, peer) in self
+            .connected(|capabilities| capabilities.logging.is_some())
+            .await
+        {
+            if let Err(err) = peer.set_level(request.clone()).await {
+                tracing::warn!("设置后端 {name} 的日志级别失败: {err}");
+            }
+        }
+    }
+
+    /// 会话结束后取消只有它订阅的后端资源，并按剩下的会话中最低的日志级别重新设置后端
+    async fn close(&self, session: usize) {
+        let (uris, level) = self.upstream.remove(session).await;
+        for uri in uris {
+            let Some((backend, backend_uri)) = split_uri(&uri) else {
+                continue;
+            };
+            // 没有连接的后端重新连接后按剩下的订阅恢复，不需要取消
+            let Ok(peer) = self.peer(backend).await else {
+                continue;
+            };
+            let request = UnsubscribeRequestParam {
+                uri: backend_uri.to_string(),
+            };
+            if let Err(err) = peer.unsubscribe(request).await {
+                tracing::warn!("取消后端 {backend} 的资源订阅 {backend_uri} 失败: {err}");
+            }
+        }
+        if let Some(level) = level {
+            self.set_level(level).await;
+        }
+    }
+}
+
+impl Drop for Hub {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/**
+ * 一个客户端会话，ProxyServer 的所有克隆共用。会话结束、rmcp 释放 ProxyServer 后在后台清理会话的订阅和日志级别，
+ * 不依赖之后给它发送通知失败
+ */
+struct Session {
+    hub: Arc<Hub>,
+    id: usize,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let hub = self.hub.clone();
+        let id = self.id;
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move { hub.close(id).await });
+        }
+    }
+}
+
+/**
+ * 把多个 MCP 服务器合并为一个的代理，每个客户端会话一个 ProxyServer，共享同一个 Hub
+ *
+ * 工具名和提示名为 后端__名称，资源 URI 和 URI 模板为 后端+原来的 URI，调用时按前缀转发给对应的后端。
+ * 列表请求一次返回所有后端的结果，没有连接的后端不出现在列表中。
+ * 资源订阅和日志级别按会话记录，后端的资源更新和日志只转发给订阅的会话；会话结束后，
+ * 只有它订阅的资源在后端取消订阅，后端的日志级别按剩下的会话重新设置。
+ */
+#[derive(Clone)]
+pub struct ProxyServer {
+    hub: Arc<Hub>,
+    session: Arc<Session>,
+}
+
+impl ProxyServer {
+    pub fn new(hub: Arc<Hub>) -> Self {
+        let session = Arc::new(Session {
+            hub: hub.clone(),
+            id: hub.upstream.session_id(),
+        });
+        Self { hub, session }
+    }
+}
+
+impl ServerHandler for ProxyServer {
+    fn get_info(&self) -> ServerInfo {
+        let names: Vec<_> = self
+            .hub
+            .backends
+            .iter()
+            .map(|backend| backend.name.as_str())
+            .collect();
+        let mut capabilities = ServerCapabilities::builder()
+            .enable_prompts()
+            .enable_prompts_list_changed()
+            .enable_resources()
+            .enable_resources_list_changed()
+            .enable_tools()
+            .enable_tool_list_changed()
+            .build();
+        // 日志和资源订阅只在有后端支持时声明
+        if self
+            .hub
+            .supports(|capabilities| capabilities.logging.is_some())
+        {
+            capabilities.logging = Some(JsonObject::default());
+        }
+        if self.hub.supports(|capabilities| {
+            capabilities
+                .resources
+                .as_ref()
+                .is_some_and(|resources| resources.subscribe == Some(true))
+        }) && let Some(resources) = capabilities.resources.as_mut()
+        {
+            resources.subscribe = Some(true);
+        }
+        ServerInfo {
+            capabilities,
+            server_info: Implementation::from_build_env(),
+            instructions: Some(format!(
+                "此服务器代理了 {} 的工具、提示和资源。工具名和提示名为 后端__名称，资源 URI 为 后端+原来的 URI。",
+                names.join("、")
+            )),
+            ..Default::default()
+        }
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.hub.upstream.add(self.session.id, context.peer).await;
+    }
+
+    async fn list_tools(
+        &self,
+        _: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let tools = self
+            .hub
+            .collect(
+                |capabilities| capabilities.tools.is_some(),
+                |peer| async move { peer.list_all_tools().await },
+                |backend, tool: &mut Tool| tool.name = namespaced(backend, &tool.name).into(),
+            )
+            .await;
+        Ok(ListToolsResult {
+            tools,
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        mut request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let (peer, name) = self
+            .hub
+            .route(split_name(&request.name), &request.name)
+            .await?;
+        request.name = name.into();
+        let request = ClientRequest::CallToolRequest(Request::new(request));
+        match self.hub.forward(&peer, request, &context).await? {
+            ServerResult::CallToolResult(result) => Ok(result),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn list_prompts(
+        &self,
+        _: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        let prompts = self
+            .hub
+            .collect(
+                |capabilities| capabilities.prompts.is_some(),
+                |peer| async move { peer.list_all_prompts().await },
+                |backend, prompt: &mut Prompt| prompt.name = namespaced(backend, &prompt.name),
+            )
+            .await;
+        Ok(ListPromptsResult {
+            prompts,
+            next_cursor: None,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        mut request: GetPromptRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let (peer, name) = self
+            .hub
+            .route(split_name(&request.name), &request.name)
+            .await?;
+        request.name = name;
+        let request = ClientRequest::GetPromptRequest(Request::new(request));
+        match self.hub.forward(&peer, request, &context).await? {
+            ServerResult::GetPromptResult(result) => Ok(result),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    async fn list_resources(
+        &self,
+        _: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let resources = self
+            .hub
+            .collect(
+                |capabilities| capabilities.resources.is_some(),
+                |peer| async move { peer.list_all_resources().await },
+                |backend, resource: &mut Resource| {
+                    resource.raw.uri = namespaced_uri(backend, &resource.raw.uri);
+                },
+            )
+            .await;
+        Ok(ListResourcesResult {
+            resources,
+            next_cursor: None,
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        let resource_templates = self
+            .hub
+            .collect(
+                |capabilities| capabilities.resources.is_some(),
+                |peer| async move { peer.list_all_resource_templates().await },
+                |backend, template: &mut ResourceTemplate| {
+                    template.raw.uri_template = namespaced_uri(backend, &template.raw.uri_template);
+                },
+            )
+            .await;
+        Ok(ListResourceTemplatesResult {
+            resource_templates,
+            next_cursor: None,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        ReadResourceRequestParam { uri }: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let (peer, backend_uri) = self.hub.route(split_uri(&uri), &uri).await?;
+        let request = ClientRequest::ReadResourceRequest(Request::new(ReadResourceRequestParam {
+            uri: backend_uri,
+        }));
+        let ServerResult::ReadResourceResult(mut result) =
+            self.hub.forward(&peer, request, &context).await?
+        else {
+            return Err(unexpected_response());
+        };
+        let backend = split_uri(&uri).map_or("", |(backend, _)| backend);
+        for contents in &mut result.contents {
+            match contents {
+                ResourceContents::TextResourceContents { uri, .. }
+                | ResourceContents::BlobResourceContents { uri, .. } => {
+                    *uri = namespaced_uri(backend, uri);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// 订阅转发给后端，同时记录订阅的会话，后端的资源更新只发给这些会话
+    async fn subscribe(
+        &self,
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let (peer, backend_uri) = self.hub.route(split_uri(&uri), &uri).await?;
+        let request = ClientRequest::SubscribeRequest(Request::new(SubscribeRequestParam {
+            uri: backend_uri,
+        }));
+        match self.hub.forward(&peer, request, &context).await? {
+            ServerResult::EmptyResult(_) => {
+                self.hub.upstream.subscribe(&uri, self.session.id).await;
+                Ok(())
+            }
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// 其他会话还订阅了这个资源时只取消当前会话的订阅，不转发给后端
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let (peer, backend_uri) = self.hub.route(split_uri(&uri), &uri).await?;
+        if self.hub.upstream.unsubscribe(&uri, self.session.id).await {
+            return Ok(());
+        }
+        let request = ClientRequest::UnsubscribeRequest(Request::new(UnsubscribeRequestParam {
+            uri: backend_uri,
+        }));
+        match self.hub.forward(&peer, request, &context).await? {
+            ServerResult::EmptyResult(_) => Ok(()),
+            _ => Err(unexpected_response()),
+        }
+    }
+
+    /// 记录会话的日志级别，所有会话中最低的级别设置到支持日志的后端
+    async fn set_level(
+        &self,
+        SetLevelRequestParam { level }: SetLevelRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let level = self.hub.upstream.set_level(self.session.id, level).await;
+        self.hub.set_level(level).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod proxy_test {
+    use super::{BackendConfig, ProxyConfig, namespaced, namespaced_uri, split_name, split_uri};
+
+    #[test]
+    fn config() {
+        let config = ProxyConfig::from_json(
+            r#"{
+                "mcpServers": {
+                    "counter": { "command": "cargo", "args": ["run", "--example", "count_server_stdio"] },
+                    "email": { "type": "sse", "url": "http://127.0.0.1:8080/sse" }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &config.servers["counter"],
+            BackendConfig::Stdio { command, args, .. } if command == "cargo" && args.len() == 3
+        ));
+        assert!(matches!(
+            &config.servers["email"],
+            BackendConfig::Sse { url } if url == "http://127.0.0.1:8080/sse"
+        ));
+
+        for name in ["my_server", "1st", "a+b", ""] {
+            let json = format!(r#"{{ "mcpServers": {{ "{name}": {{ "url": "http://x" }} }} }}"#);
+            assert!(ProxyConfig::from_json(&json).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn namespacing() {
+        assert_eq!(
+            split_name(&namespaced("counter", "get_value")),
+            Some(("counter", "get_value"))
+        );
+        assert_eq!(
+            split_name(&namespaced("a-b", "_private")),
+            Some(("a-b", "_private"))
+        );
+        assert_eq!(split_name("increment"), None);
+        assert_eq!(
+            split_uri(&namespaced_uri("files", "file:///tmp/a+b.txt")),
+            Some(("files", "file:///tmp/a+b.txt"))
+        );
+        assert_eq!(
+            namespaced_uri("db", "db://{table}/{id}"),
+            "db+db://{table}/{id}"
+        );
+    }
+}
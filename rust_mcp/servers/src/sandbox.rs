@@ -0,0 +1,388 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rmcp::ErrorData as McpError;
+use serde_json::json;
+
+/**
+ * 工作区工具的沙箱策略，所有工具都通过它访问文件和执行命令
+ *
+ *  1、目录限制：路径都相对于 root，绝对路径也必须在 root 下；.. 不能跳出 root，符号链接按实际指向的位置检查。
+ *  2、大小限制：读取、写入、解压的文件不能超过 max_file_bytes，搜索和列目录最多返回 max_results 条。
+ *  3、超时：每次工具调用最多执行 timeout，命令超时后被杀掉。
+ *  4、命令白名单：只能执行 allowed_commands 中的程序，只写程序名，不能带路径；
+ *     参数（包括选项中的值，如 -f文件、--file=文件）不能是绝对路径或者包含 ..，指向已存在文件的参数按 resolve 检查。
+ *     git、cargo 这类可以通过配置或者构建脚本执行任意代码的程序不在默认白名单中。
+ *  5、审计：每次工具调用写一行 JSON（时间、工具、参数摘要、结果、耗时）到 audit_log，没有设置时写到 stderr
+ *     （stdio 传输使用 stdout，不能写到 stdout）。
+ */
+#[derive(Debug, Clone)]
+pub struct SandboxPolicy {
+    pub root: PathBuf,
+    pub max_file_bytes: u64,
+    pub max_results: usize,
+    pub timeout: Duration,
+    pub allowed_commands: Vec<String>,
+    pub audit_log: Option<PathBuf>,
+}
+
+impl SandboxPolicy {
+    /// root 必须是存在的目录，默认 1 MiB、200 条、30 秒，允许 ls、cat
+    pub fn new(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        let root = root.as_ref().canonicalize()?;
+        if !root.is_dir() {
+            return Err(std::io::Error::other(format!(
+                "{} 不是目录",
+                root.display()
+            )));
+        }
+        Ok(Self {
+            root,
+            max_file_bytes: 1024 * 1024,
+            max_results: 200,
+            timeout: Duration::from_secs(30),
+            allowed_commands: ["ls", "cat"].into_iter().map(str::to_string).collect(),
+            audit_log: None,
+        })
+    }
+
+    /**
+     * 把工具参数中的路径转换为 root 下的绝对路径
+     *
+     * 先按字面去掉 . 和 ..（.. 超出 root 时报错），再检查已经存在的最深一级目录的真实路径，
+     * 防止通过指向 root 外面的符号链接读写文件。最后一级是符号链接（包括指向不存在的文件）时报错，
+     * 避免写入时跟随链接。返回的路径不一定存在。
+     */
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, McpError> {
+        let escape = || {
+            McpError::invalid_params(
+                format!("路径 {path} 不在工作区 {} 中", self.root.display()),
+                None,
+            )
+        };
+        let input = Path::new(path);
+        let relative = if input.is_absolute() {
+            input.strip_prefix(&self.root).map_err(|_| escape())?
+        } else {
+            input
+        };
+        let mut resolved = self.root.clone();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if resolved == self.root {
+                        return Err(escape());
+                    }
+                    resolved.pop();
+                }
+                Component::RootDir | Component::Prefix(_) => return Err(escape()),
+            }
+        }
+
+        // exists() 会跟随符号链接，指向不存在的文件的链接也要当作已存在
+        if resolved
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            return Err(McpError::invalid_params(
+                format!("路径 {path} 是符号链接"),
+                None,
+            ));
+        }
+        let mut existing = resolved.as_path();
+        while existing.symlink_metadata().is_err() {
+            existing = existing.parent().ok_or_else(escape)?;
+        }
+        let real = existing.canonicalize().map_err(|_| escape())?;
+        if !real.starts_with(&self.root) {
+            return Err(escape());
+        }
+        match resolved.strip_prefix(existing) {
+            Ok(rest) if !rest.as_os_str().is_empty() => Ok(real.join(rest)),
+            _ => Ok(real),
+        }
+    }
+
+    /// 相对于 root 的路径，用于返回给客户端
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    /// 文件大小不能超过 max_file_bytes
+    pub fn check_size(&self, path: &Path, size: u64) -> Result<(), McpError> {
+        if size > self.max_file_bytes {
+            return Err(McpError::invalid_params(
+                format!(
+                    "{} 的大小 {size} 字节超过限制 {} 字节",
+                    self.relative(path).display(),
+                    self.max_file_bytes
+                ),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /// 程序必须在白名单中
+    pub fn check_command(&self, command: &str) -> Result<(), McpError> {
+        if command.contains(['/', '\\']) || !self.allowed_commands.iter().any(|c| c == command) {
+            return Err(McpError::invalid_params(
+                format!(
+                    "不允许执行 {command}，允许的命令: {}",
+                    self.allowed_commands.join(", ")
+                ),
+                None,
+            ));
+        }
+        Ok(())
+    }
+
+    /**
+     * 命令参数中的路径不能离开 root：绝对路径、包含 .. 和 ~ 开头的参数直接拒绝，
+     * --file=文件 检查 = 后面的值，不以 - 开头的参数（包括 --file 文件 中的文件）都按路径检查；
+     * 短选项可能合在一起写（-la/etc 中 /etc 是 -a 的值），无法知道值从哪个字母开始，所以 -f文件、-I../..
+     * 这样的短选项中任何位置出现 /、~ 或 .. 都拒绝，路径需要作为单独的参数传入。
+     * 相对于 cwd 已经存在的路径按 resolve 检查符号链接。
+     */
+    pub fn check_args(&self, args: &[String], cwd: &Path) -> Result<(), McpError> {
+        for arg in args {
+            let value = if let Some(option) = arg.strip_prefix("--") {
+                match option.split_once('=') {
+                    Some((_, value)) => value,
+                    None => continue,
+                }
+            } else if let Some(option) = arg.strip_prefix('-') {
+                if option.contains(['/', '~']) || option.contains("..") {
+                    return Err(McpError::invalid_params(
+                        format!("短选项 {arg} 中不能包含路径，请把路径作为单独的参数传入"),
+                        None,
+                    ));
+                }
+                let mut chars = option.chars();
+                chars.next();
+                let value = chars.as_str();
+                value.strip_prefix('=').unwrap_or(value)
+            } else {
+                arg.as_str()
+            };
+            if value.is_empty() {
+                continue;
+            }
+            let path = Path::new(value);
+            if path.is_absolute()
+                || value.starts_with('~')
+                || path
+                    .components()
+                    .any(|component| component == Component::ParentDir)
+            {
+                return Err(McpError::invalid_params(
+                    format!("参数 {arg} 不能是绝对路径或者包含 ..，请使用工作区中的相对路径"),
+                    None,
+                ));
+            }
+            let joined = cwd.join(path);
+            if joined.symlink_metadata().is_ok() {
+                self.resolve(&joined.to_string_lossy())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 审计日志，多个会话共用
+pub struct Audit {
+    file: Mutex<Option<File>>,
+}
+
+impl Audit {
+    pub fn new(policy: &SandboxPolicy) -> std::io::Result<Self> {
+        let file = match &policy.audit_log {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(
+        &self,
+        tool: &str,
+        detail: &str,
+        result: Result<(), &McpError>,
+        elapsed: Duration,
+    ) {
+        let line = json!({
+            "time": SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            "tool": tool,
+            "detail": detail,
+            "ok": result.is_ok(),
+            "error": result.err().map(|err| err.message.to_string()),
+            "ms": elapsed.as_millis() as u64,
+        })
+        .to_string();
+        let mut file = self.file.lock().unwrap();
+        match file.as_mut() {
+            Some(file) => {
+                let _ = writeln!(file, "{line}");
+            }
+            None => eprintln!("{line}"),
+        }
+    }
+}
+
+/// 沙箱策略和审计日志
+pub struct Sandbox {
+    pub policy: SandboxPolicy,
+    audit: Audit,
+}
+
+impl Sandbox {
+    pub fn new(policy: SandboxPolicy) -> std::io::Result<Arc<Self>> {
+        let audit = Audit::new(&policy)?;
+        Ok(Arc::new(Self { policy, audit }))
+    }
+
+    /**
+     * 在阻塞线程中执行一次工具调用，超过 timeout 时返回错误，并记录审计日志
+     * 超时之后阻塞线程中的文件操作不能被取消，命令由 run_command 自己在超时后杀掉
+     */
+    pub async fn run<T: Send + 'static>(
+        self: &Arc<Self>,
+        tool: &str,
+        detail: String,
+        f: impl FnOnce(&SandboxPolicy) -> Result<T, McpError> + Send + 'static,
+    ) -> Result<T, McpError> {
+        let start = Instant::now();
+        let sandbox = self.clone();
+        let task = tokio::task::spawn_blocking(move || f(&sandbox.policy));
+        let result = match tokio::time::timeout(self.policy.timeout, task).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(err)) => Err(McpError::internal_error(err.to_string(), None)),
+            Err(_) => Err(McpError::internal_error(
+                format!("{tool} 超过 {:?} 没有完成", self.policy.timeout),
+                None,
+            )),
+        };
+        self.audit
+            .record(tool, &detail, result.as_ref().map(|_| ()), start.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod sandbox_test {
+    use super::SandboxPolicy;
+
+    #[test]
+    fn resolve_inside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        let root = &policy.root;
+
+        assert_eq!(
+            policy.resolve("src/main.rs").unwrap(),
+            root.join("src/main.rs")
+        );
+        assert_eq!(
+            policy.resolve("./src/../Cargo.toml").unwrap(),
+            root.join("Cargo.toml")
+        );
+        assert_eq!(policy.resolve(".").unwrap(), *root);
+        assert!(policy.resolve("../outside").is_err());
+        assert!(policy.resolve("src/../../outside").is_err());
+        assert!(policy.resolve("/etc/passwd").is_err());
+        assert_eq!(
+            policy.resolve(root.join("src").to_str().unwrap()).unwrap(),
+            root.join("src")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_symlink_escape() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        assert!(policy.resolve("link/secret.txt").is_err());
+
+        // 最后一级是符号链接时拒绝，包括指向不存在的文件的链接
+        std::os::unix::fs::symlink(
+            outside.path().join("missing.txt"),
+            dir.path().join("dangling"),
+        )
+        .unwrap();
+        assert!(policy.resolve("dangling").is_err());
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("a.txt"), dir.path().join("b.txt")).unwrap();
+        assert!(policy.resolve("b.txt").is_err());
+    }
+
+    #[test]
+    fn command_allowlist() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        assert!(policy.check_command("cat").is_ok());
+        assert!(policy.check_command("git").is_err());
+        assert!(policy.check_command("rm").is_err());
+        assert!(policy.check_command("/bin/cat").is_err());
+    }
+
+    #[test]
+    fn command_args_inside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let src = policy.root.join("src");
+
+        assert!(
+            policy
+                .check_args(&args(&["-l", "src", "a.txt"]), &policy.root)
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check_args(&args(&["/etc/passwd"]), &policy.root)
+                .is_err()
+        );
+        assert!(policy.check_args(&args(&["../outside"]), &src).is_err());
+        assert!(
+            policy
+                .check_args(&args(&["--file=/etc/passwd"]), &policy.root)
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_args(&args(&["~/.ssh/id_rsa"]), &policy.root)
+                .is_err()
+        );
+        // 短选项后面直接跟着的值，以及长选项后面单独的参数
+        for short in [
+            "-f/etc/passwd",
+            "-I../..",
+            "-o=/tmp/out",
+            "-la/etc",
+            "-xf~/.ssh",
+        ] {
+            assert!(policy.check_args(&args(&[short]), &policy.root).is_err());
+        }
+        assert!(
+            policy
+                .check_args(&args(&["--file", "/etc/passwd"]), &policy.root)
+                .is_err()
+        );
+        assert!(
+            policy
+                .check_args(&args(&["-la", "-n5", "--color"]), &policy.root)
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,318 @@
+/**
+ * 通过 SMTP 发送邮件的公共代码：SMTP 配置、创建 lettre 的异步传输器、构建带附件的邮件
+ *
+ * email 模块的 MCP 工具使用这里的代码发送邮件。SmtpConfig::qq 和 smtp_transport 沿用根项目 src/web/email.rs 中
+ * lettre 示例的设置：587 端口、STARTTLS（Tls::Required）、授权码认证、30 秒超时，只是把它们改成了可配置的。
+ */
+use std::{fmt, time::Duration};
+
+use lettre::{
+    AsyncSmtpTransport, Message, Tokio1Executor,
+    address::AddressError,
+    message::{Attachment, Mailbox, MultiPart, SinglePart, header::ContentType},
+    transport::smtp::{
+        self,
+        authentication::Credentials,
+        client::{Tls, TlsParameters},
+    },
+};
+
+/// SMTP 连接的加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// 明文连接后使用 STARTTLS 升级，端口一般为 587
+    Required,
+    /// 直接使用 TLS 连接，端口一般为 465
+    Wrapper,
+    /// 不加密，只用于本地的测试服务器（如 MailHog、Mailpit）
+    None,
+}
+
+/// SMTP 服务器配置
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub tls: SmtpTls,
+    pub timeout: Duration,
+}
+
+impl SmtpConfig {
+    /// QQ 邮箱，password 为邮箱设置中生成的授权码
+    pub fn qq(username: &str, password: &str) -> Self {
+        Self {
+            host: "smtp.qq.com".to_string(),
+            port: 587,
+            username: Some(username.to_string()),
+            password: Some(password.to_string()),
+            tls: SmtpTls::Required,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// 本地不加密、不认证的 SMTP 服务器
+    pub fn local(port: u16) -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: None,
+            password: None,
+            tls: SmtpTls::None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /**
+     * 从环境变量读取：SMTP_HOST、SMTP_PORT、SMTP_USERNAME、SMTP_PASSWORD、SMTP_TLS（required、wrapper、none）
+     * 没有设置 SMTP_HOST 时使用本地 1025 端口的测试服务器
+     */
+    pub fn from_env() -> Result<Self, EmailError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let Some(host) = var("SMTP_HOST") else {
+            return Ok(Self::local(
+                var("SMTP_PORT")
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or(1025),
+            ));
+        };
+        let tls = match var("SMTP_TLS").as_deref() {
+            None | Some("required") => SmtpTls::Required,
+            Some("wrapper") => SmtpTls::Wrapper,
+            Some("none") => SmtpTls::None,
+            Some(other) => return Err(EmailError::Config(format!("SMTP_TLS 不支持 {other}"))),
+        };
+        let port = match var("SMTP_PORT") {
+            Some(port) => port
+                .parse()
+                .map_err(|_| EmailError::Config(format!("SMTP_PORT 不是有效的端口: {port}")))?,
+            None if tls == SmtpTls::Wrapper => 465,
+            None if tls == SmtpTls::None => 25,
+            None => 587,
+        };
+        Ok(Self {
+            host,
+            port,
+            username: var("SMTP_USERNAME"),
+            password: var("SMTP_PASSWORD"),
+            tls,
+            timeout: Duration::from_secs(30),
+        })
+    }
+}
+
+/// 邮件发送过程中的错误
+#[derive(Debug)]
+pub enum EmailError {
+    Config(String),
+    Address(AddressError),
+    Message(lettre::error::Error),
+    Smtp(smtp::Error),
+}
+
+impl fmt::Display for EmailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmailError::Config(message) => write!(f, "SMTP 配置错误: {message}"),
+            EmailError::Address(err) => write!(f, "邮箱地址无效: {err}"),
+            EmailError::Message(err) => write!(f, "构建邮件失败: {err}"),
+            EmailError::Smtp(err) => write!(f, "SMTP 发送失败: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EmailError {}
+
+impl From<AddressError> for EmailError {
+    fn from(err: AddressError) -> Self {
+        EmailError::Address(err)
+    }
+}
+
+impl From<lettre::error::Error> for EmailError {
+    fn from(err: lettre::error::Error) -> Self {
+        EmailError::Message(err)
+    }
+}
+
+impl From<smtp::Error> for EmailError {
+    fn from(err: smtp::Error) -> Self {
+        EmailError::Smtp(err)
+    }
+}
+
+/// 根据配置创建异步 SMTP 传输器
+pub fn smtp_transport(
+    config: &SmtpConfig,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>, EmailError> {
+    let tls_parameters = || TlsParameters::builder(config.host.clone()).build();
+    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+        .port(config.port)
+        .timeout(Some(config.timeout));
+    builder = match config.tls {
+        // 使用Required而不是Wrapper，避免TLS配置冲突
+        SmtpTls::Required => builder.tls(Tls::Required(tls_parameters()?)),
+        SmtpTls::Wrapper => builder.tls(Tls::Wrapper(tls_parameters()?)),
+        SmtpTls::None => builder.tls(Tls::None),
+    };
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    Ok(builder.build())
+}
+
+/// 邮件附件
+#[derive(Debug, Clone)]
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// 要发送的邮件，to 可以有多个收件人
+#[derive(Debug, Clone, Default)]
+pub struct Email {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    pub html: bool,
+    pub attachments: Vec<EmailAttachment>,
+}
+
+/// 构建邮件消息，有附件时为 multipart/mixed；message.formatted() 为完整的 MIME 文本
+pub fn build_message(email: &Email) -> Result<Message, EmailError> {
+    let from: Mailbox = email.from.parse()?;
+    let mut builder = Message::builder()
+        .from(from.clone())
+        .reply_to(from)
+        .subject(email.subject.clone());
+    for to in &email.to {
+        builder = builder.to(to.parse()?);
+    }
+    let content_type = if email.html {
+        ContentType::TEXT_HTML
+    } else {
+        ContentType::TEXT_PLAIN
+    };
+    let message = if email.attachments.is_empty() {
+        builder.header(content_type).body(email.body.clone())?
+    } else {
+        let mut multipart = MultiPart::mixed().singlepart(
+            SinglePart::builder()
+                .header(content_type)
+                .body(email.body.clone()),
+        );
+        for attachment in &email.attachments {
+            let content_type = ContentType::parse(&attachment.content_type)
+                .unwrap_or_else(|_| ContentType::parse("application/octet-stream").unwrap());
+            multipart = multipart.singlepart(
+                Attachment::new(attachment.filename.clone())
+                    .body(attachment.content.clone(), content_type),
+            );
+        }
+        builder.multipart(multipart)?
+    };
+    Ok(message)
+}
+
+#[cfg(test)]
+mod smtp_test {
+    use super::{Email, EmailAttachment, SmtpConfig, build_message, smtp_transport};
+    use lettre::AsyncTransport;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    fn qq_email(subject: &str, body: &str, html: bool) -> Email {
+        Email {
+            from: "sender@qq.com".to_string(),
+            to: vec!["receiver@163.com".to_string()],
+            subject: subject.to_string(),
+            body: body.to_string(),
+            html,
+            attachments: Vec::new(),
+        }
+    }
+
+    // 渲染完整的 MIME 文本，不发送
+    #[test]
+    fn formatted_message() {
+        let mut email = qq_email("附件", "见附件", false);
+        email.attachments.push(EmailAttachment {
+            filename: "report.csv".to_string(),
+            content_type: "text/csv".to_string(),
+            content: b"id,name\n1,rust\n".to_vec(),
+        });
+        let message = build_message(&email).unwrap();
+        let text = String::from_utf8(message.formatted()).unwrap();
+        assert!(text.contains("To: receiver@163.com"));
+        assert!(text.contains("Content-Type: multipart/mixed"));
+        assert!(text.contains("filename=\"report.csv\""));
+
+        assert!(
+            build_message(&Email {
+                to: vec!["not-an-email".to_string()],
+                ..email
+            })
+            .is_err()
+        );
+    }
+
+    /**
+     * 只实现 EHLO、MAIL、RCPT、DATA、QUIT 的本地 SMTP 服务器，返回收到的 DATA 内容
+     */
+    async fn fake_smtp_server(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+        let mut data = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 OK\r\n").await.unwrap();
+                } else {
+                    data.push_str(&line);
+                    data.push('\n');
+                }
+                continue;
+            }
+            let command = line.to_ascii_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") {
+                b"250-localhost\r\n250 8BITMIME\r\n"
+            } else if command.starts_with("DATA") {
+                in_data = true;
+                b"354 End data with <CR><LF>.<CR><LF>\r\n"
+            } else if command.starts_with("QUIT") {
+                writer.write_all(b"221 Bye\r\n").await.unwrap();
+                break;
+            } else {
+                b"250 OK\r\n"
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+        data
+    }
+
+    // 发送到本地的 SMTP 服务器
+    #[tokio::test]
+    async fn send_to_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_smtp_server(listener));
+
+        let message = build_message(&qq_email("本地测试", "hello smtp", false)).unwrap();
+        let transport = smtp_transport(&SmtpConfig::local(port)).unwrap();
+        transport.send(message).await.unwrap();
+        drop(transport);
+
+        let data = server.await.unwrap();
+        assert!(data.contains("To: receiver@163.com"));
+        assert!(data.contains("hello smtp"));
+    }
+}
@@ -0,0 +1,731 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use rmcp::handler::server::tool::ToolRouter;
+use rmcp::model::{CallToolResult, Content, Implementation, ServerCapabilities, ServerInfo};
+use rmcp::{ErrorData as McpError, ServerHandler, tool, tool_handler, tool_router};
+use rust_macro::mcp_tools;
+use walkdir::WalkDir;
+
+use crate::sandbox::{Sandbox, SandboxPolicy};
+
+/**
+ * 工作区工具：读写文件、替换文件内容、正则搜索、列目录、zip/tar 打包解包、执行白名单中的命令
+ *
+ * 所有路径都相对于沙箱的 root，限制和审计见 SandboxPolicy。每个会话一个 WorkspaceServer，共享同一个 Sandbox。
+ */
+pub struct WorkspaceServer {
+    sandbox: Arc<Sandbox>,
+    pub(crate) tool_router: ToolRouter<WorkspaceServer>,
+}
+
+impl WorkspaceServer {
+    pub fn new(sandbox: Arc<Sandbox>) -> Self {
+        Self {
+            sandbox,
+            tool_router: Self::tool_router(),
+        }
+    }
+}
+
+fn io_error(path: &Path, policy: &SandboxPolicy, err: io::Error) -> McpError {
+    McpError::internal_error(format!("{}: {err}", policy.relative(path).display()), None)
+}
+
+/// 读取文本文件，超过大小限制或者不是 UTF-8 时报错
+fn read_text(policy: &SandboxPolicy, path: &Path) -> Result<String, McpError> {
+    let meta = fs::metadata(path).map_err(|e| io_error(path, policy, e))?;
+    if !meta.is_file() {
+        return Err(McpError::invalid_params(
+            format!("{} 不是文件", policy.relative(path).display()),
+            None,
+        ));
+    }
+    policy.check_size(path, meta.len())?;
+    let bytes = fs::read(path).map_err(|e| io_error(path, policy, e))?;
+    String::from_utf8(bytes).map_err(|_| {
+        McpError::invalid_params(
+            format!("{} 不是 UTF-8 文本文件", policy.relative(path).display()),
+            None,
+        )
+    })
+}
+
+fn write_text(
+    policy: &SandboxPolicy,
+    path: &Path,
+    content: &str,
+    append: bool,
+) -> Result<(), McpError> {
+    let existing = if append {
+        fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+    } else {
+        0
+    };
+    policy.check_size(path, existing + content.len() as u64)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| io_error(parent, policy, e))?;
+    }
+    let result = if append {
+        use std::io::Write;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+    } else {
+        fs::write(path, content)
+    };
+    result.map_err(|e| io_error(path, policy, e))
+}
+
+/// 目录下的文件和目录，跳过 .git 和 target
+fn walk(root: &Path, max_depth: usize) -> impl Iterator<Item = walkdir::DirEntry> {
+    WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            name != ".git" && name != "target"
+        })
+        .filter_map(Result::ok)
+}
+
+/// 打包 source（文件或目录）到 destination，格式由扩展名决定
+fn archive(policy: &SandboxPolicy, source: &Path, destination: &Path) -> Result<usize, McpError> {
+    let name = destination.to_string_lossy().to_lowercase();
+    let base = source.parent().unwrap_or(&policy.root);
+    let files: Vec<PathBuf> = if source.is_dir() {
+        walk(source, usize::MAX)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect()
+    } else {
+        vec![source.to_path_buf()]
+    };
+    let mut total = 0;
+    for file in &files {
+        total += fs::metadata(file)
+            .map_err(|e| io_error(file, policy, e))?
+            .len();
+    }
+    policy.check_size(source, total)?;
+
+    let output = File::create(destination).map_err(|e| io_error(destination, policy, e))?;
+    let zip_error = |e: zip::result::ZipError| McpError::internal_error(e.to_string(), None);
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipWriter::new(output);
+        let options = zip::write::SimpleFileOptions::default();
+        for file in &files {
+            let entry = file.strip_prefix(base).unwrap_or(file);
+            zip.start_file(entry.to_string_lossy(), options)
+                .map_err(zip_error)?;
+            let mut input = File::open(file).map_err(|e| io_error(file, policy, e))?;
+            io::copy(&mut input, &mut zip).map_err(|e| io_error(file, policy, e))?;
+        }
+        zip.finish().map_err(zip_error)?;
+    } else if name.ends_with(".tar") {
+        let mut tar = tar::Builder::new(output);
+        for file in &files {
+            let entry = file.strip_prefix(base).unwrap_or(file);
+            tar.append_path_with_name(file, entry)
+                .map_err(|e| io_error(file, policy, e))?;
+        }
+        tar.finish().map_err(|e| io_error(destination, policy, e))?;
+    } else {
+        return Err(McpError::invalid_params("只支持 .zip 和 .tar", None));
+    }
+    Ok(files.len())
+}
+
+/**
+ * 把一个条目写到 path，最多写入 limit 字节。条目中声明的大小不可信，按实际读到的字节数计算，
+ * 超过 limit 时删除写了一半的文件并报错，返回写入的字节数
+ */
+fn unpack_entry(
+    policy: &SandboxPolicy,
+    entry: impl Read,
+    path: &Path,
+    source: &Path,
+    total: u64,
+) -> Result<u64, McpError> {
+    let limit = policy.max_file_bytes.saturating_sub(total);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| io_error(parent, policy, e))?;
+    }
+    let mut output = File::create(path).map_err(|e| io_error(path, policy, e))?;
+    let written =
+        io::copy(&mut entry.take(limit + 1), &mut output).map_err(|e| io_error(path, policy, e))?;
+    if written > limit {
+        drop(output);
+        let _ = fs::remove_file(path);
+        policy.check_size(source, total + written)?;
+    }
+    Ok(written)
+}
+
+/// 解包前检查每个条目：不能跳出目标目录，总大小不能超过限制
+fn unarchive(policy: &SandboxPolicy, source: &Path, destination: &Path) -> Result<usize, McpError> {
+    let name = source.to_string_lossy().to_lowercase();
+    let target = |entry: &Path| -> Result<PathBuf, McpError> {
+        let joined = destination.join(entry);
+        policy.resolve(joined.to_str().unwrap_or_default())
+    };
+    let mut total = 0u64;
+    let mut count = 0;
+    if name.ends_with(".zip") {
+        let file = File::open(source).map_err(|e| io_error(source, policy, e))?;
+        let mut zip = zip::ZipArchive::new(file)
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        for index in 0..zip.len() {
+            let mut entry = zip
+                .by_index(index)
+                .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+            let path = entry.enclosed_name().ok_or_else(|| {
+                McpError::invalid_params(format!("压缩包中的路径 {} 不安全", entry.name()), None)
+            })?;
+            let path = target(&path)?;
+            if entry.is_dir() {
+                fs::create_dir_all(&path).map_err(|e| io_error(&path, policy, e))?;
+                continue;
+            }
+            // 声明的大小已经超过限制时不用解压
+            policy.check_size(source, total.saturating_add(entry.size()))?;
+            total += unpack_entry(policy, &mut entry, &path, source, total)?;
+            count += 1;
+        }
+    } else if name.ends_with(".tar") {
+        let file = File::open(source).map_err(|e| io_error(source, policy, e))?;
+        let mut tar = tar::Archive::new(file);
+        for entry in tar.entries().map_err(|e| io_error(source, policy, e))? {
+            let mut entry = entry.map_err(|e| io_error(source, policy, e))?;
+            let kind = entry.header().entry_type();
+            if !(kind.is_file() || kind.is_dir()) {
+                return Err(McpError::invalid_params(
+                    "压缩包中不能有链接和特殊文件",
+                    None,
+                ));
+            }
+            let path = entry
+                .path()
+                .map_err(|e| io_error(source, policy, e))?
+                .into_owned();
+            // 和 zip 的 enclosed_name 一样，只接受不含 ..、根目录和盘符的相对路径
+            if !path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(McpError::invalid_params(
+                    format!("压缩包中的路径 {} 不安全", path.display()),
+                    None,
+                ));
+            }
+            let path = target(&path)?;
+            if kind.is_dir() {
+                fs::create_dir_all(&path).map_err(|e| io_error(&path, policy, e))?;
+                continue;
+            }
+            total += unpack_entry(policy, &mut entry, &path, source, total)?;
+            count += 1;
+        }
+    } else {
+        return Err(McpError::invalid_params("只支持 .zip 和 .tar", None));
+    }
+    Ok(count)
+}
+
+/**
+ * 在后台线程中读取子进程的输出，最多保留 limit 字节；超过时设置 overflow 并停止读取，
+ * 由 run_command 杀掉进程，输出不会在内存中无限增长
+ */
+fn read_capped(
+    pipe: impl Read + Send + 'static,
+    limit: u64,
+    overflow: Arc<AtomicBool>,
+) -> std::thread::JoinHandle<(Vec<u8>, bool)> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.take(limit + 1).read_to_end(&mut bytes);
+        let truncated = bytes.len() as u64 > limit;
+        if truncated {
+            bytes.truncate(limit as usize);
+            overflow.store(true, Ordering::Relaxed);
+        }
+        (bytes, truncated)
+    })
+}
+
+/// 执行命令，超时后杀掉进程；stdout 或 stderr 超过大小限制时杀掉进程并截断输出
+fn run_command(
+    policy: &SandboxPolicy,
+    command: &str,
+    args: &[String],
+    cwd: &Path,
+) -> Result<String, McpError> {
+    policy.check_command(command)?;
+    policy.check_args(args, cwd)?;
+    // 不用 stdout_capture：它会把全部输出读进内存，这里通过管道边读边计数
+    let pipe_error = |e: io::Error| McpError::internal_error(e.to_string(), None);
+    let (stdout_reader, stdout_writer) = os_pipe::pipe().map_err(pipe_error)?;
+    let (stderr_reader, stderr_writer) = os_pipe::pipe().map_err(pipe_error)?;
+    // 表达式持有管道的写端，启动后马上释放，子进程退出时读端才能读到 EOF
+    let handle = duct::cmd(command, args)
+        .dir(cwd)
+        .stdin_null()
+        .stdout_file(stdout_writer)
+        .stderr_file(stderr_writer)
+        .unchecked()
+        .start()
+        .map_err(|e| McpError::invalid_params(format!("启动 {command} 失败: {e}"), None))?;
+    let overflow = Arc::new(AtomicBool::new(false));
+    let limit = policy.max_file_bytes;
+    let stdout = read_capped(stdout_reader, limit, overflow.clone());
+    let stderr = read_capped(stderr_reader, limit, overflow.clone());
+    // 比工具的超时稍短，保证进程在工具调用返回之前被杀掉
+    let deadline = Instant::now() + policy.timeout.saturating_sub(Duration::from_millis(500));
+    let status = loop {
+        if let Some(output) = handle
+            .try_wait()
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        {
+            break output.status;
+        }
+        if overflow.load(Ordering::Relaxed) {
+            let _ = handle.kill();
+            break handle
+                .wait()
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?
+                .status;
+        }
+        if Instant::now() >= deadline {
+            let _ = handle.kill();
+            let _ = handle.wait();
+            return Err(McpError::internal_error(
+                format!("{command} 超时，已终止"),
+                None,
+            ));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let output = |reader: std::thread::JoinHandle<(Vec<u8>, bool)>| {
+        let (bytes, truncated) = reader.join().unwrap_or_default();
+        let text = String::from_utf8_lossy(&bytes).to_string();
+        if truncated {
+            format!("{text}\n...（输出超过 {limit} 字节，已截断）")
+        } else {
+            text
+        }
+    };
+    Ok(format!(
+        "exit status: {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+        status
+            .code()
+            .map_or("killed".to_string(), |code| code.to_string()),
+        output(stdout),
+        output(stderr)
+    ))
+}
+
+fn text(text: impl Into<String>) -> CallToolResult {
+    CallToolResult::success(vec![Content::text(text.into())])
+}
+
+#[mcp_tools]
+#[tool_router]
+impl WorkspaceServer {
+    #[tool(description = "读取工作区中的文本文件")]
+    async fn read_file(
+        &self,
+        /// 相对于工作区的文件路径
+        path: String,
+        /// 从第几行开始读取（从 1 开始），默认为 1
+        #[arg(default = 1, range(min = 1))]
+        start_line: usize,
+        /// 最多读取的行数，不设置时读到文件末尾
+        #[arg(range(min = 1))]
+        max_lines: Option<usize>,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = path.clone();
+        self.sandbox
+            .run("read_file", detail, move |policy| {
+                let path = policy.resolve(&path)?;
+                let content = read_text(policy, &path)?;
+                let lines = content
+                    .lines()
+                    .skip(start_line - 1)
+                    .take(max_lines.unwrap_or(usize::MAX));
+                Ok(text(lines.collect::<Vec<_>>().join("\n")))
+            })
+            .await
+    }
+
+    #[tool(description = "写入文本文件，目录不存在时自动创建")]
+    async fn write_file(
+        &self,
+        /// 相对于工作区的文件路径
+        path: String,
+        /// 文件内容
+        content: String,
+        /// 是否追加到文件末尾，默认覆盖
+        #[arg(default = false)]
+        append: bool,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!("{path} ({} 字节, append = {append})", content.len());
+        self.sandbox
+            .run("write_file", detail, move |policy| {
+                let path = policy.resolve(&path)?;
+                write_text(policy, &path, &content, append)?;
+                Ok(text(format!("已写入 {}", policy.relative(&path).display())))
+            })
+            .await
+    }
+
+    #[tool(
+        description = "把文件中的 old_text 替换为 new_text，old_text 必须存在且唯一（replace_all 为 true 时替换全部）"
+    )]
+    async fn patch_file(
+        &self,
+        /// 相对于工作区的文件路径
+        path: String,
+        /// 要替换的原文，包含足够的上下文使它在文件中唯一
+        #[arg(length(min = 1))]
+        old_text: String,
+        /// 替换后的内容
+        new_text: String,
+        /// 是否替换所有出现的位置
+        #[arg(default = false)]
+        replace_all: bool,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!(
+            "{path} ({} 字节 -> {} 字节)",
+            old_text.len(),
+            new_text.len()
+        );
+        self.sandbox
+            .run("patch_file", detail, move |policy| {
+                let path = policy.resolve(&path)?;
+                let content = read_text(policy, &path)?;
+                let count = content.matches(&old_text).count();
+                if count == 0 {
+                    return Err(McpError::invalid_params("文件中没有找到 old_text", None));
+                }
+                if count > 1 && !replace_all {
+                    return Err(McpError::invalid_params(
+                        format!("old_text 出现了 {count} 次，请加上更多上下文或者设置 replace_all"),
+                        None,
+                    ));
+                }
+                write_text(policy, &path, &content.replace(&old_text, &new_text), false)?;
+                Ok(text(format!(
+                    "已替换 {} 中的 {count} 处",
+                    policy.relative(&path).display()
+                )))
+            })
+            .await
+    }
+
+    #[tool(description = "用正则表达式搜索工作区中的文本文件，返回 文件:行号: 内容")]
+    async fn search(
+        &self,
+        /// 正则表达式
+        #[arg(length(min = 1, max = 1000))]
+        pattern: String,
+        /// 搜索的目录，默认为工作区根目录
+        #[arg(default = ".".to_string())]
+        path: String,
+        /// 只搜索路径匹配这个正则表达式的文件，例如 \.rs$
+        include: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!("{pattern} in {path}");
+        self.sandbox
+            .run("search", detail, move |policy| {
+                let invalid = |e: regex::Error| McpError::invalid_params(e.to_string(), None);
+                let regex = Regex::new(&pattern).map_err(invalid)?;
+                let include = include
+                    .as_deref()
+                    .map(Regex::new)
+                    .transpose()
+                    .map_err(invalid)?;
+                let root = policy.resolve(&path)?;
+                let mut matches = Vec::new();
+                'files: for entry in walk(&root, usize::MAX) {
+                    let file = entry.path();
+                    let relative = policy.relative(file).to_string_lossy().to_string();
+                    if !entry.file_type().is_file()
+                        || include
+                            .as_ref()
+                            .is_some_and(|include| !include.is_match(&relative))
+                    {
+                        continue;
+                    }
+                    // 跳过二进制和超过大小限制的文件
+                    let Ok(content) = read_text(policy, file) else {
+                        continue;
+                    };
+                    for (number, line) in content.lines().enumerate() {
+                        if regex.is_match(line) {
+                            matches.push(format!("{relative}:{}: {line}", number + 1));
+                            if matches.len() >= policy.max_results {
+                                matches
+                                    .push(format!("...（超过 {} 条，已截断）", policy.max_results));
+                                break 'files;
+                            }
+                        }
+                    }
+                }
+                Ok(text(matches.join("\n")))
+            })
+            .await
+    }
+
+    #[tool(description = "列出目录中的文件和子目录")]
+    async fn list_dir(
+        &self,
+        /// 目录，默认为工作区根目录
+        #[arg(default = ".".to_string())]
+        path: String,
+        /// 递归的深度，默认为 1（只列出直接的子项）
+        #[arg(default = 1, range(min = 1, max = 10))]
+        max_depth: usize,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!("{path} (depth = {max_depth})");
+        self.sandbox
+            .run("list_dir", detail, move |policy| {
+                let root = policy.resolve(&path)?;
+                if !root.is_dir() {
+                    return Err(McpError::invalid_params(format!("{path} 不是目录"), None));
+                }
+                let mut lines: Vec<String> = walk(&root, max_depth)
+                    .take(policy.max_results + 1)
+                    .map(|entry| {
+                        let relative = policy.relative(entry.path()).display().to_string();
+                        if entry.file_type().is_dir() {
+                            format!("{relative}/")
+                        } else {
+                            let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+                            format!("{relative} ({size} 字节)")
+                        }
+                    })
+                    .collect();
+                if lines.len() > policy.max_results {
+                    lines.truncate(policy.max_results);
+                    lines.push(format!("...（超过 {} 条，已截断）", policy.max_results));
+                }
+                Ok(text(lines.join("\n")))
+            })
+            .await
+    }
+
+    #[tool(description = "把文件或目录打包为 zip 或 tar，格式由 destination 的扩展名决定")]
+    async fn archive(
+        &self,
+        /// 要打包的文件或目录
+        source: String,
+        /// 生成的压缩包路径，以 .zip 或 .tar 结尾
+        destination: String,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!("{source} -> {destination}");
+        self.sandbox
+            .run("archive", detail, move |policy| {
+                let source = policy.resolve(&source)?;
+                let destination = policy.resolve(&destination)?;
+                let count = archive(policy, &source, &destination)?;
+                Ok(text(format!(
+                    "已打包 {count} 个文件到 {}",
+                    policy.relative(&destination).display()
+                )))
+            })
+            .await
+    }
+
+    #[tool(description = "解压 zip 或 tar 到目录，压缩包中的路径不能跳出目标目录")]
+    async fn unarchive(
+        &self,
+        /// 压缩包路径，以 .zip 或 .tar 结尾
+        source: String,
+        /// 解压到的目录
+        destination: String,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!("{source} -> {destination}");
+        self.sandbox
+            .run("unarchive", detail, move |policy| {
+                let source = policy.resolve(&source)?;
+                let destination = policy.resolve(&destination)?;
+                let count = unarchive(policy, &source, &destination)?;
+                Ok(text(format!(
+                    "已解压 {count} 个文件到 {}",
+                    policy.relative(&destination).display()
+                )))
+            })
+            .await
+    }
+
+    #[tool(description = "在工作区中执行白名单中的命令，返回退出码和输出")]
+    async fn run_command(
+        &self,
+        /// 程序名，必须在白名单中，不能带路径
+        command: String,
+        /// 命令参数，不经过 shell 解析，路径参数必须是工作区中的相对路径，不能包含 ..
+        #[arg(default = Vec::new())]
+        args: Vec<String>,
+        /// 工作目录，默认为工作区根目录
+        #[arg(default = ".".to_string())]
+        cwd: String,
+    ) -> Result<CallToolResult, McpError> {
+        let detail = format!("{command} {} (cwd = {cwd})", args.join(" "));
+        self.sandbox
+            .run("run_command", detail, move |policy| {
+                let cwd = policy.resolve(&cwd)?;
+                Ok(text(run_command(policy, &command, &args, &cwd)?))
+            })
+            .await
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for WorkspaceServer {
+    fn get_info(&self) -> ServerInfo {
+        let policy = &self.sandbox.policy;
+        ServerInfo {
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            server_info: Implementation::from_build_env(),
+            instructions: Some(format!(
+                "此服务器提供工作区 {} 中的文件读写、搜索、打包和命令执行工具。路径都相对于工作区，\
+                 单个文件不超过 {} 字节，每次调用不超过 {:?}，允许执行的命令: {}。",
+                policy.root.display(),
+                policy.max_file_bytes,
+                policy.timeout,
+                policy.allowed_commands.join(", ")
+            )),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod workspace_test {
+    use std::io::Write;
+
+    use super::{archive, run_command, unarchive};
+    use crate::sandbox::SandboxPolicy;
+
+    #[test]
+    fn archive_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        let root = &policy.root;
+        std::fs::create_dir_all(root.join("docs/sub")).unwrap();
+        std::fs::write(root.join("docs/a.txt"), "a").unwrap();
+        std::fs::write(root.join("docs/sub/b.txt"), "b").unwrap();
+
+        for name in ["docs.zip", "docs.tar"] {
+            let count = archive(&policy, &root.join("docs"), &root.join(name)).unwrap();
+            assert_eq!(count, 2);
+            let out = root.join(format!("out-{name}"));
+            assert_eq!(unarchive(&policy, &root.join(name), &out).unwrap(), 2);
+            assert_eq!(
+                std::fs::read_to_string(out.join("docs/sub/b.txt")).unwrap(),
+                "b"
+            );
+        }
+    }
+
+    #[test]
+    fn unarchive_rejects_escape() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        let path = policy.root.join("evil.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        zip.start_file("../../escape.txt", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"x").unwrap();
+        zip.finish().unwrap();
+
+        assert!(unarchive(&policy, &path, &policy.root.join("out")).is_err());
+        assert!(!dir.path().parent().unwrap().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn unarchive_rejects_tar_escape() {
+        let dir = tempfile::tempdir().unwrap();
+        let policy = SandboxPolicy::new(dir.path()).unwrap();
+        let path = policy.root.join("evil.tar");
+        let mut tar = tar::Builder::new(std::fs::File::create(&path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_entry_type(tar::EntryType::Regular);
+        // set_path 会拒绝 ..，直接写入头部的名字字段
+        let name = b"../victim.txt";
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.set_cksum();
+        tar.append(&header, &b"pwned"[..]).unwrap();
+        tar.finish().unwrap();
+        drop(tar);
+
+        assert!(unarchive(&policy, &path, &policy.root.join("out")).is_err());
+        assert!(!policy.root.join("victim.txt").exists());
+    }
+
+    // 总大小按实际解压出的字节数计算
+    #[test]
+    fn unarchive_total_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut policy = SandboxPolicy::new(dir.path()).unwrap();
+        policy.max_file_bytes = 10;
+        let path = policy.root.join("big.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for name in ["a.txt", "b.txt"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"123456").unwrap();
+        }
+        zip.finish().unwrap();
+
+        let out = policy.root.join("out");
+        assert!(unarchive(&policy, &path, &out).is_err());
+        assert!(out.join("a.txt").exists());
+        assert!(!out.join("b.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_timeout_and_allowlist() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut policy = SandboxPolicy::new(dir.path()).unwrap();
+        policy.allowed_commands.push("sleep".to_string());
+        policy.timeout = std::time::Duration::from_secs(1);
+        let root = policy.root.clone();
+
+        let output = run_command(&policy, "ls", &[], &root).unwrap();
+        assert!(output.starts_with("exit status: 0"));
+        assert!(run_command(&policy, "rm", &["-rf".to_string()], &root).is_err());
+        assert!(run_command(&policy, "cat", &["/etc/passwd".to_string()], &root).is_err());
+        assert!(run_command(&policy, "sleep", &["5".to_string()], &root).is_err());
+    }
+
+    // 输出超过限制时杀掉进程，不等到超时
+    #[cfg(unix)]
+    #[test]
+    fn command_output_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut policy = SandboxPolicy::new(dir.path()).unwrap();
+        policy.allowed_commands.push("yes".to_string());
+        policy.max_file_bytes = 1024;
+        policy.timeout = std::time::Duration::from_secs(10);
+        let root = policy.root.clone();
+
+        let start = std::time::Instant::now();
+        let output = run_command(&policy, "yes", &[], &root).unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert!(output.starts_with("exit status: killed"));
+        assert!(output.contains("输出超过 1024 字节，已截断"));
+    }
+}
@@ -0,0 +1,640 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rmcp::{
+    ClientHandler, ErrorData as McpError, RoleClient, RoleServer, ServerHandler, ServiceExt,
+    model::*,
+    service::{NotificationContext, PeerRequestOptions, RequestContext, RunningService},
+    transport::SseServer,
+};
+use serde_json::json;
+use servers::counter::Counter;
+use servers::proxy::{Hub, ProxyConfig, ProxyServer};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio_util::sync::CancellationToken;
+
+/// 测试等待通知的时间上限
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 一个空闲的本地地址
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+/// 在 addr 上启动提供 Counter 的 SSE 服务器，取消返回的 token 停止服务器
+async fn serve_counter(addr: SocketAddr) -> CancellationToken {
+    SseServer::serve(addr)
+        .await
+        .unwrap()
+        .with_service(Counter::new)
+}
+
+/**
+ * 把 listen 上的连接转发到 target，取消返回的 token 时关闭监听和所有连接，模拟后端停止
+ * rmcp 的 SseServer 取消后不会关闭已经建立的 SSE 流，不能直接用来测试断开
+ */
+async fn relay(listen: SocketAddr, target: SocketAddr) -> CancellationToken {
+    let listener = tokio::net::TcpListener::bind(listen).await.unwrap();
+    let ct = CancellationToken::new();
+    let stop = ct.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut inbound, _) = tokio::select! {
+                accepted = listener.accept() => accepted.unwrap(),
+                _ = stop.cancelled() => break,
+            };
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                let mut outbound = tokio::net::TcpStream::connect(target).await.unwrap();
+                tokio::select! {
+                    _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound) => {}
+                    _ = stop.cancelled() => {}
+                }
+            });
+        }
+    });
+    ct
+}
+
+/// 在随机端口上启动提供 Counter 的 SSE 服务器，返回 SSE 地址
+async fn counter_backend() -> String {
+    let addr = free_addr();
+    serve_counter(addr).await;
+    format!("http://{addr}/sse")
+}
+
+/**
+ * 测试用的后端：wait 工具一直等到请求被取消，notify 工具发送一条日志和 mem://a 的资源更新，
+ * progress 工具收到请求后立即发送进度通知；收到的取消订阅和日志级别请求记录到 requests
+ */
+#[derive(Clone)]
+struct Probe {
+    started: UnboundedSender<()>,
+    cancelled: UnboundedSender<()>,
+    requests: UnboundedSender<String>,
+}
+
+impl ServerHandler for Probe {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_tools()
+                .build(),
+            ..Default::default()
+        }
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        match request.name.as_ref() {
+            "wait" => {
+                let _ = self.started.send(());
+                context.ct.cancelled().await;
+                let _ = self.cancelled.send(());
+                Err(McpError::internal_error("已取消", None))
+            }
+            "notify" => {
+                let _ = context
+                    .peer
+                    .notify_logging_message(LoggingMessageNotificationParam {
+                        level: LoggingLevel::Info,
+                        logger: None,
+                        data: json!("hello"),
+                    })
+                    .await;
+                let _ = context
+                    .peer
+                    .notify_resource_updated(ResourceUpdatedNotificationParam {
+                        uri: "mem://a".to_string(),
+                    })
+                    .await;
+                Ok(CallToolResult::success(vec![Content::text("ok")]))
+            }
+            "progress" => {
+                if let Some(progress_token) = context.meta.get_progress_token() {
+                    let _ = context
+                        .peer
+                        .notify_progress(ProgressNotificationParam {
+                            progress_token,
+                            progress: 1.0,
+                            total: Some(1.0),
+                            message: None,
+                        })
+                        .await;
+                }
+                Ok(CallToolResult::success(vec![Content::text("ok")]))
+            }
+            name => Err(McpError::invalid_params(format!("没有工具 {name}"), None)),
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        _: SubscribeRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let _ = self.requests.send(format!("unsubscribe {}", request.uri));
+        Ok(())
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let _ = self.requests.send(format!("set_level {:?}", request.level));
+        Ok(())
+    }
+}
+
+/// 启动 Probe 后端，返回 SSE 地址、wait 工具开始和被取消时的通知，以及后端收到的请求
+async fn probe_backend() -> (
+    String,
+    UnboundedReceiver<()>,
+    UnboundedReceiver<()>,
+    UnboundedReceiver<String>,
+) {
+    let addr = free_addr();
+    let (started, started_rx) = unbounded_channel();
+    let (cancelled, cancelled_rx) = unbounded_channel();
+    let (requests, requests_rx) = unbounded_channel();
+    let probe = Probe {
+        started,
+        cancelled,
+        requests,
+    };
+    SseServer::serve(addr)
+        .await
+        .unwrap()
+        .with_service(move || probe.clone());
+    (
+        format!("http://{addr}/sse"),
+        started_rx,
+        cancelled_rx,
+        requests_rx,
+    )
+}
+
+/// 记录代理发来的通知的客户端
+#[derive(Clone)]
+struct Events {
+    list_changed: UnboundedSender<()>,
+    progress: UnboundedSender<ProgressNotificationParam>,
+    logging: UnboundedSender<LoggingMessageNotificationParam>,
+    updated: UnboundedSender<String>,
+}
+
+struct EventsRx {
+    list_changed: UnboundedReceiver<()>,
+    progress: UnboundedReceiver<ProgressNotificationParam>,
+    logging: UnboundedReceiver<LoggingMessageNotificationParam>,
+    updated: UnboundedReceiver<String>,
+}
+
+fn events() -> (Events, EventsRx) {
+    let (list_changed, list_changed_rx) = unbounded_channel();
+    let (progress, progress_rx) = unbounded_channel();
+    let (logging, logging_rx) = unbounded_channel();
+    let (updated, updated_rx) = unbounded_channel();
+    (
+        Events {
+            list_changed,
+            progress,
+            logging,
+            updated,
+        },
+        EventsRx {
+            list_changed: list_changed_rx,
+            progress: progress_rx,
+            logging: logging_rx,
+            updated: updated_rx,
+        },
+    )
+}
+
+impl ClientHandler for Events {
+    async fn on_tool_list_changed(&self, _: NotificationContext<RoleClient>) {
+        let _ = self.list_changed.send(());
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _: NotificationContext<RoleClient>,
+    ) {
+        let _ = self.progress.send(params);
+    }
+
+    async fn on_logging_message(
+        &self,
+        params: LoggingMessageNotificationParam,
+        _: NotificationContext<RoleClient>,
+    ) {
+        let _ = self.logging.send(params);
+    }
+
+    async fn on_resource_updated(
+        &self,
+        params: ResourceUpdatedNotificationParam,
+        _: NotificationContext<RoleClient>,
+    ) {
+        let _ = self.updated.send(params.uri);
+    }
+}
+
+/// 等待下一条通知，超时则测试失败
+async fn next<T>(rx: &mut UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(TIMEOUT, rx.recv())
+        .await
+        .expect("等待通知超时")
+        .unwrap()
+}
+
+/// 一段时间内没有收到通知
+async fn nothing<T>(rx: &mut UnboundedReceiver<T>) -> bool {
+    tokio::time::timeout(Duration::from_millis(300), rx.recv())
+        .await
+        .is_err()
+}
+
+async fn start(config: &str) -> Arc<Hub> {
+    Hub::start(ProxyConfig::from_json(config).unwrap()).await
+}
+
+/// 通过内存中的双向管道连接到代理，每次连接是一个新的会话
+async fn connect<H: ClientHandler>(hub: &Arc<Hub>, handler: H) -> RunningService<RoleClient, H> {
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server = ProxyServer::new(hub.clone());
+    tokio::spawn(async move {
+        let service = server.serve(server_io).await.unwrap();
+        let _ = service.waiting().await;
+    });
+    handler.serve(client_io).await.unwrap()
+}
+
+async fn call<H: ClientHandler>(
+    client: &RunningService<RoleClient, H>,
+    name: &str,
+) -> Result<CallToolResult, rmcp::service::ServiceError> {
+    client
+        .call_tool(CallToolRequestParam {
+            name: name.to_string().into(),
+            arguments: None,
+        })
+        .await
+}
+
+fn text(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text().map(|text| text.text.clone()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[tokio::test]
+async fn merge_and_route() {
+    let first = counter_backend().await;
+    let second = counter_backend().await;
+    // 没有服务器的地址，连接失败后在后台重试，不影响其他后端
+    let config = format!(
+        r#"{{
+            "mcpServers": {{
+                "first": {{ "type": "sse", "url": "{first}" }},
+                "second": {{ "type": "sse", "url": "{second}" }},
+                "down": {{ "type": "sse", "url": "http://127.0.0.1:1/sse" }}
+            }}
+        }}"#
+    );
+    let client = connect(&start(&config).await, ()).await;
+
+    let mut names: Vec<_> = client
+        .list_all_tools()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|tool| tool.name.to_string())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "first__get_value",
+            "first__increment",
+            "second__get_value",
+            "second__increment"
+        ]
+    );
+
+    // 每个后端有自己的计数
+    call(&client, "first__increment").await.unwrap();
+    call(&client, "first__increment").await.unwrap();
+    call(&client, "second__increment").await.unwrap();
+    assert_eq!(text(&call(&client, "first__get_value").await.unwrap()), "2");
+    assert_eq!(
+        text(&call(&client, "second__get_value").await.unwrap()),
+        "1"
+    );
+
+    assert!(call(&client, "increment").await.is_err());
+    assert!(call(&client, "down__increment").await.is_err());
+    assert!(call(&client, "missing__increment").await.is_err());
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn restart_backend() {
+    let target = free_addr();
+    serve_counter(target).await;
+    let addr = free_addr();
+    let ct = relay(addr, target).await;
+    let config = format!(
+        r#"{{ "mcpServers": {{ "counter": {{ "type": "sse", "url": "http://{addr}/sse" }} }} }}"#
+    );
+    let (handler, mut rx) = events();
+    let client = connect(&start(&config).await, handler).await;
+    call(&client, "counter__increment").await.unwrap();
+
+    // 后端停止：客户端收到列表变化的通知，调用失败
+    ct.cancel();
+    next(&mut rx.list_changed).await;
+    assert!(call(&client, "counter__increment").await.is_err());
+    assert!(client.list_all_tools().await.unwrap().is_empty());
+
+    // 后端在同一个地址重新启动，代理重新连接后调用恢复
+    let ct = relay(addr, target).await;
+    next(&mut rx.list_changed).await;
+    call(&client, "counter__increment").await.unwrap();
+    assert_eq!(
+        text(&call(&client, "counter__get_value").await.unwrap()),
+        "1"
+    );
+
+    client.cancel().await.unwrap();
+    ct.cancel();
+}
+
+/**
+ * count_server_stdio 示例的可执行文件：cargo test 会编译所有示例，只运行这个测试文件时先编译示例，
+ * 不用 cargo run 启动，测试需要结束的是服务器进程本身
+ */
+#[cfg(unix)]
+fn count_server() -> std::path::PathBuf {
+    let exe = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .and_then(|deps| deps.parent())
+        .unwrap()
+        .join("examples")
+        .join("count_server_stdio");
+    if !exe.exists() {
+        let status = std::process::Command::new(env!("CARGO"))
+            .args(["build", "--example", "count_server_stdio"])
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+    exe
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn restart_stdio_backend() {
+    let dir = tempfile::tempdir().unwrap();
+    let pid_file = dir.path().join("pid");
+    // 通过 sh 启动，把服务器进程的 pid 写入文件，exec 后 pid 不变
+    let script = format!(
+        "echo $$ > '{}'; exec '{}'",
+        pid_file.display(),
+        count_server().display()
+    );
+    let config = json!({
+        "mcpServers": { "counter": { "command": "sh", "args": ["-c", script] } }
+    });
+    let (handler, mut rx) = events();
+    let client = connect(&start(&config.to_string()).await, handler).await;
+    call(&client, "counter__increment").await.unwrap();
+    let pid = std::fs::read_to_string(&pid_file).unwrap();
+
+    // 子进程被杀死：客户端收到列表变化的通知，调用失败
+    let status = std::process::Command::new("kill")
+        .args(["-9", pid.trim()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    next(&mut rx.list_changed).await;
+    assert!(call(&client, "counter__increment").await.is_err());
+
+    // 代理重新启动子进程，新的进程从 0 开始计数
+    next(&mut rx.list_changed).await;
+    assert_ne!(std::fs::read_to_string(&pid_file).unwrap(), pid);
+    call(&client, "counter__increment").await.unwrap();
+    assert_eq!(
+        text(&call(&client, "counter__get_value").await.unwrap()),
+        "1"
+    );
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn forward_cancellation() {
+    let (url, mut started, mut cancelled, _requests) = probe_backend().await;
+    let config =
+        format!(r#"{{ "mcpServers": {{ "probe": {{ "type": "sse", "url": "{url}" }} }} }}"#);
+    let client = connect(&start(&config).await, ()).await;
+
+    let request = ClientRequest::CallToolRequest(Request::new(CallToolRequestParam {
+        name: "probe__wait".into(),
+        arguments: None,
+    }));
+    let handle = client
+        .send_cancellable_request(request, PeerRequestOptions::no_options())
+        .await
+        .unwrap();
+    next(&mut started).await;
+    // 客户端取消请求后，代理把取消通知转发给后端
+    handle.cancel(Some("不等了".to_string())).await.unwrap();
+    next(&mut cancelled).await;
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn forward_progress() {
+    let (url, _started, _cancelled, _requests) = probe_backend().await;
+    let config =
+        format!(r#"{{ "mcpServers": {{ "probe": {{ "type": "sse", "url": "{url}" }} }} }}"#);
+    let (handler, mut rx) = events();
+    let client = connect(&start(&config).await, handler).await;
+
+    // 后端收到请求后立即发送的进度通知换回客户端的 progress token
+    let request = ClientRequest::CallToolRequest(Request::new(CallToolRequestParam {
+        name: "probe__progress".into(),
+        arguments: None,
+    }));
+    let handle = client
+        .send_cancellable_request(request, PeerRequestOptions::no_options())
+        .await
+        .unwrap();
+    let progress_token = handle.progress_token.clone();
+    handle.await_response().await.unwrap();
+    let progress = next(&mut rx.progress).await;
+    assert_eq!(progress.progress_token, progress_token);
+    assert_eq!(progress.total, Some(1.0));
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn notifications_to_subscribed_session() {
+    let (url, _started, _cancelled, _requests) = probe_backend().await;
+    let config =
+        format!(r#"{{ "mcpServers": {{ "probe": {{ "type": "sse", "url": "{url}" }} }} }}"#);
+    let hub = start(&config).await;
+    let (first_handler, mut first) = events();
+    let (second_handler, mut second) = events();
+    let first_client = connect(&hub, first_handler).await;
+    let second_client = connect(&hub, second_handler).await;
+
+    first_client
+        .subscribe(SubscribeRequestParam {
+            uri: "probe+mem://a".to_string(),
+        })
+        .await
+        .unwrap();
+    first_client
+        .set_level(SetLevelRequestParam {
+            level: LoggingLevel::Info,
+        })
+        .await
+        .unwrap();
+    second_client
+        .set_level(SetLevelRequestParam {
+            level: LoggingLevel::Error,
+        })
+        .await
+        .unwrap();
+
+    // 资源更新只发给订阅的会话，info 日志只发给级别为 info 的会话
+    call(&second_client, "probe__notify").await.unwrap();
+    let log = next(&mut first.logging).await;
+    assert_eq!(log.logger.as_deref(), Some("probe"));
+    assert_eq!(log.data, json!("hello"));
+    assert_eq!(next(&mut first.updated).await, "probe+mem://a");
+    assert!(nothing(&mut second.logging).await);
+    assert!(nothing(&mut second.updated).await);
+
+    // 取消订阅后不再收到资源更新
+    first_client
+        .unsubscribe(UnsubscribeRequestParam {
+            uri: "probe+mem://a".to_string(),
+        })
+        .await
+        .unwrap();
+    call(&first_client, "probe__notify").await.unwrap();
+    next(&mut first.logging).await;
+    assert!(nothing(&mut first.updated).await);
+
+    first_client.cancel().await.unwrap();
+    second_client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn capabilities_from_backends() {
+    let counter = counter_backend().await;
+    let config =
+        format!(r#"{{ "mcpServers": {{ "counter": {{ "type": "sse", "url": "{counter}" }} }} }}"#);
+    let client = connect(&start(&config).await, ()).await;
+    let capabilities = &client.peer_info().unwrap().capabilities;
+    // Counter 不支持日志和资源订阅，代理也不声明
+    assert!(capabilities.logging.is_none());
+    assert_ne!(
+        capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.subscribe),
+        Some(true)
+    );
+    client.cancel().await.unwrap();
+
+    let (probe, _started, _cancelled, _requests) = probe_backend().await;
+    let config = format!(
+        r#"{{
+            "mcpServers": {{
+                "counter": {{ "type": "sse", "url": "{counter}" }},
+                "probe": {{ "type": "sse", "url": "{probe}" }}
+            }}
+        }}"#
+    );
+    let client = connect(&start(&config).await, ()).await;
+    let capabilities = &client.peer_info().unwrap().capabilities;
+    assert!(capabilities.logging.is_some());
+    assert_eq!(
+        capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.subscribe),
+        Some(true)
+    );
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn session_end_releases_backend_state() {
+    let (url, _started, _cancelled, mut requests) = probe_backend().await;
+    let config =
+        format!(r#"{{ "mcpServers": {{ "probe": {{ "type": "sse", "url": "{url}" }} }} }}"#);
+    let hub = start(&config).await;
+    let first = connect(&hub, ()).await;
+    let second = connect(&hub, ()).await;
+
+    first
+        .subscribe(SubscribeRequestParam {
+            uri: "probe+mem://a".to_string(),
+        })
+        .await
+        .unwrap();
+    first
+        .set_level(SetLevelRequestParam {
+            level: LoggingLevel::Debug,
+        })
+        .await
+        .unwrap();
+    assert_eq!(next(&mut requests).await, "set_level Debug");
+    second
+        .set_level(SetLevelRequestParam {
+            level: LoggingLevel::Error,
+        })
+        .await
+        .unwrap();
+    assert_eq!(next(&mut requests).await, "set_level Debug");
+
+    // 断开后不再收到任何通知，也要取消只有它订阅的资源，后端的日志级别按剩下的会话恢复
+    first.cancel().await.unwrap();
+    assert_eq!(next(&mut requests).await, "unsubscribe mem://a");
+    assert_eq!(next(&mut requests).await, "set_level Error");
+
+    second.cancel().await.unwrap();
+}
@@ -0,0 +1,246 @@
+use std::net::SocketAddr;
+
+use rmcp::{
+    RoleClient, ServiceExt,
+    model::{CallToolRequestParam, CallToolResult},
+    service::RunningService,
+    transport::StreamableHttpClientTransport,
+};
+use serde_json::{Value, json};
+use servers::email::EmailState;
+use servers::http::{HttpConfig, router};
+use servers::smtp::SmtpConfig;
+
+/// 在随机端口上启动服务器，联系人保存在临时目录中，附件目录为临时目录下的 attachments
+async fn start(dir: &tempfile::TempDir) -> SocketAddr {
+    let attachment_dir = dir.path().join("attachments");
+    std::fs::create_dir_all(&attachment_dir).unwrap();
+    let state = EmailState::new(
+        SmtpConfig::local(1025),
+        "mcp@localhost",
+        dir.path().join("email_store.json"),
+        Some(attachment_dir),
+    )
+    .unwrap();
+    let config = HttpConfig {
+        allowed_origins: vec!["https://allowed.example".to_string()],
+        ..Default::default()
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router(state, config)).await.unwrap();
+    });
+    addr
+}
+
+async fn connect(addr: SocketAddr) -> RunningService<RoleClient, ()> {
+    let transport = StreamableHttpClientTransport::from_uri(format!("http://{addr}/mcp"));
+    ().serve(transport).await.unwrap()
+}
+
+async fn call(
+    client: &RunningService<RoleClient, ()>,
+    name: &str,
+    arguments: Value,
+) -> CallToolResult {
+    client
+        .call_tool(CallToolRequestParam {
+            name: name.to_string().into(),
+            arguments: arguments.as_object().cloned(),
+        })
+        .await
+        .unwrap()
+}
+
+fn text(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|content| content.as_text().map(|text| text.text.clone()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn initialize_body() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-03-26",
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "0.1.0" }
+        }
+    })
+}
+
+#[tokio::test]
+async fn tools_on_one_endpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let addr = start(&dir).await;
+    let client = connect(addr).await;
+
+    let tools = client.list_tools(None).await.unwrap();
+    let names: Vec<_> = tools
+        .tools
+        .iter()
+        .map(|tool| tool.name.to_string())
+        .collect();
+    for name in [
+        "increment",
+        "get_value",
+        "send_email",
+        "contact_list",
+        "list_sent",
+    ] {
+        assert!(
+            names.contains(&name.to_string()),
+            "缺少工具 {name}: {names:?}"
+        );
+    }
+
+    call(
+        &client,
+        "add_contact",
+        json!({ "name": "张三", "email": "zhangsan@example.com", "groups": ["同事"] }),
+    )
+    .await;
+    let contacts = call(&client, "contact_list", json!({ "group": "同事" })).await;
+    assert!(text(&contacts).contains("zhangsan@example.com"));
+    let contacts = call(&client, "contact_list", json!({ "group": "家人" })).await;
+    assert_eq!(text(&contacts), "没有符合条件的联系人");
+
+    // dry_run 只返回 MIME 邮件，收件人按分组解析
+    let mime = call(
+        &client,
+        "send_email",
+        json!({ "to": "同事", "subject": "周报", "body": "本周进展", "dry_run": true }),
+    )
+    .await;
+    assert!(text(&mime).contains("To: zhangsan@example.com"));
+    // 有收件人解析不到时整封邮件都不生成
+    let partial = client
+        .call_tool(CallToolRequestParam {
+            name: "send_email".into(),
+            arguments: json!({ "to": "张三, typo-group", "subject": "周报", "body": "本周进展", "dry_run": true })
+                .as_object()
+                .cloned(),
+        })
+        .await;
+    assert!(format!("{partial:?}").contains("找不到收件人: typo-group"));
+    let sent = call(&client, "list_sent", json!({})).await;
+    assert_eq!(text(&sent), "没有已发送的邮件");
+
+    // 参数校验失败
+    let invalid = client
+        .call_tool(CallToolRequestParam {
+            name: "contact_list".into(),
+            arguments: json!({ "limit": 0 }).as_object().cloned(),
+        })
+        .await;
+    assert!(invalid.is_err());
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn attachments_in_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+    let addr = start(&dir).await;
+    std::fs::write(dir.path().join("attachments/report.csv"), "id,name\n").unwrap();
+    let client = connect(addr).await;
+    let send = |attachment: &str| {
+        client.call_tool(CallToolRequestParam {
+            name: "send_email".into(),
+            arguments: json!({
+                "to": "lisi@example.com",
+                "subject": "附件",
+                "body": "见附件",
+                "attachments": [attachment],
+                "dry_run": true
+            })
+            .as_object()
+            .cloned(),
+        })
+    };
+
+    let mime = send("report.csv").await.unwrap();
+    assert!(text(&mime).contains("filename=\"report.csv\""));
+    // 附件目录以外的文件被拒绝
+    assert!(send("/etc/passwd").await.is_err());
+    assert!(send("../secret.txt").await.is_err());
+    assert!(send("missing.txt").await.is_err());
+
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn session_state() {
+    let dir = tempfile::tempdir().unwrap();
+    let addr = start(&dir).await;
+    let first = connect(addr).await;
+    let second = connect(addr).await;
+
+    call(&first, "increment", json!({})).await;
+    call(&first, "increment", json!({})).await;
+    call(&second, "increment", json!({})).await;
+    assert_eq!(text(&call(&first, "get_value", json!({})).await), "2");
+    assert_eq!(text(&call(&second, "get_value", json!({})).await), "1");
+
+    first.cancel().await.unwrap();
+    second.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn session_header_and_origin() {
+    let dir = tempfile::tempdir().unwrap();
+    let addr = start(&dir).await;
+    let url = format!("http://{addr}/mcp");
+    let http = reqwest::Client::new();
+    let post = |origin: Option<&str>| {
+        let mut request = http
+            .post(&url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(&initialize_body());
+        if let Some(origin) = origin {
+            request = request.header("Origin", origin);
+        }
+        request.send()
+    };
+
+    // initialize 返回会话 ID
+    let response = post(None).await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.headers().contains_key("mcp-session-id"));
+
+    // 没有会话 ID 的普通请求被拒绝
+    let response = http
+        .post(&url)
+        .header("Accept", "application/json, text/event-stream")
+        .json(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(!response.status().is_success());
+
+    assert_eq!(
+        post(Some("https://evil.example")).await.unwrap().status(),
+        403
+    );
+    assert!(
+        post(Some("https://allowed.example"))
+            .await
+            .unwrap()
+            .status()
+            .is_success()
+    );
+    assert!(
+        post(Some("http://localhost:5173"))
+            .await
+            .unwrap()
+            .status()
+            .is_success()
+    );
+}
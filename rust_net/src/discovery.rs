@@ -0,0 +1,682 @@
+/**
+ * 基于 UDP 组播/广播的局域网服务发现
+ *
+ * 局域网内的机器不需要任何配置就可以互相找到对方：
+ *  1、每个服务周期性地在所有合适的网卡上发送公告（announce），内容包括服务名称、版本、端口和实例ID。
+ *  2、每个节点都监听同一个组播组，把收到的公告放进本地注册表，公告带有 TTL，超过 TTL 没有刷新的节点会被移除。
+ *  3、discover 会先发送一次查询（query），收到查询的节点立即回复公告，不需要等到下一个公告周期。
+ *  4、节点正常退出时会发送下线通知（bye），其他节点立即把它从注册表中移除。
+ *
+ * 报文是一行以 '|' 分隔的文本：
+ *      SD1|announce|<name>|<version>|<port>|<instance>|<ttl_ms>
+ *      SD1|query|<name>
+ *      SD1|bye|<name>|<instance>
+ * 所以各个字段不能为空，也不能包含 '|' 和换行，ServiceInfo::new 和 Message::encode 会检查。
+ *
+ * 组播需要 SO_REUSEADDR（同一台机器上的多个节点绑定同一个端口）和 IP_MULTICAST_IF（指定从哪个网卡发送），
+ * 标准库和 tokio 都不支持在 bind 之前设置这些选项，所以这里使用 socket2 创建 socket。
+ */
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{net::UdpSocket, sync::Notify, task::JoinHandle};
+
+const PROTOCOL: &str = "SD1";
+/// 收到的公告中 TTL 的上限，报文来自局域网内的任何人，过大的 TTL 会让 Instant 溢出
+const MAX_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// 发送到组播组，只有加入了该组的节点才能收到
+    Multicast,
+    /// 发送到每个网卡的子网广播地址，适用于不支持组播的网络
+    Broadcast,
+}
+
+/// 参与服务发现的网卡
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveryInterface {
+    pub addr: Ipv4Addr,
+    pub broadcast: Ipv4Addr,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    pub transport: Transport,
+    pub group: Ipv4Addr,
+    pub port: u16,
+    /// 为空时通过 netdev 自动选择合适的网卡
+    pub interfaces: Vec<DiscoveryInterface>,
+    /// 公告的发送周期
+    pub interval: Duration,
+    /// 公告的有效期，一般是发送周期的数倍，允许丢失几个公告
+    pub ttl: Duration,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            transport: Transport::Multicast,
+            group: Ipv4Addr::new(239, 255, 42, 99),
+            port: 42999,
+            interfaces: Vec::new(),
+            interval: Duration::from_secs(2),
+            ttl: Duration::from_secs(6),
+        }
+    }
+}
+
+impl DiscoveryConfig {
+    /// 只在本地回环网卡上收发，用于测试或者同一台机器上的进程之间互相发现
+    pub fn loopback(port: u16) -> Self {
+        Self {
+            port,
+            interfaces: vec![DiscoveryInterface {
+                addr: Ipv4Addr::LOCALHOST,
+                broadcast: Ipv4Addr::new(127, 255, 255, 255),
+            }],
+            ..Default::default()
+        }
+    }
+}
+
+/// 获取适合做服务发现的网卡：已启用、支持组播、有 IPv4 地址的非隧道网卡
+pub fn suitable_interfaces() -> Vec<DiscoveryInterface> {
+    netdev::get_interfaces()
+        .iter()
+        .filter(|item| item.is_up() && item.is_multicast() && !item.is_loopback() && !item.is_tun())
+        .flat_map(|item| item.ipv4.iter())
+        .map(|net| DiscoveryInterface {
+            addr: net.addr(),
+            broadcast: net.broadcast(),
+        })
+        .collect()
+}
+
+/// 对外公告的服务信息，instance 用来区分同名服务的多个实例
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceInfo {
+    pub name: String,
+    pub version: String,
+    pub port: u16,
+    pub instance: String,
+}
+
+impl ServiceInfo {
+    /// name 和 version 会写入报文，不能为空，也不能包含 '|' 和换行
+    pub fn new(name: &str, version: &str, port: u16) -> Result<Self> {
+        let service = Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            port,
+            instance: new_instance_id(),
+        };
+        service.validate()?;
+        Ok(service)
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_field("name", &self.name)?;
+        check_field("version", &self.version)?;
+        check_field("instance", &self.instance)
+    }
+}
+
+/// 报文字段以 '|' 分隔、以换行结束，字段中出现这些字符会破坏报文格式
+fn check_field(field: &str, value: &str) -> Result<()> {
+    if value.is_empty() || value.contains(['|', '\r', '\n']) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{field} 不能为空，也不能包含 '|' 和换行: {value:?}"),
+        ));
+    }
+    Ok(())
+}
+
+fn new_instance_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{:x}-{:x}", std::process::id(), nanos)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Message {
+    Announce { service: ServiceInfo, ttl: Duration },
+    Query { name: String },
+    Bye { name: String, instance: String },
+}
+
+impl Message {
+    fn encode(&self) -> Result<String> {
+        Ok(match self {
+            Message::Announce { service, ttl } => {
+                service.validate()?;
+                format!(
+                    "{PROTOCOL}|announce|{}|{}|{}|{}|{}",
+                    service.name,
+                    service.version,
+                    service.port,
+                    service.instance,
+                    ttl.as_millis()
+                )
+            }
+            Message::Query { name } => {
+                check_field("name", name)?;
+                format!("{PROTOCOL}|query|{name}")
+            }
+            Message::Bye { name, instance } => {
+                check_field("name", name)?;
+                check_field("instance", instance)?;
+                format!("{PROTOCOL}|bye|{name}|{instance}")
+            }
+        })
+    }
+
+    fn decode(text: &str) -> Option<Self> {
+        let parts: Vec<&str> = text.trim().split('|').collect();
+        match parts.as_slice() {
+            [PROTOCOL, "announce", name, version, port, instance, ttl] => Some(Message::Announce {
+                service: ServiceInfo {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    port: port.parse().ok()?,
+                    instance: instance.to_string(),
+                },
+                ttl: Duration::from_millis(ttl.parse().ok()?).min(MAX_TTL),
+            }),
+            [PROTOCOL, "query", name] => Some(Message::Query {
+                name: name.to_string(),
+            }),
+            [PROTOCOL, "bye", name, instance] => Some(Message::Bye {
+                name: name.to_string(),
+                instance: instance.to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 注册表中的一个节点，addr 是公告的来源 IP 加上公告中的服务端口
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub service: ServiceInfo,
+    pub addr: SocketAddr,
+    pub expires_at: Instant,
+}
+
+/// 按 TTL 过期的节点注册表
+#[derive(Debug, Default)]
+pub struct Registry {
+    peers: HashMap<(String, String), Peer>,
+}
+
+impl Registry {
+    /// 加入或刷新节点，ttl 大到过期时间无法表示时忽略这个公告
+    pub fn upsert(&mut self, service: ServiceInfo, addr: SocketAddr, ttl: Duration) {
+        let Some(expires_at) = Instant::now().checked_add(ttl) else {
+            return;
+        };
+        let key = (service.name.clone(), service.instance.clone());
+        self.peers.insert(
+            key,
+            Peer {
+                service,
+                addr,
+                expires_at,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, name: &str, instance: &str) -> Option<Peer> {
+        self.peers.remove(&(name.to_string(), instance.to_string()))
+    }
+
+    /// 移除所有已经过期的节点
+    pub fn expire(&mut self, now: Instant) {
+        self.peers.retain(|_, peer| peer.expires_at > now);
+    }
+
+    pub fn lookup(&mut self, name: &str) -> Vec<Peer> {
+        self.expire(Instant::now());
+        let mut peers: Vec<Peer> = self
+            .peers
+            .values()
+            .filter(|peer| peer.service.name == name)
+            .cloned()
+            .collect();
+        peers.sort_by(|a, b| a.service.instance.cmp(&b.service.instance));
+        peers
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+struct Shared {
+    registry: Mutex<Registry>,
+    changed: Notify,
+}
+
+/// 服务发现节点：既可以只监听（被动发现其他服务），也可以同时公告自己的服务
+pub struct Discovery {
+    config: DiscoveryConfig,
+    service: Option<ServiceInfo>,
+    instance: String,
+    senders: Arc<Vec<(UdpSocket, SocketAddr)>>,
+    shared: Arc<Shared>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Discovery {
+    /// 启动服务发现，service 为 None 时只监听不公告
+    pub async fn start(mut config: DiscoveryConfig, service: Option<ServiceInfo>) -> Result<Self> {
+        if config.interfaces.is_empty() {
+            config.interfaces = suitable_interfaces();
+        }
+        if config.interfaces.is_empty() {
+            return Err(Error::new(ErrorKind::NotFound, "没有可用于服务发现的网卡"));
+        }
+        // 字段是公开的，可能没有经过 ServiceInfo::new 的检查
+        if let Some(service) = &service {
+            service.validate()?;
+        }
+
+        let receiver = UdpSocket::from_std(bind_receiver(&config)?)?;
+        let mut senders = Vec::new();
+        for interface in config.interfaces.iter() {
+            senders.push(bind_sender(&config, interface)?);
+        }
+        let senders = Arc::new(senders);
+
+        let shared = Arc::new(Shared {
+            registry: Mutex::new(Registry::default()),
+            changed: Notify::new(),
+        });
+        let instance = service
+            .as_ref()
+            .map(|service| service.instance.clone())
+            .unwrap_or_else(new_instance_id);
+
+        let mut tasks = vec![tokio::spawn(receive_loop(
+            receiver,
+            senders.clone(),
+            shared.clone(),
+            service.clone(),
+            instance.clone(),
+            config.ttl,
+        ))];
+        if let Some(service) = service.clone() {
+            tasks.push(tokio::spawn(announce_loop(
+                senders.clone(),
+                service,
+                config.interval,
+                config.ttl,
+            )));
+        }
+
+        Ok(Self {
+            config,
+            service,
+            instance,
+            senders,
+            shared,
+            tasks,
+        })
+    }
+
+    /// 查找指定名称的服务，找到至少一个节点或者超时后返回
+    pub async fn discover(&self, name: &str, timeout: Duration) -> Result<Vec<Peer>> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let query = Message::Query {
+            name: name.to_string(),
+        };
+        broadcast(&self.senders, &query).await?;
+
+        loop {
+            // 先注册通知再检查注册表，避免错过检查之后、等待之前到达的公告
+            let changed = self.shared.changed.notified();
+            let peers = self.peers(name);
+            if !peers.is_empty() {
+                return Ok(peers);
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                return Ok(Vec::new());
+            }
+        }
+    }
+
+    /// 当前注册表中指定名称的节点（不包括自己）
+    pub fn peers(&self, name: &str) -> Vec<Peer> {
+        self.shared.registry.lock().unwrap().lookup(name)
+    }
+
+    pub fn config(&self) -> &DiscoveryConfig {
+        &self.config
+    }
+
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// 停止服务发现，公告过服务的节点会发送下线通知
+    pub async fn shutdown(mut self) -> Result<()> {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        if let Some(service) = self.service.take() {
+            let bye = Message::Bye {
+                name: service.name,
+                instance: service.instance,
+            };
+            broadcast(&self.senders, &bye).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+}
+
+/// 使用默认配置在本机所有合适的网卡上查找服务
+pub async fn discover(name: &str, timeout: Duration) -> Result<Vec<Peer>> {
+    let discovery = Discovery::start(DiscoveryConfig::default(), None).await?;
+    discovery.discover(name, timeout).await
+}
+
+fn bind_receiver(config: &DiscoveryConfig) -> Result<std::net::UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, config.port).into())?;
+
+    if config.transport == Transport::Multicast {
+        for interface in config.interfaces.iter() {
+            socket.join_multicast_v4(&config.group, &interface.addr)?;
+        }
+    }
+    Ok(socket.into())
+}
+
+fn bind_sender(
+    config: &DiscoveryConfig,
+    interface: &DiscoveryInterface,
+) -> Result<(UdpSocket, SocketAddr)> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_nonblocking(true)?;
+    let target = match config.transport {
+        Transport::Multicast => {
+            socket.set_multicast_if_v4(&interface.addr)?;
+            socket.set_multicast_loop_v4(true)?;
+            socket.set_multicast_ttl_v4(1)?;
+            SocketAddrV4::new(config.group, config.port)
+        }
+        Transport::Broadcast => {
+            socket.set_broadcast(true)?;
+            SocketAddrV4::new(interface.broadcast, config.port)
+        }
+    };
+    socket.bind(&SocketAddrV4::new(interface.addr, 0).into())?;
+    let socket: std::net::UdpSocket = socket.into();
+    Ok((UdpSocket::from_std(socket)?, SocketAddr::V4(target)))
+}
+
+/// 在所有网卡上发送同一条消息，只要有一个网卡发送成功就认为成功
+async fn broadcast(senders: &[(UdpSocket, SocketAddr)], message: &Message) -> Result<()> {
+    let bytes = message.encode()?;
+    let mut last_error = None;
+    let mut sent = false;
+    for (socket, target) in senders.iter() {
+        match socket.send_to(bytes.as_bytes(), target).await {
+            Ok(_) => sent = true,
+            Err(e) => {
+                tracing::warn!("向{target}发送服务发现消息失败: {e}");
+                last_error = Some(e);
+            }
+        }
+    }
+    match (sent, last_error) {
+        (false, Some(e)) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+async fn announce_loop(
+    senders: Arc<Vec<(UdpSocket, SocketAddr)>>,
+    service: ServiceInfo,
+    interval: Duration,
+    ttl: Duration,
+) {
+    let announce = Message::Announce { service, ttl };
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = broadcast(&senders, &announce).await {
+            tracing::error!("发送服务公告失败: {e}");
+        }
+    }
+}
+
+async fn receive_loop(
+    receiver: UdpSocket,
+    senders: Arc<Vec<(UdpSocket, SocketAddr)>>,
+    shared: Arc<Shared>,
+    service: Option<ServiceInfo>,
+    instance: String,
+    ttl: Duration,
+) {
+    let mut buf = [0; 1024];
+    loop {
+        let (length, src) = match receiver.recv_from(&mut buf).await {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::error!("服务发现接收失败: {e}");
+                return;
+            }
+        };
+        let Some(message) = std::str::from_utf8(&buf[..length])
+            .ok()
+            .and_then(Message::decode)
+        else {
+            continue;
+        };
+
+        match message {
+            Message::Announce { service: peer, ttl } => {
+                if peer.instance == instance {
+                    continue;
+                }
+                let addr = SocketAddr::new(src.ip(), peer.port);
+                shared.registry.lock().unwrap().upsert(peer, addr, ttl);
+                shared.changed.notify_waiters();
+            }
+            Message::Query { name } => {
+                if let Some(service) = service.as_ref().filter(|s| s.name == name) {
+                    let announce = Message::Announce {
+                        service: service.clone(),
+                        ttl,
+                    };
+                    if let Err(e) = broadcast(&senders, &announce).await {
+                        tracing::error!("回复服务查询失败: {e}");
+                    }
+                }
+            }
+            Message::Bye { name, instance } => {
+                if shared
+                    .registry
+                    .lock()
+                    .unwrap()
+                    .remove(&name, &instance)
+                    .is_some()
+                {
+                    shared.changed.notify_waiters();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod discovery_test {
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        time::{Duration, Instant},
+    };
+
+    use super::{Discovery, DiscoveryConfig, MAX_TTL, Message, Registry, ServiceInfo};
+
+    #[test]
+    fn message_round_trip() {
+        let announce = Message::Announce {
+            service: ServiceInfo::new("status", "1.0.1", 8889).unwrap(),
+            ttl: Duration::from_secs(6),
+        };
+        assert_eq!(Message::decode(&announce.encode().unwrap()), Some(announce));
+        assert_eq!(
+            Message::decode("SD1|query|status"),
+            Some(Message::Query {
+                name: "status".to_string()
+            })
+        );
+        assert_eq!(Message::decode("SD2|query|status"), None);
+        assert_eq!(Message::decode("SD1|announce|status|1.0|port|x|1"), None);
+    }
+
+    #[test]
+    fn reject_delimiter_in_fields() {
+        assert!(ServiceInfo::new("a|b", "1", 1).is_err());
+        assert!(ServiceInfo::new("status", "1.0\n", 1).is_err());
+        assert!(ServiceInfo::new("", "1", 1).is_err());
+        let query = Message::Query {
+            name: "x|bye".to_string(),
+        };
+        assert!(query.encode().is_err());
+    }
+
+    #[test]
+    fn registry_expire() {
+        let mut registry = Registry::default();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8080);
+        registry.upsert(
+            ServiceInfo::new("a", "1", 1).unwrap(),
+            addr,
+            Duration::from_secs(60),
+        );
+        registry.upsert(ServiceInfo::new("a", "1", 2).unwrap(), addr, Duration::ZERO);
+        registry.upsert(
+            ServiceInfo::new("b", "1", 3).unwrap(),
+            addr,
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(registry.lookup("a").len(), 1);
+        assert_eq!(registry.len(), 2);
+
+        registry.expire(Instant::now() + Duration::from_secs(61));
+        assert!(registry.is_empty());
+    }
+
+    // 报文中的 TTL 来自网络，过大时限制为 MAX_TTL，直接传入过大的 TTL 也不会 panic
+    #[test]
+    fn huge_ttl() {
+        let text = format!("SD1|announce|status|1.0|8889|x|{}", u64::MAX);
+        let Some(Message::Announce { service, ttl }) = Message::decode(&text) else {
+            panic!("公告解析失败");
+        };
+        assert_eq!(ttl, MAX_TTL);
+
+        let mut registry = Registry::default();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8889);
+        registry.upsert(service.clone(), addr, ttl);
+        assert_eq!(registry.len(), 1);
+        registry.upsert(
+            ServiceInfo::new("status", "1.0", 1).unwrap(),
+            addr,
+            Duration::MAX,
+        );
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn discover_on_loopback() {
+        let mut config = DiscoveryConfig::loopback(43101);
+        config.interval = Duration::from_millis(100);
+        config.ttl = Duration::from_millis(300);
+
+        let service = ServiceInfo::new("lab-status", "1.0.1", 8889).unwrap();
+        let announcer = Discovery::start(config.clone(), Some(service.clone()))
+            .await
+            .unwrap();
+        let observer = Discovery::start(config, None).await.unwrap();
+
+        let peers = observer
+            .discover("lab-status", Duration::from_secs(3))
+            .await
+            .unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].service, service);
+        assert_eq!(peers[0].addr.port(), 8889);
+
+        // 自己的公告不会出现在自己的注册表中
+        assert!(announcer.peers("lab-status").is_empty());
+        assert!(
+            observer
+                .discover("unknown", Duration::from_millis(200))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // 下线通知会立即把节点移除
+        announcer.shutdown().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(observer.peers("lab-status").is_empty());
+    }
+
+    #[tokio::test]
+    async fn expire_without_announce() {
+        let mut config = DiscoveryConfig::loopback(43102);
+        config.interval = Duration::from_secs(60);
+        config.ttl = Duration::from_millis(200);
+
+        let announcer = Discovery::start(
+            config.clone(),
+            Some(ServiceInfo::new("lab-ping", "1", 1).unwrap()),
+        )
+        .await
+        .unwrap();
+        let observer = Discovery::start(config, None).await.unwrap();
+        assert_eq!(
+            observer
+                .discover("lab-ping", Duration::from_secs(3))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // 公告周期远大于 TTL，节点会在 TTL 之后过期
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(observer.peers("lab-ping").is_empty());
+        drop(announcer);
+    }
+}
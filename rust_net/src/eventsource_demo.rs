@@ -4,6 +4,380 @@
  * 提供一个简单的包装器用于[ reqwest ]以提供事件源实现。您可以通过查看 MDN 文档了解更多关于服务器发送事件（SSE）的信息。
  */
 
+/**
+ * 自动重连的 SSE 客户端
+ *
+ * reqwest-eventsource 只负责把事件打印出来，这里自己解析 text/event-stream 协议：
+ *  1、event: 事件名称，默认为 "message"；data: 事件数据，多行 data 用 '\n' 拼接；id: 事件ID；retry: 服务端建议的重连间隔（毫秒）。
+ *  2、以 ':' 开头的行是注释（一般用作心跳），空行表示一个事件结束。
+ *  3、连接断开后按照 retry 间隔重连，连续失败时按指数退避增加等待时间，并通过 Last-Event-ID 请求头告诉服务端从哪里继续。
+ *  4、服务端返回 204 No Content 表示不需要再重连。
+ *
+ * 每个事件名称可以注册一个类型，data 会被 serde_json 反序列化为该类型；连接状态的变化也会作为流中的元素返回。
+ * rust_actix 的 /sse 端点只发送 data 字段，/sse2 端点同时发送 id 和 data 字段，重连时根据 Last-Event-ID 从下一个事件继续，
+ * 两个端点的事件都对应默认的 "message" 事件。
+ */
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use futures_util::{Stream, StreamExt};
+use reqwest::{StatusCode, header};
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc;
+
+/// 一个完整的 SSE 事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+    /// 按照规范，没有 id 字段的事件沿用上一个事件的ID
+    pub id: Option<String>,
+}
+
+/// 增量解析 text/event-stream，数据块可以在任意位置被截断
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+    /// 当前事件中的 id 字段，事件分发时才生效
+    id: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 最近一次分发的事件的ID，重连时作为 Last-Event-ID 发送；还没有收到空行的事件不算
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// 服务端最近一次通过 retry 字段建议的重连间隔
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        // 行结束符可以是 \r\n、\n 或者单独的 \r
+        let mut start = 0;
+        let mut index = 0;
+        while index < self.buffer.len() {
+            let byte = self.buffer[index];
+            if byte != b'\n' && byte != b'\r' {
+                index += 1;
+                continue;
+            }
+            // \r 是最后一个字节时，无法判断后面是否紧跟 \n，等待下一个数据块
+            if byte == b'\r' && index + 1 == self.buffer.len() {
+                break;
+            }
+            let line = String::from_utf8_lossy(&self.buffer[start..index]).to_string();
+            if byte == b'\r' && self.buffer[index + 1] == b'\n' {
+                index += 1;
+            }
+            index += 1;
+            start = index;
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match self.data.as_mut() {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            // 包含 NUL 字符的 id 必须被忽略
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        self.last_event_id.clone_from(&self.id);
+        let event = self.event.take();
+        // 没有 data 字段的事件不会被分发，但 id 和 retry 仍然生效
+        let data = self.data.take()?;
+        Some(SseEvent {
+            event: event
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "message".to_string()),
+            data,
+            id: self.last_event_id.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// 正在建立连接，attempt 表示连续失败后的第几次尝试
+    Connecting {
+        attempt: u32,
+    },
+    Open,
+    /// 连接断开，retry_in 后重连
+    Closed {
+        error: Option<String>,
+        retry_in: Duration,
+    },
+    /// 不再重连：服务端返回 204、超过最大重试次数或者客户端被丢弃
+    Stopped {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SseUpdate<T> {
+    State(ConnectionState),
+    Event(T),
+    /// 没有注册类型的事件
+    Unhandled(SseEvent),
+    DecodeError {
+        event: SseEvent,
+        error: String,
+    },
+}
+
+type Decoder<T> = Arc<dyn Fn(&str) -> Result<T, serde_json::Error> + Send + Sync>;
+
+pub struct SseClient<T> {
+    url: String,
+    client: reqwest::Client,
+    headers: header::HeaderMap,
+    decoders: HashMap<String, Decoder<T>>,
+    retry: Duration,
+    max_backoff: Duration,
+    max_attempts: Option<u32>,
+    last_event_id: Option<String>,
+}
+
+impl<T: Send + 'static> SseClient<T> {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+            headers: header::HeaderMap::new(),
+            decoders: HashMap::new(),
+            retry: Duration::from_secs(3),
+            max_backoff: Duration::from_secs(60),
+            max_attempts: None,
+            last_event_id: None,
+        }
+    }
+
+    /// 为事件名称注册数据类型，map 把反序列化的结果转换为统一的事件类型 T
+    pub fn on<D, F>(mut self, event: &str, map: F) -> Self
+    where
+        D: DeserializeOwned,
+        F: Fn(D) -> T + Send + Sync + 'static,
+    {
+        let decoder: Decoder<T> = Arc::new(move |data| serde_json::from_str(data).map(&map));
+        self.decoders.insert(event.to_string(), decoder);
+        self
+    }
+
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn header(mut self, key: header::HeaderName, value: header::HeaderValue) -> Self {
+        self.headers.insert(key, value);
+        self
+    }
+
+    /// 默认的重连间隔，服务端通过 retry 字段可以修改
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// 连续失败的最大次数，成功建立连接后重新计数
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// 从指定的事件ID之后开始接收
+    pub fn last_event_id(mut self, id: &str) -> Self {
+        self.last_event_id = Some(id.to_string());
+        self
+    }
+
+    /// 在后台任务中连接并返回事件流，丢弃流之后后台任务会自动退出
+    pub fn stream(self) -> impl Stream<Item = SseUpdate<T>> {
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(self.run(tx));
+        futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|item| (item, rx))
+        })
+    }
+
+    async fn run(mut self, tx: mpsc::Sender<SseUpdate<T>>) {
+        let mut failures = 0;
+        loop {
+            if tx
+                .send(SseUpdate::State(ConnectionState::Connecting {
+                    attempt: failures,
+                }))
+                .await
+                .is_err()
+            {
+                return;
+            }
+
+            let error = match self.connect(&tx, &mut failures).await {
+                Ok(Some(reason)) => {
+                    let state = ConnectionState::Stopped { reason };
+                    let _ = tx.send(SseUpdate::State(state)).await;
+                    return;
+                }
+                Ok(None) => None,
+                Err(e) => Some(e),
+            };
+            if tx.is_closed() {
+                return;
+            }
+
+            if error.is_some() {
+                failures += 1;
+            }
+            if let Some(max) = self.max_attempts.filter(|max| failures >= *max) {
+                let state = ConnectionState::Stopped {
+                    reason: format!("连续失败{max}次"),
+                };
+                let _ = tx.send(SseUpdate::State(state)).await;
+                return;
+            }
+
+            let retry_in = self.backoff(failures);
+            let state = ConnectionState::Closed { error, retry_in };
+            if tx.send(SseUpdate::State(state)).await.is_err() {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(retry_in) => {}
+                _ = tx.closed() => return,
+            }
+        }
+    }
+
+    /// 连续失败时等待 retry * 2^(failures-1)
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.retry.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// 建立一次连接并读取到连接结束，返回 Ok(Some(reason)) 表示不再重连
+    async fn connect(
+        &mut self,
+        tx: &mpsc::Sender<SseUpdate<T>>,
+        failures: &mut u32,
+    ) -> Result<Option<String>, String> {
+        let mut request = self
+            .client
+            .get(&self.url)
+            .headers(self.headers.clone())
+            .header(header::ACCEPT, "text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache");
+        if let Some(id) = self.last_event_id.as_ref() {
+            request = request.header("Last-Event-ID", id);
+        }
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if response.status() == StatusCode::NO_CONTENT {
+            return Ok(Some("服务端返回204".to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(format!("服务端返回{}", response.status()));
+        }
+
+        *failures = 0;
+        if tx
+            .send(SseUpdate::State(ConnectionState::Open))
+            .await
+            .is_err()
+        {
+            return Ok(Some("客户端已关闭".to_string()));
+        }
+
+        let mut parser = SseParser::new();
+        let mut body = response.bytes_stream();
+        loop {
+            // 流被丢弃后立即断开连接，不需要等到下一个事件到达
+            let chunk = tokio::select! {
+                chunk = body.next() => chunk,
+                _ = tx.closed() => return Ok(Some("客户端已关闭".to_string())),
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            let chunk = chunk.map_err(|e| e.to_string())?;
+            for event in parser.feed(&chunk) {
+                if tx.send(self.decode(event)).await.is_err() {
+                    return Ok(Some("客户端已关闭".to_string()));
+                }
+            }
+            if let Some(retry) = parser.retry() {
+                self.retry = retry;
+            }
+            if let Some(id) = parser.last_event_id() {
+                self.last_event_id = Some(id.to_string());
+            }
+        }
+        Ok(None)
+    }
+
+    fn decode(&self, event: SseEvent) -> SseUpdate<T> {
+        match self.decoders.get(&event.event) {
+            Some(decoder) => match decoder(&event.data) {
+                Ok(value) => SseUpdate::Event(value),
+                Err(e) => SseUpdate::DecodeError {
+                    event,
+                    error: e.to_string(),
+                },
+            },
+            None => SseUpdate::Unhandled(event),
+        }
+    }
+}
+
 #[cfg(test)]
 mod eventsource_test {
     use std::time::Duration;
@@ -50,3 +424,192 @@ mod eventsource_test {
         }
     }
 }
+
+#[cfg(test)]
+mod sse_client_test {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use serde::Deserialize;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use super::{ConnectionState, SseClient, SseEvent, SseParser, SseUpdate};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Count {
+        count: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Status {
+        online: bool,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum AppEvent {
+        Count(Count),
+        Status(Status),
+    }
+
+    #[test]
+    fn parse_fields() {
+        let mut parser = SseParser::new();
+        let mut events =
+            parser.feed(b": keep-alive\nretry: 1500\nid: 7\nevent: status\ndata: {\"online\"");
+        assert!(events.is_empty());
+        events.extend(
+            parser.feed(b": true}\r\n\r\ndata: line1\rdata:line2\n\ndata: {\"count\": 1}\n"),
+        );
+        events.extend(parser.feed(b"\n"));
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "status".to_string(),
+                    data: "{\"online\": true}".to_string(),
+                    id: Some("7".to_string()),
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "line1\nline2".to_string(),
+                    id: Some("7".to_string()),
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "{\"count\": 1}".to_string(),
+                    id: Some("7".to_string()),
+                },
+            ]
+        );
+        assert_eq!(parser.retry(), Some(Duration::from_millis(1500)));
+        assert_eq!(parser.last_event_id(), Some("7"));
+
+        // 没有 data 的事件不会分发，\r 在数据块末尾时等待下一个数据块
+        assert!(parser.feed(b"id: 8\n\ndata: x\r").is_empty());
+        assert_eq!(parser.last_event_id(), Some("8"));
+        assert_eq!(parser.feed(b"\n\n").len(), 1);
+
+        // 数据块在事件中间截断时，事件分发之前 last_event_id 不变，重连后服务端会重发这个事件
+        assert!(parser.feed(b"id: 9\ndata: y\n").is_empty());
+        assert_eq!(parser.last_event_id(), Some("8"));
+        let events = parser.feed(b"\n");
+        assert_eq!(events[0].id.as_deref(), Some("9"));
+        assert_eq!(parser.last_event_id(), Some("9"));
+    }
+
+    /**
+     * 本地 SSE 服务：每个连接依次使用 responses 中的一个响应，并把请求头中的 Last-Event-ID 发送给测试代码
+     */
+    async fn serve(
+        responses: Vec<&'static str>,
+    ) -> (String, mpsc::UnboundedReceiver<Option<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let length = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..length]);
+                }
+                let request = String::from_utf8(request).unwrap().to_lowercase();
+                let last_id = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("last-event-id: "))
+                    .map(|id| id.to_string());
+                tx.send(last_id).unwrap();
+
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    const FIRST: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+        retry: 20\nid: 1\nevent: count\ndata: {\"count\": 1}\n\n\
+        event: status\ndata: {\"online\": true}\n\n\
+        event: unknown\ndata: ?\n\n";
+    const SECOND: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+        id: 2\nevent: count\ndata: {\"count\": \"two\"}\n\n";
+    const NO_CONTENT: &str = "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n";
+    const UNAVAILABLE: &str =
+        "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+
+    #[tokio::test]
+    async fn reconnect_with_last_event_id() {
+        let (url, mut last_ids) = serve(vec![FIRST, SECOND, NO_CONTENT]).await;
+        let updates: Vec<SseUpdate<AppEvent>> = SseClient::new(&url)
+            .on("count", AppEvent::Count)
+            .on("status", AppEvent::Status)
+            .stream()
+            .collect()
+            .await;
+
+        assert_eq!(
+            updates[0],
+            SseUpdate::State(ConnectionState::Connecting { attempt: 0 })
+        );
+        assert_eq!(updates[1], SseUpdate::State(ConnectionState::Open));
+        assert_eq!(
+            updates[2],
+            SseUpdate::Event(AppEvent::Count(Count { count: 1 }))
+        );
+        assert_eq!(
+            updates[3],
+            SseUpdate::Event(AppEvent::Status(Status { online: true }))
+        );
+        assert!(matches!(&updates[4], SseUpdate::Unhandled(event) if event.event == "unknown"));
+        // 服务端通过 retry 字段把重连间隔改成了 20ms
+        assert_eq!(
+            updates[5],
+            SseUpdate::State(ConnectionState::Closed {
+                error: None,
+                retry_in: Duration::from_millis(20)
+            })
+        );
+        assert!(
+            matches!(&updates[8], SseUpdate::DecodeError { event, .. } if event.id.as_deref() == Some("2"))
+        );
+        assert!(matches!(
+            updates.last(),
+            Some(SseUpdate::State(ConnectionState::Stopped { .. }))
+        ));
+
+        assert_eq!(last_ids.recv().await.unwrap(), None);
+        assert_eq!(last_ids.recv().await.unwrap().as_deref(), Some("1"));
+        assert_eq!(last_ids.recv().await.unwrap().as_deref(), Some("2"));
+    }
+
+    #[tokio::test]
+    async fn backoff_until_max_attempts() {
+        let (url, _last_ids) = serve(vec![UNAVAILABLE, UNAVAILABLE]).await;
+        let states: Vec<ConnectionState> = SseClient::<AppEvent>::new(&url)
+            .retry(Duration::from_millis(10))
+            .max_attempts(2)
+            .stream()
+            .filter_map(|update| async move {
+                match update {
+                    SseUpdate::State(state) => Some(state),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+
+        assert_eq!(states.len(), 4);
+        assert!(
+            matches!(&states[1], ConnectionState::Closed { error: Some(_), retry_in } if *retry_in == Duration::from_millis(10))
+        );
+        assert_eq!(states[2], ConnectionState::Connecting { attempt: 1 });
+        assert!(matches!(states[3], ConnectionState::Stopped { .. }));
+    }
+}
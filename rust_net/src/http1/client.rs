@@ -0,0 +1,233 @@
+/**
+ * 复用连接的 HTTP/1.1 客户端，只支持 http://
+ *
+ * 响应允许复用连接时，连接会按照 host:port 放回空闲连接池，下一个请求优先使用空闲连接。
+ * 空闲连接可能已经被服务端关闭（例如超过了服务端的 keep-alive 超时时间），
+ * 这种情况下幂等请求（GET、HEAD、PUT、DELETE、OPTIONS）会换一个连接自动重试，新建立的连接失败时不再重试。
+ */
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    sync::Mutex,
+    time::Duration,
+};
+
+use http_types::{Body, Method, Request, Response};
+use tokio::{io::BufReader, net::TcpStream};
+
+use super::codec;
+
+type Connection = BufReader<TcpStream>;
+
+pub struct Http1Client {
+    idle: Mutex<HashMap<String, Vec<Connection>>>,
+    timeout: Duration,
+    max_idle_per_host: usize,
+}
+
+impl Default for Http1Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Http1Client {
+    pub fn new() -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            timeout: Duration::from_secs(30),
+            max_idle_per_host: 8,
+        }
+    }
+
+    /// 单个请求（包括建立连接和重试）的超时时间
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 每个 host:port 最多保留的空闲连接数
+    pub fn max_idle_per_host(mut self, max: usize) -> Self {
+        self.max_idle_per_host = max;
+        self
+    }
+
+    /// 当前空闲连接的总数
+    pub fn idle_connections(&self) -> usize {
+        self.idle.lock().unwrap().values().map(Vec::len).sum()
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response> {
+        let url =
+            http_types::Url::parse(url).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        self.send(Request::new(Method::Get, url)).await
+    }
+
+    pub async fn send(&self, request: Request) -> Result<Response> {
+        tokio::time::timeout(self.timeout, self.send_inner(request))
+            .await
+            .map_err(|_| Error::new(ErrorKind::TimedOut, "请求超时"))?
+    }
+
+    async fn send_inner(&self, mut request: Request) -> Result<Response> {
+        let url = request.url();
+        if url.scheme() != "http" {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("不支持的协议: {}", url.scheme()),
+            ));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "URL 中没有主机名"))?;
+        let key = format!("{}:{}", host, url.port_or_known_default().unwrap_or(80));
+        let method = request.method();
+
+        // 重试时需要重新发送报文体，所以先把报文体读出来
+        let body = request
+            .take_body()
+            .into_bytes()
+            .await
+            .map_err(|e| Error::other(e.to_string()))?;
+
+        loop {
+            let pooled = self.checkout(&key);
+            let reused = pooled.is_some();
+            let mut connection = match pooled {
+                Some(connection) => connection,
+                None => BufReader::new(TcpStream::connect(&key).await?),
+            };
+            if !body.is_empty() {
+                request.set_body(Body::from_bytes(body.clone()));
+            }
+
+            match exchange(&mut connection, &mut request, method).await {
+                Ok(response) => {
+                    if codec::keep_alive(response.version(), response.as_ref()) {
+                        self.checkin(key, connection);
+                    }
+                    return Ok(response);
+                }
+                Err(e) if reused && is_idempotent(method) && is_stale(&e) => {
+                    tracing::debug!("空闲连接已失效，重试请求: {e}");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn checkout(&self, key: &str) -> Option<Connection> {
+        self.idle.lock().unwrap().get_mut(key)?.pop()
+    }
+
+    fn checkin(&self, key: String, connection: Connection) {
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.entry(key).or_default();
+        if connections.len() < self.max_idle_per_host {
+            connections.push(connection);
+        }
+    }
+}
+
+async fn exchange(
+    connection: &mut Connection,
+    request: &mut Request,
+    method: Method,
+) -> Result<Response> {
+    codec::write_request(connection, request).await?;
+    codec::read_response(connection, method).await
+}
+
+fn is_idempotent(method: Method) -> bool {
+    matches!(
+        method,
+        Method::Get | Method::Head | Method::Put | Method::Delete | Method::Options
+    )
+}
+
+/// 服务端关闭了空闲连接时可能出现的错误
+fn is_stale(error: &Error) -> bool {
+    matches!(
+        error.kind(),
+        ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+    )
+}
+
+#[cfg(test)]
+mod client_test {
+    use std::time::Duration;
+
+    use http_types::{Method, Request, Response, StatusCode};
+
+    use super::Http1Client;
+    use crate::http1::server::{HttpServer, Router};
+
+    /// 响应中返回客户端的地址，用来判断两个请求是否使用了同一个连接
+    fn router() -> Router {
+        Router::new()
+            .get("/peer", |request: Request| async move {
+                let mut response = Response::new(StatusCode::Ok);
+                response.set_body(request.peer_addr().unwrap().to_string());
+                response
+            })
+            .post("/upper", |mut request: Request| async move {
+                let mut response = Response::new(StatusCode::Ok);
+                response.set_body(request.body_string().await.unwrap().to_uppercase());
+                response
+            })
+    }
+
+    #[tokio::test]
+    async fn reuse_connection() {
+        let server = HttpServer::bind("127.0.0.1:0", router()).await.unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let task = server.spawn();
+
+        let client = Http1Client::new();
+        let mut first = client.get(&format!("{url}/peer")).await.unwrap();
+        let mut second = client.get(&format!("{url}/peer")).await.unwrap();
+        assert_eq!(
+            first.body_string().await.unwrap(),
+            second.body_string().await.unwrap()
+        );
+        assert_eq!(client.idle_connections(), 1);
+
+        let mut request = Request::new(Method::Post, format!("{url}/upper").as_str());
+        request.set_body("hello");
+        let mut response = client.send(request).await.unwrap();
+        assert_eq!(response.body_string().await.unwrap(), "HELLO");
+
+        let response = client.get(&format!("{url}/missing")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NotFound);
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn retry_on_stale_connection() {
+        let server = HttpServer::bind("127.0.0.1:0", router())
+            .await
+            .unwrap()
+            .keep_alive_timeout(Duration::from_millis(50));
+        let url = format!("http://{}/peer", server.local_addr().unwrap());
+        let task = server.spawn();
+
+        let client = Http1Client::new();
+        let mut first = client.get(&url).await.unwrap();
+        assert_eq!(client.idle_connections(), 1);
+        // 等待服务端关闭空闲连接，客户端使用失效的连接后自动在新连接上重试
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let mut second = client.get(&url).await.unwrap();
+        assert_eq!(second.status(), StatusCode::Ok);
+        assert_ne!(
+            first.body_string().await.unwrap(),
+            second.body_string().await.unwrap()
+        );
+
+        task.abort();
+    }
+}
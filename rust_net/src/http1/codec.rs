@@ -0,0 +1,657 @@
+/**
+ * HTTP/1.1 报文的解析和序列化（RFC 9112）
+ *
+ * 报文体的长度由以下规则决定：
+ *  1、HEAD 请求的响应，以及 1xx、204、304 响应没有报文体。
+ *  2、Transfer-Encoding 的最后一个编码是 chunked 时，按照分块读取：每块以十六进制长度开头，长度为 0 的块表示结束，之后可以跟随 trailer。
+ *  3、否则按照 Content-Length 读取。同时出现 Transfer-Encoding 和 Content-Length 的报文会被拒绝，避免请求走私。
+ *  4、请求两者都没有时报文体为空；响应两者都没有时一直读取到连接关闭，并补上 Connection: close。
+ *
+ * 请求的 Host 只能是 主机[:端口]，HTTP/1.1 请求必须有且只有一个 Host，否则按无效请求处理（服务器返回 400），
+ * 避免 Host: x/admin、Host: x? 这样的值改变路由看到的路径。
+ *
+ * 读取完成后报文体已经完整地在内存中，所以会删除 Transfer-Encoding 头；写出时根据报文体重新计算 Content-Length，
+ * 只有报文中带有 Transfer-Encoding: chunked 头或者报文体长度未知时才使用分块编码。
+ */
+use std::{
+    io::{Error, ErrorKind, Result},
+    str::FromStr,
+};
+
+use http_types::{
+    Body, Method, Request, Response, StatusCode, Url, Version,
+    headers::{
+        CONNECTION, CONTENT_LENGTH, HOST, HeaderName, HeaderValue, Headers, TRANSFER_ENCODING,
+    },
+};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// 起始行和所有报文头的总长度上限
+pub const MAX_HEAD_SIZE: usize = 64 * 1024;
+/// 报文体的长度上限
+pub const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+/// 分块编码时每块的长度
+const CHUNK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    Empty,
+    Length(usize),
+    Chunked,
+    /// 读取到连接关闭，只用于响应
+    Close,
+}
+
+struct Head {
+    start: String,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// 读取一个请求，连接在请求开始之前被关闭时返回 None
+pub async fn read_request<R>(reader: &mut R) -> Result<Option<Request>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let Some(head) = read_head(reader).await? else {
+        return Ok(None);
+    };
+    let mut parts = head.start.splitn(3, ' ');
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid(format!("无效的请求行: {}", head.start)));
+    };
+    let method =
+        Method::from_str(method).map_err(|_| invalid(format!("不支持的请求方法: {method}")))?;
+    let version = parse_version(version)?;
+    let host = request_host(&head.headers, version)?;
+
+    let url = if target.starts_with("http://") || target.starts_with("https://") {
+        Url::parse(target)
+    } else if target.starts_with('/') {
+        Url::parse(&format!("http://{}{target}", host.unwrap_or("localhost")))
+    } else {
+        return Err(invalid(format!("不支持的请求目标: {target}")));
+    };
+    let url = url.map_err(|e| invalid(format!("无效的请求目标{target}: {e}")))?;
+
+    let mut request = Request::new(method, url);
+    request.set_version(Some(version));
+    for (name, value) in head.headers {
+        request.append_header(name, value);
+    }
+
+    let framing = framing(request.as_ref(), false)?;
+    let body = read_body(reader, framing).await?;
+    request.remove_header(TRANSFER_ENCODING);
+    if !body.is_empty() {
+        request.set_body(Body::from_bytes(body));
+    }
+    Ok(Some(request))
+}
+
+/// 读取一个响应，method 是对应请求的方法，HEAD 请求的响应没有报文体
+pub async fn read_response<R>(reader: &mut R, method: Method) -> Result<Response>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let head = read_head(reader)
+            .await?
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "连接在响应之前被关闭"))?;
+        let mut parts = head.start.splitn(3, ' ');
+        let (Some(version), Some(code)) = (parts.next(), parts.next()) else {
+            return Err(invalid(format!("无效的状态行: {}", head.start)));
+        };
+        let version = parse_version(version)?;
+        let code: u16 = code
+            .parse()
+            .map_err(|_| invalid(format!("无效的状态码: {code}")))?;
+        // 100 Continue 之类的临时响应之后还有最终响应
+        if (100..200).contains(&code) && code != 101 {
+            continue;
+        }
+        let status =
+            StatusCode::try_from(code).map_err(|_| invalid(format!("未知的状态码: {code}")))?;
+
+        let mut response = Response::new(status);
+        response.set_version(Some(version));
+        for (name, value) in head.headers {
+            response.append_header(name, value);
+        }
+
+        let framing = if method == Method::Head {
+            Framing::Empty
+        } else {
+            framing(response.as_ref(), true)?
+        };
+        let body = read_body(reader, framing).await?;
+        response.remove_header(TRANSFER_ENCODING);
+        if framing == Framing::Close {
+            response.insert_header(CONNECTION, "close");
+        }
+        if !body.is_empty() {
+            response.set_body(Body::from_bytes(body));
+        }
+        return Ok(response);
+    }
+}
+
+/// 写出请求，请求的报文体会被取走
+pub async fn write_request<W>(writer: &mut W, request: &mut Request) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let url = request.url();
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let mut head = format!(
+        "{} {} {}\r\n",
+        request.method(),
+        target,
+        version_text(request.version())
+    );
+    if request.header(HOST).is_none() {
+        let host = url.host_str().unwrap_or("localhost");
+        match url.port() {
+            Some(port) => head.push_str(&format!("host: {host}:{port}\r\n")),
+            None => head.push_str(&format!("host: {host}\r\n")),
+        }
+    }
+
+    let chunked = is_chunked(request.as_ref()) || request.len().is_none();
+    let body = into_bytes(request.take_body()).await?;
+    // 没有报文体的请求不需要 Content-Length
+    let length = (!chunked && !body.is_empty()).then_some(body.len());
+    write_message(writer, head, request.as_ref(), &body, chunked, length).await
+}
+
+/// 写出响应，head_only 为 true 时（HEAD 请求）只写出报文头
+pub async fn write_response<W>(
+    writer: &mut W,
+    response: &mut Response,
+    head_only: bool,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let status = response.status();
+    let head = format!(
+        "{} {} {}\r\n",
+        version_text(response.version()),
+        status as u16,
+        status.canonical_reason()
+    );
+    let no_body = status.is_informational()
+        || status == StatusCode::NoContent
+        || status == StatusCode::NotModified;
+
+    let chunked = !no_body && (is_chunked(response.as_ref()) || response.len().is_none());
+    let body = into_bytes(response.take_body()).await?;
+    let length = (!no_body && !chunked).then_some(body.len());
+    let body = if head_only || no_body { &[][..] } else { &body };
+    write_message(
+        writer,
+        head,
+        response.as_ref(),
+        body,
+        chunked && !head_only,
+        length,
+    )
+    .await
+}
+
+/// 按照版本和 Connection 头判断连接是否可以复用：HTTP/1.1 默认复用，HTTP/1.0 需要 Connection: keep-alive
+pub fn keep_alive(version: Option<Version>, headers: &Headers) -> bool {
+    let has = |token: &str| {
+        headers.get(CONNECTION).is_some_and(|values| {
+            values
+                .iter()
+                .flat_map(|value| value.as_str().split(','))
+                .any(|item| item.trim().eq_ignore_ascii_case(token))
+        })
+    };
+    if has("close") {
+        false
+    } else {
+        has("keep-alive") || version != Some(Version::Http1_0)
+    }
+}
+
+async fn write_message<W>(
+    writer: &mut W,
+    mut head: String,
+    headers: &Headers,
+    body: &[u8],
+    chunked: bool,
+    length: Option<usize>,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    for (name, values) in headers.iter() {
+        if *name == CONTENT_LENGTH || *name == TRANSFER_ENCODING {
+            continue;
+        }
+        for value in values.iter() {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+    }
+    if chunked {
+        head.push_str("transfer-encoding: chunked\r\n");
+    } else if let Some(length) = length {
+        head.push_str(&format!("content-length: {length}\r\n"));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+
+    if chunked {
+        for chunk in body.chunks(CHUNK_SIZE) {
+            writer
+                .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                .await?;
+            writer.write_all(chunk).await?;
+            writer.write_all(b"\r\n").await?;
+        }
+        writer.write_all(b"0\r\n\r\n").await?;
+    } else {
+        writer.write_all(body).await?;
+    }
+    writer.flush().await
+}
+
+async fn read_head<R>(reader: &mut R) -> Result<Option<Head>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut size = 0;
+    // 报文之前多余的空行需要忽略
+    let start = loop {
+        match read_line(reader, &mut size, MAX_HEAD_SIZE).await? {
+            None => return Ok(None),
+            Some(line) if line.is_empty() => continue,
+            Some(line) => break line,
+        }
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader, &mut size, MAX_HEAD_SIZE)
+            .await?
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "报文头不完整"))?;
+        if line.is_empty() {
+            break;
+        }
+        // 已经废弃的多行报文头（以空白开头的续行）和名称后面的空白都不允许出现
+        let (name, value) = line
+            .split_once(':')
+            .filter(|(name, _)| !name.is_empty() && !name.ends_with([' ', '\t']))
+            .filter(|_| !line.starts_with([' ', '\t']))
+            .ok_or_else(|| invalid(format!("无效的报文头: {line}")))?;
+        let name = HeaderName::from_str(name).map_err(|e| invalid(e.to_string()))?;
+        let value = HeaderValue::from_str(value.trim()).map_err(|e| invalid(e.to_string()))?;
+        headers.push((name, value));
+    }
+    Ok(Some(Head { start, headers }))
+}
+
+/// 读取一行并去掉行尾的 \r\n 或 \n，size 累计已经读取的长度
+async fn read_line<R>(reader: &mut R, size: &mut usize, limit: usize) -> Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = Vec::new();
+    let remaining = limit.saturating_sub(*size) as u64 + 1;
+    let length = (&mut *reader)
+        .take(remaining)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if length == 0 {
+        return Ok(None);
+    }
+    *size += length;
+    if line.last() != Some(&b'\n') {
+        return Err(if *size > limit {
+            invalid("报文头过长".to_string())
+        } else {
+            Error::new(ErrorKind::UnexpectedEof, "行不完整")
+        });
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| invalid("报文头不是有效的 UTF-8".to_string()))
+}
+
+fn framing(headers: &Headers, response: bool) -> Result<Framing> {
+    let length = headers.get(CONTENT_LENGTH);
+    if headers.get(TRANSFER_ENCODING).is_some() {
+        if length.is_some() {
+            return Err(invalid(
+                "同时出现 Transfer-Encoding 和 Content-Length".to_string(),
+            ));
+        }
+        return if is_chunked(headers) {
+            Ok(Framing::Chunked)
+        } else if response {
+            Ok(Framing::Close)
+        } else {
+            Err(invalid("不支持的 Transfer-Encoding".to_string()))
+        };
+    }
+    match length {
+        Some(values) => {
+            let length: usize = values
+                .last()
+                .as_str()
+                .trim()
+                .parse()
+                .map_err(|_| invalid(format!("无效的 Content-Length: {values}")))?;
+            if values
+                .iter()
+                .any(|value| value.as_str().trim() != length.to_string())
+            {
+                return Err(invalid("多个不同的 Content-Length".to_string()));
+            }
+            Ok(Framing::Length(length))
+        }
+        None if response => Ok(Framing::Close),
+        None => Ok(Framing::Empty),
+    }
+}
+
+/// Transfer-Encoding 的最后一个编码是否为 chunked
+fn is_chunked(headers: &Headers) -> bool {
+    headers.get(TRANSFER_ENCODING).is_some_and(|values| {
+        values
+            .iter()
+            .flat_map(|value| value.as_str().split(','))
+            .last()
+            .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+    })
+}
+
+async fn read_body<R>(reader: &mut R, framing: Framing) -> Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    match framing {
+        Framing::Empty => Ok(Vec::new()),
+        Framing::Length(length) => {
+            if length > MAX_BODY_SIZE {
+                return Err(invalid(format!("报文体过大: {length}")));
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            Ok(body)
+        }
+        Framing::Chunked => read_chunked(reader).await,
+        Framing::Close => {
+            let mut body = Vec::new();
+            (&mut *reader)
+                .take(MAX_BODY_SIZE as u64 + 1)
+                .read_to_end(&mut body)
+                .await?;
+            if body.len() > MAX_BODY_SIZE {
+                return Err(invalid("报文体过大".to_string()));
+            }
+            Ok(body)
+        }
+    }
+}
+
+async fn read_chunked<R>(reader: &mut R) -> Result<Vec<u8>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut body = Vec::new();
+    // 块长度行和 trailer 共用报文头的长度限制
+    let mut size = 0;
+    loop {
+        let line = read_line(reader, &mut size, MAX_HEAD_SIZE)
+            .await?
+            .ok_or_else(|| Error::new(ErrorKind::UnexpectedEof, "分块报文体不完整"))?;
+        // 分号后面是块扩展，直接忽略
+        let length = line.split(';').next().unwrap_or_default().trim();
+        let length = usize::from_str_radix(length, 16)
+            .map_err(|_| invalid(format!("无效的块长度: {line}")))?;
+        if length == 0 {
+            break;
+        }
+        // 块长度由对端决定，先减后比较，避免相加溢出
+        if length > MAX_BODY_SIZE - body.len() {
+            return Err(invalid("报文体过大".to_string()));
+        }
+        let start = body.len();
+        body.resize(start + length, 0);
+        reader.read_exact(&mut body[start..]).await?;
+        let end = read_line(reader, &mut size, MAX_HEAD_SIZE).await?;
+        if end.as_deref() != Some("") {
+            return Err(invalid("块数据之后缺少换行".to_string()));
+        }
+    }
+    // trailer 以空行结束，这里不保留 trailer 中的字段
+    loop {
+        match read_line(reader, &mut size, MAX_HEAD_SIZE).await? {
+            Some(line) if !line.is_empty() => continue,
+            Some(_) => break,
+            None => return Err(Error::new(ErrorKind::UnexpectedEof, "trailer 不完整")),
+        }
+    }
+    Ok(body)
+}
+
+/**
+ * 请求的 Host 头：HTTP/1.1 请求必须有且只有一个，HTTP/1.0 可以没有；
+ * 值只能包含主机名、IP（包括 [IPv6]）和端口中出现的字符，不能有 / ? # @ \ 和空白
+ */
+fn request_host(headers: &[(HeaderName, HeaderValue)], version: Version) -> Result<Option<&str>> {
+    let mut hosts = headers
+        .iter()
+        .filter(|(name, _)| *name == HOST)
+        .map(|(_, value)| value.as_str());
+    let host = hosts.next();
+    if hosts.next().is_some() {
+        return Err(invalid("重复的 Host 头".to_string()));
+    }
+    let Some(host) = host else {
+        if version == Version::Http1_1 {
+            return Err(invalid("HTTP/1.1 请求缺少 Host 头".to_string()));
+        }
+        return Ok(None);
+    };
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._~:[]%".contains(c));
+    if !valid {
+        return Err(invalid(format!("无效的 Host 头: {host}")));
+    }
+    Ok(Some(host))
+}
+
+fn parse_version(text: &str) -> Result<Version> {
+    match text {
+        "HTTP/1.1" => Ok(Version::Http1_1),
+        "HTTP/1.0" => Ok(Version::Http1_0),
+        _ => Err(invalid(format!("不支持的协议版本: {text}"))),
+    }
+}
+
+fn version_text(version: Option<Version>) -> &'static str {
+    match version {
+        Some(Version::Http1_0) => "HTTP/1.0",
+        _ => "HTTP/1.1",
+    }
+}
+
+async fn into_bytes(body: Body) -> Result<Vec<u8>> {
+    body.into_bytes()
+        .await
+        .map_err(|e| Error::other(e.to_string()))
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod codec_test {
+    use std::io::ErrorKind;
+
+    use http_types::{Method, Request, Response, StatusCode, Version};
+    use tokio::io::{AsyncWriteExt, BufReader};
+
+    use super::{
+        MAX_HEAD_SIZE, keep_alive, read_request, read_response, write_request, write_response,
+    };
+
+    #[tokio::test]
+    async fn chunked_request_split_across_writes() {
+        let (client, server) = tokio::io::duplex(16);
+        let writer = tokio::spawn(async move {
+            let mut client = client;
+            let raw = "POST /echo?x=1 HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n\
+                       5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nX-Trailer: yes\r\n\r\n";
+            // 每次只写几个字节，检查解析器能够处理任意位置的截断
+            for piece in raw.as_bytes().chunks(3) {
+                client.write_all(piece).await.unwrap();
+            }
+            client
+        });
+
+        let mut reader = BufReader::new(server);
+        let mut request = read_request(&mut reader).await.unwrap().unwrap();
+        assert_eq!(request.method(), Method::Post);
+        assert_eq!(request.url().as_str(), "http://example.com/echo?x=1");
+        assert!(request.header("transfer-encoding").is_none());
+        assert_eq!(request.body_string().await.unwrap(), "hello, world");
+
+        // 对端关闭连接后返回 None
+        drop(writer.await.unwrap());
+        assert!(read_request(&mut reader).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn request_round_trip() {
+        let mut request = Request::new(Method::Put, "http://127.0.0.1:8080/items/1?force=true");
+        request.insert_header("X-Token", "abc");
+        request.set_body("payload");
+
+        let mut buffer = Vec::new();
+        write_request(&mut buffer, &mut request).await.unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with("PUT /items/1?force=true HTTP/1.1\r\n"));
+        assert!(text.contains("host: 127.0.0.1:8080\r\n"));
+        assert!(text.contains("content-length: 7\r\n"));
+
+        let mut parsed = read_request(&mut &buffer[..]).await.unwrap().unwrap();
+        assert_eq!(parsed.method(), Method::Put);
+        assert_eq!(parsed.url().path(), "/items/1");
+        assert_eq!(parsed.header("x-token").unwrap().as_str(), "abc");
+        assert_eq!(parsed.body_string().await.unwrap(), "payload");
+    }
+
+    #[tokio::test]
+    async fn chunked_response_round_trip() {
+        let mut response = Response::new(StatusCode::Ok);
+        response.insert_header("Transfer-Encoding", "chunked");
+        response.set_body("x".repeat(20_000));
+
+        let mut buffer = Vec::new();
+        write_response(&mut buffer, &mut response, false)
+            .await
+            .unwrap();
+        let text = String::from_utf8_lossy(&buffer);
+        assert!(text.contains("transfer-encoding: chunked\r\n"));
+        assert!(!text.contains("content-length"));
+        assert!(text.ends_with("0\r\n\r\n"));
+
+        let mut parsed = read_response(&mut &buffer[..], Method::Get).await.unwrap();
+        assert_eq!(parsed.body_string().await.unwrap().len(), 20_000);
+        assert!(keep_alive(parsed.version(), parsed.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn response_until_close() {
+        let raw = b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nall the rest";
+        let mut response = read_response(&mut &raw[..], Method::Get).await.unwrap();
+        assert_eq!(response.version(), Some(Version::Http1_0));
+        assert!(!keep_alive(response.version(), response.as_ref()));
+        assert_eq!(response.body_string().await.unwrap(), "all the rest");
+
+        // HEAD 请求的响应即使带有 Content-Length 也没有报文体
+        let raw = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+        let response = read_response(&mut &raw[..], Method::Head).await.unwrap();
+        assert_eq!(response.status(), StatusCode::Ok);
+        assert!(keep_alive(response.version(), response.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn reject_invalid_messages() {
+        let smuggling =
+            b"POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        let error = read_request(&mut &smuggling[..]).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let folded = b"GET / HTTP/1.1\r\nX-A: 1\r\n  continued\r\n\r\n";
+        let error = read_request(&mut &folded[..]).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let huge = format!(
+            "GET / HTTP/1.1\r\nX-Big: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD_SIZE)
+        );
+        let error = read_request(&mut huge.as_bytes()).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        let truncated = b"POST / HTTP/1.1\r\nHost: x\r\nContent-Length: 10\r\n\r\nabc";
+        let error = read_request(&mut &truncated[..]).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        // 块长度接近 usize::MAX 时不能溢出
+        let overflow = b"POST / HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         1\r\na\r\nffffffffffffffff\r\n";
+        let error = read_request(&mut &overflow[..]).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn validate_host() {
+        // Host 中的 / ? # 等字符会改变路由看到的路径，重复、缺少 Host 的 HTTP/1.1 请求也被拒绝
+        for host in [
+            "Host: x/admin\r\n",
+            "Host: x?\r\n",
+            "Host: x#\r\n",
+            "Host: user@x\r\n",
+            "Host: x\\admin\r\n",
+            "Host: a b\r\n",
+            "Host: \r\n",
+            "Host: x\r\nHost: y\r\n",
+            "",
+        ] {
+            let raw = format!("GET /public HTTP/1.1\r\n{host}\r\n");
+            let error = read_request(&mut raw.as_bytes()).await.unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData, "{host:?}");
+        }
+
+        for (host, url) in [
+            ("example.com:8080", "http://example.com:8080/public"),
+            ("[::1]:80", "http://[::1]/public"),
+        ] {
+            let raw = format!("GET /public HTTP/1.1\r\nHost: {host}\r\n\r\n");
+            let request = read_request(&mut raw.as_bytes()).await.unwrap().unwrap();
+            assert_eq!(request.url().as_str(), url);
+        }
+
+        // HTTP/1.0 可以没有 Host
+        let raw = b"GET /public HTTP/1.0\r\n\r\n";
+        let request = read_request(&mut &raw[..]).await.unwrap().unwrap();
+        assert_eq!(request.url().path(), "/public");
+    }
+}
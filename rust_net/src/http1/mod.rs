@@ -0,0 +1,13 @@
+/**
+ * 基于 http-types 的 HTTP/1.1 实现
+ *
+ * http-types 只定义了 Request、Response 等类型，不负责网络传输，这里补上“锤子和锯子”：
+ *  1、codec：在 TCP 流上解析和序列化 HTTP/1.1 报文，支持 Content-Length、chunked 传输编码和 keep-alive。
+ *  2、server：带路由的小型服务端，适合在工具中嵌入健康检查、指标这类简单的控制端点，不需要引入 actix。
+ *  3、client：复用连接的客户端。
+ *
+ * 报文体都会完整读入内存，不适合传输大文件。
+ */
+pub mod client;
+pub mod codec;
+pub mod server;
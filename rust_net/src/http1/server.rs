@@ -0,0 +1,411 @@
+/**
+ * 带路由的 HTTP/1.1 服务端
+ *
+ * 路由规则：
+ *  1、静态路径：/health
+ *  2、路径参数：/users/:id，处理函数中通过 param(&request, "id") 获取
+ *  3、通配符：最后一段写成 *path 的形式，匹配剩余的所有内容（可以包含 '/'），通过 param(&request, "path") 获取
+ *
+ * 路径匹配但方法不匹配时返回 405 和 Allow 头，路径都不匹配时返回 404；HEAD 请求没有对应的路由时使用 GET 的路由。
+ * 每个连接在一个任务中按顺序处理请求，等待下一个请求超过 keep_alive_timeout 的连接会被关闭。
+ */
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{ErrorKind, Result},
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+
+use http_types::{Method, Request, Response, StatusCode, headers::CONNECTION};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    task::JoinHandle,
+};
+
+use super::codec;
+
+type BoxResponse = Pin<Box<dyn Future<Output = Response> + Send>>;
+type Handler = Arc<dyn Fn(Request) -> BoxResponse + Send + Sync>;
+
+/// 路由匹配得到的路径参数，保存在请求的扩展中
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params(HashMap<String, String>);
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+/// 获取路径参数
+pub fn param<'a>(request: &'a Request, name: &str) -> Option<&'a str> {
+    request.ext().get::<Params>()?.get(name)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Rest(String),
+}
+
+#[derive(Clone)]
+struct Route {
+    method: Method,
+    segments: Vec<Segment>,
+    handler: Handler,
+}
+
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route<F, Fut>(mut self, method: Method, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let segments = split(path)
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Rest(name.to_string())
+                } else {
+                    Segment::Static(segment.to_string())
+                }
+            })
+            .collect();
+        let handler: Handler = Arc::new(move |request| Box::pin(handler(request)));
+        self.routes.push(Route {
+            method,
+            segments,
+            handler,
+        });
+        self
+    }
+
+    pub fn get<F, Fut>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.route(Method::Get, path, handler)
+    }
+
+    pub fn post<F, Fut>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.route(Method::Post, path, handler)
+    }
+
+    /// 根据方法和路径分发请求
+    pub async fn handle(&self, mut request: Request) -> Response {
+        let path = request.url().path().to_string();
+        let matched: Vec<(&Route, Params)> = self
+            .routes
+            .iter()
+            .filter_map(|route| matches(&route.segments, &path).map(|params| (route, params)))
+            .collect();
+        if matched.is_empty() {
+            return Response::new(StatusCode::NotFound);
+        }
+
+        let method = request.method();
+        let found = matched
+            .iter()
+            .find(|(route, _)| route.method == method)
+            .or_else(|| {
+                (method == Method::Head)
+                    .then(|| {
+                        matched
+                            .iter()
+                            .find(|(route, _)| route.method == Method::Get)
+                    })
+                    .flatten()
+            });
+        match found {
+            Some((route, params)) => {
+                request.ext_mut().insert(params.clone());
+                (route.handler)(request).await
+            }
+            None => {
+                let mut allow: Vec<String> = matched
+                    .iter()
+                    .map(|(route, _)| route.method.to_string())
+                    .collect();
+                allow.sort();
+                allow.dedup();
+                let mut response = Response::new(StatusCode::MethodNotAllowed);
+                response.insert_header("Allow", allow.join(", "));
+                response
+            }
+        }
+    }
+}
+
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn matches(segments: &[Segment], path: &str) -> Option<Params> {
+    let mut params = HashMap::new();
+    let mut parts = split(path);
+    for segment in segments {
+        match segment {
+            Segment::Static(text) => {
+                if parts.next()? != text {
+                    return None;
+                }
+            }
+            Segment::Param(name) => {
+                params.insert(name.clone(), parts.next()?.to_string());
+            }
+            Segment::Rest(name) => {
+                let rest: Vec<&str> = parts.by_ref().collect();
+                params.insert(name.clone(), rest.join("/"));
+            }
+        }
+    }
+    parts.next().is_none().then_some(Params(params))
+}
+
+pub struct HttpServer {
+    listener: TcpListener,
+    router: Arc<Router>,
+    keep_alive_timeout: Duration,
+}
+
+impl HttpServer {
+    pub async fn bind(addr: impl ToSocketAddrs, router: Router) -> Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            router: Arc::new(router),
+            keep_alive_timeout: Duration::from_secs(5),
+        })
+    }
+
+    /// 连接空闲（等待下一个请求）的最长时间
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub async fn serve(self) {
+        loop {
+            let (stream, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::error!("接受连接失败: {e}");
+                    continue;
+                }
+            };
+            let router = self.router.clone();
+            let timeout = self.keep_alive_timeout;
+            tokio::spawn(async move {
+                if let Err(e) = serve_connection(stream, router, timeout).await {
+                    tracing::debug!("与{peer}的连接异常结束: {e}");
+                }
+            });
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(self.serve())
+    }
+}
+
+async fn serve_connection(stream: TcpStream, router: Arc<Router>, timeout: Duration) -> Result<()> {
+    let peer = stream.peer_addr()?;
+    let local = stream.local_addr()?;
+    let mut stream = BufReader::new(stream);
+    loop {
+        // 空闲超时只限制等待下一个请求的第一个字节，请求开始之后慢速上传的报文体不会被打断
+        match tokio::time::timeout(timeout, stream.fill_buf()).await {
+            Ok(Ok(buf)) if !buf.is_empty() => {}
+            // 空闲超时或者客户端关闭了连接
+            Err(_) | Ok(Ok(_)) => return Ok(()),
+            Ok(Err(e)) => return Err(e),
+        }
+        let request = match codec::read_request(&mut stream).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                let mut response = Response::new(StatusCode::BadRequest);
+                response.insert_header(CONNECTION, "close");
+                response.set_body(e.to_string());
+                return codec::write_response(&mut stream, &mut response, false).await;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut request = request;
+        request.set_peer_addr(Some(peer));
+        request.set_local_addr(Some(local));
+        let keep_alive = codec::keep_alive(request.version(), request.as_ref());
+        let head_only = request.method() == Method::Head;
+        let version = request.version();
+
+        let mut response = router.handle(request).await;
+        response.set_version(version);
+        if !keep_alive {
+            response.insert_header(CONNECTION, "close");
+        } else if version == Some(http_types::Version::Http1_0) {
+            response.insert_header(CONNECTION, "keep-alive");
+        }
+        // 处理函数也可以通过 Connection: close 要求关闭连接
+        let keep_alive = codec::keep_alive(version, response.as_ref());
+        codec::write_response(&mut stream, &mut response, head_only).await?;
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod server_test {
+    use std::time::Duration;
+
+    use http_types::{Method, Request, Response, StatusCode};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::{HttpServer, Router, param};
+
+    fn router() -> Router {
+        Router::new()
+            .get("/health", |_| async { Response::new(StatusCode::Ok) })
+            .get("/users/:id", |request: Request| async move {
+                let mut response = Response::new(StatusCode::Ok);
+                response.set_body(format!("user {}", param(&request, "id").unwrap()));
+                response
+            })
+            .get("/files/*path", |request: Request| async move {
+                let mut response = Response::new(StatusCode::Ok);
+                response.set_body(param(&request, "path").unwrap().to_string());
+                response
+            })
+            .post("/echo", |mut request: Request| async move {
+                let mut response = Response::new(StatusCode::Ok);
+                response.set_body(request.body_string().await.unwrap());
+                response
+            })
+    }
+
+    async fn call(router: &Router, method: Method, path: &str) -> Response {
+        let url = format!("http://localhost{path}");
+        router.handle(Request::new(method, url.as_str())).await
+    }
+
+    #[tokio::test]
+    async fn route_matching() {
+        let router = router();
+        let mut response = call(&router, Method::Get, "/users/42").await;
+        assert_eq!(response.body_string().await.unwrap(), "user 42");
+        let mut response = call(&router, Method::Get, "/files/a/b/c.txt").await;
+        assert_eq!(response.body_string().await.unwrap(), "a/b/c.txt");
+        let response = call(&router, Method::Head, "/health").await;
+        assert_eq!(response.status(), StatusCode::Ok);
+
+        let response = call(&router, Method::Get, "/users/42/extra").await;
+        assert_eq!(response.status(), StatusCode::NotFound);
+        let response = call(&router, Method::Delete, "/echo").await;
+        assert_eq!(response.status(), StatusCode::MethodNotAllowed);
+        assert_eq!(response.header("allow").unwrap().as_str(), "POST");
+
+        // 同一路径上的多个路由，Allow 中每个方法只出现一次
+        let router = self::router()
+            .post("/health", |_| async { Response::new(StatusCode::Ok) })
+            .get("/:name", |_| async { Response::new(StatusCode::Ok) });
+        let response = call(&router, Method::Delete, "/health").await;
+        assert_eq!(response.header("allow").unwrap().as_str(), "GET, POST");
+    }
+
+    /**
+     * 在同一个连接上依次发送普通请求、分块请求和 Connection: close 请求
+     */
+    #[tokio::test]
+    async fn keep_alive_and_chunked_over_tcp() {
+        let server = HttpServer::bind("127.0.0.1:0", router()).await.unwrap();
+        let addr = server.local_addr().unwrap();
+        let task = server.spawn();
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                b"GET /users/7 HTTP/1.1\r\nHost: x\r\n\r\n\
+                  POST /echo HTTP/1.1\r\nHost: x\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n\
+                  GET /health HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        // 服务端处理完最后一个请求后关闭连接，所以可以一直读到结束
+        let mut text = String::new();
+        stream.read_to_string(&mut text).await.unwrap();
+        assert_eq!(text.matches("HTTP/1.1 200 OK").count(), 3);
+        assert!(text.contains("user 7"));
+        assert!(text.contains("content-length: 3\r\n"));
+        assert!(text.contains("abc"));
+        assert!(text.contains("connection: close"));
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn bad_request_and_idle_timeout() {
+        let server = HttpServer::bind("127.0.0.1:0", router())
+            .await
+            .unwrap()
+            .keep_alive_timeout(Duration::from_millis(100));
+        let addr = server.local_addr().unwrap();
+        let task = server.spawn();
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"NOT-HTTP\r\n\r\n").await.unwrap();
+        let mut text = String::new();
+        stream.read_to_string(&mut text).await.unwrap();
+        assert!(text.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        // 空闲连接超时后被服务端关闭
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0; 16];
+        let length = tokio::time::timeout(Duration::from_secs(2), stream.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(length, 0);
+
+        // 请求已经开始时，超过空闲超时的慢速上传仍然能得到响应
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nHost: x\r\nContent-Length: 6\r\nConnection: close\r\n\r\nabc")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        stream.write_all(b"def").await.unwrap();
+        let mut text = String::new();
+        stream.read_to_string(&mut text).await.unwrap();
+        assert!(text.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(text.ends_with("abcdef"));
+
+        task.abort();
+    }
+}
@@ -0,0 +1,710 @@
+/**
+ * 基于 reqwest 的 HTTP 客户端封装
+ *
+ * reqwest_demo 中每次请求都是临时创建 Client，服务之间调用时通常还需要：
+ *  1、统一的 base URL 和默认请求头（例如鉴权、User-Agent）。
+ *  2、单个请求可以覆盖客户端的默认超时时间。
+ *  3、幂等方法（GET、HEAD、PUT、DELETE、OPTIONS）在网络错误、429、5xx 时按照指数退避自动重试，POST/PATCH 不会重试。
+ *  4、JSON 请求/响应的类型化封装，非 2xx 响应的错误体会被解析出来放进 HttpError::Status 中。
+ *  5、请求/响应日志钩子（HttpHook），默认的 TracingHook 使用 tracing 打印日志。
+ *
+ * HttpApi trait 只包含一个 send 方法，JSON 等辅助方法都在 HttpApiExt 中基于 send 实现，
+ * 所以测试时既可以让 HttpClient 指向 httpmock 启动的模拟服务器，也可以自己实现 HttpApi 完全不走网络。
+ */
+use std::{future::Future, sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use reqwest::{
+    Method, StatusCode,
+    header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    #[error("无效的URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("无效的请求头: {0}")]
+    Header(String),
+    #[error("请求失败: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[error("服务端返回{status}: {body}")]
+    Status {
+        status: StatusCode,
+        body: String,
+        /// 错误体是 JSON 时解析后的内容
+        json: Option<serde_json::Value>,
+    },
+    #[error("JSON解析失败: {source}, 响应内容: {body}")]
+    Decode {
+        source: serde_json::Error,
+        body: String,
+    },
+    #[error("JSON序列化失败: {0}")]
+    Encode(serde_json::Error),
+}
+
+impl HttpError {
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            HttpError::Status { status, .. } => Some(*status),
+            HttpError::Transport(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// 将 JSON 错误体解析为调用方定义的错误类型
+    pub fn error_body<E: DeserializeOwned>(&self) -> Option<E> {
+        match self {
+            HttpError::Status {
+                json: Some(json), ..
+            } => serde_json::from_value(json.clone()).ok(),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, HttpError>;
+
+/// 与具体 HTTP 库无关的请求描述，path 是相对于 base URL 的路径
+#[derive(Debug, Clone)]
+pub struct ApiRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: HeaderMap,
+    pub body: Option<Bytes>,
+    /// 覆盖客户端的默认超时时间
+    pub timeout: Option<Duration>,
+}
+
+impl ApiRequest {
+    pub fn new(method: Method, path: &str) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            query: Vec::new(),
+            headers: HeaderMap::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    pub fn get(path: &str) -> Self {
+        Self::new(Method::GET, path)
+    }
+
+    pub fn post(path: &str) -> Self {
+        Self::new(Method::POST, path)
+    }
+
+    pub fn put(path: &str) -> Self {
+        Self::new(Method::PUT, path)
+    }
+
+    pub fn delete(path: &str) -> Self {
+        Self::new(Method::DELETE, path)
+    }
+
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Result<Self> {
+        let (name, value) = parse_header(key, value)?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    pub fn json<B: Serialize + ?Sized>(mut self, body: &B) -> Result<Self> {
+        let bytes = serde_json::to_vec(body).map_err(HttpError::Encode)?;
+        self.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self.body = Some(bytes.into());
+        Ok(self)
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 幂等方法重复执行的效果与执行一次相同，只有它们可以安全地重试
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self.method,
+            Method::GET
+                | Method::HEAD
+                | Method::PUT
+                | Method::DELETE
+                | Method::OPTIONS
+                | Method::TRACE
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl ApiResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).map_err(|source| HttpError::Decode {
+            source,
+            body: self.text(),
+        })
+    }
+
+    /// 非 2xx 响应转换为 HttpError::Status
+    pub fn error_for_status(self) -> Result<Self> {
+        if self.status.is_success() {
+            return Ok(self);
+        }
+        Err(HttpError::Status {
+            status: self.status,
+            json: serde_json::from_slice(&self.body).ok(),
+            body: self.text(),
+        })
+    }
+}
+
+/// 可替换的 HTTP 调用接口，测试时可以使用自定义的实现代替真实的网络请求
+pub trait HttpApi: Send + Sync {
+    fn send(&self, request: ApiRequest) -> impl Future<Output = Result<ApiResponse>> + Send;
+}
+
+/// 基于 HttpApi::send 的 JSON 辅助方法，所有 HttpApi 的实现都可以直接使用
+pub trait HttpApiExt: HttpApi {
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> impl Future<Output = Result<T>> + Send {
+        self.request_json(ApiRequest::get(path))
+    }
+
+    fn post_json<B, T>(&self, path: &str, body: &B) -> impl Future<Output = Result<T>> + Send
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let request = ApiRequest::post(path).json(body);
+        async move { self.request_json(request?).await }
+    }
+
+    fn put_json<B, T>(&self, path: &str, body: &B) -> impl Future<Output = Result<T>> + Send
+    where
+        B: Serialize + ?Sized,
+        T: DeserializeOwned,
+    {
+        let request = ApiRequest::put(path).json(body);
+        async move { self.request_json(request?).await }
+    }
+
+    fn delete(&self, path: &str) -> impl Future<Output = Result<ApiResponse>> + Send {
+        let request = ApiRequest::delete(path);
+        async move { self.send(request).await?.error_for_status() }
+    }
+
+    /// 发送请求，检查状态码并将响应体解析为 T
+    fn request_json<T: DeserializeOwned>(
+        &self,
+        mut request: ApiRequest,
+    ) -> impl Future<Output = Result<T>> + Send {
+        request
+            .headers
+            .entry(reqwest::header::ACCEPT)
+            .or_insert(HeaderValue::from_static("application/json"));
+        async move { self.send(request).await?.error_for_status()?.json() }
+    }
+}
+
+impl<C: HttpApi> HttpApiExt for C {}
+
+/// 请求/响应日志钩子，attempt 从 0 开始，大于 0 表示重试
+pub trait HttpHook: Send + Sync {
+    fn on_request(&self, _request: &ApiRequest, _url: &Url, _attempt: u32) {}
+
+    fn on_response(&self, _request: &ApiRequest, _response: &ApiResponse, _elapsed: Duration) {}
+
+    fn on_error(&self, _request: &ApiRequest, _error: &HttpError, _elapsed: Duration) {}
+}
+
+/// 使用 tracing 打印请求日志
+pub struct TracingHook;
+
+impl HttpHook for TracingHook {
+    fn on_request(&self, request: &ApiRequest, url: &Url, attempt: u32) {
+        if attempt == 0 {
+            tracing::debug!("--> {} {}", request.method, url);
+        } else {
+            tracing::warn!("--> {} {} (第{}次重试)", request.method, url, attempt);
+        }
+    }
+
+    fn on_response(&self, request: &ApiRequest, response: &ApiResponse, elapsed: Duration) {
+        tracing::debug!(
+            "<-- {} {} {} {}字节 {:?}",
+            request.method,
+            request.path,
+            response.status,
+            response.body.len(),
+            elapsed
+        );
+    }
+
+    fn on_error(&self, request: &ApiRequest, error: &HttpError, elapsed: Duration) {
+        tracing::error!(
+            "<-- {} {} 失败: {} {:?}",
+            request.method,
+            request.path,
+            error,
+            elapsed
+        );
+    }
+}
+
+/// 指数退避重试策略：第 n 次重试前等待 base_delay * 2^(n-1)，最长不超过 max_delay
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    fn should_retry(&self, result: &Result<ApiResponse>) -> bool {
+        match result {
+            Ok(response) => {
+                response.status == StatusCode::TOO_MANY_REQUESTS
+                    || response.status.is_server_error()
+            }
+            Err(HttpError::Transport(e)) => e.is_connect() || e.is_timeout() || e.is_request(),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    base_url: Url,
+    headers: HeaderMap,
+    retry: RetryPolicy,
+    hooks: Vec<Arc<dyn HttpHook>>,
+}
+
+impl HttpClient {
+    pub fn builder(base_url: &str) -> HttpClientBuilder {
+        HttpClientBuilder::new(base_url)
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// 将相对路径拼接到 base URL 上，base URL 的路径部分会被保留；路径中 ? 之后的部分作为查询参数
+    pub fn url(&self, path: &str) -> Result<Url> {
+        let mut url = self.base_url.clone();
+        let base = url.path().trim_end_matches('/').to_string();
+        let (path, query) = match path.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path, None),
+        };
+        url.set_path(&format!("{}/{}", base, path.trim_start_matches('/')));
+        if query.is_some() {
+            url.set_query(query);
+        }
+        Ok(url)
+    }
+
+    async fn send_once(&self, request: &ApiRequest, url: Url) -> Result<ApiResponse> {
+        let mut builder = self
+            .client
+            .request(request.method.clone(), url)
+            .headers(self.headers.clone())
+            .headers(request.headers.clone());
+        if !request.query.is_empty() {
+            builder = builder.query(&request.query);
+        }
+        if let Some(body) = request.body.clone() {
+            builder = builder.body(body);
+        }
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        let response = builder.send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        Ok(ApiResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl HttpApi for HttpClient {
+    async fn send(&self, request: ApiRequest) -> Result<ApiResponse> {
+        let url = self.url(&request.path)?;
+        let max_retries = if request.is_idempotent() {
+            self.retry.max_retries
+        } else {
+            0
+        };
+
+        let mut attempt = 0;
+        loop {
+            self.hooks
+                .iter()
+                .for_each(|hook| hook.on_request(&request, &url, attempt));
+            let start = std::time::Instant::now();
+            let result = self.send_once(&request, url.clone()).await;
+            let elapsed = start.elapsed();
+            match &result {
+                Ok(response) => self
+                    .hooks
+                    .iter()
+                    .for_each(|hook| hook.on_response(&request, response, elapsed)),
+                Err(e) => self
+                    .hooks
+                    .iter()
+                    .for_each(|hook| hook.on_error(&request, e, elapsed)),
+            }
+
+            if attempt >= max_retries || !self.retry.should_retry(&result) {
+                return result;
+            }
+            attempt += 1;
+            tokio::time::sleep(self.retry.delay(attempt)).await;
+        }
+    }
+}
+
+pub struct HttpClientBuilder {
+    base_url: String,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+    connect_timeout: Duration,
+    user_agent: String,
+    retry: RetryPolicy,
+    hooks: Vec<Arc<dyn HttpHook>>,
+}
+
+impl HttpClientBuilder {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            headers: Vec::new(),
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            user_agent: "rust_net-http-client/0.1".to_string(),
+            retry: RetryPolicy::default(),
+            hooks: Vec::new(),
+        }
+    }
+
+    /// 每个请求都会携带的默认请求头
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn hook(mut self, hook: impl HttpHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    pub fn build(self) -> Result<HttpClient> {
+        let base_url = Url::parse(&self.base_url)?;
+        let mut headers = HeaderMap::new();
+        for (key, value) in self.headers.iter() {
+            let (name, value) = parse_header(key, value)?;
+            headers.insert(name, value);
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .gzip(true)
+            .user_agent(self.user_agent)
+            .build()?;
+
+        Ok(HttpClient {
+            client,
+            base_url,
+            headers,
+            retry: self.retry,
+            hooks: self.hooks,
+        })
+    }
+}
+
+fn parse_header(key: &str, value: &str) -> Result<(HeaderName, HeaderValue)> {
+    let name = HeaderName::from_bytes(key.as_bytes())
+        .map_err(|e| HttpError::Header(format!("{key}: {e}")))?;
+    let value =
+        HeaderValue::from_str(value).map_err(|e| HttpError::Header(format!("{key}: {e}")))?;
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod http_client_test {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+
+    use httpmock::{Method::GET, Method::POST, MockServer};
+    use reqwest::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::{
+        ApiRequest, ApiResponse, HttpApi, HttpApiExt, HttpClient, HttpError, HttpHook, Result,
+        RetryPolicy,
+    };
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ApiErrorBody {
+        code: String,
+    }
+
+    fn fast_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        }
+    }
+
+    #[tokio::test]
+    async fn base_url_and_default_headers() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/api/users/1")
+                    .header("x-token", "secret")
+                    .query_param("verbose", "true");
+                then.status(200)
+                    .json_body(json!({"id": 1, "name": "zhangsan"}));
+            })
+            .await;
+
+        let client = HttpClient::builder(&server.url("/api"))
+            .header("x-token", "secret")
+            .build()
+            .unwrap();
+        let user: User = client
+            .request_json(ApiRequest::get("users/1").query("verbose", "true"))
+            .await
+            .unwrap();
+        assert_eq!(
+            user,
+            User {
+                id: 1,
+                name: "zhangsan".to_string()
+            }
+        );
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn url_with_query() {
+        let client = HttpClient::builder("http://localhost:8080/api/")
+            .build()
+            .unwrap();
+        assert_eq!(
+            client.url("/a?b=1&c=2").unwrap().as_str(),
+            "http://localhost:8080/api/a?b=1&c=2"
+        );
+        assert_eq!(
+            client.url("users/1").unwrap().as_str(),
+            "http://localhost:8080/api/users/1"
+        );
+    }
+
+    #[tokio::test]
+    async fn decode_error_body() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/users");
+                then.status(400).json_body(json!({"code": "name_required"}));
+            })
+            .await;
+
+        let client = HttpClient::builder(&server.base_url()).build().unwrap();
+        let error = client
+            .post_json::<_, User>("/users", &json!({}))
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            error.error_body::<ApiErrorBody>().unwrap().code,
+            "name_required"
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_idempotent_only() {
+        let server = MockServer::start_async().await;
+        let get = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/status");
+                then.status(503);
+            })
+            .await;
+        let post = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/status");
+                then.status(503);
+            })
+            .await;
+
+        let client = HttpClient::builder(&server.base_url())
+            .retry(fast_retry())
+            .build()
+            .unwrap();
+        let response = client.send(ApiRequest::get("/status")).await.unwrap();
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(get.hits_async().await, 3);
+
+        client.send(ApiRequest::post("/status")).await.unwrap();
+        assert_eq!(post.hits_async().await, 1);
+    }
+
+    #[tokio::test]
+    async fn per_request_timeout() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/slow");
+                then.status(200).delay(Duration::from_millis(500));
+            })
+            .await;
+
+        let client = HttpClient::builder(&server.base_url())
+            .retry(RetryPolicy::none())
+            .build()
+            .unwrap();
+        let error = client
+            .send(ApiRequest::get("/slow").timeout(Duration::from_millis(50)))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, HttpError::Transport(ref e) if e.is_timeout()));
+    }
+
+    #[tokio::test]
+    async fn logging_hook() {
+        struct CountHook(Arc<AtomicU32>);
+        impl HttpHook for CountHook {
+            fn on_request(&self, _: &ApiRequest, _: &url::Url, _: u32) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/flaky");
+                then.status(500);
+            })
+            .await;
+
+        let count = Arc::new(AtomicU32::new(0));
+        let client = HttpClient::builder(&server.base_url())
+            .retry(fast_retry())
+            .hook(CountHook(count.clone()))
+            .build()
+            .unwrap();
+        let error = client.get_json::<User>("/flaky").await.unwrap_err();
+        assert_eq!(error.status(), Some(StatusCode::INTERNAL_SERVER_ERROR));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+    }
+
+    /**
+     * 不启动任何服务器，直接实现 HttpApi 来模拟响应
+     */
+    #[tokio::test]
+    async fn custom_http_api() {
+        struct FakeApi;
+        impl HttpApi for FakeApi {
+            async fn send(&self, request: ApiRequest) -> Result<ApiResponse> {
+                let body = format!(r#"{{"id": 7, "name": "{}"}}"#, request.path);
+                Ok(ApiResponse {
+                    status: StatusCode::OK,
+                    headers: Default::default(),
+                    body: body.into(),
+                })
+            }
+        }
+
+        let user: User = FakeApi.get_json("lisi").await.unwrap();
+        assert_eq!(user.name, "lisi");
+    }
+}
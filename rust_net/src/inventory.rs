@@ -0,0 +1,553 @@
+/**
+ * 网卡清单（inventory）
+ *
+ * 把 netdev 获取到的所有网卡整理成稳定、可序列化的结构：
+ *  1、地址使用 ipnet 的 IpNet 表示（地址 + 前缀长度），MAC 地址使用 mac_address 的 MacAddress。
+ *  2、标志位展开为布尔字段，网关和 DNS 服务器只在支持的平台上有值。
+ *  3、网卡按名称排序、地址和 DNS 服务器排序后保存，两次采集的结果可以直接比较。
+ *
+ * 快照可以保存为 JSON，也可以输出为表格；diff 比较两个快照，用来发现实验机器上的配置漂移。
+ * 网卡序号、流量统计这类每次启动都可能变化的信息不参与比较。
+ */
+use std::{
+    fmt,
+    net::IpAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ipnet::IpNet;
+use mac_address::MacAddress;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceFlags {
+    pub up: bool,
+    pub running: bool,
+    pub loopback: bool,
+    pub broadcast: bool,
+    pub multicast: bool,
+    pub point_to_point: bool,
+    pub physical: bool,
+    pub tun: bool,
+}
+
+impl fmt::Display for InterfaceFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.up, "UP"),
+            (self.running, "RUNNING"),
+            (self.loopback, "LOOPBACK"),
+            (self.broadcast, "BROADCAST"),
+            (self.multicast, "MULTICAST"),
+            (self.point_to_point, "POINTOPOINT"),
+            (self.physical, "PHYSICAL"),
+            (self.tun, "TUN"),
+        ];
+        let set: Vec<&str> = names
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| *name)
+            .collect();
+        write!(f, "{}", set.join(","))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gateway {
+    pub mac: Option<MacAddress>,
+    pub addresses: Vec<IpAddr>,
+}
+
+impl fmt::Display for Gateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addresses: Vec<String> = self.addresses.iter().map(IpAddr::to_string).collect();
+        write!(f, "{}", addresses.join(","))?;
+        if let Some(mac) = self.mac {
+            write!(f, " ({mac})")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceInfo {
+    pub index: u32,
+    pub name: String,
+    pub friendly_name: Option<String>,
+    pub kind: String,
+    pub mac: Option<MacAddress>,
+    pub mtu: Option<u32>,
+    /// 运行状态，取值和 Linux 的 /sys/class/net/*/operstate 相同
+    pub state: String,
+    pub flags: InterfaceFlags,
+    pub addresses: Vec<IpNet>,
+    pub gateway: Option<Gateway>,
+    pub dns_servers: Vec<IpAddr>,
+    /// 是否为访问外网的默认网卡
+    pub default: bool,
+}
+
+impl From<&netdev::Interface> for InterfaceInfo {
+    fn from(interface: &netdev::Interface) -> Self {
+        let mut addresses: Vec<IpNet> = interface
+            .ipv4
+            .iter()
+            .map(|net| IpNet::V4(*net))
+            .chain(interface.ipv6.iter().map(|net| IpNet::V6(*net)))
+            .collect();
+        addresses.sort();
+        let mut dns_servers = interface.dns_servers.clone();
+        dns_servers.sort();
+
+        // netdev 拿不到 MAC 地址时再通过 mac_address 按名称查询一次
+        let mac = interface
+            .mac_addr
+            .map(|mac| MacAddress::new(mac.octets()))
+            .or_else(|| {
+                mac_address::mac_address_by_name(&interface.name)
+                    .ok()
+                    .flatten()
+            })
+            .filter(|mac| mac.bytes() != [0; 6]);
+        let gateway = interface.gateway.as_ref().map(|device| Gateway {
+            mac: Some(MacAddress::new(device.mac_addr.octets()))
+                .filter(|mac| mac.bytes() != [0; 6]),
+            addresses: device
+                .ipv4
+                .iter()
+                .map(|ip| IpAddr::V4(*ip))
+                .chain(device.ipv6.iter().map(|ip| IpAddr::V6(*ip)))
+                .collect(),
+        });
+
+        Self {
+            index: interface.index,
+            name: interface.name.clone(),
+            friendly_name: interface.friendly_name.clone(),
+            kind: interface.if_type.name(),
+            mac,
+            mtu: interface.mtu,
+            state: interface.oper_state.as_str().to_string(),
+            flags: InterfaceFlags {
+                up: interface.is_up(),
+                running: interface.is_running(),
+                loopback: interface.is_loopback(),
+                broadcast: interface.is_broadcast(),
+                multicast: interface.is_multicast(),
+                point_to_point: interface.is_point_to_point(),
+                physical: interface.is_physical(),
+                tun: interface.is_tun(),
+            },
+            addresses,
+            gateway,
+            dns_servers,
+            default: interface.default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// 采集时间（Unix 时间戳，秒）
+    pub captured_at: u64,
+    pub interfaces: Vec<InterfaceInfo>,
+}
+
+impl Inventory {
+    /// 采集本机所有网卡
+    pub fn collect() -> Self {
+        Self::from_interfaces(netdev::get_interfaces().iter().map(InterfaceInfo::from))
+    }
+
+    pub fn from_interfaces(interfaces: impl IntoIterator<Item = InterfaceInfo>) -> Self {
+        let mut interfaces: Vec<InterfaceInfo> = interfaces.into_iter().collect();
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+        let captured_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        Self {
+            captured_at,
+            interfaces,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InterfaceInfo> {
+        self.interfaces
+            .iter()
+            .find(|interface| interface.name == name)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(text: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(text)
+    }
+
+    /// 输出为文本表格，每个地址占一行
+    pub fn to_table(&self) -> String {
+        let header = [
+            "NAME", "STATE", "MAC", "MTU", "FLAGS", "ADDRESS", "GATEWAY", "DNS",
+        ];
+        let mut rows: Vec<[String; 8]> = Vec::new();
+        for interface in &self.interfaces {
+            let lines = interface
+                .addresses
+                .len()
+                .max(interface.dns_servers.len())
+                .max(1);
+            for line in 0..lines {
+                let first = line == 0;
+                let text = |value: String| if first { value } else { String::new() };
+                rows.push([
+                    text(interface.name.clone()),
+                    text(interface.state.clone()),
+                    text(interface.mac.map(|mac| mac.to_string()).unwrap_or_default()),
+                    text(interface.mtu.map(|mtu| mtu.to_string()).unwrap_or_default()),
+                    text(interface.flags.to_string()),
+                    interface
+                        .addresses
+                        .get(line)
+                        .map(IpNet::to_string)
+                        .unwrap_or_default(),
+                    text(
+                        interface
+                            .gateway
+                            .as_ref()
+                            .map(Gateway::to_string)
+                            .unwrap_or_default(),
+                    ),
+                    interface
+                        .dns_servers
+                        .get(line)
+                        .map(IpAddr::to_string)
+                        .unwrap_or_default(),
+                ]);
+            }
+        }
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let format_row = |cells: Vec<&str>| {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            line.join("  ").trim_end().to_string() + "\n"
+        };
+        let mut table = format_row(header.to_vec());
+        for row in &rows {
+            table.push_str(&format_row(row.iter().map(String::as_str).collect()));
+        }
+        table
+    }
+
+    /// 从 self（旧快照）到 newer（新快照）的变化
+    pub fn diff(&self, newer: &Inventory) -> InventoryDiff {
+        let mut diff = InventoryDiff::default();
+        for old in &self.interfaces {
+            match newer.get(&old.name) {
+                None => diff.removed.push(old.name.clone()),
+                Some(new) => {
+                    let changes = diff_interface(old, new);
+                    if !changes.is_empty() {
+                        diff.changed.push(InterfaceChanges {
+                            name: old.name.clone(),
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+        for new in &newer.interfaces {
+            if self.get(&new.name).is_none() {
+                diff.added.push(new.name.clone());
+            }
+        }
+        diff
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    Mac {
+        before: Option<MacAddress>,
+        after: Option<MacAddress>,
+    },
+    Mtu {
+        before: Option<u32>,
+        after: Option<u32>,
+    },
+    State {
+        before: String,
+        after: String,
+    },
+    Flags {
+        before: InterfaceFlags,
+        after: InterfaceFlags,
+    },
+    AddressAdded {
+        address: IpNet,
+    },
+    AddressRemoved {
+        address: IpNet,
+    },
+    Gateway {
+        before: Option<Gateway>,
+        after: Option<Gateway>,
+    },
+    DnsAdded {
+        server: IpAddr,
+    },
+    DnsRemoved {
+        server: IpAddr,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn or_none<T: ToString>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map(T::to_string)
+                .unwrap_or_else(|| "-".to_string())
+        }
+        match self {
+            Change::Mac { before, after } => {
+                write!(f, "MAC: {} -> {}", or_none(before), or_none(after))
+            }
+            Change::Mtu { before, after } => {
+                write!(f, "MTU: {} -> {}", or_none(before), or_none(after))
+            }
+            Change::State { before, after } => write!(f, "状态: {before} -> {after}"),
+            Change::Flags { before, after } => write!(f, "标志: {before} -> {after}"),
+            Change::AddressAdded { address } => write!(f, "+ 地址 {address}"),
+            Change::AddressRemoved { address } => write!(f, "- 地址 {address}"),
+            Change::Gateway { before, after } => {
+                write!(f, "网关: {} -> {}", or_none(before), or_none(after))
+            }
+            Change::DnsAdded { server } => write!(f, "+ DNS {server}"),
+            Change::DnsRemoved { server } => write!(f, "- DNS {server}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceChanges {
+    pub name: String,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventoryDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<InterfaceChanges>,
+}
+
+impl InventoryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for InventoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.added {
+            writeln!(f, "+ {name}")?;
+        }
+        for name in &self.removed {
+            writeln!(f, "- {name}")?;
+        }
+        for interface in &self.changed {
+            writeln!(f, "~ {}", interface.name)?;
+            for change in &interface.changes {
+                writeln!(f, "    {change}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn diff_interface(old: &InterfaceInfo, new: &InterfaceInfo) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old.mac != new.mac {
+        changes.push(Change::Mac {
+            before: old.mac,
+            after: new.mac,
+        });
+    }
+    if old.mtu != new.mtu {
+        changes.push(Change::Mtu {
+            before: old.mtu,
+            after: new.mtu,
+        });
+    }
+    if old.state != new.state {
+        changes.push(Change::State {
+            before: old.state.clone(),
+            after: new.state.clone(),
+        });
+    }
+    if old.flags != new.flags {
+        changes.push(Change::Flags {
+            before: old.flags,
+            after: new.flags,
+        });
+    }
+    for address in new.addresses.iter().filter(|a| !old.addresses.contains(a)) {
+        changes.push(Change::AddressAdded { address: *address });
+    }
+    for address in old.addresses.iter().filter(|a| !new.addresses.contains(a)) {
+        changes.push(Change::AddressRemoved { address: *address });
+    }
+    if old.gateway != new.gateway {
+        changes.push(Change::Gateway {
+            before: old.gateway.clone(),
+            after: new.gateway.clone(),
+        });
+    }
+    for server in new
+        .dns_servers
+        .iter()
+        .filter(|s| !old.dns_servers.contains(s))
+    {
+        changes.push(Change::DnsAdded { server: *server });
+    }
+    for server in old
+        .dns_servers
+        .iter()
+        .filter(|s| !new.dns_servers.contains(s))
+    {
+        changes.push(Change::DnsRemoved { server: *server });
+    }
+    changes
+}
+
+#[cfg(test)]
+mod inventory_test {
+    use super::{Change, Gateway, InterfaceFlags, InterfaceInfo, Inventory};
+
+    fn eth0() -> InterfaceInfo {
+        InterfaceInfo {
+            index: 2,
+            name: "eth0".to_string(),
+            friendly_name: None,
+            kind: "Ethernet".to_string(),
+            mac: Some("52:54:00:12:34:56".parse().unwrap()),
+            mtu: Some(1500),
+            state: "up".to_string(),
+            flags: InterfaceFlags {
+                up: true,
+                running: true,
+                broadcast: true,
+                multicast: true,
+                physical: true,
+                ..Default::default()
+            },
+            addresses: vec![
+                "192.168.10.5/24".parse().unwrap(),
+                "fe80::5054:ff:fe12:3456/64".parse().unwrap(),
+            ],
+            gateway: Some(Gateway {
+                mac: None,
+                addresses: vec!["192.168.10.1".parse().unwrap()],
+            }),
+            dns_servers: vec!["192.168.10.1".parse().unwrap()],
+            default: true,
+        }
+    }
+
+    #[test]
+    fn collect_local_interfaces() {
+        let inventory = Inventory::collect();
+        // 至少有一个回环网卡，并且回环网卡上有 127.0.0.1 或 ::1
+        let loopback = inventory
+            .interfaces
+            .iter()
+            .find(|interface| interface.flags.loopback)
+            .expect("没有找到回环网卡");
+        assert!(
+            loopback
+                .addresses
+                .iter()
+                .any(|address| address.addr().is_loopback())
+        );
+        // 同一台机器上连续采集两次，没有配置变化
+        assert!(inventory.diff(&Inventory::collect()).is_empty());
+        println!("{}", inventory.to_table());
+    }
+
+    #[test]
+    fn json_round_trip_and_table() {
+        let inventory = Inventory::from_interfaces([eth0()]);
+        let json = inventory.to_json().unwrap();
+        assert!(json.contains("\"mac\": \"52:54:00:12:34:56\""));
+        assert!(json.contains("\"192.168.10.5/24\""));
+        assert_eq!(Inventory::from_json(&json).unwrap(), inventory);
+
+        let table = inventory.to_table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("NAME  STATE  MAC"));
+        assert!(lines[1].starts_with("eth0  up     52:54:00:12:34:56  1500"));
+        assert!(lines[1].contains("192.168.10.1 "));
+        assert!(lines[1].contains("UP,RUNNING,BROADCAST,MULTICAST,PHYSICAL "));
+        assert!(
+            lines[2]
+                .trim_start()
+                .starts_with("fe80::5054:ff:fe12:3456/64")
+        );
+    }
+
+    #[test]
+    fn detect_drift() {
+        let before = Inventory::from_interfaces([eth0()]);
+        let mut changed = eth0();
+        changed.index = 7;
+        changed.mtu = Some(9000);
+        changed.addresses[0] = "192.168.10.6/24".parse().unwrap();
+        changed.dns_servers.push("1.1.1.1".parse().unwrap());
+        let mut wlan = eth0();
+        wlan.name = "wlan0".to_string();
+        let after = Inventory::from_interfaces([changed, wlan]);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, vec!["wlan0".to_string()]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        // 网卡序号不参与比较
+        assert_eq!(
+            diff.changed[0].changes,
+            vec![
+                Change::Mtu {
+                    before: Some(1500),
+                    after: Some(9000)
+                },
+                Change::AddressAdded {
+                    address: "192.168.10.6/24".parse().unwrap()
+                },
+                Change::AddressRemoved {
+                    address: "192.168.10.5/24".parse().unwrap()
+                },
+                Change::DnsAdded {
+                    server: "1.1.1.1".parse().unwrap()
+                },
+            ]
+        );
+        let text = diff.to_string();
+        assert!(text.contains("+ wlan0\n"));
+        assert!(text.contains("    MTU: 1500 -> 9000\n"));
+
+        let reverse = after.diff(&before);
+        assert_eq!(reverse.removed, vec!["wlan0".to_string()]);
+    }
+}
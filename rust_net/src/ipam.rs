@@ -0,0 +1,576 @@
+/**
+ * 基于 ipnet 的简易 IP 地址管理（IPAM）
+ *
+ * 层级结构：地址池（Pool） -> 子网（Subnet） -> 地址（Lease）
+ *  1、地址池是一个大网段，例如 10.0.0.0/16 或 fd00::/48，不同地址池之间不能重叠。
+ *  2、从地址池中按前缀长度分配子网，采用最佳适配：优先使用能容纳该前缀的最小空闲块，减少碎片。
+ *  3、从子网中分配单个地址，网关等地址可以提前预留，预留的地址不会被自动分配。
+ *
+ * 状态保存为 JSON 文件，revision 字段每次保存加一：
+ *  1、加载时校验网段重叠、地址越界等冲突（例如手工编辑过文件）。
+ *  2、保存时如果文件中的 revision 和加载时不同，说明有其他人修改过文件，返回 StaleRevision 而不是覆盖。
+ *
+ * 另外提供 aggregate（合并相邻网段）、summarize（计算汇总路由）、difference（网段集合差）等工具函数，IPv4 和 IPv6 都适用。
+ */
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum IpamError {
+    #[error("{0}与{1}重叠")]
+    Overlap(IpNet, IpNet),
+    #[error("地址池{0}不存在")]
+    PoolNotFound(String),
+    #[error("地址池{0}已存在")]
+    PoolExists(String),
+    #[error("地址池{0}中还有已分配的子网")]
+    PoolInUse(String),
+    #[error("{0}不在地址池{1}中")]
+    OutOfPool(IpNet, IpNet),
+    #[error("前缀长度/{prefix}不适用于{pool}")]
+    InvalidPrefix { pool: IpNet, prefix: u8 },
+    #[error("地址池{pool}中没有空闲的/{prefix}子网")]
+    Exhausted { pool: IpNet, prefix: u8 },
+    #[error("子网{0}没有被分配")]
+    SubnetNotFound(IpNet),
+    #[error("{0}不属于任何已分配的子网")]
+    NoSubnet(IpAddr),
+    #[error("地址{0}已被{1}占用")]
+    AddressInUse(IpAddr, String),
+    #[error("子网{0}中没有空闲地址")]
+    AddressExhausted(IpNet),
+    #[error("地址{0}没有被分配")]
+    AddressNotFound(IpAddr),
+    #[error("状态文件已被修改（加载时的版本为{expected}，当前版本为{actual}）")]
+    StaleRevision { expected: u64, actual: u64 },
+    #[error("读写状态文件失败: {0}")]
+    Io(#[from] io::Error),
+    #[error("状态文件格式错误: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, IpamError>;
+
+/// 两个网段是否有公共地址，CIDR 网段要么互不相交，要么一个包含另一个
+pub fn overlaps(a: &IpNet, b: &IpNet) -> bool {
+    a.contains(b) || b.contains(a)
+}
+
+/// 合并重叠和相邻的网段，例如 10.0.0.0/25 + 10.0.0.128/25 = 10.0.0.0/24，结果按地址排序
+pub fn aggregate(nets: &[IpNet]) -> Vec<IpNet> {
+    IpNet::aggregate(&nets.to_vec())
+}
+
+/// 能覆盖所有网段的最小网段（汇总路由），网段为空或者同时包含 IPv4 和 IPv6 时返回 None
+pub fn summarize(nets: &[IpNet]) -> Option<IpNet> {
+    let mut summary = nets.first()?.trunc();
+    while !nets.iter().all(|net| summary.contains(net)) {
+        summary = summary.supernet()?;
+    }
+    Some(summary)
+}
+
+/// 从 from 中去掉 remove 覆盖的地址，剩余部分以最少的网段表示
+pub fn difference(from: &[IpNet], remove: &[IpNet]) -> Vec<IpNet> {
+    let mut rest = aggregate(from);
+    for hole in aggregate(remove) {
+        rest = rest
+            .into_iter()
+            .flat_map(|net| subtract(net, hole))
+            .collect();
+    }
+    aggregate(&rest)
+}
+
+/// net 减去一个网段：不断对半拆分 net，保留不包含 hole 的那一半
+fn subtract(net: IpNet, hole: IpNet) -> Vec<IpNet> {
+    if !overlaps(&net, &hole) {
+        return vec![net];
+    }
+    if hole.contains(&net) {
+        return Vec::new();
+    }
+    let mut rest = Vec::new();
+    let mut current = net;
+    while current != hole {
+        // current 严格包含 hole，所以 current 的前缀一定可以再加一
+        let mut halves = current.subnets(current.prefix_len() + 1).unwrap();
+        let (low, high) = (halves.next().unwrap(), halves.next().unwrap());
+        if low.contains(&hole) {
+            rest.push(high);
+            current = low;
+        } else {
+            rest.push(low);
+            current = high;
+        }
+    }
+    rest
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    pub owner: String,
+    /// 预留的地址（网关、DNS 等），不会被自动分配
+    #[serde(default)]
+    pub reserved: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subnet {
+    pub owner: String,
+    #[serde(default)]
+    pub leases: BTreeMap<IpAddr, Lease>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pool {
+    pub cidr: IpNet,
+    #[serde(default)]
+    pub subnets: BTreeMap<IpNet, Subnet>,
+}
+
+impl Pool {
+    /// 地址池中还没有分配的部分
+    pub fn free(&self) -> Vec<IpNet> {
+        let allocated: Vec<IpNet> = self.subnets.keys().copied().collect();
+        difference(&[self.cidr], &allocated)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ipam {
+    #[serde(default)]
+    revision: u64,
+    #[serde(default)]
+    pools: BTreeMap<String, Pool>,
+}
+
+/// 保存前只需要读取文件中的版本号
+#[derive(Deserialize)]
+struct Revision {
+    #[serde(default)]
+    revision: u64,
+}
+
+impl Ipam {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn pools(&self) -> &BTreeMap<String, Pool> {
+        &self.pools
+    }
+
+    pub fn pool(&self, name: &str) -> Option<&Pool> {
+        self.pools.get(name)
+    }
+
+    pub fn add_pool(&mut self, name: &str, cidr: IpNet) -> Result<()> {
+        if self.pools.contains_key(name) {
+            return Err(IpamError::PoolExists(name.to_string()));
+        }
+        let cidr = cidr.trunc();
+        if let Some(pool) = self.pools.values().find(|pool| overlaps(&pool.cidr, &cidr)) {
+            return Err(IpamError::Overlap(cidr, pool.cidr));
+        }
+        self.pools.insert(
+            name.to_string(),
+            Pool {
+                cidr,
+                subnets: BTreeMap::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// 只能删除没有分配子网的地址池
+    pub fn remove_pool(&mut self, name: &str) -> Result<Pool> {
+        let pool = self.pool_mut(name)?;
+        if !pool.subnets.is_empty() {
+            return Err(IpamError::PoolInUse(name.to_string()));
+        }
+        Ok(self.pools.remove(name).unwrap())
+    }
+
+    /// 从地址池中分配一个指定前缀长度的子网
+    pub fn allocate_subnet(&mut self, pool: &str, prefix: u8, owner: &str) -> Result<IpNet> {
+        let pool = self.pool_mut(pool)?;
+        if prefix < pool.cidr.prefix_len() || prefix > pool.cidr.max_prefix_len() {
+            return Err(IpamError::InvalidPrefix {
+                pool: pool.cidr,
+                prefix,
+            });
+        }
+        // 最佳适配：能放下该前缀的空闲块中最小的一个，同样大小时取地址最小的
+        let block = pool
+            .free()
+            .into_iter()
+            .filter(|block| block.prefix_len() <= prefix)
+            .max_by(|a, b| a.prefix_len().cmp(&b.prefix_len()).then(b.cmp(a)))
+            .ok_or(IpamError::Exhausted {
+                pool: pool.cidr,
+                prefix,
+            })?;
+        let subnet = block.subnets(prefix).unwrap().next().unwrap();
+        pool.subnets.insert(subnet, Subnet::new(owner));
+        Ok(subnet)
+    }
+
+    /// 分配一个指定的子网，例如登记已经在使用的网段
+    pub fn claim_subnet(&mut self, pool: &str, cidr: IpNet, owner: &str) -> Result<()> {
+        let pool = self.pool_mut(pool)?;
+        let cidr = cidr.trunc();
+        if !pool.cidr.contains(&cidr) {
+            return Err(IpamError::OutOfPool(cidr, pool.cidr));
+        }
+        if let Some(used) = pool.subnets.keys().find(|used| overlaps(used, &cidr)) {
+            return Err(IpamError::Overlap(cidr, *used));
+        }
+        pool.subnets.insert(cidr, Subnet::new(owner));
+        Ok(())
+    }
+
+    /// 释放子网，子网中的地址分配也一起释放
+    pub fn release_subnet(&mut self, cidr: IpNet) -> Result<Subnet> {
+        self.pools
+            .values_mut()
+            .find_map(|pool| pool.subnets.remove(&cidr))
+            .ok_or(IpamError::SubnetNotFound(cidr))
+    }
+
+    pub fn subnet(&self, cidr: &IpNet) -> Option<&Subnet> {
+        self.pools.values().find_map(|pool| pool.subnets.get(cidr))
+    }
+
+    /// 预留一个地址，reason 记录在 owner 中
+    pub fn reserve(&mut self, ip: IpAddr, reason: &str) -> Result<()> {
+        self.insert_lease(ip, reason, true)
+    }
+
+    /// 分配一个指定的地址
+    pub fn claim_address(&mut self, ip: IpAddr, owner: &str) -> Result<()> {
+        self.insert_lease(ip, owner, false)
+    }
+
+    /// 从子网中分配地址最小的空闲地址，IPv4 子网不包含网络地址和广播地址
+    pub fn allocate_address(&mut self, subnet: IpNet, owner: &str) -> Result<IpAddr> {
+        let leases = &mut self.subnet_mut(&subnet)?.leases;
+        let ip = subnet
+            .hosts()
+            .find(|ip| !leases.contains_key(ip))
+            .ok_or(IpamError::AddressExhausted(subnet))?;
+        leases.insert(ip, Lease::new(owner, false));
+        Ok(ip)
+    }
+
+    /// 释放地址，包括预留的地址
+    pub fn release_address(&mut self, ip: IpAddr) -> Result<Lease> {
+        let (cidr, _) = self.find_subnet(ip).ok_or(IpamError::NoSubnet(ip))?;
+        self.subnet_mut(&cidr)?
+            .leases
+            .remove(&ip)
+            .ok_or(IpamError::AddressNotFound(ip))
+    }
+
+    /// 地址所在的已分配子网
+    pub fn find_subnet(&self, ip: IpAddr) -> Option<(IpNet, &Subnet)> {
+        self.pools.values().find_map(|pool| {
+            pool.subnets
+                .iter()
+                .find(|(cidr, _)| cidr.contains(&ip))
+                .map(|(cidr, subnet)| (*cidr, subnet))
+        })
+    }
+
+    /// 检查地址池、子网、地址之间的冲突
+    pub fn validate(&self) -> Result<()> {
+        let pools: Vec<&Pool> = self.pools.values().collect();
+        for (i, pool) in pools.iter().enumerate() {
+            if let Some(other) = pools[i + 1..]
+                .iter()
+                .find(|other| overlaps(&pool.cidr, &other.cidr))
+            {
+                return Err(IpamError::Overlap(pool.cidr, other.cidr));
+            }
+
+            let subnets: Vec<&IpNet> = pool.subnets.keys().collect();
+            for (j, cidr) in subnets.iter().enumerate() {
+                if !pool.cidr.contains(*cidr) {
+                    return Err(IpamError::OutOfPool(**cidr, pool.cidr));
+                }
+                if let Some(other) = subnets[j + 1..].iter().find(|other| overlaps(cidr, other)) {
+                    return Err(IpamError::Overlap(**cidr, **other));
+                }
+                let outside = pool.subnets[*cidr]
+                    .leases
+                    .keys()
+                    .find(|ip| !cidr.contains(*ip));
+                if let Some(ip) = outside {
+                    return Err(IpamError::NoSubnet(*ip));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 从 JSON 文件加载，文件不存在时返回空的状态
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let ipam: Ipam = serde_json::from_str(&text)?;
+        ipam.validate()?;
+        Ok(ipam)
+    }
+
+    /// 保存到 JSON 文件，文件在加载之后被其他人修改过时返回 StaleRevision
+    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let actual = serde_json::from_str::<Revision>(&text)?.revision;
+                if actual != self.revision {
+                    return Err(IpamError::StaleRevision {
+                        expected: self.revision,
+                        actual,
+                    });
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        self.revision += 1;
+        let json = serde_json::to_string_pretty(self)?;
+        // 先写临时文件再重命名，避免写到一半时留下损坏的文件
+        let mut temp = PathBuf::from(path);
+        temp.as_mut_os_string().push(".tmp");
+        let result = fs::write(&temp, json).and_then(|_| fs::rename(&temp, path));
+        if let Err(e) = result {
+            self.revision -= 1;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    fn pool_mut(&mut self, name: &str) -> Result<&mut Pool> {
+        self.pools
+            .get_mut(name)
+            .ok_or_else(|| IpamError::PoolNotFound(name.to_string()))
+    }
+
+    fn subnet_mut(&mut self, cidr: &IpNet) -> Result<&mut Subnet> {
+        self.pools
+            .values_mut()
+            .find_map(|pool| pool.subnets.get_mut(cidr))
+            .ok_or(IpamError::SubnetNotFound(*cidr))
+    }
+
+    fn insert_lease(&mut self, ip: IpAddr, owner: &str, reserved: bool) -> Result<()> {
+        let (cidr, _) = self.find_subnet(ip).ok_or(IpamError::NoSubnet(ip))?;
+        let leases = &mut self.subnet_mut(&cidr)?.leases;
+        if let Some(lease) = leases.get(&ip) {
+            return Err(IpamError::AddressInUse(ip, lease.owner.clone()));
+        }
+        leases.insert(ip, Lease::new(owner, reserved));
+        Ok(())
+    }
+}
+
+impl Subnet {
+    fn new(owner: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            leases: BTreeMap::new(),
+        }
+    }
+}
+
+impl Lease {
+    fn new(owner: &str, reserved: bool) -> Self {
+        Self {
+            owner: owner.to_string(),
+            reserved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod ipam_test {
+    use std::net::IpAddr;
+
+    use ipnet::IpNet;
+
+    use super::{Ipam, IpamError, aggregate, difference, summarize};
+
+    fn net(text: &str) -> IpNet {
+        text.parse().unwrap()
+    }
+
+    fn nets(texts: &[&str]) -> Vec<IpNet> {
+        texts.iter().map(|text| net(text)).collect()
+    }
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn aggregate_and_summarize() {
+        let list = nets(&[
+            "10.0.0.0/25",
+            "10.0.0.128/25",
+            "10.0.1.0/24",
+            "10.0.3.0/24",
+            "fd00::/65",
+            "fd00:0:0:0:8000::/65",
+        ]);
+        assert_eq!(
+            aggregate(&list),
+            nets(&["10.0.0.0/23", "10.0.3.0/24", "fd00::/64"])
+        );
+        assert_eq!(summarize(&list[..4]), Some(net("10.0.0.0/22")));
+        assert_eq!(summarize(&list[4..]), Some(net("fd00::/64")));
+        // 同时包含 IPv4 和 IPv6 时没有汇总路由
+        assert_eq!(summarize(&list), None);
+    }
+
+    #[test]
+    fn set_difference() {
+        assert_eq!(
+            difference(&nets(&["10.0.0.0/24"]), &nets(&["10.0.0.64/26"])),
+            nets(&["10.0.0.0/26", "10.0.0.128/25"])
+        );
+        assert_eq!(
+            difference(&nets(&["10.0.0.0/24"]), &nets(&["10.0.0.0/16"])),
+            vec![]
+        );
+        assert_eq!(
+            difference(&nets(&["fd00::/48"]), &nets(&["fd00::/49", "10.0.0.0/8"])),
+            nets(&["fd00:0:0:8000::/49"])
+        );
+    }
+
+    #[test]
+    fn allocate_subnets() {
+        let mut ipam = Ipam::new();
+        ipam.add_pool("lab", net("10.1.0.0/22")).unwrap();
+        assert!(matches!(
+            ipam.add_pool("other", net("10.1.2.0/24")),
+            Err(IpamError::Overlap(..))
+        ));
+
+        let a = ipam.allocate_subnet("lab", 24, "alice").unwrap();
+        let b = ipam.allocate_subnet("lab", 25, "bob").unwrap();
+        assert_eq!(a, net("10.1.0.0/24"));
+        assert_eq!(b, net("10.1.1.0/25"));
+        // 最佳适配：/25 优先使用 bob 剩下的另一半
+        assert_eq!(
+            ipam.allocate_subnet("lab", 25, "carol").unwrap(),
+            net("10.1.1.128/25")
+        );
+        assert!(matches!(
+            ipam.claim_subnet("lab", net("10.1.0.128/25"), "dave"),
+            Err(IpamError::Overlap(..))
+        ));
+        ipam.claim_subnet("lab", net("10.1.3.0/24"), "dave")
+            .unwrap();
+        ipam.allocate_subnet("lab", 24, "erin").unwrap();
+        assert!(matches!(
+            ipam.allocate_subnet("lab", 24, "frank"),
+            Err(IpamError::Exhausted { .. })
+        ));
+
+        ipam.release_subnet(a).unwrap();
+        assert_eq!(ipam.pool("lab").unwrap().free(), vec![a]);
+        assert_eq!(ipam.allocate_subnet("lab", 24, "frank").unwrap(), a);
+    }
+
+    #[test]
+    fn allocate_addresses() {
+        let mut ipam = Ipam::new();
+        ipam.add_pool("v4", net("192.168.0.0/16")).unwrap();
+        ipam.add_pool("v6", net("fd00:1::/48")).unwrap();
+
+        let subnet = ipam.allocate_subnet("v4", 30, "ops").unwrap();
+        ipam.reserve(ip("192.168.0.1"), "gateway").unwrap();
+        assert!(matches!(
+            ipam.claim_address(ip("192.168.0.1"), "web"),
+            Err(IpamError::AddressInUse(..))
+        ));
+        assert_eq!(
+            ipam.allocate_address(subnet, "web").unwrap(),
+            ip("192.168.0.2")
+        );
+        assert!(matches!(
+            ipam.allocate_address(subnet, "db"),
+            Err(IpamError::AddressExhausted(_))
+        ));
+        ipam.release_address(ip("192.168.0.2")).unwrap();
+        assert_eq!(
+            ipam.allocate_address(subnet, "db").unwrap(),
+            ip("192.168.0.2")
+        );
+
+        let subnet6 = ipam.allocate_subnet("v6", 64, "ops").unwrap();
+        ipam.reserve(ip("fd00:1::"), "router").unwrap();
+        assert_eq!(
+            ipam.allocate_address(subnet6, "web").unwrap(),
+            ip("fd00:1::1")
+        );
+        assert!(matches!(
+            ipam.reserve(ip("fd00:2::1"), "nowhere"),
+            Err(IpamError::NoSubnet(_))
+        ));
+    }
+
+    #[test]
+    fn persist_and_detect_conflicts() {
+        let path = std::env::temp_dir().join(format!("ipam_test_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut ipam = Ipam::load(&path).unwrap();
+        ipam.add_pool("lab", net("10.2.0.0/16")).unwrap();
+        let subnet = ipam.allocate_subnet("lab", 24, "alice").unwrap();
+        ipam.reserve(ip("10.2.0.1"), "gateway").unwrap();
+        ipam.save(&path).unwrap();
+
+        let mut first = Ipam::load(&path).unwrap();
+        let mut second = Ipam::load(&path).unwrap();
+        assert_eq!(first, ipam);
+        first.allocate_address(subnet, "web").unwrap();
+        first.save(&path).unwrap();
+        // second 加载之后文件被 first 修改过，不能直接覆盖
+        second.allocate_address(subnet, "db").unwrap();
+        assert!(matches!(
+            second.save(&path),
+            Err(IpamError::StaleRevision {
+                expected: 1,
+                actual: 2
+            })
+        ));
+
+        // 手工编辑出重叠的子网，加载时报错
+        let text = std::fs::read_to_string(&path).unwrap().replace(
+            "\"10.2.0.0/24\"",
+            "\"10.2.0.0/24\": {\"owner\": \"x\"}, \"10.2.0.0/25\"",
+        );
+        std::fs::write(&path, text).unwrap();
+        assert!(matches!(Ipam::load(&path), Err(IpamError::Overlap(..))));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,15 @@
+pub mod discovery;
 pub mod eventsource_demo;
+pub mod http1;
+pub mod http_client;
 pub mod http_types;
+pub mod inventory;
+pub mod ipam;
 pub mod ipnet_demo;
 pub mod netdev_demo;
+pub mod proxy;
 pub mod reqwest_demo;
+pub mod stun;
 pub mod stunclient_demo;
 pub mod tcp;
 pub mod udp;
@@ -0,0 +1,162 @@
+/**
+ * 转发规则配置，使用 TOML 格式：
+ *
+ * ```toml
+ * [[rule]]
+ * name = "web"
+ * protocol = "tcp"
+ * listen = "127.0.0.1:8080"
+ * target = "10.0.0.5:80"
+ * hexdump = true
+ *
+ * [[rule]]
+ * name = "dns"
+ * protocol = "udp"
+ * listen = "127.0.0.1:5353"
+ * target = "10.0.0.53:53"
+ * idle_timeout = 30
+ * max_sessions = 256
+ * ```
+ *
+ * target 可以是域名：TCP 每次建立连接时重新解析；UDP 只在规则启动时解析一次，之后域名的变化不会生效，除非修改这条规则或者重启代理。
+ * idle_timeout 是 UDP 会话的空闲超时时间（秒），max_sessions 是 UDP 会话数的上限，会话表满时丢弃新客户端的数据报。
+ */
+use std::{collections::HashSet, net::SocketAddr, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::ProxyError;
+
+/// 没有配置 idle_timeout 时 UDP 会话的空闲超时时间
+const DEFAULT_IDLE_TIMEOUT: u64 = 60;
+/// 没有配置 max_sessions 时 UDP 会话数的上限，每个会话占用一个 socket
+const DEFAULT_MAX_SESSIONS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub protocol: Protocol,
+    pub listen: SocketAddr,
+    pub target: String,
+    /// 是否以十六进制打印转发的数据
+    #[serde(default)]
+    pub hexdump: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_sessions: Option<usize>,
+}
+
+impl Rule {
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout.unwrap_or(DEFAULT_IDLE_TIMEOUT))
+    }
+
+    pub fn max_sessions(&self) -> usize {
+        self.max_sessions.unwrap_or(DEFAULT_MAX_SESSIONS)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+impl ProxyConfig {
+    pub fn from_toml(text: &str) -> Result<Self, ProxyError> {
+        let config: ProxyConfig = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProxyError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// 规则名称不能重复，同一个协议的监听地址不能重复（端口为 0 的除外）
+    pub fn validate(&self) -> Result<(), ProxyError> {
+        let mut names = HashSet::new();
+        let mut listens = HashSet::new();
+        for rule in &self.rules {
+            if !names.insert(rule.name.as_str()) {
+                return Err(ProxyError::Invalid(format!("规则名称{}重复", rule.name)));
+            }
+            if rule.listen.port() != 0 && !listens.insert((rule.protocol, rule.listen)) {
+                return Err(ProxyError::Invalid(format!(
+                    "规则{}的监听地址{}重复",
+                    rule.name, rule.listen
+                )));
+            }
+            if rule.idle_timeout == Some(0) {
+                return Err(ProxyError::Invalid(format!(
+                    "规则{}的 idle_timeout 必须大于 0",
+                    rule.name
+                )));
+            }
+            if rule.max_sessions == Some(0) {
+                return Err(ProxyError::Invalid(format!(
+                    "规则{}的 max_sessions 必须大于 0",
+                    rule.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod config_test {
+    use std::time::Duration;
+
+    use super::{Protocol, ProxyConfig};
+
+    #[test]
+    fn parse_rules() {
+        let config = ProxyConfig::from_toml(
+            r#"
+            [[rule]]
+            name = "web"
+            protocol = "tcp"
+            listen = "127.0.0.1:8080"
+            target = "example.com:80"
+            hexdump = true
+
+            [[rule]]
+            name = "dns"
+            protocol = "udp"
+            listen = "127.0.0.1:5353"
+            target = "10.0.0.53:53"
+            idle_timeout = 30
+            max_sessions = 256
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 2);
+        assert_eq!(config.rules[0].protocol, Protocol::Tcp);
+        assert!(config.rules[0].hexdump);
+        assert_eq!(config.rules[0].idle_timeout(), Duration::from_secs(60));
+        assert_eq!(config.rules[1].idle_timeout(), Duration::from_secs(30));
+        assert_eq!(config.rules[0].max_sessions(), 1024);
+        assert_eq!(config.rules[1].max_sessions(), 256);
+    }
+
+    #[test]
+    fn reject_duplicates() {
+        let rule = |name: &str| {
+            format!(
+                "[[rule]]\nname = \"{name}\"\nprotocol = \"tcp\"\nlisten = \"127.0.0.1:9000\"\ntarget = \"127.0.0.1:80\"\n"
+            )
+        };
+        assert!(ProxyConfig::from_toml(&(rule("a") + &rule("a"))).is_err());
+        assert!(ProxyConfig::from_toml(&(rule("a") + &rule("b"))).is_err());
+        assert!(ProxyConfig::from_toml("[[rule]]\nname = \"a\"").is_err());
+    }
+}
@@ -0,0 +1,333 @@
+/**
+ * 端口转发代理
+ *
+ * 把本地端口转发到实验环境中的服务，同时观察流量：
+ *  1、tcp：每个连接双向复制数据，支持半关闭。
+ *  2、udp：按客户端地址维护会话表，空闲超时后回收。
+ *  3、stats：每条规则的连接数、活跃连接数、双向字节数，开启 hexdump 的规则会打印转发的数据。
+ *  4、config：TOML 格式的规则文件，watch 定期检查文件内容，变化时只重启新增和修改过的规则，未修改的规则和已经建立的连接不受影响。
+ *
+ * 规则被停止时，TCP 已经建立的连接会继续转发直到关闭，UDP 的会话会立即被回收。
+ */
+pub mod config;
+pub mod stats;
+mod tcp;
+mod udp;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::{
+    net::{TcpListener, UdpSocket, lookup_host},
+    sync::Mutex,
+    task::JoinHandle,
+};
+
+use config::{Protocol, ProxyConfig, Rule};
+use stats::{Stats, StatsSnapshot};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyError {
+    #[error("读取配置文件失败: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("配置文件格式错误: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("配置无效: {0}")]
+    Invalid(String),
+}
+
+/// 一次加载配置的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    pub started: Vec<String>,
+    pub stopped: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// 启动失败的规则和原因，例如监听端口被占用
+    pub failed: Vec<(String, String)>,
+}
+
+struct Running {
+    rule: Rule,
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct Proxy {
+    running: HashMap<String, Running>,
+    /// 统计按规则名称保存，规则修改后重启不会清零
+    stats: HashMap<String, Arc<Stats>>,
+}
+
+impl Proxy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 让正在运行的规则和配置一致
+    pub async fn apply(&mut self, config: &ProxyConfig) -> ReloadSummary {
+        let mut summary = ReloadSummary::default();
+        let wanted: HashMap<&str, &Rule> = config
+            .rules
+            .iter()
+            .map(|rule| (rule.name.as_str(), rule))
+            .collect();
+
+        // 先停止删除和修改过的规则，释放监听端口之后再启动新的规则
+        let stale: Vec<String> = self
+            .running
+            .iter()
+            .filter(|(name, running)| wanted.get(name.as_str()) != Some(&&running.rule))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in stale {
+            self.stop(&name).await;
+            summary.stopped.push(name);
+        }
+        self.stats
+            .retain(|name, _| wanted.contains_key(name.as_str()));
+
+        for rule in &config.rules {
+            if self.running.contains_key(&rule.name) {
+                summary.unchanged.push(rule.name.clone());
+                continue;
+            }
+            match self.start(rule).await {
+                Ok(()) => summary.started.push(rule.name.clone()),
+                Err(e) => {
+                    tracing::error!("启动规则{}失败: {e}", rule.name);
+                    summary.failed.push((rule.name.clone(), e.to_string()));
+                }
+            }
+        }
+        summary
+    }
+
+    /// 规则实际监听的地址，配置中端口为 0 时由系统分配
+    pub fn local_addr(&self, name: &str) -> Option<SocketAddr> {
+        self.running.get(name).map(|running| running.local_addr)
+    }
+
+    pub fn stats(&self) -> BTreeMap<String, StatsSnapshot> {
+        self.stats
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.snapshot()))
+            .collect()
+    }
+
+    pub async fn shutdown(&mut self) {
+        let names: Vec<String> = self.running.keys().cloned().collect();
+        for name in names {
+            self.stop(&name).await;
+        }
+    }
+
+    async fn start(&mut self, rule: &Rule) -> std::io::Result<()> {
+        let stats = self.stats.entry(rule.name.clone()).or_default().clone();
+        let shared = Arc::new(rule.clone());
+        let (local_addr, task) = match rule.protocol {
+            Protocol::Tcp => {
+                let listener = TcpListener::bind(rule.listen).await?;
+                let local_addr = listener.local_addr()?;
+                (
+                    local_addr,
+                    tokio::spawn(tcp::serve(listener, shared, stats)),
+                )
+            }
+            Protocol::Udp => {
+                let target = lookup_host(&rule.target)
+                    .await?
+                    .next()
+                    .ok_or_else(|| std::io::Error::other(format!("无法解析{}", rule.target)))?;
+                let socket = UdpSocket::bind(rule.listen).await?;
+                let local_addr = socket.local_addr()?;
+                (
+                    local_addr,
+                    tokio::spawn(udp::serve(socket, target, shared, stats)),
+                )
+            }
+        };
+        tracing::info!(
+            "规则{}已启动: {:?} {local_addr} -> {}",
+            rule.name,
+            rule.protocol,
+            rule.target
+        );
+        self.running.insert(
+            rule.name.clone(),
+            Running {
+                rule: rule.clone(),
+                local_addr,
+                task,
+            },
+        );
+        Ok(())
+    }
+
+    async fn stop(&mut self, name: &str) {
+        if let Some(running) = self.running.remove(name) {
+            running.task.abort();
+            // 等待任务真正结束，监听 socket 被关闭后端口才能被重新绑定
+            let _ = running.task.await;
+            tracing::info!("规则{name}已停止");
+        }
+    }
+}
+
+/// 定期检查配置文件，内容变化时重新加载；新的配置无效时保留当前的规则，
+/// 有规则启动失败（例如端口被占用）时下次检查会再次加载同样的内容，直到全部启动成功
+pub fn watch(proxy: Arc<Mutex<Proxy>>, path: PathBuf, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last: Option<String> = None;
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let text = match tokio::fs::read_to_string(&path).await {
+                Ok(text) => text,
+                Err(e) => {
+                    tracing::warn!("读取{}失败: {e}", path.display());
+                    continue;
+                }
+            };
+            if last.as_ref() == Some(&text) {
+                continue;
+            }
+            match ProxyConfig::from_toml(&text) {
+                Ok(config) => {
+                    let summary = proxy.lock().await.apply(&config).await;
+                    tracing::info!("重新加载{}: {summary:?}", path.display());
+                    if !summary.failed.is_empty() {
+                        continue;
+                    }
+                }
+                Err(e) => tracing::error!("{}无效，保留当前规则: {e}", path.display()),
+            }
+            last = Some(text);
+        }
+    })
+}
+
+#[cfg(test)]
+mod proxy_test {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::Mutex,
+    };
+
+    use super::{Proxy, watch};
+
+    /// 接受连接后写入固定内容并关闭的服务端
+    async fn banner_server(banner: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.write_all(banner.as_bytes()).await;
+            }
+        });
+        addr
+    }
+
+    fn config(target: &str) -> String {
+        format!(
+            "[[rule]]\nname = \"lab\"\nprotocol = \"tcp\"\nlisten = \"127.0.0.1:0\"\ntarget = \"{target}\"\n\n\
+             [[rule]]\nname = \"dns\"\nprotocol = \"udp\"\nlisten = \"127.0.0.1:0\"\ntarget = \"127.0.0.1:53\"\n"
+        )
+    }
+
+    async fn fetch(proxy: &Arc<Mutex<Proxy>>) -> String {
+        let addr = proxy.lock().await.local_addr("lab").unwrap();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut text = String::new();
+        stream.read_to_string(&mut text).await.unwrap();
+        text
+    }
+
+    /// 等待 watch 加载新的配置，规则重启后监听地址会变化
+    async fn wait_reload(proxy: &Arc<Mutex<Proxy>>, old: Option<std::net::SocketAddr>) {
+        for _ in 0..100 {
+            let addr = proxy.lock().await.local_addr("lab");
+            if addr.is_some() && addr != old {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("配置没有被重新加载");
+    }
+
+    #[tokio::test]
+    async fn hot_reload_rules() {
+        let first = banner_server("first").await;
+        let second = banner_server("second").await;
+        let path = std::env::temp_dir().join(format!("proxy_test_{}.toml", std::process::id()));
+        std::fs::write(&path, config(&first)).unwrap();
+
+        let proxy = Arc::new(Mutex::new(Proxy::new()));
+        let watcher = watch(proxy.clone(), path.clone(), Duration::from_millis(20));
+        wait_reload(&proxy, None).await;
+        assert_eq!(fetch(&proxy).await, "first");
+        let dns = proxy.lock().await.local_addr("dns");
+        assert!(dns.is_some());
+
+        // 只修改了 lab 规则，dns 规则保持运行
+        let old = proxy.lock().await.local_addr("lab");
+        std::fs::write(&path, config(&second)).unwrap();
+        wait_reload(&proxy, old).await;
+        assert_eq!(fetch(&proxy).await, "second");
+        assert_eq!(proxy.lock().await.local_addr("dns"), dns);
+
+        // 无效的配置不会影响正在运行的规则
+        let old = proxy.lock().await.local_addr("lab");
+        std::fs::write(&path, "[[rule]]\nname = ").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(proxy.lock().await.local_addr("lab"), old);
+        assert_eq!(fetch(&proxy).await, "second");
+
+        // 统计在规则重启之后保留
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let stats = proxy.lock().await.stats();
+        assert_eq!(stats["lab"].total, 3);
+        assert_eq!(stats["lab"].downstream_bytes, 5 + 6 + 6);
+
+        watcher.abort();
+        proxy.lock().await.shutdown().await;
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// 规则因为端口被占用启动失败时，文件内容不变也会在下次检查时重试
+    #[tokio::test]
+    async fn retry_failed_rule() {
+        let target = banner_server("retry").await;
+        let blocker = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen = blocker.local_addr().unwrap();
+        let path = std::env::temp_dir().join(format!("proxy_retry_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            format!(
+                "[[rule]]\nname = \"lab\"\nprotocol = \"tcp\"\nlisten = \"{listen}\"\ntarget = \"{target}\"\n"
+            ),
+        )
+        .unwrap();
+
+        let proxy = Arc::new(Mutex::new(Proxy::new()));
+        let watcher = watch(proxy.clone(), path.clone(), Duration::from_millis(20));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(proxy.lock().await.local_addr("lab").is_none());
+
+        drop(blocker);
+        wait_reload(&proxy, None).await;
+        assert_eq!(fetch(&proxy).await, "retry");
+
+        watcher.abort();
+        proxy.lock().await.shutdown().await;
+        std::fs::remove_file(&path).unwrap();
+    }
+}
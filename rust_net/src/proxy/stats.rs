@@ -0,0 +1,136 @@
+/**
+ * 转发统计和十六进制打印
+ *
+ * 计数器使用原子变量，转发过程中实时更新，随时可以读取快照。
+ * upstream 表示客户端 -> 目标的方向，downstream 表示目标 -> 客户端的方向。
+ */
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// TCP 为连接数，UDP 为会话数
+    total: AtomicU64,
+    active: AtomicU64,
+    upstream_bytes: AtomicU64,
+    downstream_bytes: AtomicU64,
+    /// 连接目标失败的次数
+    errors: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StatsSnapshot {
+    pub total: u64,
+    pub active: u64,
+    pub upstream_bytes: u64,
+    pub downstream_bytes: u64,
+    pub errors: u64,
+}
+
+impl Stats {
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            total: self.total.load(Ordering::Relaxed),
+            active: self.active.load(Ordering::Relaxed),
+            upstream_bytes: self.upstream_bytes.load(Ordering::Relaxed),
+            downstream_bytes: self.downstream_bytes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn opened(&self) {
+        self.total.fetch_add(1, Ordering::Relaxed);
+        self.active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn closed(&self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn transferred(&self, direction: Direction, bytes: usize) {
+        let counter = match direction {
+            Direction::Upstream => &self.upstream_bytes,
+            Direction::Downstream => &self.downstream_bytes,
+        };
+        counter.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Upstream,
+    Downstream,
+}
+
+impl Direction {
+    fn arrow(self) -> &'static str {
+        match self {
+            Direction::Upstream => "->",
+            Direction::Downstream => "<-",
+        }
+    }
+}
+
+/// 按照 `hexdump -C` 的格式输出：偏移量、16 个字节的十六进制、可打印字符
+pub fn hexdump(data: &[u8]) -> String {
+    let mut output = String::new();
+    for (index, line) in data.chunks(16).enumerate() {
+        let hex = hex::encode(line);
+        let mut bytes = String::new();
+        for (i, pair) in hex.as_bytes().chunks(2).enumerate() {
+            if i == 8 {
+                bytes.push(' ');
+            }
+            bytes.push_str(std::str::from_utf8(pair).unwrap());
+            bytes.push(' ');
+        }
+        let text: String = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        output.push_str(&format!("{:08x}  {bytes:<49} |{text}|\n", index * 16));
+    }
+    output
+}
+
+/// 开启了 hexdump 的规则通过 tracing 打印转发的数据
+pub(crate) fn log_data(rule: &str, peer: &str, direction: Direction, data: &[u8]) {
+    tracing::info!(
+        "[{rule}] {peer} {} {} 字节\n{}",
+        direction.arrow(),
+        data.len(),
+        hexdump(data)
+    );
+}
+
+#[cfg(test)]
+mod stats_test {
+    use super::hexdump;
+
+    #[test]
+    fn hexdump_format() {
+        let dump = hexdump(b"GET / HTTP/1.1\r\nHost: a\r\n");
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "00000000  47 45 54 20 2f 20 48 54  54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|"
+        );
+        assert_eq!(
+            lines[1],
+            "00000010  48 6f 73 74 3a 20 61 0d  0a                       |Host: a..|"
+        );
+        assert!(hexdump(&[]).is_empty());
+    }
+}
@@ -0,0 +1,206 @@
+/**
+ * TCP 转发：每个客户端连接对应一个到目标的连接，两个方向各有一个循环负责复制数据
+ *
+ * 一个方向读到 EOF 后关闭另一端的写方向（半关闭），两个方向都结束后连接才算关闭。
+ */
+use std::{net::SocketAddr, sync::Arc};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        TcpListener, TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+};
+
+use super::{
+    config::Rule,
+    stats::{Direction, Stats, log_data},
+};
+
+pub(crate) async fn serve(listener: TcpListener, rule: Arc<Rule>, stats: Arc<Stats>) {
+    loop {
+        let (client, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::error!("[{}] 接受连接失败: {e}", rule.name);
+                continue;
+            }
+        };
+        let rule = rule.clone();
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            let upstream = match TcpStream::connect(&rule.target).await {
+                Ok(upstream) => upstream,
+                Err(e) => {
+                    stats.error();
+                    tracing::warn!("[{}] 连接目标{}失败: {e}", rule.name, rule.target);
+                    return;
+                }
+            };
+            tracing::debug!("[{}] {peer} -> {}", rule.name, rule.target);
+            stats.opened();
+            forward(client, upstream, peer, &rule, &stats).await;
+            stats.closed();
+            tracing::debug!("[{}] {peer} 连接关闭", rule.name);
+        });
+    }
+}
+
+async fn forward(
+    client: TcpStream,
+    upstream: TcpStream,
+    peer: SocketAddr,
+    rule: &Rule,
+    stats: &Stats,
+) {
+    let (client_read, client_write) = client.into_split();
+    let (upstream_read, upstream_write) = upstream.into_split();
+    let peer = peer.to_string();
+    tokio::join!(
+        pump(
+            client_read,
+            upstream_write,
+            Direction::Upstream,
+            &peer,
+            rule,
+            stats
+        ),
+        pump(
+            upstream_read,
+            client_write,
+            Direction::Downstream,
+            &peer,
+            rule,
+            stats
+        ),
+    );
+}
+
+async fn pump(
+    mut reader: OwnedReadHalf,
+    mut writer: OwnedWriteHalf,
+    direction: Direction,
+    peer: &str,
+    rule: &Rule,
+    stats: &Stats,
+) {
+    let mut buf = vec![0; 16 * 1024];
+    loop {
+        let length = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(length) => length,
+        };
+        if rule.hexdump {
+            log_data(&rule.name, peer, direction, &buf[..length]);
+        }
+        if writer.write_all(&buf[..length]).await.is_err() {
+            break;
+        }
+        stats.transferred(direction, length);
+    }
+    let _ = writer.shutdown().await;
+}
+
+#[cfg(test)]
+mod tcp_test {
+    use std::sync::Arc;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::serve;
+    use crate::proxy::{
+        config::{Protocol, Rule},
+        stats::Stats,
+    };
+
+    /// 回显服务端，返回监听地址
+    async fn echo_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = stream.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn forward_and_count() {
+        let target = echo_server().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let rule = Arc::new(Rule {
+            name: "echo".to_string(),
+            protocol: Protocol::Tcp,
+            listen: proxy_addr,
+            target,
+            hexdump: true,
+            idle_timeout: None,
+            max_sessions: None,
+        });
+        let stats = Arc::new(Stats::default());
+        let task = tokio::spawn(serve(listener, rule, stats.clone()));
+
+        let mut client = TcpStream::connect(proxy_addr).await.unwrap();
+        client.write_all(b"hello proxy").await.unwrap();
+        // 半关闭写方向，回显服务端读到 EOF 后也会关闭，客户端可以一直读到结束
+        client.shutdown().await.unwrap();
+        let mut echoed = Vec::new();
+        client.read_to_end(&mut echoed).await.unwrap();
+        assert_eq!(echoed, b"hello proxy");
+
+        // 等待转发任务退出
+        for _ in 0..50 {
+            if stats.snapshot().active == 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total, 1);
+        assert_eq!(snapshot.active, 0);
+        assert_eq!(snapshot.upstream_bytes, 11);
+        assert_eq!(snapshot.downstream_bytes, 11);
+
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn count_target_errors() {
+        // 先占用一个端口再释放，连接这个端口会被拒绝
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = closed.local_addr().unwrap().to_string();
+        drop(closed);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = listener.local_addr().unwrap();
+        let rule = Arc::new(Rule {
+            name: "broken".to_string(),
+            protocol: Protocol::Tcp,
+            listen: proxy_addr,
+            target,
+            hexdump: false,
+            idle_timeout: None,
+            max_sessions: None,
+        });
+        let stats = Arc::new(Stats::default());
+        let task = tokio::spawn(serve(listener, rule, stats.clone()));
+
+        let mut client = TcpStream::connect(proxy_addr).await.unwrap();
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert!(buf.is_empty());
+        assert_eq!(stats.snapshot().errors, 1);
+        assert_eq!(stats.snapshot().total, 0);
+
+        task.abort();
+    }
+}
@@ -0,0 +1,272 @@
+/**
+ * UDP 转发：按照客户端地址维护会话表，类似 NAT
+ *
+ *  1、收到新客户端的数据报时创建会话：绑定一个新的本地端口并 connect 到目标，之后该客户端的数据报都从这个端口发出。
+ *  2、每个会话有一个任务接收目标的响应，再通过监听 socket 发回给对应的客户端。
+ *  3、会话在 idle_timeout 内两个方向都没有数据时被回收，会话任务随之结束。
+ *  4、会话数达到 max_sessions 时丢弃新客户端的数据报并计为错误，已有的会话不受影响，避免伪造来源地址的数据报耗尽文件描述符。
+ */
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{net::UdpSocket, task::JoinHandle};
+
+use super::{
+    config::Rule,
+    stats::{Direction, Stats, log_data},
+};
+
+struct Session {
+    upstream: Arc<UdpSocket>,
+    last_active: Arc<Mutex<Instant>>,
+    task: JoinHandle<()>,
+    stats: Arc<Stats>,
+}
+
+/// 会话被回收或者会话表被丢弃（规则被停止）时，结束会话任务
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.stats.closed();
+    }
+}
+
+/// target 在启动规则时解析一次，接收循环中不做域名解析
+pub(crate) async fn serve(
+    socket: UdpSocket,
+    target: SocketAddr,
+    rule: Arc<Rule>,
+    stats: Arc<Stats>,
+) {
+    let socket = Arc::new(socket);
+    let mut sessions: HashMap<SocketAddr, Session> = HashMap::new();
+    let timeout = rule.idle_timeout();
+    let max_sessions = rule.max_sessions();
+    let mut sweep = tokio::time::interval((timeout / 2).max(Duration::from_millis(10)));
+    let mut buf = vec![0; 65536];
+
+    loop {
+        let (length, peer) = tokio::select! {
+            received = socket.recv_from(&mut buf) => match received {
+                Ok(received) => received,
+                Err(e) => {
+                    // Windows 上对端不可达时 recv_from 会返回错误，忽略即可
+                    tracing::debug!("[{}] 接收失败: {e}", rule.name);
+                    continue;
+                }
+            },
+            _ = sweep.tick() => {
+                sessions.retain(|_, session| session.last_active.lock().unwrap().elapsed() < timeout);
+                continue;
+            }
+        };
+
+        let full = sessions.len() >= max_sessions;
+        let session = match sessions.entry(peer) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(_) if full => {
+                stats.error();
+                tracing::debug!(
+                    "[{}] 会话数达到上限{max_sessions}，丢弃{peer}的数据报",
+                    rule.name
+                );
+                continue;
+            }
+            Entry::Vacant(entry) => {
+                match open_session(&socket, peer, target, &rule, &stats).await {
+                    Ok(session) => entry.insert(session),
+                    Err(e) => {
+                        stats.error();
+                        tracing::warn!("[{}] 为{peer}创建会话失败: {e}", rule.name);
+                        continue;
+                    }
+                }
+            }
+        };
+        *session.last_active.lock().unwrap() = Instant::now();
+        if rule.hexdump {
+            log_data(
+                &rule.name,
+                &peer.to_string(),
+                Direction::Upstream,
+                &buf[..length],
+            );
+        }
+        match session.upstream.send(&buf[..length]).await {
+            Ok(_) => stats.transferred(Direction::Upstream, length),
+            Err(e) => tracing::debug!("[{}] 向目标发送失败: {e}", rule.name),
+        }
+    }
+}
+
+async fn open_session(
+    socket: &Arc<UdpSocket>,
+    peer: SocketAddr,
+    target: SocketAddr,
+    rule: &Arc<Rule>,
+    stats: &Arc<Stats>,
+) -> std::io::Result<Session> {
+    let bind: SocketAddr = if target.is_ipv4() {
+        "0.0.0.0:0".parse().unwrap()
+    } else {
+        "[::]:0".parse().unwrap()
+    };
+    let upstream = Arc::new(UdpSocket::bind(bind).await?);
+    upstream.connect(target).await?;
+
+    let last_active = Arc::new(Mutex::new(Instant::now()));
+    let task = tokio::spawn(reply_loop(
+        socket.clone(),
+        upstream.clone(),
+        peer,
+        last_active.clone(),
+        rule.clone(),
+        stats.clone(),
+    ));
+    stats.opened();
+    Ok(Session {
+        upstream,
+        last_active,
+        task,
+        stats: stats.clone(),
+    })
+}
+
+/// 把目标的响应发回给客户端
+async fn reply_loop(
+    socket: Arc<UdpSocket>,
+    upstream: Arc<UdpSocket>,
+    peer: SocketAddr,
+    last_active: Arc<Mutex<Instant>>,
+    rule: Arc<Rule>,
+    stats: Arc<Stats>,
+) {
+    let mut buf = vec![0; 65536];
+    let peer_text = peer.to_string();
+    loop {
+        let length = match upstream.recv(&mut buf).await {
+            Ok(length) => length,
+            Err(e) => {
+                tracing::debug!("[{}] 接收目标响应失败: {e}", rule.name);
+                continue;
+            }
+        };
+        *last_active.lock().unwrap() = Instant::now();
+        if rule.hexdump {
+            log_data(
+                &rule.name,
+                &peer_text,
+                Direction::Downstream,
+                &buf[..length],
+            );
+        }
+        if socket.send_to(&buf[..length], peer).await.is_ok() {
+            stats.transferred(Direction::Downstream, length);
+        }
+    }
+}
+
+#[cfg(test)]
+mod udp_test {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::net::UdpSocket;
+
+    use super::serve;
+    use crate::proxy::{
+        config::{Protocol, Rule},
+        stats::Stats,
+    };
+
+    /// 回显服务端，响应中带上看到的来源地址，用来确认不同客户端使用了不同的会话
+    async fn echo_server() -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 1500];
+            while let Ok((length, peer)) = socket.recv_from(&mut buf).await {
+                let mut reply = buf[..length].to_vec();
+                reply.extend_from_slice(format!("@{peer}").as_bytes());
+                let _ = socket.send_to(&reply, peer).await;
+            }
+        });
+        addr
+    }
+
+    async fn request(client: &UdpSocket, data: &[u8]) -> String {
+        client.send(data).await.unwrap();
+        let mut buf = [0; 1500];
+        let length = tokio::time::timeout(Duration::from_secs(2), client.recv(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        String::from_utf8(buf[..length].to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn sessions_and_timeout() {
+        let target = echo_server().await;
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = socket.local_addr().unwrap();
+        let rule = Rule {
+            name: "udp".to_string(),
+            protocol: Protocol::Udp,
+            listen: proxy_addr,
+            target,
+            hexdump: true,
+            idle_timeout: Some(1),
+            max_sessions: Some(2),
+        };
+        let stats = Arc::new(Stats::default());
+        let target = rule.target.parse().unwrap();
+        let task = tokio::spawn(serve(socket, target, Arc::new(rule), stats.clone()));
+
+        let first = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        first.connect(proxy_addr).await.unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        second.connect(proxy_addr).await.unwrap();
+
+        let a1 = request(&first, b"a").await;
+        let a2 = request(&first, b"b").await;
+        let b1 = request(&second, b"c").await;
+        // 同一个客户端复用会话（目标看到的来源地址相同），不同客户端使用不同的会话
+        let source = |reply: &str| reply.split_once('@').unwrap().1.to_string();
+        assert!(a1.starts_with("a@"));
+        assert_eq!(source(&a1), source(&a2));
+        assert_ne!(source(&a1), source(&b1));
+
+        // 会话表已满，新的客户端被丢弃并计为错误，已有的会话不受影响
+        let third = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        third.connect(proxy_addr).await.unwrap();
+        third.send(b"e").await.unwrap();
+        let mut buf = [0; 1500];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), third.recv(&mut buf))
+                .await
+                .is_err()
+        );
+        assert_eq!(stats.snapshot().errors, 1);
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total, 2);
+        assert_eq!(snapshot.active, 2);
+        assert_eq!(snapshot.upstream_bytes, 3);
+        assert_eq!(
+            snapshot.downstream_bytes,
+            (a1.len() + a2.len() + b1.len()) as u64
+        );
+
+        // 超过空闲时间后会话被回收，再次发送时创建新会话
+        tokio::time::sleep(Duration::from_millis(1600)).await;
+        assert_eq!(stats.snapshot().active, 0);
+        let a3 = request(&first, b"d").await;
+        assert_ne!(source(&a3), source(&a1));
+        assert_eq!(stats.snapshot().total, 3);
+
+        task.abort();
+    }
+}
@@ -0,0 +1,368 @@
+/**
+ * STUN 消息编解码（RFC 5389，以及 RFC 5780 中用于 NAT 行为探测的属性）
+ *
+ *   0                   1                   2                   3
+ *   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+ *  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+ *  |0 0|     STUN Message Type     |         Message Length        |
+ *  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+ *  |                         Magic Cookie                          |
+ *  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+ *  |                     Transaction ID (96 bits)                  |
+ *  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+ *
+ * 消息头之后是若干个 TLV 格式的属性，每个属性的值按 4 字节对齐。
+ * 属性类型小于 0x8000 的属性是"必须理解"的，服务端不认识时需要返回 420 错误并在 UNKNOWN-ATTRIBUTES 中列出。
+ */
+use std::{
+    io::{Error, ErrorKind, Result},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+pub const MAGIC_COOKIE: u32 = 0x2112_A442;
+pub const HEADER_LEN: usize = 20;
+
+pub const BINDING_REQUEST: u16 = 0x0001;
+pub const BINDING_SUCCESS: u16 = 0x0101;
+pub const BINDING_ERROR: u16 = 0x0111;
+
+const MAPPED_ADDRESS: u16 = 0x0001;
+const CHANGE_REQUEST: u16 = 0x0003;
+const ERROR_CODE: u16 = 0x0009;
+const UNKNOWN_ATTRIBUTES: u16 = 0x000A;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const SOFTWARE: u16 = 0x8022;
+const RESPONSE_ORIGIN: u16 = 0x802B;
+const OTHER_ADDRESS: u16 = 0x802C;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attribute {
+    MappedAddress(SocketAddr),
+    XorMappedAddress(SocketAddr),
+    /// RFC 5780：要求服务端从另一个 IP 和/或另一个端口发送响应
+    ChangeRequest {
+        change_ip: bool,
+        change_port: bool,
+    },
+    ErrorCode {
+        code: u16,
+        reason: String,
+    },
+    UnknownAttributes(Vec<u16>),
+    Software(String),
+    /// RFC 5780：响应实际是从哪个地址发出的
+    ResponseOrigin(SocketAddr),
+    /// RFC 5780：服务端的备用地址（IP 和端口都不同）
+    OtherAddress(SocketAddr),
+    Unknown {
+        kind: u16,
+        value: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub kind: u16,
+    pub transaction_id: [u8; 12],
+    pub attributes: Vec<Attribute>,
+}
+
+impl Message {
+    pub fn binding_request() -> Self {
+        Self {
+            kind: BINDING_REQUEST,
+            transaction_id: new_transaction_id(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, attribute: Attribute) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// 优先使用 XOR-MAPPED-ADDRESS，老的服务端只会返回 MAPPED-ADDRESS
+    pub fn mapped_address(&self) -> Option<SocketAddr> {
+        self.attributes
+            .iter()
+            .find_map(|attr| match attr {
+                Attribute::XorMappedAddress(addr) => Some(*addr),
+                _ => None,
+            })
+            .or_else(|| {
+                self.attributes.iter().find_map(|attr| match attr {
+                    Attribute::MappedAddress(addr) => Some(*addr),
+                    _ => None,
+                })
+            })
+    }
+
+    pub fn other_address(&self) -> Option<SocketAddr> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::OtherAddress(addr) => Some(*addr),
+            _ => None,
+        })
+    }
+
+    pub fn change_request(&self) -> Option<(bool, bool)> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::ChangeRequest {
+                change_ip,
+                change_port,
+            } => Some((*change_ip, *change_port)),
+            _ => None,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for attribute in self.attributes.iter() {
+            let (kind, value) = self.encode_attribute(attribute);
+            body.extend_from_slice(&kind.to_be_bytes());
+            body.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            body.extend_from_slice(&value);
+            body.resize(body.len().div_ceil(4) * 4, 0);
+        }
+
+        let mut buf = Vec::with_capacity(HEADER_LEN + body.len());
+        buf.extend_from_slice(&self.kind.to_be_bytes());
+        buf.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        buf.extend_from_slice(&self.transaction_id);
+        buf.extend_from_slice(&body);
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf.len() < HEADER_LEN {
+            return Err(invalid("STUN 消息长度不足"));
+        }
+        let kind = u16::from_be_bytes([buf[0], buf[1]]);
+        if kind & 0xC000 != 0 {
+            return Err(invalid("STUN 消息的最高两位必须为 0"));
+        }
+        let length = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+        if u32::from_be_bytes(buf[4..8].try_into().unwrap()) != MAGIC_COOKIE {
+            return Err(invalid("magic cookie 不匹配"));
+        }
+        if !length.is_multiple_of(4) || buf.len() < HEADER_LEN + length {
+            return Err(invalid("STUN 消息长度不正确"));
+        }
+        let transaction_id: [u8; 12] = buf[8..20].try_into().unwrap();
+
+        let mut attributes = Vec::new();
+        let mut rest = &buf[HEADER_LEN..HEADER_LEN + length];
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return Err(invalid("属性被截断"));
+            }
+            let attr_kind = u16::from_be_bytes([rest[0], rest[1]]);
+            let attr_len = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+            let padded = attr_len.div_ceil(4) * 4;
+            if rest.len() < 4 + attr_len {
+                return Err(invalid("属性被截断"));
+            }
+            let value = &rest[4..4 + attr_len];
+            attributes.push(decode_attribute(attr_kind, value, &transaction_id)?);
+            rest = &rest[(4 + padded).min(rest.len())..];
+        }
+
+        Ok(Self {
+            kind,
+            transaction_id,
+            attributes,
+        })
+    }
+
+    fn encode_attribute(&self, attribute: &Attribute) -> (u16, Vec<u8>) {
+        match attribute {
+            Attribute::MappedAddress(addr) => (MAPPED_ADDRESS, encode_address(*addr)),
+            Attribute::XorMappedAddress(addr) => (
+                XOR_MAPPED_ADDRESS,
+                encode_address(xor_address(*addr, &self.transaction_id)),
+            ),
+            Attribute::ChangeRequest {
+                change_ip,
+                change_port,
+            } => {
+                let flags = (u32::from(*change_ip) << 2) | (u32::from(*change_port) << 1);
+                (CHANGE_REQUEST, flags.to_be_bytes().to_vec())
+            }
+            Attribute::ErrorCode { code, reason } => {
+                let mut value = vec![0, 0, (code / 100) as u8, (code % 100) as u8];
+                value.extend_from_slice(reason.as_bytes());
+                (ERROR_CODE, value)
+            }
+            Attribute::UnknownAttributes(kinds) => (
+                UNKNOWN_ATTRIBUTES,
+                kinds.iter().flat_map(|kind| kind.to_be_bytes()).collect(),
+            ),
+            Attribute::Software(software) => (SOFTWARE, software.as_bytes().to_vec()),
+            Attribute::ResponseOrigin(addr) => (RESPONSE_ORIGIN, encode_address(*addr)),
+            Attribute::OtherAddress(addr) => (OTHER_ADDRESS, encode_address(*addr)),
+            Attribute::Unknown { kind, value } => (*kind, value.clone()),
+        }
+    }
+}
+
+/// 必须理解的属性类型范围是 0x0000-0x7FFF
+pub fn is_comprehension_required(kind: u16) -> bool {
+    kind < 0x8000
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// RFC 5389 要求事务 ID 从 [0, 2^96-1] 中均匀随机选取，使用操作系统的随机数
+fn new_transaction_id() -> [u8; 12] {
+    let mut id = [0; 12];
+    getrandom::fill(&mut id).expect("读取系统随机数失败");
+    id
+}
+
+fn encode_address(addr: SocketAddr) -> Vec<u8> {
+    let mut value = vec![0];
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            value.push(0x01);
+            value.extend_from_slice(&addr.port().to_be_bytes());
+            value.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            value.push(0x02);
+            value.extend_from_slice(&addr.port().to_be_bytes());
+            value.extend_from_slice(&ip.octets());
+        }
+    }
+    value
+}
+
+fn decode_address(value: &[u8]) -> Result<SocketAddr> {
+    if value.len() < 4 {
+        return Err(invalid("地址属性长度不足"));
+    }
+    let port = u16::from_be_bytes([value[2], value[3]]);
+    let ip = match (value[1], value.len()) {
+        (0x01, 8) => IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(&value[4..8]).unwrap())),
+        (0x02, 20) => IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&value[4..20]).unwrap())),
+        _ => return Err(invalid("未知的地址族")),
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// XOR-MAPPED-ADDRESS：端口和 magic cookie 的高 16 位异或，IPv4 和 magic cookie 异或，IPv6 和 magic cookie + 事务ID 异或
+fn xor_address(addr: SocketAddr, transaction_id: &[u8; 12]) -> SocketAddr {
+    let cookie = MAGIC_COOKIE.to_be_bytes();
+    let port = addr.port() ^ (MAGIC_COOKIE >> 16) as u16;
+    let ip = match addr.ip() {
+        IpAddr::V4(ip) => {
+            let mut octets = ip.octets();
+            octets.iter_mut().zip(cookie).for_each(|(b, k)| *b ^= k);
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        IpAddr::V6(ip) => {
+            let mut octets = ip.octets();
+            let key = cookie.iter().chain(transaction_id.iter());
+            octets.iter_mut().zip(key).for_each(|(b, k)| *b ^= k);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+    };
+    SocketAddr::new(ip, port)
+}
+
+fn decode_attribute(kind: u16, value: &[u8], transaction_id: &[u8; 12]) -> Result<Attribute> {
+    let attribute = match kind {
+        MAPPED_ADDRESS => Attribute::MappedAddress(decode_address(value)?),
+        XOR_MAPPED_ADDRESS => {
+            Attribute::XorMappedAddress(xor_address(decode_address(value)?, transaction_id))
+        }
+        CHANGE_REQUEST if value.len() == 4 => {
+            let flags = u32::from_be_bytes(value.try_into().unwrap());
+            Attribute::ChangeRequest {
+                change_ip: flags & 0x4 != 0,
+                change_port: flags & 0x2 != 0,
+            }
+        }
+        ERROR_CODE if value.len() >= 4 => Attribute::ErrorCode {
+            code: (value[2] & 0x7) as u16 * 100 + value[3] as u16,
+            reason: String::from_utf8_lossy(&value[4..]).to_string(),
+        },
+        UNKNOWN_ATTRIBUTES => Attribute::UnknownAttributes(
+            value
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect(),
+        ),
+        SOFTWARE => Attribute::Software(String::from_utf8_lossy(value).to_string()),
+        RESPONSE_ORIGIN => Attribute::ResponseOrigin(decode_address(value)?),
+        OTHER_ADDRESS => Attribute::OtherAddress(decode_address(value)?),
+        _ => Attribute::Unknown {
+            kind,
+            value: value.to_vec(),
+        },
+    };
+    Ok(attribute)
+}
+
+#[cfg(test)]
+mod message_test {
+    use std::net::SocketAddr;
+
+    use super::{Attribute, BINDING_SUCCESS, Message};
+
+    #[test]
+    fn encode_and_decode() {
+        let v4: SocketAddr = "192.0.2.1:32853".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::1]:32853".parse().unwrap();
+        let request = Message::binding_request();
+        let response = Message {
+            kind: BINDING_SUCCESS,
+            transaction_id: request.transaction_id,
+            attributes: vec![
+                Attribute::XorMappedAddress(v4),
+                Attribute::MappedAddress(v6),
+                Attribute::Software("rust_net".to_string()),
+                Attribute::OtherAddress(v6),
+                Attribute::ChangeRequest {
+                    change_ip: true,
+                    change_port: false,
+                },
+            ],
+        };
+        let bytes = response.encode();
+        assert_eq!(bytes.len() % 4, 0);
+
+        let decoded = Message::decode(&bytes).unwrap();
+        assert_eq!(decoded, response);
+        assert_eq!(decoded.mapped_address(), Some(v4));
+        assert_eq!(decoded.change_request(), Some((true, false)));
+    }
+
+    /**
+     * RFC 5769 2.2 中的 IPv4 响应示例（去掉了 MESSAGE-INTEGRITY 和 FINGERPRINT）
+     */
+    #[test]
+    fn rfc5769_xor_mapped_address() {
+        let mut bytes = vec![
+            0x01, 0x01, 0x00, 0x0c, 0x21, 0x12, 0xa4, 0x42, 0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34,
+            0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae,
+        ];
+        bytes.extend_from_slice(&[
+            0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43,
+        ]);
+        let message = Message::decode(&bytes).unwrap();
+        assert_eq!(
+            message.mapped_address(),
+            Some("192.0.2.1:32853".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn reject_invalid() {
+        assert!(Message::decode(&[0; 8]).is_err());
+        let mut bytes = Message::binding_request().encode();
+        bytes[4] = 0;
+        assert!(Message::decode(&bytes).is_err());
+    }
+}
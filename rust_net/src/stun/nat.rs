@@ -0,0 +1,305 @@
+/**
+ * NAT 行为分类（RFC 5780 第 4.3、4.4 节）
+ *
+ * 映射行为（Mapping）：同一个内部地址访问不同的目标时，NAT 是否复用同一个外部映射
+ *  1、测试一：向主地址 A1:P1 发送请求，得到映射地址 M1。M1 等于本地地址说明没有经过 NAT。
+ *  2、测试二：向 A2:P1 发送请求得到 M2，M2 == M1 说明映射与目标无关（Endpoint-Independent）。
+ *  3、测试三：向 A2:P2 发送请求得到 M3，M3 == M2 说明映射只与目标 IP 有关（Address-Dependent），否则与目标 IP 和端口都有关。
+ *
+ * 过滤行为（Filtering）：NAT 允许哪些外部地址的数据报进入已建立的映射
+ *  1、测试二：请求服务端从 A2:P2 响应（改变 IP 和端口），收到响应说明不过滤（Endpoint-Independent）。
+ *  2、测试三：请求服务端从 A1:P2 响应（只改变端口），收到响应说明只按 IP 过滤（Address-Dependent），否则按 IP 和端口过滤。
+ *
+ * 映射与过滤都是 Endpoint-Independent 的 NAT 对 P2P 最友好，两者都是 Address-And-Port-Dependent 的就是常说的对称型 NAT。
+ */
+use std::{
+    io::{Error, ErrorKind, Result},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use tokio::net::UdpSocket;
+
+use super::message::{Attribute, BINDING_SUCCESS, Message};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MappingBehavior {
+    /// 映射地址就是本地地址，没有经过 NAT
+    NoNat,
+    EndpointIndependent,
+    AddressDependent,
+    AddressAndPortDependent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilteringBehavior {
+    EndpointIndependent,
+    AddressDependent,
+    AddressAndPortDependent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NatReport {
+    pub local: SocketAddr,
+    pub mapped: SocketAddr,
+    pub mapping: MappingBehavior,
+    pub filtering: FilteringBehavior,
+}
+
+/// 根据三次映射测试的结果判断映射行为，m2、m3 只有在需要时才会测试
+pub fn classify_mapping(
+    local: SocketAddr,
+    m1: SocketAddr,
+    m2: SocketAddr,
+    m3: Option<SocketAddr>,
+) -> MappingBehavior {
+    if m1 == local {
+        MappingBehavior::NoNat
+    } else if m2 == m1 {
+        MappingBehavior::EndpointIndependent
+    } else if m3 == Some(m2) {
+        MappingBehavior::AddressDependent
+    } else {
+        MappingBehavior::AddressAndPortDependent
+    }
+}
+
+/// 根据两次过滤测试是否收到响应判断过滤行为
+pub fn classify_filtering(change_ip_and_port: bool, change_port: bool) -> FilteringBehavior {
+    if change_ip_and_port {
+        FilteringBehavior::EndpointIndependent
+    } else if change_port {
+        FilteringBehavior::AddressDependent
+    } else {
+        FilteringBehavior::AddressAndPortDependent
+    }
+}
+
+pub struct NatClassifier {
+    server: SocketAddr,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl NatClassifier {
+    /// server 必须是支持 RFC 5780 的 STUN 服务端（响应中带有 OTHER-ADDRESS）
+    pub fn new(server: SocketAddr) -> Self {
+        Self {
+            server,
+            timeout: Duration::from_millis(500),
+            retries: 2,
+        }
+    }
+
+    /// 每次请求的等待时间和重传次数，过滤测试收不到响应时需要等待 timeout * (retries + 1)
+    pub fn with_timeout(mut self, timeout: Duration, retries: u32) -> Self {
+        self.timeout = timeout;
+        self.retries = retries;
+        self
+    }
+
+    /// 使用同一个 socket 完成所有测试，socket 的本地地址就是被测试的内部地址
+    pub async fn classify(&self, socket: &UdpSocket) -> Result<NatReport> {
+        let local = socket.local_addr()?;
+
+        let response = self
+            .transaction(socket, self.server, Message::binding_request())
+            .await?
+            .ok_or_else(|| Error::new(ErrorKind::TimedOut, "STUN 服务端没有响应"))?;
+        let m1 = mapped(&response)?;
+        let other = response.other_address().ok_or_else(|| {
+            Error::new(
+                ErrorKind::Unsupported,
+                "STUN 服务端不支持 RFC 5780（缺少 OTHER-ADDRESS）",
+            )
+        })?;
+
+        let mapping = if m1 == local {
+            MappingBehavior::NoNat
+        } else {
+            let m2 = self
+                .binding(socket, SocketAddr::new(other.ip(), self.server.port()))
+                .await?;
+            let m3 = if m2 == m1 {
+                None
+            } else {
+                Some(self.binding(socket, other).await?)
+            };
+            classify_mapping(local, m1, m2, m3)
+        };
+
+        let change_both = Message::binding_request().with(Attribute::ChangeRequest {
+            change_ip: true,
+            change_port: true,
+        });
+        let change_both = self
+            .transaction(socket, self.server, change_both)
+            .await?
+            .is_some();
+        let change_port = if change_both {
+            false
+        } else {
+            let request = Message::binding_request().with(Attribute::ChangeRequest {
+                change_ip: false,
+                change_port: true,
+            });
+            self.transaction(socket, self.server, request)
+                .await?
+                .is_some()
+        };
+
+        Ok(NatReport {
+            local,
+            mapped: m1,
+            mapping,
+            filtering: classify_filtering(change_both, change_port),
+        })
+    }
+
+    async fn binding(&self, socket: &UdpSocket, target: SocketAddr) -> Result<SocketAddr> {
+        let response = self
+            .transaction(socket, target, Message::binding_request())
+            .await?
+            .ok_or_else(|| Error::new(ErrorKind::TimedOut, format!("{target} 没有响应")))?;
+        mapped(&response)
+    }
+
+    /// 发送请求并等待事务ID相同的响应，超时重传，全部超时返回 None
+    async fn transaction(
+        &self,
+        socket: &UdpSocket,
+        target: SocketAddr,
+        request: Message,
+    ) -> Result<Option<Message>> {
+        let bytes = request.encode();
+        let mut buf = [0; 1500];
+        for _ in 0..=self.retries {
+            socket.send_to(&bytes, target).await?;
+            let deadline = tokio::time::Instant::now() + self.timeout;
+            loop {
+                let received = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await;
+                let Ok(received) = received else {
+                    break;
+                };
+                let (length, _) = received?;
+                // 丢弃之前的请求迟到的响应
+                match Message::decode(&buf[..length]) {
+                    Ok(response) if response.transaction_id == request.transaction_id => {
+                        return Ok(Some(response));
+                    }
+                    _ => continue,
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn mapped(response: &Message) -> Result<SocketAddr> {
+    if response.kind != BINDING_SUCCESS {
+        return Err(Error::other("STUN 服务端返回了错误响应"));
+    }
+    response
+        .mapped_address()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "响应中没有映射地址"))
+}
+
+#[cfg(test)]
+mod nat_test {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::net::UdpSocket;
+
+    use super::{
+        FilteringBehavior, MappingBehavior, NatClassifier, classify_filtering, classify_mapping,
+    };
+    use crate::stun::server::StunServer;
+
+    fn addr(text: &str) -> SocketAddr {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn mapping_rules() {
+        let local = addr("192.168.1.10:5000");
+        let m1 = addr("203.0.113.1:6000");
+        assert_eq!(
+            classify_mapping(local, local, local, None),
+            MappingBehavior::NoNat
+        );
+        assert_eq!(
+            classify_mapping(local, m1, m1, None),
+            MappingBehavior::EndpointIndependent
+        );
+        let m2 = addr("203.0.113.1:6001");
+        assert_eq!(
+            classify_mapping(local, m1, m2, Some(m2)),
+            MappingBehavior::AddressDependent
+        );
+        assert_eq!(
+            classify_mapping(local, m1, m2, Some(addr("203.0.113.1:6002"))),
+            MappingBehavior::AddressAndPortDependent
+        );
+    }
+
+    #[test]
+    fn filtering_rules() {
+        assert_eq!(
+            classify_filtering(true, false),
+            FilteringBehavior::EndpointIndependent
+        );
+        assert_eq!(
+            classify_filtering(false, true),
+            FilteringBehavior::AddressDependent
+        );
+        assert_eq!(
+            classify_filtering(false, false),
+            FilteringBehavior::AddressAndPortDependent
+        );
+    }
+
+    /**
+     * 回环网卡上没有 NAT，所有测试都能收到响应
+     */
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn classify_on_loopback() {
+        let server =
+            StunServer::bind_with_alternate(addr("127.0.0.1:0"), "127.0.0.2".parse().unwrap())
+                .await
+                .unwrap();
+        let primary = server.local_addr().unwrap();
+        let other = server.other_addr().unwrap();
+        assert_eq!(other.ip().to_string(), "127.0.0.2");
+        assert_ne!(other.port(), primary.port());
+        let tasks = server.spawn();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let report = NatClassifier::new(primary)
+            .with_timeout(Duration::from_millis(200), 1)
+            .classify(&socket)
+            .await
+            .unwrap();
+        assert_eq!(report.mapped, socket.local_addr().unwrap());
+        assert_eq!(report.mapping, MappingBehavior::NoNat);
+        assert_eq!(report.filtering, FilteringBehavior::EndpointIndependent);
+
+        tasks.iter().for_each(|task| task.abort());
+    }
+
+    #[tokio::test]
+    async fn require_rfc5780_server() {
+        let server = StunServer::bind(addr("127.0.0.1:0")).await.unwrap();
+        let primary = server.local_addr().unwrap();
+        let tasks = server.spawn();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let error = NatClassifier::new(primary)
+            .classify(&socket)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+
+        tasks.iter().for_each(|task| task.abort());
+    }
+}
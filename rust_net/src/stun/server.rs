@@ -0,0 +1,258 @@
+/**
+ * STUN Binding 服务端（RFC 5389），可选支持 RFC 5780 的 NAT 行为探测
+ *
+ * 最基本的 STUN 服务端只做一件事：把请求的源地址（也就是 NAT 映射后的地址）放进 XOR-MAPPED-ADDRESS 返回给客户端。
+ *
+ * NAT 行为探测需要服务端有两个 IP 地址（A1、A2）和两个端口（P1、P2），总共监听 4 个地址：
+ *      A1:P1（主地址）  A1:P2
+ *      A2:P1           A2:P2（OTHER-ADDRESS，备用地址）
+ * 客户端通过 CHANGE-REQUEST 要求服务端从另一个 IP 和/或另一个端口发送响应，用来判断 NAT 的过滤行为。
+ *
+ * 在本机测试时可以使用 127.0.0.1 和 127.0.0.2 作为两个 IP：Linux 上整个 127.0.0.0/8 都属于回环网卡，
+ * macOS 上需要先执行 `sudo ifconfig lo0 alias 127.0.0.2` 添加别名。
+ */
+use std::{
+    io::{Error, ErrorKind, Result},
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::{net::UdpSocket, task::JoinHandle};
+
+use super::message::{
+    Attribute, BINDING_ERROR, BINDING_REQUEST, BINDING_SUCCESS, Message, is_comprehension_required,
+};
+
+const SOFTWARE: &str = "rust_net stun server";
+/// 接收出错后等待的时间，连续出错时加倍，最长 MAX_RECV_RETRY_DELAY
+const RECV_RETRY_DELAY: Duration = Duration::from_millis(10);
+const MAX_RECV_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+pub struct StunServer {
+    /// sockets[ip][port]，只有主地址时只有 sockets[0][0]
+    sockets: Vec<Vec<Arc<UdpSocket>>>,
+}
+
+impl StunServer {
+    /// 只监听一个地址，收到 CHANGE-REQUEST 时返回 420 错误
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        let socket = Arc::new(UdpSocket::bind(addr).await?);
+        Ok(Self {
+            sockets: vec![vec![socket]],
+        })
+    }
+
+    /// 在 primary 和 alternate_ip 上各监听两个端口，支持 RFC 5780 的行为探测
+    pub async fn bind_with_alternate(primary: SocketAddr, alternate_ip: IpAddr) -> Result<Self> {
+        if primary.ip() == alternate_ip {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "备用 IP 必须和主 IP 不同",
+            ));
+        }
+        let a1p1 = UdpSocket::bind(primary).await?;
+        let port1 = a1p1.local_addr()?.port();
+        let a1p2 = UdpSocket::bind(SocketAddr::new(primary.ip(), 0)).await?;
+        let port2 = a1p2.local_addr()?.port();
+        let a2p1 = UdpSocket::bind(SocketAddr::new(alternate_ip, port1)).await?;
+        let a2p2 = UdpSocket::bind(SocketAddr::new(alternate_ip, port2)).await?;
+
+        Ok(Self {
+            sockets: vec![
+                vec![Arc::new(a1p1), Arc::new(a1p2)],
+                vec![Arc::new(a2p1), Arc::new(a2p2)],
+            ],
+        })
+    }
+
+    /// 主地址，客户端应该把请求发送到这里
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.sockets[0][0].local_addr()
+    }
+
+    /// 备用地址（另一个 IP + 另一个端口），只监听一个地址时为 None
+    pub fn other_addr(&self) -> Option<SocketAddr> {
+        self.sockets.get(1).and_then(|row| row[1].local_addr().ok())
+    }
+
+    /// 为每个监听地址启动一个接收任务
+    pub fn spawn(self) -> Vec<JoinHandle<()>> {
+        let sockets = Arc::new(self.sockets);
+        let mut tasks = Vec::new();
+        for ip in 0..sockets.len() {
+            for port in 0..sockets[ip].len() {
+                tasks.push(tokio::spawn(serve_socket(sockets.clone(), ip, port)));
+            }
+        }
+        tasks
+    }
+}
+
+async fn serve_socket(sockets: Arc<Vec<Vec<Arc<UdpSocket>>>>, ip: usize, port: usize) {
+    let socket = sockets[ip][port].clone();
+    let mut buf = [0; 1500];
+    let mut delay = RECV_RETRY_DELAY;
+    loop {
+        let (length, peer) = match socket.recv_from(&mut buf).await {
+            Ok(result) => {
+                delay = RECV_RETRY_DELAY;
+                result
+            }
+            // 之前的响应触发的 ICMP 端口不可达会让下一次 recv_from 返回一次错误，直接继续接收
+            Err(e) if is_transient(&e) => {
+                tracing::debug!("STUN 服务端接收失败: {e}");
+                continue;
+            }
+            // 其他错误通常会一直出现，等待一段时间再重试，避免空转占满 CPU
+            Err(e) => {
+                tracing::error!("STUN 服务端接收失败，{delay:?}后重试: {e}");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECV_RETRY_DELAY);
+                continue;
+            }
+        };
+        let request = match Message::decode(&buf[..length]) {
+            Ok(message) if message.kind == BINDING_REQUEST => message,
+            // 不是 Binding 请求的消息（包括各种响应和指示）直接丢弃
+            _ => continue,
+        };
+
+        let (reply_ip, reply_port, response) = handle(&sockets, ip, port, peer, &request);
+        let reply_socket = &sockets[reply_ip][reply_port];
+        if let Err(e) = reply_socket.send_to(&response.encode(), peer).await {
+            tracing::error!("STUN 服务端向{peer}发送响应失败: {e}");
+        }
+    }
+}
+
+fn is_transient(e: &Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::Interrupted
+            | ErrorKind::WouldBlock
+    )
+}
+
+/// 处理 Binding 请求，返回用于发送响应的 socket 下标和响应消息
+fn handle(
+    sockets: &[Vec<Arc<UdpSocket>>],
+    ip: usize,
+    port: usize,
+    peer: SocketAddr,
+    request: &Message,
+) -> (usize, usize, Message) {
+    let mut unknown: Vec<u16> = request
+        .attributes
+        .iter()
+        .filter_map(|attr| match attr {
+            Attribute::Unknown { kind, .. } if is_comprehension_required(*kind) => Some(*kind),
+            _ => None,
+        })
+        .collect();
+
+    let (change_ip, change_port) = request.change_request().unwrap_or_default();
+    let alternate = sockets.len() > 1;
+    if (change_ip || change_port) && !alternate {
+        // 没有备用地址时无法处理 CHANGE-REQUEST，按照"不认识的必须理解属性"处理
+        unknown.push(0x0003);
+    }
+    if !unknown.is_empty() {
+        let response = Message {
+            kind: BINDING_ERROR,
+            transaction_id: request.transaction_id,
+            attributes: vec![
+                Attribute::ErrorCode {
+                    code: 420,
+                    reason: "Unknown Attribute".to_string(),
+                },
+                Attribute::UnknownAttributes(unknown),
+                Attribute::Software(SOFTWARE.to_string()),
+            ],
+        };
+        return (ip, port, response);
+    }
+
+    let reply_ip = if change_ip { 1 - ip } else { ip };
+    let reply_port = if change_port { 1 - port } else { port };
+    let mut response = Message {
+        kind: BINDING_SUCCESS,
+        transaction_id: request.transaction_id,
+        attributes: vec![
+            Attribute::XorMappedAddress(peer),
+            Attribute::MappedAddress(peer),
+            Attribute::Software(SOFTWARE.to_string()),
+        ],
+    };
+    if alternate {
+        if let Ok(origin) = sockets[reply_ip][reply_port].local_addr() {
+            response.attributes.push(Attribute::ResponseOrigin(origin));
+        }
+        // OTHER-ADDRESS 是相对于收到请求的地址而言，IP 和端口都不同的那个地址
+        if let Ok(other) = sockets[1 - ip][1 - port].local_addr() {
+            response.attributes.push(Attribute::OtherAddress(other));
+        }
+    }
+    (reply_ip, reply_port, response)
+}
+
+#[cfg(test)]
+mod server_test {
+    use std::{net::SocketAddr, time::Duration};
+
+    use stunclient::StunClient;
+    use tokio::net::UdpSocket;
+
+    use super::StunServer;
+    use crate::stun::message::{Attribute, BINDING_ERROR, Message};
+
+    /**
+     * stunclient 指向本地的 STUN 服务端，得到的外部地址就是 socket 自己的地址
+     */
+    #[tokio::test]
+    async fn stunclient_query_local_server() {
+        let server = StunServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let tasks = server.spawn();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut client = StunClient::new(addr);
+        client.set_timeout(Duration::from_secs(2));
+        let external = client.query_external_address_async(&socket).await.unwrap();
+        assert_eq!(external, socket.local_addr().unwrap());
+
+        tasks.iter().for_each(|task| task.abort());
+    }
+
+    #[tokio::test]
+    async fn reject_change_request_without_alternate() {
+        let server = StunServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr: SocketAddr = server.local_addr().unwrap();
+        let tasks = server.spawn();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let request = Message::binding_request().with(Attribute::ChangeRequest {
+            change_ip: true,
+            change_port: true,
+        });
+        socket.send_to(&request.encode(), addr).await.unwrap();
+
+        let mut buf = [0; 1500];
+        let (length, _) = socket.recv_from(&mut buf).await.unwrap();
+        let response = Message::decode(&buf[..length]).unwrap();
+        assert_eq!(response.kind, BINDING_ERROR);
+        assert!(
+            response
+                .attributes
+                .contains(&Attribute::UnknownAttributes(vec![0x0003]))
+        );
+
+        tasks.iter().for_each(|task| task.abort());
+    }
+}
@@ -32,4 +32,29 @@ mod stunclient_test {
         println!("port = {}", response.port());
         println!("{}-{}", response.is_ipv4(), response.is_ipv6());
     }
+
+    // 不依赖公网：启动本地的 STUN 服务端（crate::stun::server），让 StunClient 指向它
+    #[tokio::test]
+    async fn test_2() {
+        let server = crate::stun::server::StunServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let stun_server = server.local_addr().unwrap();
+        let tasks = server.spawn();
+
+        // 同步版本的 query_external_address 会阻塞线程，放到 spawn_blocking 中执行
+        let response = tokio::task::spawn_blocking(move || {
+            let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let stun_client = StunClient::new(stun_server);
+            let response = stun_client.query_external_address(&socket).unwrap();
+            assert_eq!(response, socket.local_addr().unwrap());
+            response
+        })
+        .await
+        .unwrap();
+
+        println!("ip = {}", response.ip());
+        println!("port = {}", response.port());
+        tasks.iter().for_each(|task| task.abort());
+    }
 }
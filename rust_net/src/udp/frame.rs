@@ -0,0 +1,176 @@
+/**
+ * UDP RPC 的数据报帧格式
+ *
+ * UDP 是面向数据报的协议，一次 send 对应一次 recv，不存在粘包问题，但是数据报可能丢失、重复或乱序到达。
+ * 所以每个数据报都需要携带一个请求ID，客户端用它把响应和请求对应起来，服务端用它识别重传的请求。
+ *
+ *  +-------+---------+------+------------+-------------+---------+---------+
+ *  | magic | version | kind | request_id | command_len | command | payload |
+ *  |  2B   |   1B    |  1B  |  8B (BE)   |     1B      |   N B   |  剩余   |
+ *  +-------+---------+------+------------+-------------+---------+---------+
+ *
+ *  1、magic：固定为 "UR"，用来过滤掉不属于本协议的数据报。
+ *  2、kind：0 表示请求，1 表示成功响应，2 表示错误响应（payload 为 UTF-8 错误信息）。
+ *  3、command：命令名称，最长 255 字节；响应中会原样带回请求的命令名称。
+ */
+use std::io::{Error, ErrorKind, Result};
+
+pub const MAGIC: [u8; 2] = *b"UR";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 2 + 1 + 1 + 8 + 1;
+
+/// 单个数据报的最大长度，超过这个长度的帧在很多网络上都会被分片甚至丢弃
+pub const MAX_DATAGRAM: usize = 1472;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    Request,
+    Response,
+    Error,
+}
+
+impl FrameKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            FrameKind::Request => 0,
+            FrameKind::Response => 1,
+            FrameKind::Error => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self> {
+        match byte {
+            0 => Ok(FrameKind::Request),
+            1 => Ok(FrameKind::Response),
+            2 => Ok(FrameKind::Error),
+            other => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("未知的帧类型: {other}"),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub request_id: u64,
+    pub command: String,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn request(request_id: u64, command: impl Into<String>, payload: Vec<u8>) -> Self {
+        Self {
+            kind: FrameKind::Request,
+            request_id,
+            command: command.into(),
+            payload,
+        }
+    }
+
+    /// 根据请求帧构造成功响应
+    pub fn response(request: &Frame, payload: Vec<u8>) -> Self {
+        Self {
+            kind: FrameKind::Response,
+            request_id: request.request_id,
+            command: request.command.clone(),
+            payload,
+        }
+    }
+
+    /// 根据请求帧构造错误响应
+    pub fn error(request: &Frame, message: &str) -> Self {
+        Self {
+            kind: FrameKind::Error,
+            request_id: request.request_id,
+            command: request.command.clone(),
+            payload: message.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let command = self.command.as_bytes();
+        if command.len() > u8::MAX as usize {
+            return Err(Error::new(ErrorKind::InvalidInput, "命令名称超过255字节"));
+        }
+        let total = HEADER_LEN + command.len() + self.payload.len();
+        if total > MAX_DATAGRAM {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("数据报长度 {total} 超过上限 {MAX_DATAGRAM}"),
+            ));
+        }
+
+        let mut buf = Vec::with_capacity(total);
+        buf.extend_from_slice(&MAGIC);
+        buf.push(VERSION);
+        buf.push(self.kind.to_byte());
+        buf.extend_from_slice(&self.request_id.to_be_bytes());
+        buf.push(command.len() as u8);
+        buf.extend_from_slice(command);
+        buf.extend_from_slice(&self.payload);
+        Ok(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf.len() < HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "数据报长度不足"));
+        }
+        if buf[0..2] != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "magic 不匹配"));
+        }
+        if buf[2] != VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("不支持的协议版本: {}", buf[2]),
+            ));
+        }
+        let kind = FrameKind::from_byte(buf[3])?;
+        let request_id = u64::from_be_bytes(buf[4..12].try_into().unwrap());
+        let command_len = buf[12] as usize;
+        let rest = &buf[HEADER_LEN..];
+        if rest.len() < command_len {
+            return Err(Error::new(ErrorKind::InvalidData, "命令名称被截断"));
+        }
+        let command = String::from_utf8(rest[..command_len].to_vec())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        Ok(Self {
+            kind,
+            request_id,
+            command,
+            payload: rest[command_len..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod frame_test {
+    use super::{Frame, FrameKind, MAX_DATAGRAM};
+
+    #[test]
+    fn encode_and_decode() {
+        let request = Frame::request(42, "info", b"hello".to_vec());
+        let bytes = request.encode().unwrap();
+        assert_eq!(Frame::decode(&bytes).unwrap(), request);
+
+        let response = Frame::error(&request, "boom");
+        let decoded = Frame::decode(&response.encode().unwrap()).unwrap();
+        assert_eq!(decoded.kind, FrameKind::Error);
+        assert_eq!(decoded.request_id, 42);
+        assert_eq!(decoded.command, "info");
+        assert_eq!(decoded.payload, b"boom");
+    }
+
+    #[test]
+    fn reject_invalid() {
+        assert!(Frame::decode(b"info").is_err());
+        assert!(Frame::decode(b"XX\x01\x00\x00\x00\x00\x00\x00\x00\x00\x01\x00").is_err());
+        assert!(
+            Frame::request(1, "big", vec![0; MAX_DATAGRAM])
+                .encode()
+                .is_err()
+        );
+    }
+}
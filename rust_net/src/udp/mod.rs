@@ -1,2 +1,4 @@
 pub mod client;
+pub mod frame;
+pub mod rpc;
 pub mod server;
@@ -0,0 +1,365 @@
+/**
+ * 基于 UDP 的请求/响应服务（RPC）
+ *
+ * 适用于局域网内的服务发现、状态探测这类"小请求、小响应"的场景：
+ *  1、服务端：UdpRpcServer 维护一个命令注册表，每个命令对应一个处理函数，收到请求后根据命令名称分发。
+ *  2、客户端：UdpRpcClient 为每个请求分配一个请求ID，超时未收到响应就按照 RetryPolicy 重传同一个请求。
+ *
+ * UDP 不保证可靠传输，所以需要在两端都做去重：
+ *  1、服务端缓存最近处理过的 (对端地址, 请求ID) 的响应，重传的请求直接返回缓存的响应，处理函数不会被重复执行。
+ *  2、客户端只接受仍在等待中的请求ID对应的响应，重传导致的重复响应、已经超时的迟到响应都会被丢弃。
+ */
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Result},
+    net::SocketAddr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{net::UdpSocket, sync::oneshot, task::JoinHandle};
+
+use super::frame::{Frame, FrameKind, MAX_DATAGRAM};
+
+type Handler = Arc<dyn Fn(&[u8]) -> std::result::Result<Vec<u8>, String> + Send + Sync>;
+
+/// 服务端响应缓存的保留时长，需要大于客户端完整的重试时间
+const RESPONSE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+pub struct UdpRpcServer {
+    socket: UdpSocket,
+    handlers: HashMap<String, Handler>,
+    responses: HashMap<(SocketAddr, u64), (Instant, Vec<u8>)>,
+}
+
+impl UdpRpcServer {
+    pub async fn bind(addr: &str) -> Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr).await?,
+            handlers: HashMap::new(),
+            responses: HashMap::new(),
+        })
+    }
+
+    /// 注册命令，同名命令会被覆盖
+    pub fn register<F>(&mut self, command: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&[u8]) -> std::result::Result<Vec<u8>, String> + Send + Sync + 'static,
+    {
+        self.handlers.insert(command.to_string(), Arc::new(handler));
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// 循环接收并处理请求，直到 socket 出错
+    pub async fn serve(mut self) -> Result<()> {
+        let mut buf = [0; MAX_DATAGRAM];
+        loop {
+            let (length, peer) = self.socket.recv_from(&mut buf).await?;
+            let request = match Frame::decode(&buf[..length]) {
+                Ok(frame) if frame.kind == FrameKind::Request => frame,
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!("丢弃来自{peer}的无效数据报: {e}");
+                    continue;
+                }
+            };
+
+            let Some(response) = self.handle(peer, &request) else {
+                continue;
+            };
+            if let Err(e) = self.socket.send_to(&response, peer).await {
+                tracing::error!("向{peer}发送响应失败: {e}");
+            }
+        }
+    }
+
+    /// 在后台任务中运行服务端
+    pub fn spawn(self) -> JoinHandle<Result<()>> {
+        tokio::spawn(self.serve())
+    }
+
+    /// 处理请求并返回编码后的响应，连错误响应也无法编码时记录日志并返回 None，不发送空数据报
+    fn handle(&mut self, peer: SocketAddr, request: &Frame) -> Option<Vec<u8>> {
+        let now = Instant::now();
+        self.responses
+            .retain(|_, (created, _)| now.duration_since(*created) < RESPONSE_CACHE_TTL);
+
+        let key = (peer, request.request_id);
+        if let Some((_, cached)) = self.responses.get(&key) {
+            tracing::debug!("收到{peer}重传的请求{}，返回缓存的响应", request.request_id);
+            return Some(cached.clone());
+        }
+
+        let frame = match self.handlers.get(&request.command) {
+            Some(handler) => match handler(&request.payload) {
+                Ok(payload) => Frame::response(request, payload),
+                Err(message) => Frame::error(request, &message),
+            },
+            None => Frame::error(request, &format!("未知命令: {}", request.command)),
+        };
+        let bytes = match frame
+            .encode()
+            .or_else(|e| Frame::error(request, &e.to_string()).encode())
+        {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!("编码发给{peer}的响应{}失败: {e}", request.request_id);
+                return None;
+            }
+        };
+        self.responses.insert(key, (now, bytes.clone()));
+        Some(bytes)
+    }
+}
+
+/// 客户端重试策略：每次发送后等待 timeout，最多重传 retries 次
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(500),
+            retries: 3,
+        }
+    }
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Frame>>>>;
+
+pub struct UdpRpcClient {
+    socket: Arc<UdpSocket>,
+    policy: RetryPolicy,
+    next_id: AtomicU64,
+    pending: Pending,
+    receiver: JoinHandle<()>,
+}
+
+impl UdpRpcClient {
+    pub async fn connect(server: SocketAddr) -> Result<Self> {
+        Self::with_policy(server, RetryPolicy::default()).await
+    }
+
+    pub async fn with_policy(server: SocketAddr, policy: RetryPolicy) -> Result<Self> {
+        let local = if server.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = Arc::new(UdpSocket::bind(local).await?);
+        // connect 之后内核只会把来自 server 的数据报交给这个 socket
+        socket.connect(server).await?;
+
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let receiver = tokio::spawn(Self::receive_loop(socket.clone(), pending.clone()));
+
+        // 请求ID从随机位置开始，避免客户端重启后和服务端缓存中的旧请求ID冲突
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+
+        Ok(Self {
+            socket,
+            policy,
+            next_id: AtomicU64::new(seed),
+            pending,
+            receiver,
+        })
+    }
+
+    /// 发送请求并等待响应，超时后按照重试策略重传
+    pub async fn call(&self, command: &str, payload: &[u8]) -> Result<Vec<u8>> {
+        let request_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let bytes = Frame::request(request_id, command, payload.to_vec()).encode()?;
+
+        let (tx, mut rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(request_id, tx);
+
+        let mut result = Err(Error::new(
+            ErrorKind::TimedOut,
+            format!(
+                "命令{command}在{}次尝试后仍未收到响应",
+                self.policy.retries + 1
+            ),
+        ));
+        for attempt in 0..=self.policy.retries {
+            if attempt > 0 {
+                tracing::debug!("请求{request_id}超时，第{attempt}次重传");
+            }
+            if let Err(e) = self.socket.send(&bytes).await {
+                result = Err(e);
+                break;
+            }
+            match tokio::time::timeout(self.policy.timeout, &mut rx).await {
+                Ok(Ok(frame)) => {
+                    result = match frame.kind {
+                        FrameKind::Response => Ok(frame.payload),
+                        _ => Err(Error::other(String::from_utf8_lossy(&frame.payload))),
+                    };
+                    break;
+                }
+                Ok(Err(_)) => {
+                    result = Err(Error::new(ErrorKind::BrokenPipe, "接收任务已退出"));
+                    break;
+                }
+                Err(_) => continue,
+            }
+        }
+
+        self.pending.lock().unwrap().remove(&request_id);
+        result
+    }
+
+    async fn receive_loop(socket: Arc<UdpSocket>, pending: Pending) {
+        let mut buf = [0; MAX_DATAGRAM];
+        loop {
+            let length = match socket.recv(&mut buf).await {
+                Ok(length) => length,
+                // 对端端口不可达时部分系统会返回 ConnectionRefused，交给调用方的超时处理
+                Err(e) if e.kind() == ErrorKind::ConnectionRefused => continue,
+                Err(e) => {
+                    tracing::error!("UDP 客户端接收失败: {e}");
+                    return;
+                }
+            };
+            let frame = match Frame::decode(&buf[..length]) {
+                Ok(frame) if frame.kind != FrameKind::Request => frame,
+                _ => continue,
+            };
+            // 找不到等待者说明是重复的响应或者迟到的响应，直接丢弃
+            match pending.lock().unwrap().remove(&frame.request_id) {
+                Some(tx) => {
+                    let _ = tx.send(frame);
+                }
+                None => tracing::debug!("丢弃重复的响应{}", frame.request_id),
+            }
+        }
+    }
+}
+
+impl Drop for UdpRpcClient {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+#[cfg(test)]
+mod rpc_test {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use tokio::net::UdpSocket;
+
+    use super::{RetryPolicy, UdpRpcClient, UdpRpcServer};
+    use crate::udp::frame::{Frame, MAX_DATAGRAM};
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            timeout: Duration::from_millis(100),
+            retries: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn call_registered_command() {
+        let mut server = UdpRpcServer::bind("127.0.0.1:0").await.unwrap();
+        server
+            .register("info", |_| Ok(b"version: 1.0.1".to_vec()))
+            .register("echo", |payload| Ok(payload.to_vec()));
+        let addr = server.local_addr().unwrap();
+        let handle = server.spawn();
+
+        let client = UdpRpcClient::connect(addr).await.unwrap();
+        assert_eq!(client.call("info", b"").await.unwrap(), b"version: 1.0.1");
+        assert_eq!(client.call("echo", b"ping").await.unwrap(), b"ping");
+
+        let error = client.call("status", b"").await.unwrap_err();
+        assert!(error.to_string().contains("未知命令"));
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn retry_until_response() {
+        // 模拟丢包：第一次收到的请求不做响应
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; MAX_DATAGRAM];
+            let (length, _) = socket.recv_from(&mut buf).await.unwrap();
+            let first = Frame::decode(&buf[..length]).unwrap();
+
+            let (length, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let second = Frame::decode(&buf[..length]).unwrap();
+            assert_eq!(first.request_id, second.request_id);
+
+            let response = Frame::response(&second, b"pong".to_vec());
+            socket
+                .send_to(&response.encode().unwrap(), peer)
+                .await
+                .unwrap();
+        });
+
+        let client = UdpRpcClient::with_policy(addr, fast_policy())
+            .await
+            .unwrap();
+        assert_eq!(client.call("ping", b"").await.unwrap(), b"pong");
+    }
+
+    #[tokio::test]
+    async fn timeout_after_retries() {
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = silent.local_addr().unwrap();
+
+        let client = UdpRpcClient::with_policy(addr, fast_policy())
+            .await
+            .unwrap();
+        let error = client.call("ping", b"").await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn suppress_duplicates() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let mut server = UdpRpcServer::bind("127.0.0.1:0").await.unwrap();
+        server.register("incr", move |_| {
+            let value = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(value.to_string().into_bytes())
+        });
+        let addr = server.local_addr().unwrap();
+        let handle = server.spawn();
+
+        // 服务端：同一个请求ID重复发送，处理函数只执行一次
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let request = Frame::request(7, "incr", Vec::new()).encode().unwrap();
+        let mut buf = [0; MAX_DATAGRAM];
+        for _ in 0..3 {
+            socket.send_to(&request, addr).await.unwrap();
+            let (length, _) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(Frame::decode(&buf[..length]).unwrap().payload, b"1");
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // 客户端：连续调用不会收到上一次调用的响应
+        let client = UdpRpcClient::connect(addr).await.unwrap();
+        assert_eq!(client.call("incr", b"").await.unwrap(), b"2");
+        assert_eq!(client.call("incr", b"").await.unwrap(), b"3");
+        handle.abort();
+    }
+}
@@ -11,7 +11,7 @@ mod test {
         let upd_server = UdpSocket::bind("0.0.0.0:8889")?;
 
         // 接受连接并从连接中获取内容
-        let mut content = [0, 255];
+        let mut content = [0; 255];
         let (length, connect_src) = upd_server.recv_from(&mut content)?;
 
         let command = String::from_utf8(content[..length].to_vec()).unwrap();
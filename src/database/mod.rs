@@ -23,4 +23,5 @@ pub mod models;
 pub mod mysql;
 pub mod postgresql;
 pub mod redis_demo;
+pub mod sql_schema;
 pub mod sqlite_demo;
@@ -0,0 +1,83 @@
+/**
+ * rust_macro 中 sql! 宏使用的表结构文件（项目根目录的 sql_schema.txt），根据 sea-orm 实体生成
+ *
+ * 每行为 表名 列名 Rust类型，可以为空的列为 Option<T>，Json、Date 等类型对应 sea_orm::prelude 中的同名类型。
+ * cargo test sql_schema_up_to_date 检查文件和实体是否一致，
+ * 实体变化之后运行 UPDATE_SQL_SCHEMA=1 cargo test sql_schema_up_to_date 重新生成，提交新的文件即可。
+ */
+use sea_orm::{ColumnTrait, ColumnType, EntityTrait, IdenStatic, Iterable};
+
+use crate::database::models::{city, dict, dict_group, driving_school, question};
+
+/// 列类型对应的 Rust 类型，不认识的类型为 Value，sql! 宏不检查这类参数的类型
+fn rust_type(column_type: &ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::TinyInteger => "i8",
+        ColumnType::SmallInteger => "i16",
+        ColumnType::Integer => "i32",
+        ColumnType::BigInteger => "i64",
+        ColumnType::TinyUnsigned => "u8",
+        ColumnType::SmallUnsigned => "u16",
+        ColumnType::Unsigned => "u32",
+        ColumnType::BigUnsigned => "u64",
+        ColumnType::Float => "f32",
+        ColumnType::Double => "f64",
+        ColumnType::Boolean => "bool",
+        ColumnType::Char(_) | ColumnType::String(_) | ColumnType::Text => "String",
+        ColumnType::Json | ColumnType::JsonBinary => "Json",
+        ColumnType::Date => "Date",
+        ColumnType::Time => "Time",
+        ColumnType::DateTime | ColumnType::Timestamp => "DateTime",
+        ColumnType::Decimal(_) => "Decimal",
+        ColumnType::Uuid => "Uuid",
+        _ => "Value",
+    }
+}
+
+fn entity_lines<E: EntityTrait>(entity: E, lines: &mut Vec<String>) {
+    let table = entity.table_name();
+    for column in E::Column::iter() {
+        let def = column.def();
+        let ty = rust_type(def.get_column_type());
+        let ty = if def.is_null() {
+            format!("Option<{ty}>")
+        } else {
+            ty.to_string()
+        };
+        lines.push(format!("{table} {} {ty}", column.as_str()));
+    }
+}
+
+/// 所有实体的表结构
+pub fn schema_text() -> String {
+    let mut lines = vec![
+        "# sql! 宏使用的表结构，由 src/database/sql_schema.rs 根据 sea-orm 实体生成，不要手动修改"
+            .to_string(),
+    ];
+    entity_lines(city::Entity, &mut lines);
+    entity_lines(dict::Entity, &mut lines);
+    entity_lines(dict_group::Entity, &mut lines);
+    entity_lines(driving_school::Entity, &mut lines);
+    entity_lines(question::Entity, &mut lines);
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod sql_schema_test {
+    use super::schema_text;
+
+    #[test]
+    fn sql_schema_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/sql_schema.txt");
+        let expected = schema_text();
+        if std::env::var_os("UPDATE_SQL_SCHEMA").is_some() {
+            std::fs::write(path, &expected).unwrap();
+            return;
+        }
+        let actual = std::fs::read_to_string(path).unwrap_or_default();
+        assert_eq!(
+            actual, expected,
+            "sql_schema.txt 和实体不一致，运行 UPDATE_SQL_SCHEMA=1 cargo test sql_schema_up_to_date 重新生成"
+        );
+    }
+}
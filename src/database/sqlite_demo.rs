@@ -2,6 +2,7 @@
 mod sqlite_test {
     use crate::database::models::city;
     use crate::database::models::question;
+    use rust_macro::sql;
     use sea_orm::ActiveModelTrait;
     use sea_orm::ConnectionTrait;
     use sea_orm::Database;
@@ -152,4 +153,58 @@ mod sqlite_test {
             println!("{:?}", question);
         }
     }
+
+    /**
+     * sql! 宏：表名、列名和参数的类型在编译期检查，表结构见项目根目录的 sql_schema.txt
+     * 写错列名时编译报错，例如 questoin_category：表 question 没有列 questoin_category，是不是 question_category？
+     */
+    #[tokio::test]
+    async fn sql_macro() {
+        let path = PathBuf::from("src/database/sqlite.db");
+        let db_url = format!("sqlite:{}?mode=rwc", path.to_str().unwrap());
+
+        let db: DatabaseConnection = Database::connect(&db_url).await.unwrap();
+
+        // 参数的类型为对应列的类型，options 为 Json，image 可以为空
+        let insert = sql!(
+            Sqlite,
+            "INSERT INTO question (question, options, answer, question_category, case_category, image)
+             VALUES (?, ?, ?, ?, ?, NULL)",
+            "1 + 1 = ?",
+            serde_json::json!(["1", "2", "3"]),
+            "2",
+            9,
+            9
+        );
+        db.execute(insert).await.unwrap();
+
+        // 查询的结果可以直接转换为实体
+        let select = sql!(
+            Sqlite,
+            "SELECT * FROM question WHERE question_category = ? AND answer LIKE ? ORDER BY id DESC LIMIT ?",
+            9,
+            "2%",
+            10u64
+        );
+        let questions = question::Entity::find()
+            .from_raw_sql(select)
+            .all(&db)
+            .await
+            .unwrap();
+        assert!(!questions.is_empty());
+
+        // 可以为空的列的参数类型为 Option<T>
+        let update = sql!(
+            Sqlite,
+            "UPDATE question SET description = ?, update_by = ? WHERE id = ?",
+            Some("加法".to_string()),
+            None,
+            questions[0].id
+        );
+        db.execute(update).await.unwrap();
+
+        let delete = sql!(Sqlite, "DELETE FROM question WHERE case_category = ?", 9);
+        let result = db.execute(delete).await.unwrap();
+        println!("删除了 {} 条记录", result.rows_affected());
+    }
 }
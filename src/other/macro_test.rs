@@ -45,4 +45,680 @@ mod test {
     fn attr_macro_test() {
         test_attr();
     }
+
+    /**
+     * 访问器派生宏测试
+     */
+    use rust_macro::{Getters, Setters, With};
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Debug, Default, Getters, Setters, With)]
+    struct User<'a> {
+        id: u64,
+        name: String,
+        #[getter(rename = "user_tags")]
+        #[with(rename = "tagged")]
+        tags: Vec<String>,
+        #[getter(copy)]
+        location: Point,
+        #[getter(vis = "pub(crate)")]
+        nickname: &'a str,
+        #[getter(skip)]
+        #[setter(skip)]
+        #[with(skip)]
+        password: String,
+    }
+
+    #[test]
+    fn accessor_derive_test() {
+        let user = User::default()
+            .with_id(7)
+            .with_name("zhangsan".to_string())
+            .tagged(vec!["admin".to_string()])
+            .with_nickname("san");
+        // 基本类型、共享引用和标注了 copy 的字段按值返回，其他字段返回引用
+        let id: u64 = user.id();
+        let name: &String = user.name();
+        let nickname: &str = user.nickname();
+        let location: Point = user.location();
+        assert_eq!(id, 7);
+        assert_eq!(name, "zhangsan");
+        assert_eq!(user.user_tags(), &["admin".to_string()]);
+        assert_eq!(nickname, "san");
+        assert_eq!(location, Point::default());
+
+        let mut user = user;
+        user.set_id(8)
+            .set_location(Point { x: 1, y: 2 })
+            .set_name("lisi".to_string());
+        assert_eq!(user.id(), 8);
+        assert_eq!(user.location().y, 2);
+        assert_eq!(user.name(), "lisi");
+        assert!(user.password.is_empty());
+    }
+
+    /**
+     * Builder 派生宏测试
+     */
+    use rust_macro::Builder;
+    use std::collections::HashMap;
+
+    fn not_empty(value: &str) -> Result<(), &'static str> {
+        if value.is_empty() {
+            Err("不能为空")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn positive(value: &u64) -> Result<(), &'static str> {
+        if *value == 0 {
+            Err("必须大于0")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_server(server: &Server) -> Result<(), Vec<(&'static str, String)>> {
+        if server.port < 1024 && server.user != "root" {
+            return Err(vec![("port", format!("{}需要 root 权限", server.port))]);
+        }
+        Ok(())
+    }
+
+    #[derive(Debug, Builder)]
+    #[builder(validate = check_server)]
+    struct Server {
+        #[builder(into, validate = not_empty)]
+        host: String,
+        port: u16,
+        #[builder(default = "nobody".to_string(), into)]
+        user: String,
+        #[builder(default)]
+        workers: usize,
+        #[builder(validate = positive)]
+        timeout: Option<u64>,
+        #[builder(push = "add_alias", into)]
+        aliases: Vec<String>,
+        headers: HashMap<String, String>,
+    }
+
+    #[test]
+    fn builder_derive_test() {
+        let server = Server::builder()
+            .port(8080)
+            .host("localhost")
+            .timeout(30)
+            .add_alias("web")
+            .add_alias("api")
+            .push_headers(("x-env".to_string(), "dev".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(server.host, "localhost");
+        assert_eq!(server.port, 8080);
+        assert_eq!(server.user, "nobody");
+        assert_eq!(server.workers, 0);
+        assert_eq!(server.timeout, Some(30));
+        assert_eq!(server.aliases, ["web", "api"]);
+        assert_eq!(server.headers["x-env"], "dev");
+
+        // 所有校验失败的字段一起返回
+        let error = ServerBuilder::new().host("").port(80).build().unwrap_err();
+        assert_eq!(
+            error.errors,
+            vec![
+                ("host", "不能为空".to_string()),
+                ("port", "80需要 root 权限".to_string())
+            ]
+        );
+        assert_eq!(
+            error.to_string(),
+            "构建Server失败: host: 不能为空; port: 80需要 root 权限"
+        );
+        // Option 字段的校验函数接收内部的值
+        let error = Server::builder()
+            .host("localhost")
+            .port(8080)
+            .timeout(0)
+            .build()
+            .unwrap_err();
+        assert_eq!(error.errors, vec![("timeout", "必须大于0".to_string())]);
+
+        // 泛型结构体；漏掉 name 或 value 时没有 build 方法，编译不通过
+        #[derive(Debug, Builder)]
+        struct Pair<'a, T: Clone> {
+            name: &'a str,
+            value: T,
+        }
+        let pair = Pair::builder().value(1.5).name("pi").build().unwrap();
+        assert_eq!((pair.name, pair.value), ("pi", 1.5));
+    }
+
+    /**
+     * Error 派生宏测试
+     */
+    use rust_macro::Error;
+
+    #[derive(Debug, Error)]
+    #[code(base = 1000)]
+    #[error(serialize)]
+    enum ApiError {
+        #[msg("用户{0}不存在")]
+        #[status(404)]
+        UserNotFound(u64),
+
+        #[msg("参数{field}无效: {reason:?}")]
+        #[status(400)]
+        InvalidParam { field: String, reason: String },
+
+        #[msg("读取文件失败")]
+        #[code(2000)]
+        Io(#[from] std::io::Error),
+
+        #[msg(transparent)]
+        Json(#[from] serde_json::Error),
+
+        #[msg("未知错误 {{internal}}")]
+        Unknown,
+
+        #[msg("文件{f}格式错误")]
+        Format { f: String },
+
+        #[msg("调用远程服务失败")]
+        Remote {
+            source: Box<dyn std::error::Error + Send + Sync>,
+        },
+
+        #[msg(transparent)]
+        Other(Box<dyn std::error::Error + Send + Sync>),
+    }
+
+    #[test]
+    fn error_derive_test() {
+        let error = ApiError::UserNotFound(7);
+        assert_eq!(error.to_string(), "用户7不存在");
+        assert_eq!(
+            (error.code(), error.status(), error.name()),
+            (1000, 404, "UserNotFound")
+        );
+
+        let error = ApiError::InvalidParam {
+            field: "age".to_string(),
+            reason: "必须是\"数字\"".to_string(),
+        };
+        assert_eq!(error.to_string(), r#"参数age无效: "必须是\"数字\"""#);
+        assert_eq!((error.code(), error.status()), (1001, 400));
+        // to_json 和 Serialize 的结果相同
+        let json: serde_json::Value = serde_json::from_str(&error.to_json()).unwrap();
+        assert_eq!(json, serde_json::to_value(&error).unwrap());
+        assert_eq!(json["code"], 1001);
+        assert_eq!(json["error"], "InvalidParam");
+        assert_eq!(json["message"], error.to_string());
+
+        // #[from] 生成 From 并作为 source，错误码从 2000 开始继续递增
+        let error: ApiError = std::io::Error::other("disk").into();
+        assert_eq!((error.code(), error.status()), (2000, 500));
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "disk"
+        );
+
+        let error: ApiError = serde_json::from_str::<u8>("x").unwrap_err().into();
+        assert_eq!(error.code(), 2001);
+        assert!(error.to_string().starts_with("expected value"));
+
+        assert_eq!(ApiError::Unknown.to_string(), "未知错误 {internal}");
+        assert_eq!(ApiError::Unknown.code(), 2002);
+
+        // 名称为 f 的字段不会和 Display 的格式化参数冲突
+        let error = ApiError::Format {
+            f: "a.toml".to_string(),
+        };
+        assert_eq!(error.to_string(), "文件a.toml格式错误");
+
+        // Box<dyn Error + Send + Sync> 也可以作为 source
+        let error = ApiError::Remote {
+            source: "连接超时".into(),
+        };
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "连接超时"
+        );
+        let error = ApiError::Other(Box::new(ApiError::Remote {
+            source: "连接超时".into(),
+        }));
+        assert_eq!(error.to_string(), "调用远程服务失败");
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "连接超时"
+        );
+    }
+
+    /**
+     * #[timed]、#[traced]、#[retry] 属性宏测试
+     */
+    use rust_macro::{retry, timed, traced};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    static TIMINGS: Mutex<Vec<(&'static str, Duration)>> = Mutex::new(Vec::new());
+
+    fn record(name: &'static str, elapsed: Duration) {
+        TIMINGS.lock().unwrap().push((name, elapsed));
+    }
+
+    fn timing(name: &str) -> Option<Duration> {
+        let timings = TIMINGS.lock().unwrap();
+        timings
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, d)| *d)
+    }
+
+    #[timed(hook = record, name = "macro_test::parse_number")]
+    fn parse_number<T: std::str::FromStr>(text: &str) -> Result<T, T::Err> {
+        let value = text.trim().parse()?;
+        Ok(value)
+    }
+
+    #[timed(hook = record)]
+    async fn slow_add(a: u64, b: u64) -> u64 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        a + b
+    }
+
+    #[traced(level = "info", redact(password), skip(data))]
+    fn login(user: &str, password: &str, data: &[u8]) -> Result<usize, String> {
+        if password.is_empty() {
+            return Err("密码为空".to_string());
+        }
+        Ok(user.len() + data.len())
+    }
+
+    struct Counter {
+        value: u32,
+    }
+
+    impl Counter {
+        #[traced]
+        async fn add(&mut self, n: u32) -> u32 {
+            self.value += n;
+            self.value
+        }
+    }
+
+    #[retry(times = 3, backoff = "1ms", factor = 2, max_backoff = "3ms")]
+    fn flaky(attempts: &AtomicU32, succeed_at: u32) -> Result<u32, String> {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        if attempt < succeed_at {
+            return Err(format!("第{attempt}次失败"));
+        }
+        Ok(attempt)
+    }
+
+    fn is_timeout(error: &std::io::Error) -> bool {
+        error.kind() == std::io::ErrorKind::TimedOut
+    }
+
+    #[retry(times = 5, when = is_timeout)]
+    async fn connect(attempts: &AtomicU32, kind: std::io::ErrorKind) -> std::io::Result<()> {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(kind.into())
+    }
+
+    #[tokio::test]
+    async fn instrument_macro_test() {
+        // 签名保留泛型和 Result，? 在函数体中正常使用
+        assert_eq!(parse_number::<u8>(" 42 "), Ok(42));
+        assert!(parse_number::<u8>("x").is_err());
+        assert!(timing("macro_test::parse_number").is_some());
+
+        assert_eq!(slow_add(1, 2).await, 3);
+        let name = concat!(module_path!(), "::slow_add");
+        assert!(timing(name).unwrap() >= Duration::from_millis(20));
+
+        assert_eq!(login("admin", "123456", b"abc"), Ok(8));
+        assert_eq!(login("admin", "", b""), Err("密码为空".to_string()));
+        let mut counter = Counter { value: 1 };
+        assert_eq!(counter.add(2).await, 3);
+
+        // 第 3 次成功；重试次数用完之后返回最后一次的错误
+        let attempts = AtomicU32::new(0);
+        assert_eq!(flaky(&attempts, 3), Ok(3));
+        let attempts = AtomicU32::new(0);
+        assert_eq!(flaky(&attempts, 10), Err("第4次失败".to_string()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 4);
+
+        // when 返回 false 的错误不重试
+        let attempts = AtomicU32::new(0);
+        assert!(
+            connect(&attempts, std::io::ErrorKind::TimedOut)
+                .await
+                .is_err()
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 6);
+        let attempts = AtomicU32::new(0);
+        assert!(
+            connect(&attempts, std::io::ErrorKind::NotFound)
+                .await
+                .is_err()
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    /**
+     * #[memoize] 属性宏测试
+     */
+    use rust_macro::memoize;
+
+    static FIB_CALLS: AtomicU32 = AtomicU32::new(0);
+    static LOOKUP_CALLS: AtomicU32 = AtomicU32::new(0);
+    static FETCH_CALLS: AtomicU32 = AtomicU32::new(0);
+
+    #[memoize(capacity = 100)]
+    fn fib(n: u64) -> u64 {
+        FIB_CALLS.fetch_add(1, Ordering::SeqCst);
+        if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+    }
+
+    #[memoize(capacity = 2, ttl = "50ms")]
+    fn lookup(name: &str, id: u32) -> Result<String, String> {
+        LOOKUP_CALLS.fetch_add(1, Ordering::SeqCst);
+        if id == 0 {
+            return Err("id 不能为 0".to_string());
+        }
+        Ok(format!("{name}-{id}"))
+    }
+
+    #[memoize]
+    async fn fetch(url: String) -> String {
+        FETCH_CALLS.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        format!("body of {url}")
+    }
+
+    #[memoize(ttl = "1m")]
+    async fn resolve(host: &str) -> std::io::Result<u16> {
+        if host.is_empty() {
+            return Err(std::io::Error::other("主机名为空"));
+        }
+        Ok(host.len() as u16)
+    }
+
+    static LOAD_CALLS: AtomicU32 = AtomicU32::new(0);
+    static LOAD_RUNNING: AtomicU32 = AtomicU32::new(0);
+    static LOAD_MAX_RUNNING: AtomicU32 = AtomicU32::new(0);
+
+    // 第一次调用失败，记录同时执行的调用数
+    #[memoize]
+    async fn load(id: u32) -> Result<u32, String> {
+        let running = LOAD_RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+        LOAD_MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+        let first = LOAD_CALLS.fetch_add(1, Ordering::SeqCst) == 0;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        LOAD_RUNNING.fetch_sub(1, Ordering::SeqCst);
+        if first {
+            return Err("第一次加载失败".to_string());
+        }
+        Ok(id)
+    }
+
+    #[tokio::test]
+    async fn memoize_macro_test() {
+        // 递归调用也会使用缓存，每个 n 只计算一次
+        assert_eq!(fib(50), 12586269025);
+        assert_eq!(FIB_CALLS.load(Ordering::SeqCst), 51);
+        assert_eq!(fib(50), 12586269025);
+        let stats = fib_cache_stats();
+        assert_eq!((stats.size, stats.capacity, stats.misses), (51, 100, 51));
+        assert_eq!(stats.hits, 49);
+        fib_clear_cache();
+        assert_eq!(fib_cache_stats().size, 0);
+
+        // 引用参数作为键，错误不缓存，超过容量淘汰最久未使用的结果，过期之后重新计算
+        assert_eq!(lookup("user", 1), Ok("user-1".to_string()));
+        assert_eq!(lookup("user", 1), Ok("user-1".to_string()));
+        assert_eq!(LOOKUP_CALLS.load(Ordering::SeqCst), 1);
+        assert!(lookup("user", 0).is_err());
+        assert!(lookup("user", 0).is_err());
+        assert_eq!(LOOKUP_CALLS.load(Ordering::SeqCst), 3);
+        lookup("user", 2).unwrap();
+        lookup("user", 3).unwrap();
+        lookup("user", 1).unwrap();
+        assert_eq!(LOOKUP_CALLS.load(Ordering::SeqCst), 6);
+        std::thread::sleep(Duration::from_millis(60));
+        lookup("user", 3).unwrap();
+        assert_eq!(LOOKUP_CALLS.load(Ordering::SeqCst), 7);
+        assert_eq!(lookup_cache_stats().size, 2);
+
+        // 并发的相同调用只执行一次
+        let tasks: Vec<_> = (0..10)
+            .map(|_| tokio::spawn(fetch("https://example.com".to_string())))
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), "body of https://example.com");
+        }
+        assert_eq!(FETCH_CALLS.load(Ordering::SeqCst), 1);
+        fetch("https://example.org".to_string()).await;
+        assert_eq!(FETCH_CALLS.load(Ordering::SeqCst), 2);
+
+        assert_eq!(resolve("localhost").await.unwrap(), 9);
+        assert_eq!(resolve("localhost").await.unwrap(), 9);
+        assert!(resolve("").await.is_err());
+        assert_eq!(resolve_cache_stats().hits, 1);
+        assert_eq!(resolve_cache_stats().size, 1);
+    }
+
+    // 第一个调用失败后，等待的调用重新执行函数体，这时新的调用加入同一次执行，不会同时执行两次
+    #[tokio::test]
+    async fn memoize_single_flight_after_error() {
+        let first = tokio::spawn(load(7));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let waiting: Vec<_> = (0..5).map(|_| tokio::spawn(load(7))).collect();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let joined: Vec<_> = (0..5).map(|_| tokio::spawn(load(7))).collect();
+
+        assert!(first.await.unwrap().is_err());
+        for task in waiting.into_iter().chain(joined) {
+            assert_eq!(task.await.unwrap(), Ok(7));
+        }
+        assert_eq!(LOAD_CALLS.load(Ordering::SeqCst), 2);
+        assert_eq!(LOAD_MAX_RUNNING.load(Ordering::SeqCst), 1);
+        assert_eq!(load(7).await, Ok(7));
+        assert_eq!(LOAD_CALLS.load(Ordering::SeqCst), 2);
+    }
+
+    /**
+     * Config 派生宏测试
+     */
+    use rust_macro::Config;
+
+    #[derive(Config, Debug)]
+    struct DatabaseConfig {
+        url: String,
+        #[config(default = 10u32)]
+        max_connections: u32,
+        #[config(secret, default = "root")]
+        password: String,
+    }
+
+    #[derive(Config, Debug)]
+    #[config(prefix = "STUDY")]
+    struct AppConfig {
+        #[config(default = "127.0.0.1")]
+        host: String,
+        #[config(env = "STUDY_HTTP_PORT", default = 8080u16)]
+        port: u16,
+        #[config(default)]
+        debug: bool,
+        name: Option<String>,
+        #[config(nested)]
+        database: DatabaseConfig,
+    }
+
+    #[test]
+    fn config_derive_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml = dir.path().join("app.toml");
+        std::fs::write(
+            &toml,
+            "host = \"0.0.0.0\"\n[database]\nurl = \"mysql://localhost/study\"\nmax_connections = 5\n",
+        )
+        .unwrap();
+        let json = dir.path().join("app.json");
+        std::fs::write(
+            &json,
+            r#"{"port": 9000, "database": {"max_connections": 20}}"#,
+        )
+        .unwrap();
+
+        // 后面的文件覆盖前面的文件，没有配置的字段使用默认值
+        let config = AppConfig::load_from(&[&toml, &json]).unwrap();
+        assert_eq!(config.host, "0.0.0.0");
+        assert_eq!(config.port, 9000);
+        assert!(!config.debug);
+        assert_eq!(config.name, None);
+        assert_eq!(config.database.url, "mysql://localhost/study");
+        assert_eq!(config.database.max_connections, 20);
+        assert_eq!(config.database.password, "root");
+
+        // 环境变量的优先级最高
+        unsafe {
+            std::env::set_var("STUDY_HTTP_PORT", "7000");
+            std::env::set_var("STUDY_DEBUG", "true");
+            std::env::set_var("STUDY_DATABASE__PASSWORD", "p@ss");
+        }
+        let config = AppConfig::load_from(&[&toml]).unwrap();
+        assert_eq!(config.port, 7000);
+        assert!(config.debug);
+        assert_eq!(config.database.password, "p@ss");
+
+        // 所有字段的错误一起返回
+        unsafe {
+            std::env::set_var("STUDY_HTTP_PORT", "abc");
+            std::env::set_var("STUDY_DATABASE__MAX_CONNECTIONS", "x");
+        }
+        let err = AppConfig::load().unwrap_err();
+        println!("{err}");
+        let keys: Vec<&str> = err.errors.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["port", "database.url", "database.max_connections"]);
+        for name in [
+            "STUDY_HTTP_PORT",
+            "STUDY_DEBUG",
+            "STUDY_DATABASE__PASSWORD",
+            "STUDY_DATABASE__MAX_CONNECTIONS",
+        ] {
+            unsafe { std::env::remove_var(name) };
+        }
+
+        let text = AppConfig::describe();
+        println!("{text}");
+        assert!(text.contains("database.password [secret]"));
+        assert!(!text.contains("\"root\""));
+        assert!(text.contains("STUDY_DATABASE__URL"));
+    }
+
+    /**
+     * 枚举工具派生宏测试
+     */
+    use rust_macro::{Display, EnumCount, EnumDiscriminants, EnumIter, FromStr, VariantTable};
+    use std::str::FromStr;
+
+    #[derive(EnumIter, EnumCount, Display, FromStr, VariantTable, Debug, PartialEq)]
+    #[table(label: &'static str, color: u32 = 0x8c8c8c)]
+    enum OrderStatus {
+        #[enum_str(rename = "已成交", alias = "filled")]
+        #[table(label = "已成交", color = 0x52c41a)]
+        Filled,
+        #[enum_str(rename = "待成交", alias = "pending")]
+        #[table(label = "待成交")]
+        Pending,
+        #[enum_str(rename = "已撤单", alias = "cancelled", alias = "canceled")]
+        #[table(label = "已撤单", color = 0xff4d4f)]
+        Cancelled,
+    }
+
+    #[derive(EnumIter, Display, FromStr, Debug, PartialEq)]
+    #[enum_str(rename_all = "kebab-case", ascii_case_insensitive)]
+    enum SubCommand {
+        Test,
+        RunAll,
+        HTTPServer,
+    }
+
+    #[derive(EnumDiscriminants)]
+    #[discriminants(name = "ShapeKind", derive(EnumIter, Display))]
+    enum Shape {
+        Circle { radius: f64 },
+        Rect(f64, f64),
+        Empty,
+    }
+
+    #[derive(VariantTable, Clone, Copy)]
+    #[table(symbol: char, apply: fn(i64, i64) -> i64)]
+    enum Op {
+        #[table(symbol = '+', apply = |a, b| a + b)]
+        Add,
+        #[table(symbol = '*', apply = |a, b| a * b)]
+        Mul,
+    }
+
+    #[test]
+    fn enum_derive_test() {
+        assert_eq!(OrderStatus::COUNT, 3);
+        let labels: Vec<String> = OrderStatus::iter()
+            .map(|status| status.to_string())
+            .collect();
+        assert_eq!(labels, ["已成交", "待成交", "已撤单"]);
+        assert_eq!(OrderStatus::from_str("已撤单"), Ok(OrderStatus::Cancelled));
+        assert_eq!("canceled".parse(), Ok(OrderStatus::Cancelled));
+        let err = "unknown".parse::<OrderStatus>().unwrap_err();
+        assert_eq!(err.input, "unknown");
+        assert_eq!(
+            err.to_string(),
+            "无效的 OrderStatus: 'unknown'，可选值: 已成交、待成交、已撤单"
+        );
+        assert_eq!(format!("[{:>4}]", OrderStatus::Pending), "[ 待成交]");
+
+        // 命名规则和忽略大小写
+        let names: Vec<&str> = SubCommand::iter().map(|command| command.as_str()).collect();
+        assert_eq!(names, ["test", "run-all", "http-server"]);
+        assert_eq!("RUN-ALL".parse(), Ok(SubCommand::RunAll));
+        assert!("run_all".parse::<SubCommand>().is_err());
+
+        // 不带字段的变体
+        let shapes = [Shape::Circle { radius: 1.0 }, Shape::Rect(1.0, 2.0)];
+        for shape in &shapes {
+            let area = match shape {
+                Shape::Circle { radius } => std::f64::consts::PI * radius * radius,
+                Shape::Rect(width, height) => width * height,
+                Shape::Empty => 0.0,
+            };
+            assert!(area > 0.0);
+        }
+        assert_eq!(shapes[0].discriminant(), ShapeKind::Circle);
+        assert_eq!(ShapeKind::from(&shapes[1]), ShapeKind::Rect);
+        assert_eq!(ShapeKind::from(&Shape::Empty).to_string(), "Empty");
+        assert_eq!(ShapeKind::iter().count(), 3);
+
+        // 查表
+        assert_eq!(OrderStatus::Filled.color(), 0x52c41a);
+        assert_eq!(OrderStatus::Pending.color(), 0x8c8c8c);
+        assert_eq!(OrderStatus::Cancelled.meta().label, "已撤单");
+        assert_eq!(OrderStatus::TABLE[1].0, "Pending");
+        assert_eq!(Op::Add.symbol(), '+');
+        assert_eq!((Op::Mul.apply())(6, 7), 42);
+        let expression: Vec<String> = [Op::Add, Op::Mul]
+            .iter()
+            .map(|op| format!("2 {} 3 = {}", op.symbol(), (op.meta().apply)(2, 3)))
+            .collect();
+        assert_eq!(expression, ["2 + 3 = 5", "2 * 3 = 6"]);
+    }
 }